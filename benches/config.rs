@@ -0,0 +1,22 @@
+use criterion::{Criterion, criterion_group, criterion_main};
+use gim_config::config::{get_config, get_config_value, get_value_fast};
+use gim_config::testing::TempConfigDir;
+use std::hint::black_box;
+
+fn bench_reads(c: &mut Criterion) {
+    let _dir = TempConfigDir::new().expect("Failed to create temp config dir");
+    get_config().expect("Failed to create default config");
+
+    c.bench_function("get_config", |b| {
+        b.iter(|| black_box(get_config().unwrap()))
+    });
+    c.bench_function("get_config_value", |b| {
+        b.iter(|| black_box(get_config_value("ai", "model").unwrap()))
+    });
+    c.bench_function("get_value_fast", |b| {
+        b.iter(|| black_box(get_value_fast("ai.model").unwrap()))
+    });
+}
+
+criterion_group!(benches, bench_reads);
+criterion_main!(benches);
@@ -0,0 +1,227 @@
+use std::io::{Error, ErrorKind, Result};
+use toml::{Value, map};
+
+use crate::ai::AiConfig;
+use crate::config::{get_config, modify_config};
+
+/// The table holding one provider setup per account.
+const ACCOUNTS_SECTION: &str = "accounts";
+
+/// The top-level key naming the account whose settings are in `[ai]`.
+const ACTIVE_ACCOUNT_KEY: &str = "active_account";
+
+/// One provider setup stored under `[accounts.<name>]`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccountInfo {
+    /// The account name
+    pub name: String,
+    /// Whether this account's settings are currently in `[ai]`
+    pub active: bool,
+    /// The account's provider settings, including its own API key
+    pub ai: AiConfig,
+}
+
+/// Lists all configured accounts, sorted by name.
+///
+/// # Returns
+///
+/// * `Result<Vec<AccountInfo>>` - The accounts or an error
+pub fn list_accounts() -> Result<Vec<AccountInfo>> {
+    let config = get_config()?;
+    let active = active_account_in(&config);
+    let Some(accounts) = config.get(ACCOUNTS_SECTION).and_then(Value::as_table) else {
+        return Ok(Vec::new());
+    };
+    let mut list: Vec<AccountInfo> = accounts
+        .iter()
+        .map(|(name, settings)| AccountInfo {
+            name: name.clone(),
+            active: active.as_deref() == Some(name.as_str()),
+            ai: ai_config_of(settings),
+        })
+        .collect();
+    list.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(list)
+}
+
+/// Returns the name of the active account, if accounts are in use.
+///
+/// # Returns
+///
+/// * `Result<Option<String>>` - The active account name or an error
+pub fn active_account() -> Result<Option<String>> {
+    Ok(active_account_in(&get_config()?))
+}
+
+/// Stores a provider setup under `[accounts.<name>]`, replacing any existing one.
+///
+/// If `name` is the active account its settings are also copied into `[ai]`.
+///
+/// # Arguments
+///
+/// * `name` - The account name, e.g. `"client-a"`
+/// * `ai` - The account's provider settings
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if the name is invalid or saving fails
+pub fn add_account(name: &str, ai: &AiConfig) -> Result<()> {
+    validate_name(name)?;
+    modify_config(|config| {
+        accounts_table(config)?.insert(name.to_string(), ai.to_value());
+        if active_account_in(config).as_deref() == Some(name) {
+            set_ai_section(config, ai)?;
+        }
+        Ok(())
+    })
+}
+
+/// Removes an account; the active account can't be removed.
+///
+/// # Arguments
+///
+/// * `name` - The account name
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if the account is unknown or active
+pub fn remove_account(name: &str) -> Result<()> {
+    modify_config(|config| {
+        if active_account_in(config).as_deref() == Some(name) {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!("Account '{}' is active; switch to another one first", name),
+            ));
+        }
+        accounts_table(config)?
+            .remove(name)
+            .map(|_| ())
+            .ok_or_else(|| not_found(name))
+    })
+}
+
+/// Makes `name` the active account.
+///
+/// The current `[ai]` settings are saved back into the previously active account, then the
+/// new account's settings are copied into `[ai]`, so every reader of `[ai]` picks them up.
+///
+/// # Arguments
+///
+/// * `name` - The account to activate
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or a `NotFound` error if the account doesn't exist
+pub fn switch_account(name: &str) -> Result<()> {
+    modify_config(|config| {
+        let target = config
+            .get(ACCOUNTS_SECTION)
+            .and_then(|accounts| accounts.get(name))
+            .map(ai_config_of)
+            .ok_or_else(|| not_found(name))?;
+        if let Some(previous) = active_account_in(config) {
+            let current = AiConfig::from_config(config);
+            accounts_table(config)?.insert(previous, current.to_value());
+        }
+        set_ai_section(config, &target)?;
+        root_table(config)?.insert(ACTIVE_ACCOUNT_KEY.to_string(), Value::from(name));
+        Ok(())
+    })
+}
+
+fn active_account_in(config: &Value) -> Option<String> {
+    config
+        .get(ACTIVE_ACCOUNT_KEY)
+        .and_then(Value::as_str)
+        .map(str::to_string)
+}
+
+fn ai_config_of(settings: &Value) -> AiConfig {
+    let mut root = map::Map::new();
+    root.insert("ai".to_string(), settings.clone());
+    AiConfig::from_config(&Value::Table(root))
+}
+
+fn set_ai_section(config: &mut Value, ai: &AiConfig) -> Result<()> {
+    let root = root_table(config)?;
+    let section = root
+        .entry("ai".to_string())
+        .or_insert_with(|| Value::Table(map::Map::new()));
+    if let (Some(section), Value::Table(values)) = (section.as_table_mut(), ai.to_value()) {
+        section.extend(values);
+    }
+    Ok(())
+}
+
+fn root_table(config: &mut Value) -> Result<&mut map::Map<String, Value>> {
+    config
+        .as_table_mut()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Config is not a table"))
+}
+
+fn accounts_table(config: &mut Value) -> Result<&mut map::Map<String, Value>> {
+    root_table(config)?
+        .entry(ACCOUNTS_SECTION.to_string())
+        .or_insert_with(|| Value::Table(map::Map::new()))
+        .as_table_mut()
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Section '{}' is not a table", ACCOUNTS_SECTION),
+            )
+        })
+}
+
+fn validate_name(name: &str) -> Result<()> {
+    if name.is_empty() || name.contains('.') {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid account name '{}'", name),
+        ));
+    }
+    Ok(())
+}
+
+fn not_found(name: &str) -> Error {
+    Error::new(ErrorKind::NotFound, format!("Account '{}' not found", name))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::Secret;
+    use crate::testing::TempConfigDir;
+
+    fn account(model: &str, apikey: &str) -> AiConfig {
+        AiConfig {
+            model: model.to_string(),
+            apikey: Secret::new(apikey.to_string()),
+            url: "https://api.openai.com/v1".to_string(),
+            language: "English".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_switch_account_swaps_ai_settings() {
+        let _dir = TempConfigDir::new().unwrap();
+        add_account("client-a", &account("gpt-4o", "sk-a")).unwrap();
+        add_account("client-b", &account("gpt-4o-mini", "sk-b")).unwrap();
+
+        switch_account("client-a").unwrap();
+        assert_eq!(AiConfig::load().unwrap().apikey.expose(), "sk-a");
+
+        crate::config::update_config_value("ai", "model", Value::from("o1")).unwrap();
+        switch_account("client-b").unwrap();
+        assert_eq!(AiConfig::load().unwrap().apikey.expose(), "sk-b");
+        assert_eq!(active_account().unwrap().as_deref(), Some("client-b"));
+
+        let accounts = list_accounts().unwrap();
+        assert_eq!(
+            accounts[0].ai.model, "o1",
+            "Edits are kept in the old account"
+        );
+        assert!(accounts[1].active);
+        assert!(remove_account("client-b").is_err());
+        assert!(switch_account("missing").is_err());
+    }
+}
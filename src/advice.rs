@@ -0,0 +1,183 @@
+use std::{fmt, io::Result};
+use toml::Value;
+
+use crate::compat::is_newer_schema;
+use crate::config::get_config;
+use crate::defaults::{DefaultConfigBuilder, builtin_defaults, default_values};
+use crate::format::to_json;
+use crate::path;
+use crate::secret::is_secret;
+
+/// Keys earlier releases kept in the configuration file, with why they are gone.
+pub const DEPRECATED_KEYS: &[(&str, &str)] = &[
+    (
+        "update.tried",
+        "Update counters are kept in the state file now",
+    ),
+    (
+        "update.last_try_day",
+        "Update counters are kept in the state file now",
+    ),
+    (
+        "update.last_try",
+        "Update counters are kept in the state file now",
+    ),
+];
+
+/// What an [`Advice`] suggests doing.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum AdviceKind {
+    /// Remove a key this release no longer reads
+    RemoveDeprecated,
+    /// Add a setting the file doesn't have yet, with its default
+    AddRecommended,
+}
+
+impl fmt::Display for AdviceKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            AdviceKind::RemoveDeprecated => "remove-deprecated",
+            AdviceKind::AddRecommended => "add-recommended",
+        })
+    }
+}
+
+/// One suggestion of [`upgrade_advice`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Advice {
+    /// What to do
+    pub kind: AdviceKind,
+    /// The dotted key path
+    pub path: String,
+    /// The value in the file, `None` if the file lacks the key
+    pub current: Option<Value>,
+    /// The value to set, `None` if the key should be removed
+    pub suggested: Option<Value>,
+    /// Why, for the user
+    pub reason: String,
+}
+
+impl Advice {
+    /// Serializes the suggestion as a JSON object for front-ends.
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "kind": self.kind.to_string(),
+            "path": self.path,
+            "current": self.current.as_ref().map(to_json),
+            "suggested": self.suggested.as_ref().map(to_json),
+            "reason": self.reason,
+        })
+        .to_string()
+    }
+}
+
+/// Compares the configuration file with the current schema, for the CLI to present after
+/// a version upgrade.
+///
+/// Deprecated keys still in the file come first, then settings the file lacks, each in
+/// document order. Nothing is changed; applying a suggestion is up to the user. Secret keys are never suggested, and
+/// a file written by a newer release gets no advice.
+///
+/// # Returns
+///
+/// * `Result<Vec<Advice>>` - The suggestions, empty if the file is up to date, or an error
+///   if it can't be read
+pub fn upgrade_advice() -> Result<Vec<Advice>> {
+    Ok(advise(
+        &get_config()?,
+        &builtin_defaults(),
+        &default_values(),
+        DEPRECATED_KEYS,
+    ))
+}
+
+fn advise(
+    config: &Value,
+    declared: &DefaultConfigBuilder,
+    defaults: &Value,
+    deprecated: &[(&str, &str)],
+) -> Vec<Advice> {
+    if is_newer_schema(config) {
+        return Vec::new();
+    }
+    let mut advice: Vec<Advice> = deprecated
+        .iter()
+        .filter_map(|(key_path, reason)| {
+            Some(Advice {
+                kind: AdviceKind::RemoveDeprecated,
+                path: key_path.to_string(),
+                current: Some(path::lookup(config, key_path)?.clone()),
+                suggested: None,
+                reason: reason.to_string(),
+            })
+        })
+        .collect();
+    let keys: Vec<(String, &Value)> = path::leaf_paths(defaults)
+        .into_iter()
+        .filter(|key_path| !is_secret(key_path))
+        .filter(|key_path| {
+            !declared
+                .keys()
+                .iter()
+                .any(|key| key.secret && key.path == *key_path)
+        })
+        .filter_map(|key_path| {
+            let default = path::lookup(defaults, &key_path)?;
+            Some((key_path, default))
+        })
+        .collect();
+    for (key_path, default) in &keys {
+        if path::lookup(config, key_path).is_none() {
+            let comment = declared.key_comment(key_path).unwrap_or_default();
+            advice.push(Advice {
+                kind: AdviceKind::AddRecommended,
+                path: key_path.clone(),
+                current: None,
+                suggested: Some(Value::clone(default)),
+                reason: if comment.is_empty() {
+                    "New setting".to_string()
+                } else {
+                    format!("New setting: {}", comment)
+                },
+            });
+        }
+    }
+    advice
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_advise_reports_deprecated_and_new_keys() {
+        let declared = DefaultConfigBuilder::new()
+            .key("app.retries", 5, "How often to retry")
+            .key("app.color", "auto", "When to use colors")
+            .secret_key("app.token", "", "Access token");
+        let defaults = declared.values().unwrap();
+        let config: Value = toml::from_str("[app]\nretries = 3\nold = true\n").unwrap();
+
+        let advice = advise(
+            &config,
+            &declared,
+            &defaults,
+            &[("app.old", "No longer read")],
+        );
+        let summary: Vec<(AdviceKind, &str)> =
+            advice.iter().map(|a| (a.kind, a.path.as_str())).collect();
+        assert_eq!(
+            summary,
+            vec![
+                (AdviceKind::RemoveDeprecated, "app.old"),
+                (AdviceKind::AddRecommended, "app.color"),
+            ]
+        );
+        assert_eq!(advice[1].suggested, Some(Value::from("auto")));
+        assert!(advice[1].to_json().contains("\"add-recommended\""));
+
+        let current: Value = toml::from_str("[app]\nretries = 4\ncolor = 'never'\n").unwrap();
+        assert!(advise(&current, &declared, &defaults, &[]).is_empty());
+    }
+}
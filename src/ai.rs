@@ -0,0 +1,90 @@
+use std::io::Result;
+use toml::{Value, map};
+
+use crate::profile::effective_config;
+use crate::secret::Secret;
+
+/// The `[ai]` settings used to talk to the model provider.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct AiConfig {
+    /// Model name, e.g. `"gpt-4o"`
+    pub model: String,
+    /// API key of the provider; empty for local servers
+    pub apikey: Secret<String>,
+    /// Base URL of the provider's API
+    pub url: String,
+    /// Language of the generated commit messages
+    pub language: String,
+}
+
+impl AiConfig {
+    /// Reads the settings from a configuration's `[ai]` section.
+    ///
+    /// Missing keys are left empty.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The whole configuration
+    ///
+    /// # Returns
+    ///
+    /// * `AiConfig` - The settings found in the section
+    pub fn from_config(config: &Value) -> AiConfig {
+        let get = |key: &str| {
+            config
+                .get("ai")
+                .and_then(|ai| ai.get(key))
+                .and_then(Value::as_str)
+                .unwrap_or_default()
+                .to_string()
+        };
+        AiConfig {
+            model: get("model"),
+            apikey: Secret::new(get("apikey")),
+            url: get("url"),
+            language: get("language"),
+        }
+    }
+
+    /// Loads the settings from the configuration file, including `GIM_ENV` profile overrides.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<AiConfig>` - The current `[ai]` settings or an error
+    pub fn load() -> Result<AiConfig> {
+        Ok(AiConfig::from_config(&effective_config()?))
+    }
+
+    /// Returns the settings as an `[ai]` section table.
+    ///
+    /// The table holds a plain copy of the API key, which isn't wiped when it is dropped.
+    pub fn to_value(&self) -> Value {
+        let mut table = map::Map::new();
+        table.insert("model".to_string(), Value::from(self.model.as_str()));
+        table.insert(
+            "apikey".to_string(),
+            Value::from(self.apikey.expose().as_str()),
+        );
+        table.insert("url".to_string(), Value::from(self.url.as_str()));
+        table.insert("language".to_string(), Value::from(self.language.as_str()));
+        Value::Table(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ai_config_round_trip() {
+        let config: Value = toml::from_str("[ai]\nmodel = 'm'\nurl = 'u'\n").unwrap();
+        let ai = AiConfig::from_config(&config);
+        assert_eq!(ai.model, "m");
+        assert_eq!(ai.apikey.expose(), "");
+        assert!(format!("{:?}", ai).contains("apikey: Secret(\"<redacted>\")"));
+
+        let mut root = map::Map::new();
+        root.insert("ai".to_string(), ai.to_value());
+        assert_eq!(AiConfig::from_config(&Value::Table(root)), ai);
+    }
+}
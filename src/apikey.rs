@@ -0,0 +1,208 @@
+use std::{io::Result, ops::RangeInclusive};
+use toml::Value;
+
+use crate::ai::AiConfig;
+use crate::config::update_config_value;
+
+const BEARER_PREFIX: &str = "bearer ";
+
+/// A model provider whose API key format is known.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Provider {
+    /// OpenAI, keys like `sk-...` or `sk-proj-...`
+    OpenAi,
+    /// Anthropic, keys like `sk-ant-...`
+    Anthropic,
+    /// Any other provider; only format-independent checks apply
+    Other,
+}
+
+impl Provider {
+    /// Guesses the provider from the `[ai]` settings, by URL host first and model name second.
+    pub fn detect(ai: &AiConfig) -> Provider {
+        let url = ai.url.to_ascii_lowercase();
+        let model = ai.model.to_ascii_lowercase();
+        if url.contains("openai.com") {
+            Provider::OpenAi
+        } else if url.contains("anthropic.com") {
+            Provider::Anthropic
+        } else if !url.is_empty() {
+            Provider::Other
+        } else if model.starts_with("gpt-") || model.starts_with("o1") || model.starts_with("o3") {
+            Provider::OpenAi
+        } else if model.starts_with("claude") {
+            Provider::Anthropic
+        } else {
+            Provider::Other
+        }
+    }
+
+    fn key_prefix(self) -> Option<&'static str> {
+        match self {
+            Provider::OpenAi => Some("sk-"),
+            Provider::Anthropic => Some("sk-ant-"),
+            Provider::Other => None,
+        }
+    }
+
+    fn key_length(self) -> Option<RangeInclusive<usize>> {
+        match self {
+            Provider::OpenAi => Some(40..=200),
+            Provider::Anthropic => Some(90..=130),
+            Provider::Other => None,
+        }
+    }
+}
+
+/// Something that looks wrong with an API key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ApiKeyIssue {
+    /// The key starts with `Bearer `, copied from an `Authorization` header
+    BearerPrefix,
+    /// The key is wrapped in quotes
+    Quoted,
+    /// The key has leading, trailing or embedded whitespace
+    Whitespace,
+    /// The key lacks the prefix the provider's keys have
+    MissingPrefix(&'static str),
+    /// The key is shorter or longer than the provider's keys
+    UnexpectedLength(usize),
+}
+
+/// A lint finding together with the key it suggests instead.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ApiKeyWarning {
+    /// What looks wrong
+    pub issue: ApiKeyIssue,
+    /// A human-readable explanation
+    pub message: String,
+    /// The corrected key, if the problem can be fixed mechanically
+    pub fix: Option<String>,
+}
+
+/// Removes a pasted `Bearer ` prefix, ignoring case and surrounding whitespace.
+pub fn strip_bearer_prefix(key: &str) -> &str {
+    let trimmed = key.trim();
+    match trimmed.get(..BEARER_PREFIX.len()) {
+        Some(prefix) if prefix.eq_ignore_ascii_case(BEARER_PREFIX) => {
+            trimmed[BEARER_PREFIX.len()..].trim_start()
+        }
+        _ => trimmed,
+    }
+}
+
+/// Checks an API key against common copy-paste mistakes and the provider's key format.
+///
+/// An empty key yields no warnings, since local servers don't need one.
+///
+/// # Arguments
+///
+/// * `key` - The API key
+/// * `provider` - The provider the key is meant for
+///
+/// # Returns
+///
+/// * `Vec<ApiKeyWarning>` - The findings, empty if the key looks fine
+pub fn lint_api_key(key: &str, provider: Provider) -> Vec<ApiKeyWarning> {
+    let mut warnings = Vec::new();
+    if key.is_empty() {
+        return warnings;
+    }
+
+    let stripped = strip_bearer_prefix(key);
+    if stripped.len() != key.trim().len() {
+        warnings.push(ApiKeyWarning {
+            issue: ApiKeyIssue::BearerPrefix,
+            message: "the key starts with \"Bearer \"; only the token itself is needed".to_string(),
+            fix: Some(stripped.to_string()),
+        });
+    }
+    let unquoted = stripped.trim_matches(|c| c == '"' || c == '\'');
+    if unquoted.len() != stripped.len() {
+        warnings.push(ApiKeyWarning {
+            issue: ApiKeyIssue::Quoted,
+            message: "the key is wrapped in quotes".to_string(),
+            fix: Some(unquoted.to_string()),
+        });
+    }
+    let candidate: String = unquoted.chars().filter(|c| !c.is_whitespace()).collect();
+    if key.trim() != key || candidate.len() != unquoted.len() {
+        warnings.push(ApiKeyWarning {
+            issue: ApiKeyIssue::Whitespace,
+            message: "the key has leading, trailing or embedded whitespace".to_string(),
+            fix: Some(candidate.clone()),
+        });
+    }
+
+    if let Some(prefix) = provider.key_prefix()
+        && !candidate.starts_with(prefix)
+    {
+        warnings.push(ApiKeyWarning {
+            issue: ApiKeyIssue::MissingPrefix(prefix),
+            message: format!("keys of this provider start with \"{}\"", prefix),
+            fix: None,
+        });
+    }
+    if let Some(range) = provider.key_length()
+        && !range.contains(&candidate.len())
+    {
+        warnings.push(ApiKeyWarning {
+            issue: ApiKeyIssue::UnexpectedLength(candidate.len()),
+            message: format!(
+                "the key has {} characters, keys of this provider have {} to {}",
+                candidate.len(),
+                range.start(),
+                range.end()
+            ),
+            fix: None,
+        });
+    }
+    warnings
+}
+
+/// Writes `ai.apikey` and lints the new key against the configured provider.
+///
+/// The key is stored as given; callers decide whether to apply a suggested fix.
+///
+/// # Arguments
+///
+/// * `key` - The API key
+///
+/// # Returns
+///
+/// * `Result<Vec<ApiKeyWarning>>` - The lint findings or an error if writing fails
+pub fn set_api_key(key: &str) -> Result<Vec<ApiKeyWarning>> {
+    update_config_value("ai", "apikey", Value::from(key))?;
+    Ok(lint_api_key(key, Provider::detect(&AiConfig::load()?)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_api_key() {
+        let key = format!("sk-{}", "a".repeat(48));
+        assert!(lint_api_key(&key, Provider::OpenAi).is_empty());
+        assert!(lint_api_key("", Provider::OpenAi).is_empty());
+
+        let warnings = lint_api_key(&format!("Bearer {}", key), Provider::OpenAi);
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(warnings[0].issue, ApiKeyIssue::BearerPrefix);
+        assert_eq!(warnings[0].fix.as_deref(), Some(key.as_str()));
+
+        let warnings = lint_api_key("\"abc\"", Provider::Anthropic);
+        let issues: Vec<_> = warnings.iter().map(|w| w.issue.clone()).collect();
+        assert_eq!(
+            issues,
+            vec![
+                ApiKeyIssue::Quoted,
+                ApiKeyIssue::MissingPrefix("sk-ant-"),
+                ApiKeyIssue::UnexpectedLength(3)
+            ]
+        );
+        assert_eq!(strip_bearer_prefix(" bearer  tok "), "tok");
+    }
+}
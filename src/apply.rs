@@ -0,0 +1,158 @@
+use std::io::{Error, ErrorKind, Read, Result};
+use toml::Value;
+
+use crate::change::ChangeSet;
+use crate::config::modify_config;
+use crate::format::Format;
+use crate::merge::{MergePolicy, merge_with_policy};
+use crate::secret::SECRET_PLACEHOLDER;
+
+/// How incoming settings are combined with the existing configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ApplyStrategy {
+    /// Incoming keys overwrite existing ones; keys only present locally are kept
+    Merge,
+    /// Incoming keys are only added where the local configuration has none
+    KeepExisting,
+    /// Every section present in the input replaces the local section entirely
+    ReplaceSections,
+}
+
+/// Reads a configuration document from `reader` and applies it to the config file.
+///
+/// This backs `cat team.toml | gim config apply -`: the input is parsed and validated before
+/// anything is written, and secret placeholders produced by a shareable export are ignored
+/// so they never overwrite real credentials.
+///
+/// # Arguments
+///
+/// * `reader` - The source of the document, e.g. `std::io::stdin()`
+/// * `format` - The format of the document
+/// * `strategy` - How to combine the document with the existing configuration
+///
+/// # Returns
+///
+/// * `Result<ChangeSet>` - The keys that were changed, or an error if the input is invalid
+pub fn apply_from_reader(
+    mut reader: impl Read,
+    format: Format,
+    strategy: ApplyStrategy,
+) -> Result<ChangeSet> {
+    let mut content = String::new();
+    reader.read_to_string(&mut content)?;
+    apply_value(format.parse(&content)?, strategy)
+}
+
+/// Applies an already parsed configuration document to the config file.
+///
+/// # Arguments
+///
+/// * `incoming` - The document to apply; every top-level entry must be a table
+/// * `strategy` - How to combine the document with the existing configuration
+///
+/// # Returns
+///
+/// * `Result<ChangeSet>` - The keys that were changed, or an error if the input is invalid
+pub fn apply_value(incoming: Value, strategy: ApplyStrategy) -> Result<ChangeSet> {
+    apply_value_with(incoming, strategy, &MergePolicy::default())
+}
+
+/// Applies a configuration document, combining arrays according to `policy`.
+///
+/// The policy is used by the merging strategies; e.g. a team document can add ignore
+/// patterns with [`crate::merge::ArrayStrategy::Union`] while still replacing model lists.
+///
+/// # Arguments
+///
+/// * `incoming` - The document to apply; every top-level entry must be a table
+/// * `strategy` - How to combine the document with the existing configuration
+/// * `policy` - How arrays present in both are combined
+///
+/// # Returns
+///
+/// * `Result<ChangeSet>` - The keys that were changed, or an error if the input is invalid
+pub fn apply_value_with(
+    mut incoming: Value,
+    strategy: ApplyStrategy,
+    policy: &MergePolicy,
+) -> Result<ChangeSet> {
+    validate(&incoming)?;
+    strip_placeholders(&mut incoming);
+    modify_config(|config| {
+        let before = config.clone();
+        combine(config, &incoming, strategy, policy);
+        Ok(ChangeSet::between(&before, config))
+    })
+}
+
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "gim_config.validate", level = "debug", skip_all, err)
+)]
+fn validate(incoming: &Value) -> Result<()> {
+    let table = incoming
+        .as_table()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Input must be a table of sections"))?;
+    for (key, value) in table {
+        if !value.is_table() {
+            return Err(Error::new(
+                ErrorKind::InvalidData,
+                format!("Top-level key '{}' must be a section table", key),
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn strip_placeholders(value: &mut Value) {
+    if let Some(table) = value.as_table_mut() {
+        table.retain(|_, v| v.as_str() != Some(SECRET_PLACEHOLDER));
+        for (_, nested) in table.iter_mut() {
+            strip_placeholders(nested);
+        }
+    }
+}
+
+fn combine(config: &mut Value, incoming: &Value, strategy: ApplyStrategy, policy: &MergePolicy) {
+    match strategy {
+        ApplyStrategy::ReplaceSections => {
+            let (Some(target), Some(source)) = (config.as_table_mut(), incoming.as_table()) else {
+                return;
+            };
+            for (section, value) in source {
+                target.insert(section.clone(), value.clone());
+            }
+        }
+        ApplyStrategy::Merge | ApplyStrategy::KeepExisting => {
+            merge_with_policy(config, incoming, strategy == ApplyStrategy::Merge, policy)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_config;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_apply_from_reader_merges_and_reports() {
+        let _dir = TempConfigDir::new().unwrap();
+        let input = "[ai]\nmodel = 'gpt-4o'\napikey = '<redacted>'\n[commit]\nemoji = true\n";
+        let changes =
+            apply_from_reader(input.as_bytes(), Format::Toml, ApplyStrategy::Merge).unwrap();
+        assert_eq!(changes.paths(), vec!["ai.model", "commit.emoji"]);
+
+        let config = get_config().unwrap();
+        assert_eq!(config["ai"]["model"].as_str(), Some("gpt-4o"));
+        assert_eq!(config["ai"]["apikey"].as_str(), Some(""));
+
+        let again =
+            apply_from_reader(input.as_bytes(), Format::Toml, ApplyStrategy::Merge).unwrap();
+        assert!(again.is_empty());
+        assert!(
+            apply_from_reader("model = 1".as_bytes(), Format::Toml, ApplyStrategy::Merge).is_err()
+        );
+    }
+}
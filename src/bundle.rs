@@ -0,0 +1,230 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::{Component, Path, PathBuf},
+};
+use toml::{Value, map};
+
+use crate::config::{ensure_persistent, get_config_file, invalidate_document_cache};
+use crate::directory::{cache_dir, config_dir, state_dir, system_config_dir};
+use crate::encryption::{
+    decrypt_bytes, derive_key, encrypt_bytes, from_hex, random_salt, salt_file, to_hex,
+};
+use crate::storage::{FileLock, write_atomic};
+
+/// The first bytes of every bundle, followed by the salt and the sealed document.
+const BUNDLE_MAGIC: &[u8] = b"GIMBUNDLE1";
+
+const SALT_LEN: usize = 16;
+
+/// Packages the whole gim setup into one archive encrypted with `passphrase`.
+///
+/// The archive holds every file of the config directory, i.e. the configuration with its
+/// secrets, split section files and anything the application keeps there such as prompts,
+/// plus the salt that encrypted secret values depend on. Files that aren't UTF-8 text,
+/// such as images, are carried hex-encoded. Lock and temporary files are skipped.
+///
+/// # Arguments
+///
+/// * `passphrase` - The passphrase [`import_bundle`] will need
+///
+/// # Returns
+///
+/// * `Result<Vec<u8>>` - The encrypted archive or an error if a file can't be read
+pub fn export_bundle(passphrase: &str) -> Result<Vec<u8>> {
+    let root = config_dir()?;
+    let skipped = [state_dir()?, cache_dir()?, system_config_dir()];
+    let mut files = map::Map::new();
+    let mut binary = map::Map::new();
+    collect_files(&root, &root, &skipped, &mut files, &mut binary)?;
+
+    let mut document = map::Map::new();
+    document.insert("files".to_string(), Value::Table(files));
+    if !binary.is_empty() {
+        document.insert("binary".to_string(), Value::Table(binary));
+    }
+    if let Ok(salt) = fs::read_to_string(salt_file()?) {
+        document.insert("salt".to_string(), Value::from(salt));
+    }
+    let content = toml::to_string(&Value::Table(document))
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+
+    let salt = random_salt()?;
+    let sealed = encrypt_bytes(&derive_key(passphrase, &salt)?, content.as_bytes())?;
+    Ok([BUNDLE_MAGIC, salt.as_slice(), sealed.as_slice()].concat())
+}
+
+/// Restores a gim setup from an archive created by [`export_bundle`].
+///
+/// Files in the archive replace the local files of the same name; other local files are
+/// kept.
+///
+/// # Arguments
+///
+/// * `bytes` - The archive
+/// * `passphrase` - The passphrase the archive was exported with
+///
+/// # Returns
+///
+/// * `Result<()>` - Success, or an `InvalidData` error if the archive is malformed or the
+///   passphrase is wrong
+pub fn import_bundle(bytes: &[u8], passphrase: &str) -> Result<()> {
+    let rest = bytes
+        .strip_prefix(BUNDLE_MAGIC)
+        .filter(|rest| rest.len() > SALT_LEN)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Not a gim configuration bundle"))?;
+    ensure_persistent()?;
+    let (salt, sealed) = rest.split_at(SALT_LEN);
+    let content = decrypt_bytes(&derive_key(passphrase, salt)?, sealed)?;
+    let document: Value = std::str::from_utf8(&content)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+        .and_then(|text| toml::from_str(text).map_err(|e| Error::new(ErrorKind::InvalidData, e)))?;
+
+    let root = config_dir()?;
+    let files = document
+        .get("files")
+        .and_then(Value::as_table)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Bundle has no files"))?;
+    let binary = document.get("binary").and_then(Value::as_table);
+    let mut entries = Vec::new();
+    for (name, content) in files {
+        let content = content.as_str().map(|text| text.as_bytes().to_vec());
+        entries.push((name, content.ok_or_else(|| not_text(name))?));
+    }
+    for (name, content) in binary.into_iter().flatten() {
+        entries.push((
+            name,
+            content
+                .as_str()
+                .and_then(from_hex)
+                .ok_or_else(|| not_text(name))?,
+        ));
+    }
+    fs::create_dir_all(&root)?;
+    let _lock = FileLock::acquire(&get_config_file()?)?;
+    for (name, content) in entries {
+        let target = root.join(relative_path(name)?);
+        if let Some(parent) = target.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&target, &content)?;
+    }
+    if let Some(salt) = document.get("salt").and_then(Value::as_str) {
+        let file = salt_file()?;
+        if let Some(parent) = file.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(&file, salt.as_bytes())?;
+    }
+    invalidate_document_cache();
+    Ok(())
+}
+
+fn not_text(name: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Bundle entry '{}' is malformed", name),
+    )
+}
+
+/// Collects the files below `dir` into `files`, or hex-encoded into `binary` if they
+/// aren't UTF-8 text.
+fn collect_files(
+    root: &Path,
+    dir: &Path,
+    skipped: &[PathBuf],
+    files: &mut map::Map<String, Value>,
+    binary: &mut map::Map<String, Value>,
+) -> Result<()> {
+    let mut entries: Vec<_> = match fs::read_dir(dir) {
+        Ok(entries) => entries.collect::<Result<_>>()?,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e),
+    };
+    entries.sort_by_key(|entry| entry.file_name());
+    for entry in entries {
+        let path = entry.path();
+        let name = entry.file_name().to_string_lossy().into_owned();
+        if entry.file_type()?.is_dir() {
+            if !skipped.contains(&path) {
+                collect_files(root, &path, skipped, files, binary)?;
+            }
+        } else if !name.ends_with(".lock") && !name.ends_with(".tmp") {
+            let content = fs::read(&path).map_err(|e| {
+                Error::new(
+                    e.kind(),
+                    format!("Can't bundle '{}': {}", path.display(), e),
+                )
+            })?;
+            let relative = path.strip_prefix(root).expect("collected below root");
+            let key = relative
+                .components()
+                .map(|c| c.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/");
+            match String::from_utf8(content) {
+                Ok(text) => files.insert(key, Value::from(text)),
+                Err(e) => binary.insert(key, Value::from(to_hex(e.as_bytes()))),
+            };
+        }
+    }
+    Ok(())
+}
+
+/// Checks that a bundle entry stays inside the config directory.
+fn relative_path(name: &str) -> Result<PathBuf> {
+    let path = PathBuf::from(name);
+    if path.components().all(|c| matches!(c, Component::Normal(_))) {
+        Ok(path)
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Bundle entry '{}' points outside the config directory",
+                name
+            ),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config, update_config_value};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_bundle_round_trip() {
+        let bundle = {
+            let _dir = TempConfigDir::new().unwrap();
+            update_config_value("ai", "apikey", Value::from("sk-move")).unwrap();
+            let prompts = config_dir().unwrap().join("prompts");
+            fs::create_dir_all(&prompts).unwrap();
+            fs::write(prompts.join("commit.md"), "Write a commit message").unwrap();
+            fs::write(
+                prompts.join("logo.png"),
+                [0x89, b'P', b'N', b'G', 0xff, 0x00],
+            )
+            .unwrap();
+            export_bundle("correct horse").unwrap()
+        };
+
+        let dir = TempConfigDir::new().unwrap();
+        assert!(import_bundle(&bundle, "wrong").is_err());
+        assert!(import_bundle(b"garbage", "correct horse").is_err());
+        import_bundle(&bundle, "correct horse").unwrap();
+        assert_eq!(
+            get_config().unwrap()["ai"]["apikey"].as_str(),
+            Some("sk-move")
+        );
+        assert_eq!(
+            fs::read_to_string(dir.path().join("prompts/commit.md")).unwrap(),
+            "Write a commit message"
+        );
+        assert_eq!(
+            fs::read(dir.path().join("prompts/logo.png")).unwrap(),
+            [0x89, b'P', b'N', b'G', 0xff, 0x00]
+        );
+        assert!(relative_path("../etc/passwd").is_err());
+    }
+}
@@ -0,0 +1,270 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+};
+use toml::{Value, map};
+
+use crate::directory::cache_dir;
+use crate::storage::write_atomic;
+
+/// The first bytes of every cache file, followed by the version header and the value.
+const CACHE_MAGIC: &[u8] = b"GIMCACHE1";
+
+/// Returns the value cached under `name`, building and caching it if needed.
+///
+/// Cached values are stored in a compact binary encoding in [`cache_dir`], so reading
+/// one is much cheaper than rebuilding it, e.g. by parsing embedded catalogs of models,
+/// providers or templates. A cache file is rebuilt when it was written by another version
+/// of this crate, when `version` differs from the one it was written with, or when it is
+/// unreadable. Failing to write the cache is not an error; the value is then rebuilt on the
+/// next call.
+///
+/// # Arguments
+///
+/// * `name` - The cache entry, a plain file name such as `"schema"`
+/// * `version` - Identifies the data `build` produces, e.g. the application's version;
+///   change it whenever the embedded data changes
+/// * `build` - Produces the value when the cache is missing or stale
+///
+/// # Returns
+///
+/// * `Result<Value>` - The value, or an error if `name` isn't a plain name or `build` fails
+pub fn load_cached(
+    name: &str,
+    version: &str,
+    build: impl FnOnce() -> Result<Value>,
+) -> Result<Value> {
+    let file = cache_file(name)?;
+    let header = version_header(version);
+    if let Ok(bytes) = fs::read(&file)
+        && let Some(value) = bytes
+            .strip_prefix(header.as_slice())
+            .and_then(|body| decode(body).ok())
+    {
+        return Ok(value);
+    }
+    let value = build()?;
+    let mut bytes = header;
+    encode(&value, &mut bytes);
+    if fs::create_dir_all(cache_dir()?).is_ok() {
+        let _ = write_atomic(&file, &bytes);
+    }
+    Ok(value)
+}
+
+/// Removes the cache entry `name`; returns whether it existed.
+pub fn clear_cache(name: &str) -> Result<bool> {
+    match fs::remove_file(cache_file(name)?) {
+        Ok(()) => Ok(true),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(false),
+        Err(e) => Err(e),
+    }
+}
+
+/// Removes the cache files written by another version of this crate, which would only be
+/// rebuilt on their next use, and the unreadable ones.
+pub(crate) fn remove_outdated_caches() -> Result<Vec<PathBuf>> {
+    let dir = cache_dir()?;
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut current = CACHE_MAGIC.to_vec();
+    push_str(&mut current, env!("CARGO_PKG_VERSION"));
+    let mut removed = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "bin") {
+            continue;
+        }
+        if !fs::read(&path).is_ok_and(|bytes| bytes.starts_with(&current)) {
+            fs::remove_file(&path)?;
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
+
+fn cache_file(name: &str) -> Result<PathBuf> {
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+    if !valid {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Invalid cache name '{}': use letters, digits, '-' or '_'",
+                name
+            ),
+        ));
+    }
+    Ok(cache_dir()?.join(format!("{}.bin", name)))
+}
+
+fn version_header(version: &str) -> Vec<u8> {
+    let mut header = CACHE_MAGIC.to_vec();
+    for part in [env!("CARGO_PKG_VERSION"), version] {
+        push_str(&mut header, part);
+    }
+    header
+}
+
+fn push_str(out: &mut Vec<u8>, s: &str) {
+    out.extend_from_slice(&(s.len() as u32).to_le_bytes());
+    out.extend_from_slice(s.as_bytes());
+}
+
+fn encode(value: &Value, out: &mut Vec<u8>) {
+    match value {
+        Value::String(s) => {
+            out.push(0);
+            push_str(out, s);
+        }
+        Value::Integer(i) => {
+            out.push(1);
+            out.extend_from_slice(&i.to_le_bytes());
+        }
+        Value::Float(f) => {
+            out.push(2);
+            out.extend_from_slice(&f.to_bits().to_le_bytes());
+        }
+        Value::Boolean(b) => out.extend_from_slice(&[3, u8::from(*b)]),
+        Value::Datetime(d) => {
+            out.push(4);
+            push_str(out, &d.to_string());
+        }
+        Value::Array(items) => {
+            out.push(5);
+            out.extend_from_slice(&(items.len() as u32).to_le_bytes());
+            for item in items {
+                encode(item, out);
+            }
+        }
+        Value::Table(table) => {
+            out.push(6);
+            out.extend_from_slice(&(table.len() as u32).to_le_bytes());
+            for (key, item) in table {
+                push_str(out, key);
+                encode(item, out);
+            }
+        }
+    }
+}
+
+/// Decodes a value written by [`encode`], rejecting trailing bytes.
+fn decode(bytes: &[u8]) -> Result<Value> {
+    let mut reader = Reader { bytes, pos: 0 };
+    let value = reader.value()?;
+    if reader.pos != bytes.len() {
+        return Err(corrupt());
+    }
+    Ok(value)
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+    pos: usize,
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        let end = self.pos.checked_add(len).ok_or_else(corrupt)?;
+        let slice = self.bytes.get(self.pos..end).ok_or_else(corrupt)?;
+        self.pos = end;
+        Ok(slice)
+    }
+
+    fn array<const N: usize>(&mut self) -> Result<[u8; N]> {
+        self.take(N)?.try_into().map_err(|_| corrupt())
+    }
+
+    fn len(&mut self) -> Result<usize> {
+        Ok(u32::from_le_bytes(self.array()?) as usize)
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.len()?;
+        String::from_utf8(self.take(len)?.to_vec()).map_err(|_| corrupt())
+    }
+
+    fn value(&mut self) -> Result<Value> {
+        let [tag] = self.array()?;
+        Ok(match tag {
+            0 => Value::String(self.string()?),
+            1 => Value::Integer(i64::from_le_bytes(self.array()?)),
+            2 => Value::Float(f64::from_bits(u64::from_le_bytes(self.array()?))),
+            3 => Value::Boolean(self.array::<1>()?[0] != 0),
+            4 => Value::Datetime(self.string()?.parse().map_err(|_| corrupt())?),
+            5 => {
+                let len = self.len()?;
+                let mut items = Vec::new();
+                for _ in 0..len {
+                    items.push(self.value()?);
+                }
+                Value::Array(items)
+            }
+            6 => {
+                let len = self.len()?;
+                let mut table = map::Map::new();
+                for _ in 0..len {
+                    let key = self.string()?;
+                    table.insert(key, self.value()?);
+                }
+                Value::Table(table)
+            }
+            _ => return Err(corrupt()),
+        })
+    }
+}
+
+fn corrupt() -> Error {
+    Error::new(ErrorKind::InvalidData, "Corrupt cache file")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempConfigDir;
+    use std::cell::Cell;
+
+    #[test]
+    fn test_encoding_round_trips() {
+        let value: Value = toml::from_str(
+            "name = \"gim\"\ncount = -3\nratio = 0.5\non = true\n\
+             at = 2024-05-01T10:00:00Z\nlist = [1, \"two\", [3]]\n[nested.table]\nkey = \"v\"\n",
+        )
+        .unwrap();
+        let mut bytes = Vec::new();
+        encode(&value, &mut bytes);
+        assert_eq!(decode(&bytes).unwrap(), value);
+        assert!(decode(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_load_cached_rebuilds_on_version_change() {
+        let _dir = TempConfigDir::new().unwrap();
+        let builds = Cell::new(0);
+        let build = || {
+            builds.set(builds.get() + 1);
+            Ok(Value::from("catalog"))
+        };
+        assert_eq!(
+            load_cached("models", "1", build).unwrap(),
+            Value::from("catalog")
+        );
+        assert_eq!(
+            load_cached("models", "1", build).unwrap(),
+            Value::from("catalog")
+        );
+        assert_eq!(builds.get(), 1);
+        load_cached("models", "2", build).unwrap();
+        assert_eq!(builds.get(), 2);
+
+        fs::write(cache_file("models").unwrap(), b"garbage").unwrap();
+        load_cached("models", "2", build).unwrap();
+        assert_eq!(builds.get(), 3);
+        assert!(clear_cache("models").unwrap());
+        assert!(load_cached("../models", "1", build).is_err());
+    }
+}
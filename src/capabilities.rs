@@ -0,0 +1,141 @@
+use std::fmt;
+
+use crate::config::ensure_persistent;
+
+/// An optional part of the crate, named after its Cargo feature where it has one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+#[non_exhaustive]
+pub enum Feature {
+    /// Provider connectivity checks, the `health` feature
+    Health,
+    /// Encryption of secret values at rest, the `encryption` feature
+    Encryption,
+    /// A `config.json` file, the `json` feature
+    Json,
+    /// A `config.yaml` file, the `yaml` feature
+    Yaml,
+    /// Spans and events, the `tracing` feature
+    Tracing,
+    /// Saving the configuration at all; always compiled in
+    Persistence,
+    /// Owner-only permissions on the application directories; always compiled in
+    PrivateDirectories,
+}
+
+impl fmt::Display for Feature {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Feature::Health => "health",
+            Feature::Encryption => "encryption",
+            Feature::Json => "json",
+            Feature::Yaml => "yaml",
+            Feature::Tracing => "tracing",
+            Feature::Persistence => "persistence",
+            Feature::PrivateDirectories => "private-directories",
+        })
+    }
+}
+
+/// Whether an optional part of the crate can be used in this process.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Capability {
+    /// The optional part
+    pub feature: Feature,
+    /// Whether it was compiled in
+    pub compiled: bool,
+    /// Whether it works on this platform right now; never true if it wasn't compiled in
+    pub operational: bool,
+    /// Why it isn't operational, for the user
+    pub reason: Option<String>,
+}
+
+/// Reports which optional parts of the crate were compiled in and which work right now.
+///
+/// Front-ends use this to hide a "Check connection" button or an "Encrypt" toggle up
+/// front instead of failing when it is clicked. The runtime checks are cheap and touch
+/// neither the network nor the configuration file.
+///
+/// # Returns
+///
+/// * `Vec<Capability>` - One entry per [`Feature`], in declaration order
+pub fn capabilities() -> Vec<Capability> {
+    vec![
+        compiled(Feature::Health, cfg!(feature = "health")),
+        capability(
+            Feature::Encryption,
+            cfg!(feature = "encryption"),
+            random_available(),
+            "the operating system's random number generator is unavailable",
+        ),
+        compiled(Feature::Json, cfg!(feature = "json")),
+        compiled(Feature::Yaml, cfg!(feature = "yaml")),
+        compiled(Feature::Tracing, cfg!(feature = "tracing")),
+        match ensure_persistent() {
+            Ok(()) => compiled(Feature::Persistence, true),
+            Err(e) => Capability {
+                feature: Feature::Persistence,
+                compiled: true,
+                operational: false,
+                reason: Some(e.to_string()),
+            },
+        },
+        capability(
+            Feature::PrivateDirectories,
+            true,
+            cfg!(unix),
+            "this platform has no Unix permission bits",
+        ),
+    ]
+}
+
+/// Returns whether `feature` was compiled in and works right now.
+pub fn has_capability(feature: Feature) -> bool {
+    capabilities()
+        .into_iter()
+        .any(|capability| capability.feature == feature && capability.operational)
+}
+
+fn compiled(feature: Feature, compiled: bool) -> Capability {
+    capability(feature, compiled, true, "")
+}
+
+fn capability(feature: Feature, compiled: bool, works: bool, why_not: &str) -> Capability {
+    let reason = if !compiled {
+        Some(format!("built without the `{}` feature", feature))
+    } else if !works {
+        Some(why_not.to_string())
+    } else {
+        None
+    };
+    Capability {
+        feature,
+        compiled,
+        operational: compiled && works,
+        reason,
+    }
+}
+
+fn random_available() -> bool {
+    getrandom::getrandom(&mut [0u8; 1]).is_ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_capabilities_match_the_build() {
+        let _dir = TempConfigDir::new().unwrap();
+        let capabilities = capabilities();
+        let json = capabilities
+            .iter()
+            .find(|c| c.feature == Feature::Json)
+            .unwrap();
+        assert_eq!(json.compiled, cfg!(feature = "json"));
+        assert_eq!(json.operational, json.compiled);
+        assert_eq!(json.reason.is_some(), !json.compiled);
+        assert!(has_capability(Feature::Persistence));
+        assert!(capabilities.iter().all(|c| c.compiled || !c.operational));
+    }
+}
@@ -0,0 +1,232 @@
+use std::io::Result;
+use toml::Value;
+
+use crate::format::to_json;
+use crate::output::{OutputContext, Style};
+use crate::path;
+use crate::secret::mask_secrets;
+
+/// A single changed key, identified by its dotted path.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Change {
+    /// The dotted key path of the changed value
+    pub path: String,
+    /// The value before the change, `None` if the key was added
+    pub old: Option<Value>,
+    /// The value after the change, `None` if the key was removed
+    pub new: Option<Value>,
+}
+
+/// The list of leaf keys that differ between two configurations.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ChangeSet {
+    /// The changes, ordered by key path
+    pub changes: Vec<Change>,
+}
+
+impl ChangeSet {
+    /// Computes the leaf-level differences between `old` and `new`.
+    ///
+    /// Tables are compared key by key; any other value, including arrays, is compared as a whole.
+    ///
+    /// # Arguments
+    ///
+    /// * `old` - The configuration before the change
+    /// * `new` - The configuration after the change
+    ///
+    /// # Returns
+    ///
+    /// * `ChangeSet` - The changed keys
+    pub fn between(old: &Value, new: &Value) -> ChangeSet {
+        let mut changes = Vec::new();
+        diff_into(&mut changes, "", Some(old), Some(new));
+        changes.sort_by(|a, b| a.path.cmp(&b.path));
+        ChangeSet { changes }
+    }
+
+    /// Returns whether no key changed.
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty()
+    }
+
+    /// Returns the number of changed keys.
+    pub fn len(&self) -> usize {
+        self.changes.len()
+    }
+
+    /// Returns the dotted paths of all changed keys.
+    pub fn paths(&self) -> Vec<&str> {
+        self.changes.iter().map(|c| c.path.as_str()).collect()
+    }
+
+    /// Applies the new values of all changes to `root`, removing keys whose new value is `None`.
+    ///
+    /// # Arguments
+    ///
+    /// * `root` - The configuration to modify
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success or an error if a path is invalid or crosses a non-table value
+    pub fn apply_to(&self, root: &mut Value) -> Result<()> {
+        for change in &self.changes {
+            match &change.new {
+                Some(value) => {
+                    path::insert(root, &change.path, value.clone())?;
+                }
+                None => {
+                    path::remove(root, &change.path);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Returns the changes as a JSON array of `{"path", "old", "new"}` objects, with
+    /// `null` for the old value of added and the new value of removed keys.
+    pub(crate) fn to_json(&self) -> serde_json::Value {
+        self.changes
+            .iter()
+            .map(|c| {
+                serde_json::json!({
+                    "path": c.path,
+                    "old": c.old.as_ref().map_or(serde_json::Value::Null, to_json),
+                    "new": c.new.as_ref().map_or(serde_json::Value::Null, to_json),
+                })
+            })
+            .collect()
+    }
+
+    /// Returns a copy with the old and new values of secret keys replaced by a placeholder,
+    /// including secrets inside changed arrays of tables.
+    pub fn redacted(&self) -> ChangeSet {
+        ChangeSet {
+            changes: self
+                .changes
+                .iter()
+                .map(|c| {
+                    let mask = |v: &Option<Value>| v.as_ref().map(|v| mask_secrets(v, &c.path));
+                    Change {
+                        path: c.path.clone(),
+                        old: mask(&c.old),
+                        new: mask(&c.new),
+                    }
+                })
+                .collect(),
+        }
+    }
+
+    /// Renders the changes as a diff, one key per line, with secrets masked.
+    ///
+    /// Added keys start with `+`, removed keys with `-` and changed keys with `~`; the lines
+    /// are colored if `output` allows it.
+    ///
+    /// # Arguments
+    ///
+    /// * `output` - Where the diff is printed
+    ///
+    /// # Returns
+    ///
+    /// * `String` - The diff, empty if nothing changed
+    pub fn render(&self, output: &OutputContext) -> String {
+        self.redacted()
+            .changes
+            .iter()
+            .map(|c| {
+                let line = match (&c.old, &c.new) {
+                    (None, Some(new)) => {
+                        output.paint(&format!("+ {} = {}", c.path, new), Style::Green)
+                    }
+                    (Some(old), None) => {
+                        output.paint(&format!("- {} = {}", c.path, old), Style::Red)
+                    }
+                    (Some(old), Some(new)) => {
+                        output.paint(&format!("~ {}: {} -> {}", c.path, old, new), Style::Yellow)
+                    }
+                    (None, None) => format!("~ {}", c.path),
+                };
+                line + "\n"
+            })
+            .collect()
+    }
+}
+
+fn diff_into(changes: &mut Vec<Change>, prefix: &str, old: Option<&Value>, new: Option<&Value>) {
+    let join = |key: &str| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        }
+    };
+    match (old, new) {
+        (Some(Value::Table(old)), Some(Value::Table(new))) => {
+            for (key, old_value) in old {
+                diff_into(changes, &join(key), Some(old_value), new.get(key));
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    diff_into(changes, &join(key), None, Some(new_value));
+                }
+            }
+        }
+        (Some(Value::Table(old)), None) => {
+            for (key, old_value) in old {
+                diff_into(changes, &join(key), Some(old_value), None);
+            }
+        }
+        (None, Some(Value::Table(new))) => {
+            for (key, new_value) in new {
+                diff_into(changes, &join(key), None, Some(new_value));
+            }
+        }
+        (old, new) if old != new => changes.push(Change {
+            path: prefix.to_string(),
+            old: old.cloned(),
+            new: new.cloned(),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::secret::SECRET_PLACEHOLDER;
+
+    #[test]
+    fn test_between_reports_leaf_changes() {
+        let old: Value =
+            toml::from_str("[ai]\nmodel = 'a'\napikey = 'x'\n[update]\ntried = 1\n").unwrap();
+        let new: Value = toml::from_str("[ai]\nmodel = 'b'\napikey = 'y'\nurl = 'u'\n").unwrap();
+        let changes = ChangeSet::between(&old, &new);
+        assert_eq!(
+            changes.paths(),
+            vec!["ai.apikey", "ai.model", "ai.url", "update.tried"]
+        );
+        assert_eq!(changes.changes[3].new, None);
+
+        let redacted = changes.redacted();
+        assert_eq!(
+            redacted.changes[0].new,
+            Some(Value::from(SECRET_PLACEHOLDER))
+        );
+        assert_eq!(redacted.changes[1].new, Some(Value::from("b")));
+        assert_eq!(
+            changes.render(&OutputContext::plain()),
+            "~ ai.apikey: \"<redacted>\" -> \"<redacted>\"\n~ ai.model: \"a\" -> \"b\"\n\
+             + ai.url = \"u\"\n- update.tried = 1\n"
+        );
+
+        let old: Value = toml::from_str("[[ai.endpoints]]\nname = 'w'\napikey = 'sk-a'\n").unwrap();
+        let new: Value = toml::from_str("[[ai.endpoints]]\nname = 'w'\napikey = 'sk-b'\n").unwrap();
+        let redacted = ChangeSet::between(&old, &new).redacted();
+        assert_eq!(redacted.paths(), vec!["ai.endpoints"]);
+        let json = redacted.to_json().to_string();
+        assert!(
+            !json.contains("sk-") && json.contains(SECRET_PLACEHOLDER),
+            "{}",
+            json
+        );
+    }
+}
@@ -0,0 +1,124 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+use crate::config::{
+    ensure_persistent, get_config_file, get_config_into_toml, invalidate_document_cache,
+};
+use crate::edit::{comment_of, with_comment};
+use crate::encoding::with_line_endings;
+use crate::format::Format;
+use crate::layout::{key_file, read_file};
+use crate::path;
+use crate::storage::{FileLock, write_atomic};
+
+/// Returns the comment a user or the default document attached to a key.
+///
+/// The comment is the run of `#` lines directly above the key, or above the header of a
+/// table, e.g. `"Model name, e.g. \"gpt-4o\""` for `ai.model` in a fresh file. A key
+/// without one falls back to its trailing comment (`model = "o1"  # pinned`).
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path of a key or table, e.g. `"ai.model"`
+///
+/// # Returns
+///
+/// * `Result<Option<String>>` - The comment without its `#`, one line per comment line,
+///   `None` if the key has none, or a `NotFound` error if the file has no such key
+pub fn get_comment(key_path: &str) -> Result<Option<String>> {
+    let segments = path::split(key_path)?;
+    get_config_into_toml(false)?;
+    let (file, path_in_file) = key_file(&get_config_file()?, key_path)?;
+    ensure_toml(&file)?;
+    let text = read_file(&file)?;
+    let segments_in_file = &segments[segments.len() - path_in_file.split('.').count()..];
+    comment_of(&text, segments_in_file).ok_or_else(|| not_found(key_path, &file))
+}
+
+/// Replaces the comment above a key, leaving every other line of the file untouched.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path of a key or table
+/// * `text` - The new comment; each line becomes a `#` line and an empty text removes the
+///   comment
+///
+/// # Returns
+///
+/// * `Result<()>` - Success, or a `NotFound` error if the file has no such key or an
+///   `Unsupported` error if the configuration isn't stored as TOML
+pub fn set_comment(key_path: &str, text: &str) -> Result<()> {
+    let segments = path::split(key_path)?;
+    ensure_persistent()?;
+    get_config_into_toml(false)?;
+    let config_file = get_config_file()?;
+    let _lock = FileLock::acquire(&config_file)?;
+    let (file, path_in_file) = key_file(&config_file, key_path)?;
+    ensure_toml(&file)?;
+    let existing = read_file(&file)?;
+    let segments_in_file = &segments[segments.len() - path_in_file.split('.').count()..];
+    let edited = with_comment(&existing, segments_in_file, text)
+        .ok_or_else(|| not_found(key_path, &file))?;
+    let edited = with_line_endings(edited, Some(&existing));
+    if edited != existing {
+        write_atomic(&file, edited.as_bytes())?;
+        invalidate_document_cache();
+    }
+    Ok(())
+}
+
+fn ensure_toml(file: &Path) -> Result<()> {
+    if Format::from_path(file)? == Format::Toml {
+        return Ok(());
+    }
+    Err(Error::new(
+        ErrorKind::Unsupported,
+        format!("'{}' has no comments; only TOML files do", file.display()),
+    ))
+}
+
+fn not_found(key_path: &str, file: &Path) -> Error {
+    Error::new(
+        ErrorKind::NotFound,
+        format!("Key '{}' not found in '{}'", key_path, file.display()),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_config_value;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_get_and_set_comment() {
+        let _dir = TempConfigDir::new().unwrap();
+        assert_eq!(
+            get_comment("ai.apikey").unwrap().as_deref(),
+            Some("API key of the provider")
+        );
+        assert!(get_comment("ai").unwrap().is_some());
+
+        set_comment("ai.model", "Pinned for the team\nSee the wiki").unwrap();
+        assert_eq!(
+            get_comment("ai.model").unwrap().as_deref(),
+            Some("Pinned for the team\nSee the wiki")
+        );
+        let text = std::fs::read_to_string(get_config_file().unwrap()).unwrap();
+        assert!(text.contains("# Pinned for the team\n# See the wiki\nmodel = "));
+        assert!(text.contains("# API key of the provider"));
+        assert_eq!(
+            get_config_value("ai", "language").unwrap().as_str(),
+            Some("English")
+        );
+
+        set_comment("ai.model", "").unwrap();
+        assert_eq!(get_comment("ai.model").unwrap(), None);
+        assert_eq!(
+            get_comment("ai.missing").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+    }
+}
@@ -0,0 +1,192 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use toml::Value;
+
+use crate::config::{get_config, get_config_file};
+use crate::init::emit_warning;
+
+/// The top-level key holding the schema version a configuration file was written for.
+pub const CONFIG_VERSION_KEY: &str = "config_version";
+
+/// The schema version this build understands; a file without `config_version` is at 1.
+pub const CONFIG_VERSION: i64 = 1;
+
+static REJECT: AtomicBool = AtomicBool::new(false);
+static DETECTED: Mutex<Vec<NewerSchemaDetected>> = Mutex::new(Vec::new());
+
+/// What happens when a configuration file was written by a newer release.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ForwardCompatibility {
+    /// Read the keys this build knows and keep everything else untouched on save; the
+    /// file is reported through [`check_schema_version`] and the warning hook
+    #[default]
+    Preserve,
+    /// Fail every read with an `InvalidData` error carrying [`NewerSchemaDetected`]
+    Reject,
+}
+
+/// A configuration file whose `config_version` is newer than [`CONFIG_VERSION`].
+///
+/// Returned inside the `InvalidData` error of reads under
+/// [`ForwardCompatibility::Reject`]; use `error.get_ref()` and `downcast_ref` to get it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct NewerSchemaDetected {
+    /// The configuration file
+    pub file: PathBuf,
+    /// The version the file was written for
+    pub found: i64,
+    /// The version this build understands
+    pub supported: i64,
+}
+
+impl fmt::Display for NewerSchemaDetected {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "'{}' was written for config version {}, this build understands up to {}; upgrade \
+             gim to use its new settings",
+            self.file.display(),
+            self.found,
+            self.supported
+        )
+    }
+}
+
+impl std::error::Error for NewerSchemaDetected {}
+
+/// Sets how this process treats configuration files written by a newer release.
+pub fn set_forward_compatibility(mode: ForwardCompatibility) {
+    REJECT.store(mode == ForwardCompatibility::Reject, Ordering::Relaxed);
+}
+
+/// Returns how this process treats configuration files written by a newer release.
+pub fn forward_compatibility() -> ForwardCompatibility {
+    if REJECT.load(Ordering::Relaxed) {
+        ForwardCompatibility::Reject
+    } else {
+        ForwardCompatibility::Preserve
+    }
+}
+
+/// Returns the schema version `config` was written for.
+pub fn schema_version(config: &Value) -> i64 {
+    config
+        .get(CONFIG_VERSION_KEY)
+        .and_then(Value::as_integer)
+        .unwrap_or(CONFIG_VERSION)
+}
+
+/// Returns whether `config` was written by a newer release than this one.
+pub fn is_newer_schema(config: &Value) -> bool {
+    schema_version(config) > CONFIG_VERSION
+}
+
+/// Checks whether the configuration file was written by a newer release.
+///
+/// An older gim sharing the file with a newer one keeps working: it reads the keys it
+/// knows, writes only the keys it changes and never lowers `config_version`. Front-ends
+/// call this at startup to suggest an upgrade.
+///
+/// # Returns
+///
+/// * `Result<Option<NewerSchemaDetected>>` - The newer version, `None` if the file is
+///   current, or an error if it can't be read
+pub fn check_schema_version() -> Result<Option<NewerSchemaDetected>> {
+    let config = match get_config() {
+        Err(e) => match e
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<NewerSchemaDetected>())
+        {
+            Some(detected) => return Ok(Some(detected.clone())),
+            None => return Err(e),
+        },
+        Ok(config) => config,
+    };
+    Ok(newer(&get_config_file()?, &config))
+}
+
+/// Returns the files written by a newer release that this process read, one per file.
+pub fn newer_schemas_detected() -> Vec<NewerSchemaDetected> {
+    DETECTED.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Applies the [`ForwardCompatibility`] mode to a configuration just read from `file`.
+pub(crate) fn check_read(file: &Path, config: &Value) -> Result<()> {
+    let Some(detected) = newer(file, config) else {
+        return Ok(());
+    };
+    if forward_compatibility() == ForwardCompatibility::Reject {
+        return Err(Error::new(ErrorKind::InvalidData, detected));
+    }
+    let mut recorded = DETECTED.lock().unwrap_or_else(|e| e.into_inner());
+    if !recorded.contains(&detected) {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(file = %file.display(), found = detected.found, "newer config schema");
+        emit_warning(&detected.to_string());
+        recorded.retain(|d| d.file != detected.file);
+        recorded.push(detected);
+    }
+    Ok(())
+}
+
+/// Restores the `config_version` of `old` in `new` if a write would lower it, so an older
+/// build never claims a newer file for its own schema.
+pub(crate) fn keep_schema_version(old: &Value, new: &mut Value) {
+    let version = schema_version(old);
+    if version > schema_version(new)
+        && let Some(root) = new.as_table_mut()
+    {
+        root.insert(CONFIG_VERSION_KEY.to_string(), Value::Integer(version));
+    }
+}
+
+fn newer(file: &Path, config: &Value) -> Option<NewerSchemaDetected> {
+    is_newer_schema(config).then(|| NewerSchemaDetected {
+        file: file.to_path_buf(),
+        found: schema_version(config),
+        supported: CONFIG_VERSION,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_value_fast, update_config_value};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_newer_schema_is_read_and_preserved() {
+        let _dir = TempConfigDir::new().unwrap();
+        let file = get_config_file().unwrap();
+        std::fs::write(
+            &file,
+            "config_version = 7\n\n[ai]\nmodel = \"a\"\nreasoning = { effort = \"high\" }\n",
+        )
+        .unwrap();
+
+        assert_eq!(get_value_fast("ai.model").unwrap().as_str(), Some("a"));
+        let detected = check_schema_version().unwrap().unwrap();
+        assert_eq!((detected.found, detected.supported), (7, CONFIG_VERSION));
+        assert!(newer_schemas_detected().contains(&detected));
+
+        update_config_value("ai", "model", Value::from("b")).unwrap();
+        let text = std::fs::read_to_string(&file).unwrap();
+        assert!(text.starts_with("config_version = 7\n"));
+        assert!(text.contains("reasoning = { effort = \"high\" }"));
+        assert!(text.contains("model = \"b\""));
+
+        let mut config = get_config().unwrap();
+        config.as_table_mut().unwrap().remove(CONFIG_VERSION_KEY);
+        crate::config::save_config(&config).unwrap();
+        assert_eq!(schema_version(&get_config().unwrap()), 7);
+    }
+}
@@ -1,28 +1,107 @@
 use std::path::PathBuf;
 use std::{
     fs,
-    io::{Error, ErrorKind, Result, Write as _},
+    io::{Error, ErrorKind, Result},
+    path::Path,
+    sync::{Arc, Mutex},
+    time::SystemTime,
 };
 use toml::{Value, map};
 
-use crate::directory::config_dir;
+use crate::clock::SystemClock;
+use crate::compat::keep_schema_version;
+use crate::cooldown::{check_cooldowns, record_changes};
+use crate::defaults::{
+    default_config_document, default_values, effective_defaults, initial_document,
+    is_default_fallback_enabled,
+};
+use crate::directory::{config_dir, ensure_app_dirs, resolve_config_dir};
+use crate::format::Format;
+use crate::init::{is_read_only, is_silent};
+use crate::interpolate::{expand, has_references, interpolate_each};
+use crate::key_times::{merge_external_edits, record_key_times, remember_base};
+use crate::layout;
+use crate::lazy::{is_lazy, lazy_section_file, lazy_sections, lookup_lazy, resolve_lazy};
+use crate::locks::{
+    apply_system, check_locks, pin_within, pinned_keys, pinned_value, system_config_file, system_value,
+};
+use crate::memory::{in_memory_document, modify_in_memory};
+use crate::notify::notify_change;
+use crate::path;
+use crate::pipeline;
+use crate::policy::enforce_write_policy;
+use crate::profile::{apply_profile, current_env, profile_override};
+use crate::project::{apply_project, project_layer, project_override, project_root};
+use crate::session::{apply_session_overrides, session_override};
+use crate::storage::{FileLock, write_atomic};
+use crate::storm;
+use crate::temporary::{active_override, prune_expired};
+use crate::trace::{self, ValueOrigin};
+
+/// A parsed configuration file together with the metadata it was parsed from.
+struct CachedDocument {
+    file: PathBuf,
+    stamps: Vec<FileStamp>,
+    value: Arc<Value>,
+}
+
+/// The path, modification time and size of one file the document was read from.
+type FileStamp = (PathBuf, SystemTime, u64);
+
+/// The most recently parsed configuration, reused while the file on disk is unchanged.
+static DOCUMENT_CACHE: Mutex<Option<CachedDocument>> = Mutex::new(None);
+
+/// The configuration files this process created with the default configuration.
+static CREATED: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// The environment variable naming the configuration file explicitly, e.g. `/etc/team/gim.json`.
+pub const CONFIG_FILE_ENV: &str = "GIM_CONFIG_FILE";
 
 /// Returns the path to the configuration file.
 ///
-/// This function gets the configuration directory and appends the filename "config.toml".
+/// `GIM_CONFIG_FILE` wins if set. Otherwise this is `config.toml` in the configuration
+/// directory, unless only a `config.json` or `config.yaml` exists there and the matching
+/// feature is enabled. The extension selects the format every API reads and writes.
 ///
 /// # Returns
 ///
 /// * `Result<PathBuf>` - The path to the configuration file or an error
-fn get_config_file() -> Result<PathBuf> {
+pub fn get_config_file() -> Result<PathBuf> {
+    let explicit = std::env::var_os(CONFIG_FILE_ENV)
+        .filter(|file| !file.is_empty())
+        .map(PathBuf::from);
+    #[cfg(any(test, feature = "testing"))]
+    let explicit = explicit.filter(|_| crate::testing::thread_config_dir().is_none());
+    if let Some(file) = explicit {
+        Format::from_path(&file)?;
+        return Ok(file);
+    }
+
     let config_dir = config_dir()?;
     let config_file = config_dir.join("config.toml");
-    Ok(config_file)
+    if config_file.exists() {
+        return Ok(config_file);
+    }
+    let alternatives: &[&str] = &[
+        #[cfg(feature = "json")]
+        "config.json",
+        #[cfg(feature = "yaml")]
+        "config.yaml",
+        #[cfg(feature = "yaml")]
+        "config.yml",
+    ];
+    Ok(alternatives
+        .iter()
+        .map(|name| config_dir.join(name))
+        .find(|file| file.exists())
+        .unwrap_or(config_file))
 }
 
 /// Gets the current configuration.
 ///
 /// This is a convenience function that calls `get_config_into_toml` with logging disabled.
+/// Lazily loaded sections hold their `lazy = true` marker, see
+/// [`crate::lazy::load_lazy_section`].
 ///
 /// # Returns
 ///
@@ -31,7 +110,8 @@ pub fn get_config() -> Result<Value> {
     get_config_into_toml(false)
 }
 
-/// Gets the current configuration and prints the config file path to stdout.
+/// Gets the current configuration and prints the config file path to stdout, unless the
+/// process was initialized silent.
 ///
 /// # Returns
 ///
@@ -51,51 +131,396 @@ pub fn get_config_and_print() -> Result<Value> {
 /// # Returns
 ///
 /// * `Result<Value>` - The configuration as a TOML Value or an error
-fn get_config_into_toml(log_dir: bool) -> Result<Value> {
-    let config_file = get_config_file().expect("Failed to get config file");
+pub(crate) fn get_config_into_toml(log_dir: bool) -> Result<Value> {
+    if let Some(document) = in_memory_document(&get_config_file()?) {
+        return Ok(Value::clone(&document));
+    }
+    if serves_defaults_only()? {
+        return Ok(default_values());
+    }
+    let config_file = ensure_config_file()?;
+    if log_dir && !is_silent() {
+        println!("Config file is {}", config_file.display());
+    }
+    let config = read_config_file(&config_file)?;
+    remember_base(&config_file, &config);
+    Ok(config)
+}
+
+/// Returns the path to the configuration file, creating it with the default configuration
+/// if it doesn't exist.
+pub(crate) fn ensure_config_file() -> Result<PathBuf> {
+    let config_file = get_config_file()?;
     if !config_file.exists() {
-        if let Some(parent) = config_file.parent() {
-            fs::create_dir_all(parent)?;
-        } else {
-            return Err(Error::new(
-                ErrorKind::NotFound,
-                "config directory not found",
-            ));
+        ensure_app_dirs()?;
+        match config_file.parent() {
+            // `GIM_CONFIG_FILE` may name a file outside the app directories.
+            Some(parent) if !parent.is_dir() => fs::create_dir_all(parent)?,
+            Some(_) => {}
+            None => {
+                return Err(Error::new(
+                    ErrorKind::NotFound,
+                    "config directory not found",
+                ));
+            }
         }
-        let mut update_table = map::Map::new();
-        update_table.insert("tried".to_string(), Value::Integer(0));
-        update_table.insert("max_try".to_string(), Value::Integer(5));
-        update_table.insert(
-            "last_try_day".to_string(),
-            Value::String("2000-01-01".to_string()),
-        );
-        update_table.insert("try_interval_days".to_string(), Value::Integer(30));
-
-        let mut ai_table = map::Map::new();
-        ai_table.insert("model".to_string(), Value::String(String::new()));
-        ai_table.insert("apikey".to_string(), Value::String(String::new()));
-        ai_table.insert("url".to_string(), Value::String(String::new()));
-        ai_table.insert("language".to_string(), Value::String("English".to_string()));
-
-        let mut default_content = map::Map::new();
-        default_content.insert("update".to_string(), Value::Table(update_table));
-        default_content.insert("ai".to_string(), Value::Table(ai_table));
-        let default_content = toml::to_string(&Value::Table(default_content))
-            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
-        let mut file = fs::File::create(&config_file)?;
-        file.write_all(default_content.as_bytes())?;
-    }
-    if log_dir {
-        println!("Config file is {}", config_file.display());
+        write_default_config(&config_file)?;
+    }
+    Ok(config_file)
+}
+
+/// Returns whether this process created `config_file` with the default configuration.
+pub(crate) fn was_created_this_run(config_file: &Path) -> bool {
+    CREATED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .any(|file| file == config_file)
+}
+
+/// Writes the default configuration to `config_file` unless another writer created it first.
+///
+/// # Arguments
+///
+/// * `config_file` - The path of the configuration file to create
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if serialization or writing fails
+fn write_default_config(config_file: &Path) -> Result<()> {
+    let _lock = FileLock::acquire(config_file)?;
+    if !config_file.exists() {
+        let format = Format::from_path(config_file)?;
+        let content = match initial_document() {
+            Some(document) => format.serialize(&document)?,
+            None => {
+                let document = default_config_document();
+                if format == Format::Toml {
+                    document.text
+                } else {
+                    format.serialize(&document.value)?
+                }
+            }
+        };
+        write_atomic(config_file, content.as_bytes())?;
+        invalidate_document_cache();
+        CREATED
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(config_file.to_path_buf());
+    }
+    Ok(())
+}
+
+/// Returns whether reads are served from the defaults because no file may be created:
+/// there is no config directory, or the process is read-only and the file is missing.
+fn serves_defaults_only() -> Result<bool> {
+    if resolve_config_dir().is_ephemeral() {
+        return Ok(true);
+    }
+    Ok(is_read_only() && !get_config_file()?.exists())
+}
+
+/// Fails with `PermissionDenied` if the configuration can't be persisted.
+///
+/// This is the case when no home directory, environment variable or fallback directory
+/// is available and the built-in defaults are served read-only, when the process was
+/// initialized read-only through [`crate::init::Options::read_only`], or when the
+/// configuration was read by [`Config::from_reader`] and the file stays untouched.
+pub(crate) fn ensure_persistent() -> Result<()> {
+    if in_memory_document(&get_config_file()?).is_some() {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "The configuration was read from a stream; the config file is never written",
+        ));
+    }
+    if is_read_only() {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "The configuration was opened read-only; changes cannot be saved",
+        ));
+    }
+    if resolve_config_dir().is_ephemeral() {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "No config directory available (HOME is unset); changes cannot be saved",
+        ));
+    }
+    Ok(())
+}
+
+/// Creates the configuration file with the given document text.
+///
+/// # Arguments
+///
+/// * `content` - The TOML text to write; it must parse and is converted if the file uses
+///   another format
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an `AlreadyExists` error if a configuration file exists
+pub(crate) fn create_config(content: &str) -> Result<()> {
+    let value = Format::Toml.parse(content)?;
+    ensure_persistent()?;
+    let config_file = get_config_file()?;
+    if let Some(parent) = config_file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let _lock = FileLock::acquire(&config_file)?;
+    if config_file.exists() {
+        return Err(Error::new(
+            ErrorKind::AlreadyExists,
+            format!("Config file '{}' already exists", config_file.display()),
+        ));
+    }
+    let format = Format::from_path(&config_file)?;
+    if format == Format::Toml {
+        write_atomic(&config_file, content.as_bytes())?;
+    } else {
+        write_atomic(&config_file, format.serialize(&value)?.as_bytes())?;
+    }
+    invalidate_document_cache();
+    Ok(())
+}
+
+/// Reads and parses the configuration file at `config_file`.
+///
+/// With the split layout the section files next to it are read as well. String values are
+/// normalized if [`crate::normalize::enable_string_normalization`] was called.
+///
+/// # Arguments
+///
+/// * `config_file` - The path of the configuration file
+///
+/// # Returns
+///
+/// * `Result<Value>` - The parsed configuration or an error
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "gim_config.load",
+        level = "debug",
+        skip_all,
+        err,
+        fields(file = %config_file.display())
+    )
+)]
+fn read_config_file(config_file: &Path) -> Result<Value> {
+    if let Some(pending) = storm::pending_document(config_file) {
+        return Ok(pending);
     }
-    let content = fs::read_to_string(&config_file)?;
-    let config: Value =
-        toml::from_str(&content).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    let mut config = layout::read_document(config_file)?;
+    pipeline::run_read(config_file, &mut config)?;
     Ok(config)
 }
 
+/// Serializes `config` and atomically replaces the file at `config_file`.
+///
+/// Expired temporary overrides are pruned from the written document, which then passes
+/// through the stages of [`crate::pipeline`] in reverse. With the split layout each changed
+/// section is written to its own file. Callers are expected to hold the file lock.
+///
+/// # Arguments
+///
+/// * `config_file` - The path of the configuration file
+/// * `config` - The configuration Value to write
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if serialization or writing fails
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(
+        name = "gim_config.save",
+        level = "debug",
+        skip_all,
+        err,
+        fields(file = %config_file.display())
+    )
+)]
+fn write_config_file(config_file: &Path, config: &Value) -> Result<()> {
+    let mut config = config.clone();
+    prune_expired(&mut config, SystemTime::now());
+    pipeline::run_write(config_file, &mut config)?;
+    let result = layout::write_document(config_file, &config);
+    if result.is_ok() {
+        storm::discard_pending(config_file);
+    }
+    invalidate_document_cache();
+    result
+}
+
+/// Drops the cached document so the next fast read re-parses the file.
+pub(crate) fn invalidate_document_cache() {
+    *DOCUMENT_CACHE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Returns the parsed configuration, re-using the cached document if the file is unchanged.
+///
+/// The cache is keyed by the path, modification time and size of every file the document
+/// is read from, and is dropped whenever this crate writes the configuration.
+///
+/// # Returns
+///
+/// * `Result<Arc<Value>>` - The shared parsed configuration or an error
+fn cached_document() -> Result<Arc<Value>> {
+    if let Some(document) = in_memory_document(&get_config_file()?) {
+        return Ok(document);
+    }
+    if serves_defaults_only()? {
+        return Ok(Arc::new(default_values()));
+    }
+    let config_file = get_config_file()?;
+    if let Some(pending) = storm::pending_document(&config_file) {
+        return Ok(Arc::new(pending));
+    }
+    if !config_file.exists() {
+        get_config_into_toml(false)?;
+    }
+    let stamps = layout::document_files(&config_file)?
+        .into_iter()
+        .map(|file| {
+            let metadata = fs::metadata(&file)?;
+            Ok((file, metadata.modified()?, metadata.len()))
+        })
+        .collect::<Result<Vec<FileStamp>>>()?;
+
+    let mut cache = DOCUMENT_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(cached) = cache.as_ref()
+        && cached.file == config_file
+        && cached.stamps == stamps
+    {
+        return Ok(Arc::clone(&cached.value));
+    }
+
+    let value = Arc::new(read_config_file(&config_file)?);
+    remember_base(&config_file, &value);
+    *cache = Some(CachedDocument {
+        file: config_file,
+        stamps,
+        value: Arc::clone(&value),
+    });
+    Ok(value)
+}
+
+/// Retrieves a single value by dotted key path without re-parsing an unchanged file.
+///
+/// Repeated lookups within one process share a cached parse of the configuration, so
+/// reading several settings costs one parse plus a clone of each requested value.
+/// An unexpired temporary override of the key takes precedence over its stored value.
+/// References to other keys such as `"${ai.url}/v1"` are expanded, see
+/// [`crate::interpolate::interpolate`]; [`get_raw_value`] returns the value as written.
+/// The value of a secret key is an ordinary copy, see [`crate::secret::Secret`].
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path, e.g. `"ai.model"`
+///
+/// # Returns
+///
+/// * `Result<Value>` - The requested value or an error if it doesn't exist or one of its
+///   references can't be expanded
+pub fn get_value_fast(key_path: &str) -> Result<Value> {
+    let document = cached_document()?;
+    let value = lookup_fast(&document, key_path)?;
+    expand(key_path, value, &|target| lookup_fast(&document, target))
+}
+
+/// Retrieves a single value by dotted key path like [`get_value_fast`], but without
+/// expanding its references to other keys.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path, e.g. `"ai.model"`
+///
+/// # Returns
+///
+/// * `Result<Value>` - The value as written or an error if it doesn't exist
+pub fn get_raw_value(key_path: &str) -> Result<Value> {
+    let document = cached_document()?;
+    lookup_fast(&document, key_path)
+}
+
+/// Looks up `key_path` in `document`, an override of it first, and records the read.
+fn lookup_fast(document: &Value, key_path: &str) -> Result<Value> {
+    lookup_traced(document, key_path, || lookup_stored(document, key_path))
+}
+
+/// Resolves `key_path` from an override, `stored` or the default, and records the read
+/// with the layer that served it.
+fn lookup_traced(
+    config: &Value,
+    key_path: &str,
+    stored: impl FnOnce() -> Result<Value>,
+) -> Result<Value> {
+    let system = || ValueOrigin::System(system_config_file());
+    let (result, origin) = match pinned_value(key_path) {
+        Some(result) => (result, Some(system())),
+        None => match resolve_override(config, key_path) {
+            Some((result, origin)) => (result, Some(origin)),
+            None => match stored() {
+                Err(e) if e.kind() == ErrorKind::NotFound => match system_value(key_path) {
+                    Some(result) => (result, Some(system())),
+                    None => (Err(e), None),
+                },
+                result => (result, None),
+            },
+        },
+    };
+    let result = result.and_then(|mut value| {
+        pin_within(key_path, &mut value)?;
+        Ok(value)
+    });
+    let found = result.is_ok();
+    let result = or_default(result, key_path);
+    trace::record_read(key_path, || match (&result, found) {
+        (Err(_), _) => None,
+        (Ok(_), false) => Some(ValueOrigin::Default),
+        (Ok(_), true) => origin.or_else(|| stored_origin(config, key_path).ok()),
+    });
+    result
+}
+
+/// Returns the file the stored value of `key_path` is read from.
+fn stored_origin(config: &Value, key_path: &str) -> Result<ValueOrigin> {
+    let section = key_path.split('.').next().unwrap_or(key_path);
+    if is_lazy(config, section) {
+        return Ok(ValueOrigin::File(lazy_section_file(section)?));
+    }
+    Ok(ValueOrigin::File(get_config_file()?))
+}
+
+/// Retrieves several values by dotted key path from a single parse of the configuration.
+///
+/// Each key is resolved like [`get_value_fast`], and a missing key only fails its own entry.
+///
+/// # Arguments
+///
+/// * `key_paths` - The dotted key paths, e.g. `&["ai.model", "update.max_try"]`
+///
+/// # Returns
+///
+/// * `Result<Vec<Result<Value>>>` - One result per key in the given order, or an error if
+///   the configuration can't be read at all
+pub fn get_many(key_paths: &[&str]) -> Result<Vec<Result<Value>>> {
+    let document = cached_document()?;
+    Ok(key_paths
+        .iter()
+        .map(|key_path| {
+            let value = lookup_fast(&document, key_path)?;
+            expand(key_path, value, &|target| lookup_fast(&document, target))
+        })
+        .collect())
+}
+
 /// Retrieves a specific value from the configuration.
 ///
+/// An unexpired temporary override, the active `GIM_ENV` profile or the project
+/// configuration of the repository (see [`crate::project`]) takes precedence over the
+/// stored value. A key the file lacks yields its default, see
+/// [`crate::defaults::set_default_fallback`]. References to other keys are expanded. The
+/// value of a secret key is an ordinary copy, see [`crate::secret::Secret`].
+///
 /// # Arguments
 ///
 /// * `section` - The section name in the configuration
@@ -106,6 +531,65 @@ fn get_config_into_toml(log_dir: bool) -> Result<Value> {
 /// * `Result<Value>` - The requested value or an error if the section or key doesn't exist
 pub fn get_config_value(section: &str, key: &str) -> Result<Value> {
     let config = get_config()?;
+    let key_path = format!("{}.{}", section, key);
+    let value = lookup_traced(&config, &key_path, || {
+        lookup_lazy(&config, &key_path).unwrap_or_else(|| lookup_section_key(&config, section, key))
+    })?;
+    expand(&key_path, value, &|target| {
+        let result = match resolve_override(&config, target) {
+            Some((result, _)) => result,
+            None => lookup_stored(&config, target),
+        };
+        or_default(result, target)
+    })
+}
+
+/// Replaces a `NotFound` error with the default of `key_path`, if the fallback is enabled
+/// and the key has a default.
+fn or_default(result: Result<Value>, key_path: &str) -> Result<Value> {
+    match result {
+        Err(e) if e.kind() == ErrorKind::NotFound && is_default_fallback_enabled() => {
+            path::lookup(&effective_defaults(), key_path)
+                .cloned()
+                .ok_or(e)
+        }
+        other => other,
+    }
+}
+
+/// Returns the value overriding `key_path` and the layer it comes from, if any.
+///
+/// A session override wins over an unexpired temporary override, which wins over the
+/// active `GIM_ENV` profile, which wins over the project configuration.
+fn resolve_override(config: &Value, key_path: &str) -> Option<(Result<Value>, ValueOrigin)> {
+    if let Some(value) = session_override(key_path) {
+        let result = value.ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!("Key '{}' not found in the session override", key_path),
+            )
+        });
+        return Some((result, ValueOrigin::Session));
+    }
+    if let Some(value) = active_override(config, key_path, SystemTime::now()) {
+        return Some((Ok(value.clone()), ValueOrigin::Temporary));
+    }
+    if let Some(result) = profile_override(config, key_path) {
+        let name = current_env().unwrap_or_default();
+        return Some((result, ValueOrigin::Profile(name)));
+    }
+    let result = project_override(key_path)?;
+    let root = project_root().ok().flatten().unwrap_or_default();
+    Some((result, ValueOrigin::Project(root)))
+}
+
+/// Looks up `key_path` in `config`, loading its section if it is lazily loaded.
+fn lookup_stored(config: &Value, key_path: &str) -> Result<Value> {
+    lookup_lazy(config, key_path).unwrap_or_else(|| path::require(config, key_path).cloned())
+}
+
+/// Looks up `key` in the table `section` of `config`.
+fn lookup_section_key(config: &Value, section: &str, key: &str) -> Result<Value> {
     let section_table = config
         .get(section)
         .ok_or_else(|| {
@@ -130,12 +614,13 @@ pub fn get_config_value(section: &str, key: &str) -> Result<Value> {
                 format!("Key '{}' not found in section '{}'", key, section),
             )
         })
-        .map(|v| v.clone())
+        .cloned()
 }
 
 /// Updates a specific value in the configuration.
 ///
 /// If the value is the same as the existing one, no update is performed.
+/// The read-modify-write cycle holds the config file lock so concurrent updates are not lost.
 ///
 /// # Arguments
 ///
@@ -145,38 +630,115 @@ pub fn get_config_value(section: &str, key: &str) -> Result<Value> {
 ///
 /// # Returns
 ///
-/// * `Result<()>` - Success or an error if the section doesn't exist or saving fails
+/// * `Result<()>` - Success or an error if the section doesn't exist, the key is locked
+///   or saving fails
 pub fn update_config_value(section: &str, key: &str, value: Value) -> Result<()> {
-    let mut config = get_config_into_toml(false)?;
-    let section_table = config
-        .get_mut(section)
-        .ok_or_else(|| {
-            Error::new(
-                ErrorKind::NotFound,
-                format!("Section '{}' not found", section),
-            )
-        })?
-        .as_table_mut()
-        .ok_or_else(|| {
-            Error::new(
-                ErrorKind::InvalidData,
-                format!("Section '{}' is not a table", section),
-            )
-        })?;
+    modify_config(|config| {
+        let section_table = config
+            .get_mut(section)
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::NotFound,
+                    format!("Section '{}' not found", section),
+                )
+            })?
+            .as_table_mut()
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Section '{}' is not a table", section),
+                )
+            })?;
 
-    if let Some(existing_value) = section_table.get(key) {
-        if existing_value == &value {
+        if let Some(existing_value) = section_table.get(key)
+            && existing_value == &value
+        {
             return Ok(());
         }
+
+        section_table.insert(key.to_string(), value);
+        Ok(())
+    })
+}
+
+/// Runs a locked read-modify-write cycle on the configuration file.
+///
+/// The file is only rewritten if `f` actually changed the configuration. The changes are
+/// submitted to the write policy (see [`crate::policy`]) and the write fails if it changes
+/// a locked key (see [`crate::locks`]). Change listeners (see [`crate::notify`]) fire
+/// after the lock is released.
+///
+/// # Arguments
+///
+/// * `f` - Modifies the freshly read configuration in place
+///
+/// # Returns
+///
+/// * `Result<T>` - Whatever `f` returned, or an error if reading, `f` or writing failed
+pub(crate) fn modify_config<T>(f: impl FnOnce(&mut Value) -> Result<T>) -> Result<T> {
+    if in_memory_document(&get_config_file()?).is_some() {
+        return capture_write(f);
     }
+    ensure_persistent()?;
+    get_config_into_toml(false)?;
+    let config_file = get_config_file()?;
+    let lock = FileLock::acquire(&config_file)?;
+    let original = read_config_file(&config_file)?;
+    let mut config = original.clone();
+    let result = f(&mut config)?;
+    if config != original {
+        let committed = commit_write(&config_file, &original, config)?;
+        drop(lock);
+        if let Some((old, written)) = committed {
+            notify_change(&old, &written);
+        }
+    }
+    Ok(result)
+}
 
-    section_table.insert(key.to_string(), value);
-    save_config(&config)?;
+/// Saves `value` as the lazily loaded `section`, see [`crate::lazy::save_lazy_section`].
+///
+/// The write policy, the key locks and the cooldowns see the section's contents rather
+/// than its marker, and so do the change listeners. `write_side` stores the section,
+/// possibly rewritten by the policy, in its side file once the checks passed; then the
+/// marker is written to `config.toml`.
+pub(crate) fn commit_lazy_section(
+    section: &str,
+    value: &Value,
+    marker: &Value,
+    write_side: impl FnOnce(&Value) -> Result<()>,
+) -> Result<()> {
+    ensure_persistent()?;
+    get_config_into_toml(false)?;
+    let config_file = get_config_file()?;
+    let lock = FileLock::acquire(&config_file)?;
+    let original = read_config_file(&config_file)?;
+    let mut old = original.clone();
+    resolve_lazy(&mut old)?;
+    let mut new = old.clone();
+    path::insert(&mut new, section, value.clone())?;
+    let new = enforce_write_policy(&old, &new)?.unwrap_or(new);
+    check_locks(&old, &new, config_file.clone())?;
+    let limited = check_cooldowns(&old, &new, &[], &SystemClock)?;
+    let stored = new.get(section).cloned().unwrap_or_else(|| value.clone());
+    write_side(&stored)?;
+    let mut marked = original.clone();
+    path::insert(&mut marked, section, marker.clone())?;
+    if marked != original {
+        write_config_file(&config_file, &marked)?;
+    }
+    // Like for a regular write, a state file that can't be updated only loses the timestamp.
+    let _ = record_changes(&limited, &SystemClock);
+    drop(lock);
+    notify_change(&old, &new);
     Ok(())
 }
 
 /// Saves the provided configuration to the config file.
 ///
+/// The changes are submitted to the write policy, and the save fails with
+/// `PermissionDenied` if the policy denies them or they change a locked key.
+///
 /// # Arguments
 ///
 /// * `config` - The configuration Value to save
@@ -185,16 +747,281 @@ pub fn update_config_value(section: &str, key: &str, value: Value) -> Result<()>
 ///
 /// * `Result<()>` - Success or an error if serialization or writing fails
 pub fn save_config(config: &Value) -> Result<()> {
-    let updated_content =
-        toml::to_string(config).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
-    let config_dir = get_config_file()?;
-    fs::write(&config_dir, updated_content)?;
+    save(config, true)
+}
+
+/// Replaces the configuration with `config` like [`save_config`], but without merging in
+/// the edits made to the file since it was read, e.g. to restore a snapshot.
+pub(crate) fn replace_config(config: &Value) -> Result<()> {
+    save(config, false)
+}
+
+fn save(config: &Value, merge: bool) -> Result<()> {
+    if in_memory_document(&get_config_file()?).is_some() {
+        return capture_write(|document| {
+            *document = config.clone();
+            Ok(())
+        });
+    }
+    ensure_persistent()?;
+    let config_file = get_config_file()?;
+    let lock = FileLock::acquire(&config_file)?;
+    if !config_file.exists() {
+        return write_config_file(&config_file, config);
+    }
+    let original = read_config_file(&config_file)?;
+    let config = if merge {
+        merge_external_edits(&config_file, &original, config.clone())?
+    } else {
+        config.clone()
+    };
+    let committed = commit_write(&config_file, &original, config)?;
+    drop(lock);
+    if let Some((old, written)) = committed {
+        notify_change(&old, &written);
+    }
     Ok(())
 }
 
+/// Applies a write to the document read by [`Config::from_reader`] instead of the file.
+///
+/// The write is submitted to the policy and the key locks like a write of the file;
+/// cooldowns aren't recorded, since they live in the state file.
+fn capture_write<T>(f: impl FnOnce(&mut Value) -> Result<T>) -> Result<T> {
+    if is_read_only() {
+        return Err(Error::new(
+            ErrorKind::PermissionDenied,
+            "The configuration was read from a stream read-only; changes cannot be kept",
+        ));
+    }
+    let config_file = get_config_file()?;
+    let commit = |original: &Value, mut config: Value| {
+        keep_schema_version(original, &mut config);
+        let config = enforce_write_policy(original, &config)?.unwrap_or(config);
+        check_locks(original, &config, config_file.clone())?;
+        Ok(config)
+    };
+    let (original, written, result) = modify_in_memory(&config_file, f, commit)?;
+    notify_change(&original, &written);
+    Ok(result)
+}
+
+/// Submits a write to the policy, the key locks and the cooldowns, then writes the file.
+///
+/// Callers are expected to hold the file lock and to fire the change listeners after
+/// releasing it.
+///
+/// During a write storm (see [`crate::storm`]) the save is deferred, and the key times and
+/// cooldowns of the deferred writes are recorded and announced once it is flushed.
+///
+/// # Returns
+///
+/// * `Result<Option<(Value, Value)>>` - What the file held before and the configuration
+///   that was written, after any policy rewrite, or `None` if the save was deferred
+fn commit_write(
+    config_file: &Path,
+    original: &Value,
+    mut config: Value,
+) -> Result<Option<(Value, Value)>> {
+    keep_schema_version(original, &mut config);
+    let config = enforce_write_policy(original, &config)?.unwrap_or(config);
+    check_locks(original, &config, config_file.to_path_buf())?;
+    let deferred = storm::pending_base(config_file);
+    // The deferred writes haven't recorded their cooldowns yet.
+    let recent = deferred.as_ref().map_or(&[][..], |(_, paths)| paths);
+    let mut limited = check_cooldowns(original, &config, recent, &SystemClock)?;
+    if storm::is_storming(config_file) {
+        // A runaway caller; the file is saved periodically until the writes slow down.
+        // Reads see the deferred document, so it's kept as the application wrote it.
+        let mut pruned = config;
+        prune_expired(&mut pruned, SystemTime::now());
+        storm::defer(config_file, original, pruned, limited);
+        invalidate_document_cache();
+        return Ok(None);
+    }
+    match &deferred {
+        // Other processes may have saved the file since the deferred writes began.
+        Some((base, _)) => {
+            write_config_file(config_file, &storm::onto_current(config_file, base, &config)?)?
+        }
+        None => write_config_file(config_file, &config)?,
+    }
+    // This save supersedes the deferred one, so it records the deferred writes as well.
+    let original = match deferred {
+        Some((base, paths)) => {
+            for path in paths {
+                if !limited.contains(&path) {
+                    limited.push(path);
+                }
+            }
+            base
+        }
+        None => original.clone(),
+    };
+    // The write already succeeded; a state file that can't be updated only loses the
+    // timestamp, which at worst lets the next change through early or loses a merge.
+    let _ = record_changes(&limited, &SystemClock);
+    let _ = record_key_times(config_file, &original, &config);
+    Ok(Some((original, config)))
+}
+
+/// A handle to a parsed configuration that hands out borrowed views of its values.
+///
+/// Unlike the free functions, accessors on the handle never clone `Value`s, which keeps
+/// allocations down when a command reads many settings at startup.
+#[derive(Debug, Clone)]
+pub struct Config {
+    document: Arc<Value>,
+    raw: Arc<Value>,
+    file: PathBuf,
+    session: Vec<String>,
+    pinned: Vec<String>,
+}
+
+impl Config {
+    /// Loads the configuration, creating the default file if it doesn't exist.
+    ///
+    /// The handle shares the parsed document cached by [`get_value_fast`], so loading an
+    /// unchanged file twice parses it only once. The project configuration and the
+    /// overrides of the `GIM_ENV` profile and of the session are merged into the handle's
+    /// document, lazily loaded sections are read from their side files, and references to
+    /// other keys are expanded like [`get_value_fast`] does; [`Config::get_raw`] returns a
+    /// value as written.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Config>` - The loaded handle or an error
+    pub fn load() -> Result<Config> {
+        let mut document = cached_document()?;
+        if project_layer()?.is_some() || current_env().is_some() {
+            let mut merged = Value::clone(&document);
+            apply_project(&mut merged)?;
+            if let Some(name) = current_env() {
+                apply_profile(&mut merged, &name)?;
+            }
+            document = Arc::new(merged);
+        }
+        if !lazy_sections(&document).is_empty() {
+            resolve_lazy(Arc::make_mut(&mut document))?;
+        }
+        let session = apply_session_overrides(&mut document)?;
+        let pinned = pinned_keys()?;
+        if system_config_file().exists() {
+            apply_system(Arc::make_mut(&mut document))?;
+        }
+        let expanded = if has_references(&document) {
+            Arc::new(interpolate_each(&document))
+        } else {
+            Arc::clone(&document)
+        };
+        Ok(Config {
+            document: expanded,
+            raw: document,
+            file: get_config_file()?,
+            session,
+            pinned,
+        })
+    }
+
+    /// Returns the whole configuration document.
+    pub fn value(&self) -> &Value {
+        &self.document
+    }
+
+    /// Returns the path of the file the configuration was loaded from.
+    pub fn file(&self) -> &Path {
+        &self.file
+    }
+
+    /// Returns the value at a dotted key path such as `"ai.model"`.
+    ///
+    /// An unexpired temporary override of the key takes precedence over its stored value,
+    /// unless the key is overridden for the session. References to other keys are expanded;
+    /// a value whose references can't be expanded is returned as written.
+    pub fn get(&self, key_path: &str) -> Option<&Value> {
+        self.lookup(&self.document, key_path)
+    }
+
+    /// Returns the value at a dotted key path like [`Config::get`], but without expanding
+    /// its references to other keys.
+    pub fn get_raw(&self, key_path: &str) -> Option<&Value> {
+        self.lookup(&self.raw, key_path)
+    }
+
+    fn lookup<'a>(&self, document: &'a Value, key_path: &str) -> Option<&'a Value> {
+        let covered = |paths: &[String]| {
+            paths.iter().any(|overridden| {
+                key_path == overridden
+                    || key_path
+                        .strip_prefix(overridden.as_str())
+                        .is_some_and(|rest| rest.starts_with('.'))
+            })
+        };
+        let pinned = covered(&self.pinned);
+        let in_session = !pinned && covered(&self.session);
+        let temporary = if pinned || in_session {
+            None
+        } else {
+            active_override(document, key_path, SystemTime::now())
+        };
+        let value = temporary.or_else(|| path::lookup(document, key_path));
+        trace::record_read(key_path, || {
+            value?;
+            Some(if pinned {
+                ValueOrigin::System(system_config_file())
+            } else if in_session {
+                ValueOrigin::Session
+            } else if temporary.is_some() {
+                ValueOrigin::Temporary
+            } else if let Some(name) = current_env()
+                && profile_override(&self.raw, key_path).is_some()
+            {
+                ValueOrigin::Profile(name)
+            } else if project_override(key_path).is_some() {
+                ValueOrigin::Project(project_root().ok().flatten().unwrap_or_default())
+            } else {
+                ValueOrigin::File(self.file.clone())
+            })
+        });
+        value
+    }
+
+    /// Returns the string at a dotted key path, or `None` if it is missing or not a string.
+    ///
+    /// Secrets are served like any other string and aren't wiped, see
+    /// [`crate::secret::Secret`].
+    pub fn get_str(&self, key_path: &str) -> Option<&str> {
+        self.get(key_path)?.as_str()
+    }
+
+    /// Returns the integer at a dotted key path, or `None` if it is missing or not an integer.
+    pub fn get_integer(&self, key_path: &str) -> Option<i64> {
+        self.get(key_path)?.as_integer()
+    }
+
+    /// Returns the float at a dotted key path, or `None` if it is missing or not a float.
+    pub fn get_float(&self, key_path: &str) -> Option<f64> {
+        self.get(key_path)?.as_float()
+    }
+
+    /// Returns the boolean at a dotted key path, or `None` if it is missing or not a boolean.
+    pub fn get_bool(&self, key_path: &str) -> Option<bool> {
+        self.get(key_path)?.as_bool()
+    }
+
+    /// Returns the table at a dotted key path, or `None` if it is missing or not a table.
+    pub fn get_table(&self, key_path: &str) -> Option<&map::Map<String, Value>> {
+        self.get(key_path)?.as_table()
+    }
+}
+
 #[cfg(test)]
 mod tests {
-    use crate::config::get_config;
+    use crate::config::{
+        Config, get_config, get_config_value, get_many, get_value_fast, update_config_value,
+    };
+    use crate::testing::TempConfigDir;
+    use toml::Value;
 
     #[test]
     fn test_ensure_config_file_exists_creates_file() {
@@ -211,4 +1038,60 @@ mod tests {
         assert!(ai_table.contains_key("language"), "Missing language field");
         print!("{:?}", parsed)
     }
+
+    #[test]
+    fn test_get_value_fast_sees_updates() {
+        let _dir = TempConfigDir::new().unwrap();
+        assert_eq!(
+            get_value_fast("ai.language").unwrap(),
+            Value::from("English")
+        );
+
+        update_config_value("ai", "language", Value::from("German")).unwrap();
+        assert_eq!(
+            get_value_fast("ai.language").unwrap(),
+            Value::from("German")
+        );
+        assert!(get_value_fast("ai.missing").is_err());
+    }
+
+    #[test]
+    fn test_get_many_reads_each_key() {
+        let _dir = TempConfigDir::new().unwrap();
+        let values = get_many(&["ai.language", "ai.missing", "update.max_try"]).unwrap();
+        assert_eq!(values[0].as_ref().unwrap(), &Value::from("English"));
+        assert!(values[1].is_err());
+        assert_eq!(values[2].as_ref().unwrap(), &Value::Integer(5));
+    }
+
+    #[test]
+    fn test_missing_keys_fall_back_to_defaults_without_writing() {
+        let dir = TempConfigDir::new().unwrap();
+        let file = dir.path().join("config.toml");
+        std::fs::write(&file, "[ai]\nmodel = \"gpt-4o\"\n").unwrap();
+        assert_eq!(
+            get_config_value("update", "timezone").unwrap().as_str(),
+            Some("system")
+        );
+        assert_eq!(
+            get_value_fast("ai.language").unwrap().as_str(),
+            Some("English")
+        );
+        assert!(get_value_fast("ai.unknown").is_err());
+        assert_eq!(
+            std::fs::read_to_string(&file).unwrap(),
+            "[ai]\nmodel = \"gpt-4o\"\n"
+        );
+    }
+
+    #[test]
+    fn test_config_handle_borrows_values() {
+        let _dir = TempConfigDir::new().unwrap();
+        let config = Config::load().unwrap();
+        assert_eq!(config.get_str("ai.language"), Some("English"));
+        assert_eq!(config.get_integer("update.max_try"), Some(5));
+        assert_eq!(config.get_str("update.max_try"), None);
+        assert!(config.get_table("ai").is_some());
+        assert!(config.get("ai.missing").is_none());
+    }
 }
@@ -0,0 +1,259 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+    sync::RwLock,
+    time::{Duration, UNIX_EPOCH},
+};
+use toml::{Value, map};
+
+use crate::change::ChangeSet;
+use crate::clock::Clock;
+use crate::defaults::builtin_defaults;
+use crate::path;
+use crate::state::{modify_state, read_state};
+
+/// The section of the state file recording when each rate-limited key last changed.
+pub const COOLDOWN_SECTION: &str = "cooldown";
+
+/// Cooldowns registered by the embedding application, see [`set_cooldown`].
+static COOLDOWNS: RwLock<Vec<(String, Duration)>> = RwLock::new(Vec::new());
+
+/// The error payload of a write changing a key again before its cooldown passed.
+///
+/// It is returned inside an `io::Error` of kind `WouldBlock`; use
+/// `error.get_ref().and_then(|e| e.downcast_ref::<TooSoon>())` to inspect it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct TooSoon {
+    /// The rate-limited key path
+    pub path: String,
+    /// How long until the key may change again
+    pub retry_after: Duration,
+}
+
+impl fmt::Display for TooSoon {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Key '{}' changed too recently; retry in {} s",
+            self.path,
+            self.retry_after.as_secs().max(1)
+        )
+    }
+}
+
+impl std::error::Error for TooSoon {}
+
+/// Limits how often a key may change, e.g. `update.tried` once per hour.
+///
+/// Keys of the default document declare their cooldown with
+/// [`DefaultConfigBuilder::cooldown`](crate::defaults::DefaultConfigBuilder::cooldown);
+/// this registers one for any other key, such as a counter. A cooldown on a table covers
+/// every key below it. Writes changing the key sooner fail with [`TooSoon`].
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path
+/// * `cooldown` - The minimum time between two changes, or `None` to remove the limit
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if the path is invalid
+pub fn set_cooldown(key_path: &str, cooldown: Option<Duration>) -> Result<()> {
+    path::split(key_path)?;
+    let mut cooldowns = COOLDOWNS.write().unwrap_or_else(|e| e.into_inner());
+    cooldowns.retain(|(path, _)| path != key_path);
+    if let Some(cooldown) = cooldown {
+        cooldowns.push((key_path.to_string(), cooldown));
+    }
+    Ok(())
+}
+
+/// Returns the registered and declared cooldowns, registered ones first.
+fn cooldowns() -> Vec<(String, Duration)> {
+    let mut cooldowns = COOLDOWNS.read().unwrap_or_else(|e| e.into_inner()).clone();
+    cooldowns.extend(
+        builtin_defaults()
+            .keys()
+            .iter()
+            .filter_map(|key| key.cooldown.map(|cooldown| (key.path.clone(), cooldown))),
+    );
+    cooldowns
+}
+
+/// Returns the cooldown limiting `key_path` and the path it was declared for.
+fn cooldown_for(cooldowns: &[(String, Duration)], key_path: &str) -> Option<(String, Duration)> {
+    cooldowns
+        .iter()
+        .find(|(path, _)| {
+            key_path == path
+                || key_path
+                    .strip_prefix(path.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+        .cloned()
+}
+
+/// Returns how long until `key_path` may change again.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path
+/// * `clock` - The current time
+///
+/// # Returns
+///
+/// * `Result<Option<Duration>>` - The remaining cooldown, `None` if the key may change
+///   now, or an error if the state file can't be read
+pub fn retry_after(key_path: &str, clock: &dyn Clock) -> Result<Option<Duration>> {
+    let Some((path, cooldown)) = cooldown_for(&cooldowns(), key_path) else {
+        return Ok(None);
+    };
+    remaining(&read_state()?, &path, cooldown, clock)
+}
+
+fn remaining(
+    state: &Value,
+    path: &str,
+    cooldown: Duration,
+    clock: &dyn Clock,
+) -> Result<Option<Duration>> {
+    let Some(changed) = state
+        .get(COOLDOWN_SECTION)
+        .and_then(|section| section.get(path))
+        .and_then(Value::as_integer)
+    else {
+        return Ok(None);
+    };
+    let now = unix_seconds(clock)?;
+    let elapsed = Duration::from_secs(now.saturating_sub(changed).max(0) as u64);
+    Ok(cooldown.checked_sub(elapsed).filter(|left| !left.is_zero()))
+}
+
+fn unix_seconds(clock: &dyn Clock) -> Result<i64> {
+    clock
+        .now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Fails with [`TooSoon`] if going from `old` to `new` changes a key whose cooldown hasn't
+/// passed.
+///
+/// # Arguments
+///
+/// * `old` - The configuration before the write
+/// * `new` - The configuration after the write
+/// * `recent` - Cooldown paths changed by earlier writes whose change isn't recorded yet;
+///   they count as changed just now
+/// * `clock` - The current time
+///
+/// # Returns
+///
+/// * `Result<Vec<String>>` - The cooldown paths the write changes, to pass to
+///   [`record_changes`] once it succeeded
+pub(crate) fn check_cooldowns(
+    old: &Value,
+    new: &Value,
+    recent: &[String],
+    clock: &dyn Clock,
+) -> Result<Vec<String>> {
+    let cooldowns = cooldowns();
+    if cooldowns.is_empty() {
+        return Ok(Vec::new());
+    }
+    let mut limited: Vec<(String, Duration)> = Vec::new();
+    for change in ChangeSet::between(old, new).changes {
+        if let Some(cooldown) = cooldown_for(&cooldowns, &change.path)
+            && !limited.contains(&cooldown)
+        {
+            limited.push(cooldown);
+        }
+    }
+    if limited.is_empty() {
+        return Ok(Vec::new());
+    }
+    let state = read_state()?;
+    for (path, cooldown) in &limited {
+        let retry_after = if recent.contains(path) {
+            Some(*cooldown).filter(|left| !left.is_zero())
+        } else {
+            remaining(&state, path, *cooldown, clock)?
+        };
+        if let Some(retry_after) = retry_after {
+            return Err(Error::new(
+                ErrorKind::WouldBlock,
+                TooSoon {
+                    path: path.clone(),
+                    retry_after,
+                },
+            ));
+        }
+    }
+    Ok(limited.into_iter().map(|(path, _)| path).collect())
+}
+
+/// Records that the cooldown paths `paths` changed now.
+pub(crate) fn record_changes(paths: &[String], clock: &dyn Clock) -> Result<()> {
+    if paths.is_empty() {
+        return Ok(());
+    }
+    let now = unix_seconds(clock)?;
+    modify_state(|state| {
+        let root = state
+            .as_table_mut()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "The state file is not a table"))?;
+        let section = root
+            .entry(COOLDOWN_SECTION)
+            .or_insert_with(|| Value::Table(map::Map::new()));
+        if !section.is_table() {
+            *section = Value::Table(map::Map::new());
+        }
+        let section = section.as_table_mut().expect("replaced by a table");
+        for path in paths {
+            section.insert(path.clone(), Value::Integer(now));
+        }
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::{MockClock, SystemClock};
+    use crate::config::{get_config, save_config};
+    use crate::testing::TempConfigDir;
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_cooldown_rejects_changes_too_soon() {
+        let _dir = TempConfigDir::new().unwrap();
+        let hour = Duration::from_secs(3600);
+        set_cooldown("cooldown_test", Some(hour)).unwrap();
+        let mut config = get_config().unwrap();
+        path::insert(&mut config, "cooldown_test.tried", Value::Integer(1)).unwrap();
+        save_config(&config).unwrap();
+
+        path::insert(&mut config, "cooldown_test.tried", Value::Integer(2)).unwrap();
+        let error = save_config(&config).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::WouldBlock);
+        let too_soon = error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<TooSoon>())
+            .unwrap();
+        assert_eq!(too_soon.path, "cooldown_test");
+        assert!(too_soon.retry_after <= hour);
+
+        assert!(
+            retry_after("cooldown_test.tried", &SystemClock)
+                .unwrap()
+                .is_some()
+        );
+        let later = MockClock::new(SystemTime::now() + hour);
+        assert_eq!(retry_after("cooldown_test.tried", &later).unwrap(), None);
+        assert_eq!(retry_after("ai.model", &SystemClock).unwrap(), None);
+        set_cooldown("cooldown_test", None).unwrap();
+        save_config(&config).unwrap();
+    }
+}
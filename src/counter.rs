@@ -0,0 +1,82 @@
+use std::io::{Error, ErrorKind, Result};
+use toml::Value;
+
+use crate::config::modify_config;
+use crate::path;
+
+/// Atomically increments the integer at a dotted key path and returns the new value.
+///
+/// The read-modify-write cycle holds the config file lock, so concurrent increments from
+/// several processes are never lost. While a write storm defers this process's saves (see
+/// [`crate::storm`]), its changes are applied onto the file when saved, so only an
+/// increment of the same counter by another process in the meantime is overwritten. A
+/// missing key counts as 0.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path of the counter, e.g. `"update.tried"`
+///
+/// # Returns
+///
+/// * `Result<i64>` - The incremented value, or an `InvalidData` error if the key holds
+///   something other than an integer
+pub fn increment_counter(key_path: &str) -> Result<i64> {
+    modify_config(|config| {
+        let current = match path::lookup(config, key_path) {
+            None => 0,
+            Some(value) => value.as_integer().ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Key '{}' is not an integer counter", key_path),
+                )
+            })?,
+        };
+        let next = current.checked_add(1).ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Counter '{}' overflowed", key_path),
+            )
+        })?;
+        path::insert(config, key_path, Value::Integer(next))?;
+        Ok(next)
+    })
+}
+
+/// Sets the counter at a dotted key path back to 0.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path of the counter
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if saving fails
+pub fn reset_counter(key_path: &str) -> Result<()> {
+    modify_config(|config| path::insert(config, key_path, Value::Integer(0)).map(|_| ()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_value_fast;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_concurrent_increments_are_not_lost() {
+        let dir = TempConfigDir::new().unwrap();
+        reset_counter("update.tried").unwrap();
+        std::thread::scope(|scope| {
+            for _ in 0..4 {
+                scope.spawn(|| {
+                    dir.install();
+                    for _ in 0..5 {
+                        increment_counter("update.tried").unwrap();
+                    }
+                });
+            }
+        });
+        assert_eq!(get_value_fast("update.tried").unwrap(), Value::Integer(20));
+        assert_eq!(increment_counter("update.tried").unwrap(), 21);
+        assert!(increment_counter("ai.model").is_err());
+    }
+}
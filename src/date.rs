@@ -0,0 +1,317 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+
+/// A calendar day, counted as days since 1970-01-01.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Day(pub i64);
+
+impl Day {
+    /// Returns the UTC day containing `time`.
+    pub fn from_system_time(time: SystemTime) -> Day {
+        Day::in_zone(time, TimeZone::Utc)
+    }
+
+    /// Parses a `YYYY-MM-DD` date.
+    pub fn parse(s: &str) -> Result<Day> {
+        let invalid = || {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Invalid date '{}': expected YYYY-MM-DD", s),
+            )
+        };
+        let mut parts = s.splitn(3, '-');
+        let mut next = || -> Result<i64> {
+            parts
+                .next()
+                .and_then(|p| p.parse::<i64>().ok())
+                .ok_or_else(invalid)
+        };
+        let (year, month, day) = (next()?, next()?, next()?);
+        if !(1..=12).contains(&month) || !(1..=days_in_month(year, month)).contains(&day) {
+            return Err(invalid());
+        }
+        Ok(Day(days_from_civil(year, month, day)))
+    }
+
+    /// Returns the day containing `time` in `zone`.
+    ///
+    /// The offset is determined for `time` itself, so daylight saving time transitions of
+    /// the system zone are taken into account.
+    pub fn in_zone(time: SystemTime, zone: TimeZone) -> Day {
+        let seconds = unix_seconds(time) + zone.offset_at(time);
+        Day(seconds.div_euclid(SECONDS_PER_DAY))
+    }
+
+    /// Returns the `(year, month, day)` of this day.
+    pub fn to_civil(self) -> (i64, i64, i64) {
+        civil_from_days(self.0)
+    }
+}
+
+/// The time zone that decides where a day begins.
+///
+/// Besides the system zone only UTC and fixed offsets are supported; named zones such as
+/// `Europe/Berlin` aren't, so a zone other than the system's doesn't follow daylight saving
+/// time.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TimeZone {
+    /// The zone of the operating system, including daylight saving time
+    ///
+    /// On platforms where it can't be determined UTC is used.
+    #[default]
+    System,
+    /// Coordinated Universal Time
+    Utc,
+    /// A fixed offset east of UTC, in seconds
+    Fixed(i32),
+}
+
+impl TimeZone {
+    /// Parses `"system"` (or an empty string), `"UTC"` or a fixed offset like `"+02:00"`.
+    ///
+    /// A named zone such as `"Europe/Berlin"` fails with an `InvalidData` error that
+    /// suggests `"system"` or a fixed offset instead.
+    pub fn parse(s: &str) -> Result<TimeZone> {
+        match s.trim() {
+            "" => Ok(TimeZone::System),
+            zone if zone.eq_ignore_ascii_case("system") || zone.eq_ignore_ascii_case("local") => {
+                Ok(TimeZone::System)
+            }
+            zone if zone.eq_ignore_ascii_case("utc") || zone == "Z" => Ok(TimeZone::Utc),
+            zone if zone.contains('/') => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Unsupported time zone '{}': named zones aren't supported, use \"system\" or a fixed offset like \"+01:00\"",
+                    s
+                ),
+            )),
+            zone => parse_offset(zone).map(TimeZone::Fixed).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!(
+                        "Invalid time zone '{}': expected \"system\", \"UTC\" or an offset like \"+02:00\"",
+                        s
+                    ),
+                )
+            }),
+        }
+    }
+
+    /// Returns the offset east of UTC, in seconds, in effect at `time`.
+    pub fn offset_at(self, time: SystemTime) -> i64 {
+        match self {
+            TimeZone::System => system_offset(unix_seconds(time)),
+            TimeZone::Utc => 0,
+            TimeZone::Fixed(offset) => offset as i64,
+        }
+    }
+}
+
+impl std::fmt::Display for TimeZone {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TimeZone::System => f.write_str("system"),
+            TimeZone::Utc => f.write_str("UTC"),
+            TimeZone::Fixed(offset) => f.write_str(&format_offset(*offset as i64)),
+        }
+    }
+}
+
+/// Formats `time` as an RFC 3339 timestamp with the offset of `zone`, e.g.
+/// `2026-03-29T09:30:00+02:00`.
+pub fn format_rfc3339(time: SystemTime, zone: TimeZone) -> String {
+    let offset = zone.offset_at(time);
+    let local = unix_seconds(time) + offset;
+    let (year, month, day) = civil_from_days(local.div_euclid(SECONDS_PER_DAY));
+    let second_of_day = local.rem_euclid(SECONDS_PER_DAY);
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}{}",
+        year,
+        month,
+        day,
+        second_of_day / 3600,
+        second_of_day % 3600 / 60,
+        second_of_day % 60,
+        if offset == 0 {
+            "Z".to_string()
+        } else {
+            format_offset(offset)
+        }
+    )
+}
+
+/// Parses an RFC 3339 timestamp such as `2026-03-29T09:30:00+02:00`.
+///
+/// Fractional seconds are accepted and truncated.
+pub fn parse_rfc3339(s: &str) -> Result<SystemTime> {
+    let invalid = || {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Invalid timestamp '{}': expected RFC 3339", s),
+        )
+    };
+    let (date, rest) = s.split_once(['T', 't', ' ']).ok_or_else(invalid)?;
+    let day = Day::parse(date).map_err(|_| invalid())?;
+    let zone_start = rest.find(['Z', 'z', '+', '-']).ok_or_else(invalid)?;
+    let (time, zone) = rest.split_at(zone_start);
+    let offset = match zone {
+        "Z" | "z" => 0,
+        zone => parse_offset(zone).ok_or_else(invalid)? as i64,
+    };
+    let time = time.split('.').next().unwrap_or_default();
+    let fields: Vec<i64> = time
+        .split(':')
+        .map(|field| field.parse::<i64>().ok())
+        .collect::<Option<_>>()
+        .ok_or_else(invalid)?;
+    let [hour, minute, second] = fields[..] else {
+        return Err(invalid());
+    };
+    if !(0..24).contains(&hour) || !(0..60).contains(&minute) || !(0..=60).contains(&second) {
+        return Err(invalid());
+    }
+    let seconds = day.0 * SECONDS_PER_DAY + hour * 3600 + minute * 60 + second - offset;
+    Ok(if seconds >= 0 {
+        UNIX_EPOCH + Duration::from_secs(seconds as u64)
+    } else {
+        UNIX_EPOCH - Duration::from_secs(seconds.unsigned_abs())
+    })
+}
+
+fn unix_seconds(time: SystemTime) -> i64 {
+    match time.duration_since(UNIX_EPOCH) {
+        Ok(d) => d.as_secs() as i64,
+        Err(e) => -(e.duration().as_secs() as i64) - 1,
+    }
+}
+
+/// Parses `+HH:MM` or `-HH:MM` into seconds east of UTC.
+fn parse_offset(s: &str) -> Option<i32> {
+    let (sign, rest) = match s.as_bytes().first()? {
+        b'+' => (1, &s[1..]),
+        b'-' => (-1, &s[1..]),
+        _ => return None,
+    };
+    let (hours, minutes) = rest.split_once(':')?;
+    let (hours, minutes) = (hours.parse::<i32>().ok()?, minutes.parse::<i32>().ok()?);
+    if hours.to_string().len() > 2 || !(0..24).contains(&hours) || !(0..60).contains(&minutes) {
+        return None;
+    }
+    Some(sign * (hours * 3600 + minutes * 60))
+}
+
+fn format_offset(offset: i64) -> String {
+    let sign = if offset < 0 { '-' } else { '+' };
+    let offset = offset.abs();
+    format!("{}{:02}:{:02}", sign, offset / 3600, offset % 3600 / 60)
+}
+
+#[cfg(unix)]
+fn system_offset(seconds: i64) -> i64 {
+    let time = seconds as libc::time_t;
+    // SAFETY: `localtime_r` only writes to the `tm` we pass and is thread-safe.
+    unsafe {
+        let mut tm: libc::tm = std::mem::zeroed();
+        if libc::localtime_r(&time, &mut tm).is_null() {
+            0
+        } else {
+            tm.tm_gmtoff as i64
+        }
+    }
+}
+
+#[cfg(not(unix))]
+fn system_offset(_seconds: i64) -> i64 {
+    0
+}
+
+impl std::fmt::Display for Day {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (year, month, day) = self.to_civil();
+        write!(f, "{:04}-{:02}-{:02}", year, month, day)
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    (year % 4 == 0 && year % 100 != 0) || year % 400 == 0
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+// Howard Hinnant's days_from_civil / civil_from_days algorithms.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_day_round_trip() {
+        let day = Day::parse("2024-02-29").unwrap();
+        assert_eq!(day.to_string(), "2024-02-29");
+        assert_eq!(Day::parse("1970-01-01").unwrap(), Day(0));
+        assert!(Day::parse("2023-02-29").is_err());
+        assert_eq!(
+            Day::from_system_time(UNIX_EPOCH + Duration::from_secs(86_400 * 2 + 5)),
+            Day(2)
+        );
+    }
+
+    #[test]
+    fn test_rfc3339_and_zones() {
+        let time = parse_rfc3339("2026-03-29T01:30:00+02:00").unwrap();
+        assert_eq!(time, parse_rfc3339("2026-03-28T23:30:00Z").unwrap());
+        assert_eq!(
+            format_rfc3339(time, TimeZone::parse("+02:00").unwrap()),
+            "2026-03-29T01:30:00+02:00"
+        );
+        assert_eq!(Day::in_zone(time, TimeZone::Utc).to_string(), "2026-03-28");
+        assert_eq!(
+            Day::in_zone(time, TimeZone::Fixed(7200)).to_string(),
+            "2026-03-29"
+        );
+        assert!(TimeZone::parse("Mars/Olympus").is_err());
+        let named = TimeZone::parse("Europe/Berlin").unwrap_err();
+        assert_eq!(named.kind(), ErrorKind::InvalidData);
+        assert!(named.to_string().contains("fixed offset"), "{}", named);
+        assert!(parse_rfc3339("2026-03-29 01:30").is_err());
+    }
+}
@@ -0,0 +1,640 @@
+use std::{
+    fmt::Write as _,
+    io::{Error, ErrorKind, Result},
+    sync::{
+        RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+    time::Duration,
+};
+use toml::{Value, map};
+
+use crate::limits::{DEFAULT_MAX_DIFF_BYTES, DEFAULT_MAX_FILES};
+use crate::merge::merge_into;
+use crate::path;
+use crate::secret::mark_secret;
+
+/// The first lines of every generated configuration file.
+const HEADER: &str = "# Configuration file of gim\n";
+
+/// Defaults registered by the embedding application, overriding the built-ins.
+static APP_DEFAULTS: RwLock<Option<Value>> = RwLock::new(None);
+
+/// Whether reads of keys missing from the file fall back to their defaults.
+static FALLBACK: AtomicBool = AtomicBool::new(true);
+
+/// The document newly created configuration files get; `None` for the defaults.
+static INITIAL: RwLock<Option<Value>> = RwLock::new(None);
+
+/// What a configuration file contains when the crate creates it on first use.
+#[derive(Debug, Clone, Default, PartialEq)]
+#[non_exhaustive]
+pub enum InitialContent {
+    /// The commented default document, see [`default_config_document`]
+    #[default]
+    Defaults,
+    /// Nothing at all
+    Empty,
+    /// A document of the application's own, written without comments
+    Document(Value),
+}
+
+/// The default configuration, both as the exact text written to disk and as a Value.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefaultDocument {
+    /// The TOML text, including comments, with `\n` line endings
+    pub text: String,
+    /// The parsed form of `text`
+    pub value: Value,
+}
+
+/// One key declared through a [`DefaultConfigBuilder`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct DefaultKey {
+    /// The dotted key path
+    pub path: String,
+    /// The default value
+    pub value: Value,
+    /// The comment written above the key
+    pub comment: String,
+    /// Whether the value is a secret
+    pub secret: bool,
+    /// The values the key accepts; empty if any value of the right type is fine
+    pub allowed: Vec<Value>,
+    /// A realistic value for sample files, for keys whose default is empty
+    pub example: Option<Value>,
+    /// The minimum time between two changes of the key, see [`crate::cooldown`]
+    pub cooldown: Option<Duration>,
+}
+
+/// Declares a default configuration document: its sections, keys, comments and secrets.
+///
+/// The crate's own defaults are declared with it (see [`builtin_defaults`]), and
+/// applications embedding the crate can use it for documents of their own and register
+/// them with [`DefaultConfigBuilder::install`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DefaultConfigBuilder {
+    sections: Vec<(String, String)>,
+    keys: Vec<DefaultKey>,
+}
+
+impl DefaultConfigBuilder {
+    /// Creates an empty builder.
+    pub fn new() -> DefaultConfigBuilder {
+        DefaultConfigBuilder::default()
+    }
+
+    /// Declares a section with the comment written above its header.
+    pub fn section(mut self, name: &str, comment: &str) -> DefaultConfigBuilder {
+        self.sections.push((name.to_string(), comment.to_string()));
+        self
+    }
+
+    /// Declares a key by its dotted path, e.g. `"ai.model"`.
+    pub fn key(self, path: &str, value: impl Into<Value>, comment: &str) -> DefaultConfigBuilder {
+        self.push_key(path, value.into(), comment, false)
+    }
+
+    /// Declares a key whose value is a secret, e.g. an API key.
+    pub fn secret_key(
+        self,
+        path: &str,
+        value: impl Into<Value>,
+        comment: &str,
+    ) -> DefaultConfigBuilder {
+        self.push_key(path, value.into(), comment, true)
+    }
+
+    fn push_key(
+        mut self,
+        path: &str,
+        value: Value,
+        comment: &str,
+        secret: bool,
+    ) -> DefaultConfigBuilder {
+        self.keys.retain(|key| key.path != path);
+        self.keys.push(DefaultKey {
+            path: path.to_string(),
+            value,
+            comment: comment.to_string(),
+            secret,
+            allowed: Vec::new(),
+            example: None,
+            cooldown: None,
+        });
+        self
+    }
+
+    /// Restricts a declared key to a fixed set of values, e.g. `["auto", "always", "never"]`.
+    pub fn allowed_values<V: Into<Value>>(
+        mut self,
+        path: &str,
+        values: impl IntoIterator<Item = V>,
+    ) -> DefaultConfigBuilder {
+        if let Some(key) = self.keys.iter_mut().find(|key| key.path == path) {
+            key.allowed = values.into_iter().map(Into::into).collect();
+        }
+        self
+    }
+
+    /// Sets the value sample files show for a declared key, e.g. `"gpt-4o"` for a model.
+    pub fn example(mut self, path: &str, value: impl Into<Value>) -> DefaultConfigBuilder {
+        if let Some(key) = self.keys.iter_mut().find(|key| key.path == path) {
+            key.example = Some(value.into());
+        }
+        self
+    }
+
+    /// Limits how often a declared key may change, e.g. once per hour.
+    pub fn cooldown(mut self, path: &str, cooldown: Duration) -> DefaultConfigBuilder {
+        if let Some(key) = self.keys.iter_mut().find(|key| key.path == path) {
+            key.cooldown = Some(cooldown);
+        }
+        self
+    }
+
+    /// Returns the declared keys in declaration order.
+    pub fn keys(&self) -> &[DefaultKey] {
+        &self.keys
+    }
+
+    /// Returns the comment of a declared key.
+    pub fn key_comment(&self, path: &str) -> Option<&str> {
+        self.keys
+            .iter()
+            .find(|key| key.path == path)
+            .map(|key| key.comment.as_str())
+    }
+
+    /// Marks every secret key as secret for redaction, see [`crate::secret::mark_secret`].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success or an error if a path isn't a valid pattern
+    pub fn register_secrets(&self) -> Result<()> {
+        for key in self.keys.iter().filter(|key| key.secret) {
+            mark_secret(&key.path)?;
+        }
+        Ok(())
+    }
+
+    /// Registers the declared values as the application's defaults and marks the secret
+    /// keys for redaction, see [`set_default`] and [`DefaultConfigBuilder::register_secrets`].
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success or an error if a key path is invalid; no value is registered
+    ///   then
+    pub fn install(&self) -> Result<()> {
+        let values = self.values()?;
+        self.register_secrets()?;
+        set_default_values(&values)
+    }
+
+    /// Returns the declared values as a configuration.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Value>` - The values or an error if a key path is invalid or nests below
+    ///   another key's value
+    pub fn values(&self) -> Result<Value> {
+        let mut root = Value::Table(map::Map::new());
+        for key in &self.keys {
+            path::insert(&mut root, &key.path, key.value.clone())?;
+        }
+        Ok(root)
+    }
+
+    /// Renders any configuration with the comments declared in this builder.
+    pub fn render(&self, value: &Value) -> String {
+        let sections: Vec<(&str, &str)> = self
+            .sections
+            .iter()
+            .map(|(name, comment)| (name.as_str(), comment.as_str()))
+            .collect();
+        let keys: Vec<(&str, &str)> = self
+            .keys
+            .iter()
+            .filter(|key| !key.comment.is_empty())
+            .map(|key| (key.path.as_str(), key.comment.as_str()))
+            .collect();
+        render_document(value, &sections, &keys)
+    }
+
+    /// Builds the commented document.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<DefaultDocument>` - The document or an error if a key path is invalid
+    pub fn build(&self) -> Result<DefaultDocument> {
+        let value = self.values()?;
+        Ok(DefaultDocument {
+            text: self.render(&value),
+            value,
+        })
+    }
+}
+
+/// Returns the builder declaring the crate's built-in defaults.
+pub fn builtin_defaults() -> DefaultConfigBuilder {
+    DefaultConfigBuilder::new()
+        .section("ai", "AI provider used to generate commit messages")
+        .key("ai.model", "", "Model name, e.g. \"gpt-4o\"")
+        .example("ai.model", "gpt-4o")
+        .secret_key("ai.apikey", "", "API key of the provider")
+        .key("ai.url", "", "Base URL of the provider's API")
+        .example("ai.url", "https://api.openai.com/v1")
+        .key(
+            "ai.language",
+            "English",
+            "Language of the generated commit messages",
+        )
+        .section("update", "How often gim looks for a new release")
+        .key(
+            "update.max_try",
+            5,
+            "Maximum number of update checks per interval",
+        )
+        .key(
+            "update.try_interval_days",
+            30,
+            "Days after which a new check interval starts",
+        )
+        .key(
+            "update.timezone",
+            "system",
+            "Where a day begins: \"system\", \"UTC\" or an offset like \"+02:00\"",
+        )
+        .section("limits", "How much of a repository gim sends to the model")
+        .key(
+            "limits.max_diff_bytes",
+            DEFAULT_MAX_DIFF_BYTES,
+            "Largest diff, in bytes, sent to the model",
+        )
+        .key(
+            "limits.max_files",
+            DEFAULT_MAX_FILES,
+            "Most changed files included in the diff",
+        )
+        .key(
+            "limits.truncation",
+            "head",
+            "What happens to a larger diff: \"head\", \"per-file\" or \"reject\"",
+        )
+        .allowed_values("limits.truncation", ["head", "per-file", "reject"])
+}
+
+/// Returns the default configuration document.
+///
+/// The text is byte-for-byte identical across runs and platforms: keys are emitted in
+/// sorted order and lines always end with `\n`, so downstream projects can snapshot-test it.
+///
+/// # Returns
+///
+/// * `DefaultDocument` - The default TOML text and its parsed Value
+pub fn default_config_document() -> DefaultDocument {
+    let value = default_values();
+    DefaultDocument {
+        text: render_with_default_comments(&value),
+        value,
+    }
+}
+
+/// Renders a configuration with the comments of the default document.
+///
+/// # Arguments
+///
+/// * `value` - The configuration to render
+///
+/// # Returns
+///
+/// * `String` - The commented TOML text
+pub(crate) fn render_with_default_comments(value: &Value) -> String {
+    builtin_defaults().render(value)
+}
+
+/// Registers a default value, overriding the crate's built-in default at the same path.
+///
+/// The gim binary calls this at startup, before anything reads the configuration, so its
+/// default policy lives in the application. Defaults are written into newly created files,
+/// and every read of a key the file lacks is served the default, unless
+/// [`set_default_fallback`] turns that off; existing files aren't rewritten.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path, e.g. `"ai.language"`
+/// * `value` - The default value
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if the path is invalid or crosses a non-table value
+pub fn set_default(key_path: &str, value: Value) -> Result<()> {
+    with_app_defaults(|defaults| {
+        let mut updated = defaults
+            .clone()
+            .unwrap_or_else(|| Value::Table(map::Map::new()));
+        path::insert(&mut updated, key_path, value)?;
+        *defaults = Some(updated);
+        Ok(())
+    })
+}
+
+/// Registers every leaf of a table as a default, see [`set_default`].
+///
+/// Either all of the values are registered or, if one of the paths crosses a non-table
+/// value, none of them.
+///
+/// # Arguments
+///
+/// * `defaults` - The table of default values
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if a path crosses a non-table value
+pub(crate) fn set_default_values(defaults: &Value) -> Result<()> {
+    with_app_defaults(|registered| {
+        let mut updated = registered
+            .clone()
+            .unwrap_or_else(|| Value::Table(map::Map::new()));
+        for key_path in path::leaf_paths(defaults) {
+            let value = path::lookup(defaults, &key_path).cloned();
+            path::insert(
+                &mut updated,
+                &key_path,
+                value.expect("leaf path of the defaults"),
+            )?;
+        }
+        *registered = Some(updated);
+        Ok(())
+    })
+}
+
+/// Registers every value of a TOML document as a default, see [`set_default`].
+///
+/// # Arguments
+///
+/// * `defaults` - The TOML text, e.g. `"[ai]\nlanguage = \"Chinese\"\n"`
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an `InvalidData` error if the text doesn't parse
+pub fn set_defaults_from_toml(defaults: &str) -> Result<()> {
+    let parsed: Value =
+        toml::from_str(defaults).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    with_app_defaults(|registered| {
+        let mut updated = registered
+            .clone()
+            .unwrap_or_else(|| Value::Table(map::Map::new()));
+        merge_into(&mut updated, &parsed, true);
+        *registered = Some(updated);
+    });
+    Ok(())
+}
+
+/// Removes every default registered by the application.
+pub fn clear_app_defaults() {
+    with_app_defaults(|registered| *registered = None);
+}
+
+/// Runs `f` on the defaults registered by the application.
+///
+/// While a [`crate::testing::TempConfigDir`] is installed on the calling thread, `f` gets
+/// the defaults registered for that directory instead, so tests don't see each other's.
+fn with_app_defaults<T>(f: impl FnOnce(&mut Option<Value>) -> T) -> T {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(dir) = crate::testing::thread_config_dir() {
+        return crate::testing::with_dir_defaults(&dir, f);
+    }
+    f(&mut APP_DEFAULTS.write().unwrap_or_else(|e| e.into_inner()))
+}
+
+/// Returns the defaults in effect: the built-ins overridden by the application's.
+///
+/// Nothing is read from or written to disk.
+pub fn effective_defaults() -> Value {
+    default_values()
+}
+
+/// Sets whether reading a key the file lacks returns its default instead of `NotFound`.
+///
+/// The fallback is on by default, so settings added in a newer release work for users
+/// whose configuration file predates them, without rewriting that file. It applies to
+/// [`crate::config::get_config_value`], [`crate::config::get_value_fast`] and
+/// [`crate::config::get_many`]; [`crate::config::get_config`] always returns the file
+/// as it is.
+pub fn set_default_fallback(enabled: bool) {
+    FALLBACK.store(enabled, Ordering::Relaxed);
+}
+
+/// Returns whether reads of missing keys fall back to their defaults.
+pub fn is_default_fallback_enabled() -> bool {
+    FALLBACK.load(Ordering::Relaxed)
+}
+
+/// Sets what configuration files created by this process from now on contain.
+///
+/// Applications embedding the crate with a schema of their own use this to keep the
+/// `[ai]` and `[update]` sections out of their users' files. Reads of keys the file lacks
+/// still fall back to the defaults unless [`set_default_fallback`] turns that off.
+///
+/// # Arguments
+///
+/// * `content` - The initial content
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an `InvalidInput` error if a document isn't a table
+pub fn set_initial_content(content: InitialContent) -> Result<()> {
+    let document = match content {
+        InitialContent::Defaults => None,
+        InitialContent::Empty => Some(Value::Table(map::Map::new())),
+        InitialContent::Document(value) if value.is_table() => Some(value),
+        InitialContent::Document(_) => {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The initial document must be a table",
+            ));
+        }
+    };
+    *INITIAL.write().unwrap_or_else(|e| e.into_inner()) = document;
+    Ok(())
+}
+
+/// Returns the document set through [`set_initial_content`], `None` for the defaults.
+pub(crate) fn initial_document() -> Option<Value> {
+    INITIAL.read().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Builds the default configuration values: the built-ins overridden by the application's.
+pub(crate) fn default_values() -> Value {
+    let mut values = builtin_defaults()
+        .values()
+        .expect("built-in defaults are valid");
+    if let Some(app) = APP_DEFAULTS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+    {
+        merge_into(&mut values, app, true);
+    }
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(dir) = crate::testing::thread_config_dir()
+        && let Some(scoped) = crate::testing::with_dir_defaults(&dir, |d| d.clone())
+    {
+        merge_into(&mut values, &scoped, true);
+    }
+    values
+}
+
+/// Renders a configuration as commented TOML with a stable layout.
+///
+/// # Arguments
+///
+/// * `value` - The configuration; top-level entries are expected to be section tables
+/// * `section_comments` - Comments keyed by section name
+/// * `key_comments` - Comments keyed by dotted key path
+///
+/// # Returns
+///
+/// * `String` - The rendered document
+pub(crate) fn render_document(
+    value: &Value,
+    section_comments: &[(&str, &str)],
+    key_comments: &[(&str, &str)],
+) -> String {
+    let mut out = String::from(HEADER);
+    let Some(root) = value.as_table() else {
+        return out;
+    };
+    let mut keys: Vec<&String> = root.keys().collect();
+    keys.sort();
+    for name in keys {
+        let comment = section_comments
+            .iter()
+            .find(|(section, _)| section == name)
+            .map(|(_, comment)| *comment);
+        render_table(&mut out, name, &root[name.as_str()], comment, key_comments);
+    }
+    out
+}
+
+fn render_table(
+    out: &mut String,
+    path: &str,
+    value: &Value,
+    comment: Option<&str>,
+    key_comments: &[(&str, &str)],
+) {
+    let comment_for = |key_path: &str| {
+        key_comments
+            .iter()
+            .find(|(path, _)| *path == key_path)
+            .map(|(_, comment)| *comment)
+    };
+    out.push('\n');
+    if let Some(comment) = comment {
+        push_comment(out, comment);
+    }
+    let Some(table) = value.as_table() else {
+        let _ = writeln!(out, "{} = {}", path, value);
+        return;
+    };
+    let _ = writeln!(out, "[{}]", path);
+
+    let mut keys: Vec<&String> = table.keys().collect();
+    keys.sort();
+    for key in keys.iter().filter(|k| !table[k.as_str()].is_table()) {
+        let key_path = format!("{}.{}", path, key);
+        if let Some(comment) = comment_for(&key_path) {
+            push_comment(out, comment);
+        }
+        let _ = writeln!(out, "{} = {}", render_key(key), table[key.as_str()]);
+    }
+    for key in keys.iter().filter(|k| table[k.as_str()].is_table()) {
+        let key_path = format!("{}.{}", path, render_key(key));
+        render_table(
+            out,
+            &key_path,
+            &table[key.as_str()],
+            comment_for(&key_path),
+            key_comments,
+        );
+    }
+}
+
+fn push_comment(out: &mut String, comment: &str) {
+    for line in comment.lines() {
+        let _ = writeln!(out, "# {}", line);
+    }
+}
+
+fn render_key(key: &str) -> String {
+    let bare = !key.is_empty()
+        && key
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if bare {
+        key.to_string()
+    } else {
+        Value::from(key).to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_default_document_is_stable_and_parses() {
+        let document = default_config_document();
+        assert_eq!(document, default_config_document());
+        assert!(!document.text.contains('\r'));
+        let parsed: Value = toml::from_str(&document.text).unwrap();
+        assert_eq!(parsed, document.value);
+        assert!(
+            document
+                .text
+                .contains("# API key of the provider\napikey = \"\"\n")
+        );
+    }
+
+    #[test]
+    fn test_app_defaults_override_builtins() {
+        let dir = crate::testing::TempConfigDir::new().unwrap();
+        set_default("defaults_test.level", Value::Integer(3)).unwrap();
+        set_defaults_from_toml("[defaults_test]\nname = \"gim\"\n").unwrap();
+        let created = crate::config::get_config().unwrap();
+        assert_eq!(created["defaults_test"]["level"].as_integer(), Some(3));
+        assert_eq!(created["defaults_test"]["name"].as_str(), Some("gim"));
+        assert!(set_defaults_from_toml("not toml").is_err());
+        clear_app_defaults();
+        assert!(effective_defaults().get("defaults_test").is_none());
+
+        set_default("defaults_test.level", Value::Integer(4)).unwrap();
+        drop(dir);
+        assert!(effective_defaults().get("defaults_test").is_none());
+    }
+
+    #[test]
+    fn test_builder_install_redacts_secret_keys() {
+        let _dir = crate::testing::TempConfigDir::new().unwrap();
+        DefaultConfigBuilder::new()
+            .section("install_test", "Keys installed from a builder")
+            .key("install_test.user", "gim", "Account name")
+            .secret_key("install_test.credential", "", "Credential of the account")
+            .install()
+            .unwrap();
+        assert!(crate::secret::is_secret("install_test.credential"));
+        assert!(!crate::secret::is_secret("install_test.user"));
+        let installed = effective_defaults();
+        assert_eq!(installed["install_test"]["user"].as_str(), Some("gim"));
+
+        let changes = crate::change::ChangeSet::between(
+            &installed,
+            &toml::from_str("[install_test]\nuser = \"gim\"\ncredential = \"hunter2\"\n").unwrap(),
+        );
+        let redacted = changes.redacted().to_json().to_string();
+        assert!(redacted.contains("install_test.credential"));
+        assert!(!redacted.contains("hunter2"));
+    }
+}
@@ -0,0 +1,113 @@
+use std::io::Result;
+use toml::Value;
+
+use crate::config::get_config;
+use crate::defaults::default_values;
+use crate::display::{Origin, table_rows};
+use crate::format::to_json;
+use crate::profile::current_env;
+use crate::schema::key_schema;
+use crate::secret::SECRET_PLACEHOLDER;
+
+/// Everything an editor or TUI shows when hovering a key of `config.toml`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeyDescription {
+    /// The dotted key path
+    pub path: String,
+    /// The TOML type of the default, e.g. `"string"` or `"integer"`
+    pub value_type: &'static str,
+    /// The default value, masked if the key is a secret
+    pub default: Value,
+    /// A human-readable description
+    pub description: String,
+    /// The values the key accepts; empty if it isn't restricted
+    pub allowed: Vec<Value>,
+    /// Whether the value is classified as a secret
+    pub secret: bool,
+    /// The effective value, masked if the key is a secret
+    pub value: Value,
+    /// Where the effective value comes from
+    pub origin: Origin,
+}
+
+impl KeyDescription {
+    /// Serializes the description as JSON, e.g. to answer an editor plugin's hover request.
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "path": self.path,
+            "type": self.value_type,
+            "default": to_json(&self.default),
+            "description": self.description,
+            "allowed": self.allowed.iter().map(to_json).collect::<Vec<_>>(),
+            "secret": self.secret,
+            "value": to_json(&self.value),
+            "origin": self.origin.to_string(),
+        })
+        .to_string()
+    }
+}
+
+/// Describes a configuration key to editor plugins and TUI front-ends, so they can offer
+/// hover help for `config.toml` without duplicating the schema.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path, e.g. `"ai.model"`
+///
+/// # Returns
+///
+/// * `Result<Option<KeyDescription>>` - The description, `None` if the schema doesn't
+///   know the key, or an error if the configuration can't be read
+pub fn describe_key(key_path: &str) -> Result<Option<KeyDescription>> {
+    let Some(key) = key_schema(key_path) else {
+        return Ok(None);
+    };
+    let rows = table_rows(&get_config()?, &default_values(), current_env().as_deref());
+    let row = rows
+        .into_iter()
+        .find(|row| format!("{}.{}", row.section, row.key) == key_path);
+    let (value, origin) = match row {
+        Some(row) => (row.value, row.origin),
+        None => (key.default.clone(), Origin::Default),
+    };
+    let default = if key.secret && key.default.as_str() != Some("") {
+        Value::from(SECRET_PLACEHOLDER)
+    } else {
+        key.default.clone()
+    };
+    Ok(Some(KeyDescription {
+        value_type: key.default.type_str(),
+        default,
+        description: key.description,
+        allowed: key.allowed,
+        secret: key.secret,
+        value,
+        origin,
+        path: key.path,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::update_config_value;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_describe_key() {
+        let _dir = TempConfigDir::new().unwrap();
+        update_config_value("ai", "model", Value::from("described")).unwrap();
+        update_config_value("ai", "apikey", Value::from("sk-hover")).unwrap();
+
+        let model = describe_key("ai.model").unwrap().unwrap();
+        assert_eq!(model.value_type, "string");
+        assert_eq!(model.value.as_str(), Some("described"));
+        assert_eq!(model.origin, Origin::File);
+        assert!(!model.description.is_empty());
+
+        let apikey = describe_key("ai.apikey").unwrap().unwrap();
+        assert!(apikey.secret);
+        assert!(!apikey.to_json().contains("sk-hover"));
+        assert!(describe_key("ai.nonexistent").unwrap().is_none());
+    }
+}
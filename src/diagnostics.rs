@@ -0,0 +1,195 @@
+use std::{path::PathBuf, time::UNIX_EPOCH};
+use toml::Value;
+
+use crate::compat::newer_schemas_detected;
+use crate::config::{get_config, get_config_file};
+use crate::directory::{AppDirs, DirSource, app_dirs, resolve_config_dir};
+use crate::doctor::{StaleKey, find_stale_keys};
+use crate::export::sanitize;
+use crate::format::to_json;
+use crate::hooks::hook_failures;
+use crate::io_limits::slow_storage_warnings;
+use crate::lenient::applied_fixes;
+use crate::shadow::{ShadowedValue, shadow_report};
+use crate::snapshot::{SnapshotInfo, list_snapshots};
+use crate::storm::write_storms;
+use crate::sync::{SyncProvider, detect_sync_provider};
+use crate::trace::{AccessRecord, access_report};
+
+/// How many of the most recent key reads a bundle keeps.
+const RECENT_READS: usize = 50;
+
+/// The operating system and CPU the process runs on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PlatformInfo {
+    /// E.g. `"linux"`, `"macos"` or `"windows"`
+    pub os: &'static str,
+    /// E.g. `"x86_64"` or `"aarch64"`
+    pub arch: &'static str,
+    /// `"unix"` or `"windows"`
+    pub family: &'static str,
+}
+
+/// Everything support needs to look into a bug report, with secrets redacted.
+///
+/// Parts that can't be collected, e.g. because the config file doesn't parse, are left
+/// empty and the reason is listed in `errors`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiagnosticBundle {
+    /// The version of this crate
+    pub crate_version: &'static str,
+    /// Where the process runs
+    pub platform: PlatformInfo,
+    /// The configuration file
+    pub config_file: Option<PathBuf>,
+    /// How the config directory was determined
+    pub dir_source: DirSource,
+    /// The application directories
+    pub dirs: Option<AppDirs>,
+    /// The sync service holding the configuration file, if any
+    pub sync_provider: Option<SyncProvider>,
+    /// The configuration with secrets masked and machine-specific values removed, see
+    /// [`sanitize`]
+    pub config: Option<Value>,
+    /// The keys `gim doctor` reports as stale
+    pub stale_keys: Vec<StaleKey>,
+    /// Values hidden by other layers
+    pub shadowed: Vec<ShadowedValue>,
+    /// Lenient parse fixes, slow storage and failed hooks seen by this process
+    pub warnings: Vec<String>,
+    /// The most recent key reads recorded by access tracing, oldest first
+    pub recent_reads: Vec<AccessRecord>,
+    /// The saved snapshots, i.e. the backups taken before resets and restores
+    pub snapshots: Vec<SnapshotInfo>,
+    /// Why parts of the bundle are missing
+    pub errors: Vec<String>,
+}
+
+impl DiagnosticBundle {
+    /// Serializes the bundle as pretty-printed JSON, e.g. to attach it to an issue.
+    pub fn to_json(&self) -> String {
+        let path = |p: &PathBuf| serde_json::Value::from(p.display().to_string());
+        let strings = |items: Vec<String>| serde_json::Value::from(items);
+        let json = serde_json::json!({
+            "crate_version": self.crate_version,
+            "platform": {
+                "os": self.platform.os,
+                "arch": self.platform.arch,
+                "family": self.platform.family,
+            },
+            "config_file": self.config_file.as_ref().map(path),
+            "dir_source": format!("{:?}", self.dir_source),
+            "dirs": self.dirs.as_ref().map(|dirs| serde_json::json!({
+                "config": path(&dirs.config),
+                "data": path(&dirs.data),
+                "cache": path(&dirs.cache),
+                "state": path(&dirs.state),
+                "backups": path(&dirs.backups),
+            })),
+            "sync_provider": self.sync_provider.map(|provider| provider.to_string()),
+            "config": self.config.as_ref().map(to_json),
+            "stale_keys": self.stale_keys.iter().map(|key| serde_json::json!({
+                "path": key.path,
+                "reason": format!("{:?}", key.reason),
+            })).collect::<Vec<_>>(),
+            "shadowed": strings(self.shadowed.iter().map(ToString::to_string).collect()),
+            "warnings": strings(self.warnings.clone()),
+            "recent_reads": self.recent_reads.iter().map(|read| serde_json::json!({
+                "path": read.path,
+                "found": read.is_hit(),
+            })).collect::<Vec<_>>(),
+            "snapshots": self.snapshots.iter().map(|snapshot| serde_json::json!({
+                "label": snapshot.label,
+                "created": snapshot
+                    .created
+                    .duration_since(UNIX_EPOCH)
+                    .map_or(0, |elapsed| elapsed.as_secs()),
+            })).collect::<Vec<_>>(),
+            "errors": strings(self.errors.clone()),
+        });
+        serde_json::to_string_pretty(&json).expect("JSON values serialize") + "\n"
+    }
+}
+
+/// Collects a [`DiagnosticBundle`] for `gim bug-report`.
+///
+/// Collecting never fails; whatever can't be gathered is named in the bundle's `errors`.
+pub fn collect_diagnostics() -> DiagnosticBundle {
+    let mut errors = Vec::new();
+    let config_file = keep(get_config_file(), "config file", &mut errors);
+    let dirs = keep(app_dirs(), "directories", &mut errors);
+    let config = keep(get_config(), "configuration", &mut errors);
+    let shadowed = keep(shadow_report(), "shadowed values", &mut errors).unwrap_or_default();
+    let snapshots = keep(list_snapshots(), "snapshots", &mut errors).unwrap_or_default();
+
+    let mut warnings: Vec<String> = applied_fixes()
+        .into_values()
+        .flatten()
+        .map(|fix| fix.message)
+        .collect();
+    warnings.extend(slow_storage_warnings().into_iter().map(|w| w.suggestion));
+    warnings.extend(hook_failures().into_iter().map(|f| f.message));
+    warnings.extend(newer_schemas_detected().iter().map(ToString::to_string));
+    warnings.extend(write_storms().into_iter().map(|storm| {
+        format!(
+            "'{}' was written {} times within a second",
+            storm.path.display(),
+            storm.writes
+        )
+    }));
+    let mut recent_reads = access_report();
+    recent_reads.drain(..recent_reads.len().saturating_sub(RECENT_READS));
+
+    DiagnosticBundle {
+        crate_version: env!("CARGO_PKG_VERSION"),
+        platform: PlatformInfo {
+            os: std::env::consts::OS,
+            arch: std::env::consts::ARCH,
+            family: std::env::consts::FAMILY,
+        },
+        sync_provider: config_file.as_deref().and_then(detect_sync_provider),
+        config_file,
+        dir_source: resolve_config_dir().source,
+        dirs,
+        stale_keys: config.as_ref().map(find_stale_keys).unwrap_or_default(),
+        config: config.as_ref().map(sanitize),
+        shadowed,
+        warnings,
+        recent_reads,
+        snapshots,
+        errors,
+    }
+}
+
+fn keep<T>(result: std::io::Result<T>, what: &str, errors: &mut Vec<String>) -> Option<T> {
+    match result {
+        Ok(value) => Some(value),
+        Err(e) => {
+            errors.push(format!("{}: {}", what, e));
+            None
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::update_config_value;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_collect_diagnostics_redacts_secrets() {
+        let _dir = TempConfigDir::new().unwrap();
+        update_config_value("ai", "apikey", Value::from("sk-diagnostics-secret")).unwrap();
+        let bundle = collect_diagnostics();
+        assert!(bundle.errors.is_empty(), "{:?}", bundle.errors);
+        assert_eq!(bundle.dir_source, DirSource::Override);
+        assert!(bundle.config.is_some());
+
+        let json = bundle.to_json();
+        assert!(!json.contains("sk-diagnostics-secret"));
+        let parsed: serde_json::Value = serde_json::from_str(&json).unwrap();
+        assert_eq!(parsed["crate_version"], env!("CARGO_PKG_VERSION"));
+        assert_eq!(parsed["platform"]["os"], std::env::consts::OS);
+    }
+}
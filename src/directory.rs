@@ -1,23 +1,303 @@
 use std::{
-    io::{Error, ErrorKind, Result},
-    path::PathBuf,
+    ffi::OsString,
+    fs,
+    io::Result,
+    path::{Path, PathBuf},
+    sync::RwLock,
 };
 
+/// The environment variable relocating the config directory, e.g. off a slow network drive.
+pub const CONFIG_DIR_ENV: &str = "GIM_CONFIG_DIR";
+
+/// The environment variable consulted when no home directory can be determined.
+pub const XDG_CONFIG_HOME: &str = "XDG_CONFIG_HOME";
+
+/// The Windows profile variable consulted after `XDG_CONFIG_HOME`.
+pub const USERPROFILE: &str = "USERPROFILE";
+
+/// A caller-supplied directory used when neither the home directory nor the environment help.
+static FALLBACK_CONFIG_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// The config directory installed through [`crate::init::Options::config_dir`].
+static CONFIG_DIR_OVERRIDE: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// How the config directory was determined.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DirSource {
+    /// An explicit override, installed by the testing helpers or [`crate::init::Options`]
+    Override,
+    /// The directory named by `GIM_CONFIG_DIR`
+    Env,
+    /// `~/.config/gim` below the user's home directory
+    Home,
+    /// `$XDG_CONFIG_HOME/gim`
+    XdgConfigHome,
+    /// `%USERPROFILE%/.config/gim`
+    UserProfile,
+    /// The directory passed to [`set_fallback_config_dir`]
+    Fallback,
+    /// A temporary location that is never written; reads return the built-in defaults
+    Ephemeral,
+}
+
+/// The config directory together with the strategy that selected it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ResolvedDir {
+    /// The config directory
+    pub path: PathBuf,
+    /// How the directory was determined
+    pub source: DirSource,
+}
+
+impl ResolvedDir {
+    /// Returns whether the configuration can't be persisted in this directory.
+    pub fn is_ephemeral(&self) -> bool {
+        self.source == DirSource::Ephemeral
+    }
+}
+
+/// Sets the directory to use when the home directory and environment variables are unavailable.
+///
+/// Containers frequently run without `HOME`; applications can point this at a writable volume.
+///
+/// # Arguments
+///
+/// * `dir` - The fallback config directory, or `None` to remove it
+pub fn set_fallback_config_dir(dir: Option<PathBuf>) {
+    *FALLBACK_CONFIG_DIR
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = dir;
+}
+
+/// Sets the config directory that wins over every other strategy.
+pub(crate) fn set_config_dir_override(dir: Option<PathBuf>) {
+    *CONFIG_DIR_OVERRIDE
+        .write()
+        .unwrap_or_else(|e| e.into_inner()) = dir;
+}
+
+/// Resolves the config directory and reports which strategy selected it.
+///
+/// The chain is: the directory passed to [`crate::init::Options::config_dir`],
+/// `$GIM_CONFIG_DIR`, the home directory, `$XDG_CONFIG_HOME/gim`,
+/// `%USERPROFILE%/.config/gim`, the directory set with [`set_fallback_config_dir`], and
+/// finally an ephemeral location under the temp dir where the built-in defaults are served
+/// read-only.
+///
+/// # Returns
+/// `ResolvedDir` - The directory and its [`DirSource`]
+pub fn resolve_config_dir() -> ResolvedDir {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(dir) = crate::testing::thread_config_dir() {
+        return ResolvedDir {
+            path: dir,
+            source: DirSource::Override,
+        };
+    }
+    if let Some(dir) = CONFIG_DIR_OVERRIDE
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+    {
+        return ResolvedDir {
+            path: dir,
+            source: DirSource::Override,
+        };
+    }
+
+    let fallback = FALLBACK_CONFIG_DIR
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    resolve_with(dirs::home_dir(), |var| std::env::var_os(var), fallback)
+}
+
+fn resolve_with(
+    home: Option<PathBuf>,
+    env: impl Fn(&str) -> Option<OsString>,
+    fallback: Option<PathBuf>,
+) -> ResolvedDir {
+    let non_empty = |var: &str| env(var).filter(|v| !v.is_empty()).map(PathBuf::from);
+    let (path, source) = if let Some(dir) = non_empty(CONFIG_DIR_ENV) {
+        (dir, DirSource::Env)
+    } else if let Some(home) = home {
+        (home.join(".config").join("gim"), DirSource::Home)
+    } else if let Some(xdg) = non_empty(XDG_CONFIG_HOME) {
+        (xdg.join("gim"), DirSource::XdgConfigHome)
+    } else if let Some(profile) = non_empty(USERPROFILE) {
+        (profile.join(".config").join("gim"), DirSource::UserProfile)
+    } else if let Some(fallback) = fallback {
+        (fallback, DirSource::Fallback)
+    } else {
+        (
+            std::env::temp_dir().join("gim-config-ephemeral"),
+            DirSource::Ephemeral,
+        )
+    };
+    ResolvedDir { path, source }
+}
+
 /// Returns the application's config directory path (~/.config/gim/)
 ///
+/// When the home directory can't be determined the fallback chain of
+/// [`resolve_config_dir`] is used instead of failing.
+///
 /// # Returns
 /// `std::io::Result<PathBuf>` - On success, returns the path to the config directory
-///
-/// # Errors
-/// Returns `std::io::Error` with `ErrorKind::NotFound` if the home directory cannot be determined
 pub fn config_dir() -> Result<PathBuf> {
-    let config_dir = dirs::home_dir();
-    if config_dir.is_none() {
-        return Err(Error::new(ErrorKind::NotFound, "Home directory not found"));
+    Ok(resolve_config_dir().path)
+}
+
+/// Returns the application's state directory path (~/.local/state/gim/)
+///
+/// The state directory holds machine-local data that users don't edit by hand,
+/// such as snapshots.
+///
+/// Without a home directory it lives inside the config directory.
+///
+/// # Returns
+/// `std::io::Result<PathBuf>` - On success, returns the path to the state directory
+pub fn state_dir() -> Result<PathBuf> {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(dir) = crate::testing::thread_config_dir() {
+        return Ok(dir.join("state"));
+    }
+
+    match dirs::home_dir() {
+        Some(home) => Ok(home.join(".local").join("state").join("gim")),
+        None => Ok(config_dir()?.join("state")),
+    }
+}
+
+/// Returns the application's cache directory path (~/.cache/gim/)
+///
+/// The cache directory holds data that can be rebuilt at any time, such as the
+/// precompiled schema. Without a home directory it lives inside the config directory.
+///
+/// # Returns
+/// `std::io::Result<PathBuf>` - On success, returns the path to the cache directory
+pub fn cache_dir() -> Result<PathBuf> {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(dir) = crate::testing::thread_config_dir() {
+        return Ok(dir.join("cache"));
+    }
+
+    match dirs::cache_dir() {
+        Some(cache) => Ok(cache.join("gim")),
+        None => Ok(config_dir()?.join("cache")),
     }
+}
+
+/// Returns the application's data directory path (~/.local/share/gim/)
+///
+/// The data directory holds files the application keeps for the user, such as
+/// downloaded prompt templates. Without a home directory it lives inside the config
+/// directory.
+///
+/// # Returns
+/// `std::io::Result<PathBuf>` - On success, returns the path to the data directory
+pub fn data_dir() -> Result<PathBuf> {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(dir) = crate::testing::thread_config_dir() {
+        return Ok(dir.join("data"));
+    }
+
+    match dirs::data_dir() {
+        Some(data) => Ok(data.join("gim")),
+        None => Ok(config_dir()?.join("data")),
+    }
+}
+
+/// Returns the directory holding backups and named snapshots (`<state dir>/snapshots`).
+///
+/// # Returns
+/// `std::io::Result<PathBuf>` - On success, returns the path to the backups directory
+pub fn backups_dir() -> Result<PathBuf> {
+    Ok(state_dir()?.join("snapshots"))
+}
+
+/// Every directory the application uses.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AppDirs {
+    /// See [`config_dir`]
+    pub config: PathBuf,
+    /// See [`data_dir`]
+    pub data: PathBuf,
+    /// See [`cache_dir`]
+    pub cache: PathBuf,
+    /// See [`state_dir`]
+    pub state: PathBuf,
+    /// See [`backups_dir`]
+    pub backups: PathBuf,
+}
+
+/// Resolves every application directory without creating any.
+///
+/// # Returns
+/// `std::io::Result<AppDirs>` - The directories
+pub fn app_dirs() -> Result<AppDirs> {
+    Ok(AppDirs {
+        config: config_dir()?,
+        data: data_dir()?,
+        cache: cache_dir()?,
+        state: state_dir()?,
+        backups: backups_dir()?,
+    })
+}
 
-    let config_dir = config_dir.unwrap().join(".config").join("gim");
-    Ok(config_dir)
+/// Resolves every application directory and creates the missing ones.
+///
+/// Directories are created readable by the current user only, since the config and
+/// state directories hold secrets and machine-local data; directories that already exist
+/// are left as they are, so calling this repeatedly is cheap and changes nothing.
+///
+/// # Returns
+/// `std::io::Result<AppDirs>` - The directories, or an error if one can't be created
+pub fn ensure_app_dirs() -> Result<AppDirs> {
+    let dirs = app_dirs()?;
+    if resolve_config_dir().is_ephemeral() {
+        return Ok(dirs);
+    }
+    for dir in [&dirs.config, &dirs.data, &dirs.cache, &dirs.state, &dirs.backups] {
+        create_private_dir(dir)?;
+    }
+    Ok(dirs)
+}
+
+fn create_private_dir(dir: &Path) -> Result<()> {
+    if dir.is_dir() {
+        return Ok(());
+    }
+    let mut builder = fs::DirBuilder::new();
+    builder.recursive(true);
+    #[cfg(unix)]
+    std::os::unix::fs::DirBuilderExt::mode(&mut builder, 0o700);
+    builder.create(dir)
+}
+
+/// Returns the directory of the admin-distributed system configuration.
+///
+/// This is `/etc/gim` on Unix and `%PROGRAMDATA%\gim` on Windows. The directory is only
+/// read; the crate never writes to it.
+///
+/// # Returns
+///
+/// * `PathBuf` - The system config directory
+pub fn system_config_dir() -> PathBuf {
+    #[cfg(any(test, feature = "testing"))]
+    if let Some(dir) = crate::testing::thread_config_dir() {
+        return dir.join("system");
+    }
+
+    if cfg!(windows) {
+        std::env::var_os("PROGRAMDATA")
+            .map(PathBuf::from)
+            .unwrap_or_else(|| PathBuf::from(r"C:\ProgramData"))
+            .join("gim")
+    } else {
+        PathBuf::from("/etc/gim")
+    }
 }
 
 #[cfg(test)]
@@ -37,4 +317,55 @@ mod tests {
         let home = dirs::home_dir().unwrap();
         assert!(path.starts_with(home), "Config path should start with home directory");
     }
+
+    #[test]
+    fn test_ensure_app_dirs_is_idempotent() {
+        let temp = crate::testing::TempConfigDir::new().unwrap();
+        fs::remove_dir_all(temp.path()).unwrap();
+        let dirs = ensure_app_dirs().unwrap();
+        assert_eq!(dirs, ensure_app_dirs().unwrap());
+        for dir in [&dirs.config, &dirs.data, &dirs.cache, &dirs.state, &dirs.backups] {
+            assert!(dir.is_dir());
+        }
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            let mode = fs::metadata(&dirs.state).unwrap().permissions().mode();
+            assert_eq!(mode & 0o777, 0o700);
+        }
+    }
+
+    #[test]
+    fn test_resolve_fallback_chain() {
+        let env = |vars: &'static [(&'static str, &'static str)]| {
+            move |name: &str| {
+                vars.iter()
+                    .find(|(var, _)| *var == name)
+                    .map(|(_, value)| OsString::from(value))
+            }
+        };
+
+        let both = env(&[(XDG_CONFIG_HOME, "/xdg"), (USERPROFILE, "/up")]);
+        let xdg = resolve_with(None, both, None);
+        assert_eq!(xdg.source, DirSource::XdgConfigHome);
+        assert_eq!(xdg.path, PathBuf::from("/xdg").join("gim"));
+
+        let empty_xdg = env(&[(XDG_CONFIG_HOME, ""), (USERPROFILE, "/up")]);
+        let profile = resolve_with(None, empty_xdg, None);
+        assert_eq!(profile.source, DirSource::UserProfile);
+
+        let relocated = resolve_with(
+            Some(PathBuf::from("/home/u")),
+            env(&[(CONFIG_DIR_ENV, "/local/gim")]),
+            None,
+        );
+        assert_eq!(relocated.source, DirSource::Env);
+        assert_eq!(relocated.path, PathBuf::from("/local/gim"));
+
+        let fallback = resolve_with(None, env(&[]), Some(PathBuf::from("/data/gim")));
+        assert_eq!(fallback.source, DirSource::Fallback);
+
+        let ephemeral = resolve_with(None, env(&[]), None);
+        assert!(ephemeral.is_ephemeral());
+    }
 }
@@ -0,0 +1,224 @@
+use std::{fmt, io::Result, time::SystemTime};
+use toml::Value;
+
+use crate::config::get_config;
+use crate::defaults::default_values;
+use crate::output::{OutputContext, Style};
+use crate::path;
+use crate::profile::{PROFILES_SECTION, current_env};
+use crate::secret::{SECRET_PLACEHOLDER, is_secret};
+use crate::session::session_override;
+use crate::temporary::{TEMPORARY_SECTION, active_override};
+
+/// Where the effective value of a key comes from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Origin {
+    /// The configuration file
+    File,
+    /// An override of this process, see [`crate::session`]
+    Session,
+    /// A temporary override that hasn't expired
+    Temporary,
+    /// The `[env.<name>]` table of the active `GIM_ENV` profile
+    Profile(String),
+    /// The built-in or application default; the file doesn't set the key
+    Default,
+}
+
+impl fmt::Display for Origin {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Origin::File => f.write_str("file"),
+            Origin::Session => f.write_str("session"),
+            Origin::Temporary => f.write_str("temporary"),
+            Origin::Profile(name) => write!(f, "env:{}", name),
+            Origin::Default => f.write_str("default"),
+        }
+    }
+}
+
+/// Options of [`render_table`].
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct TableOptions {
+    /// Where the listing is printed; the header is emphasized and masked secrets are
+    /// dimmed if it allows colors
+    pub output: OutputContext,
+    /// Add a column naming where each value comes from
+    pub show_origin: bool,
+    /// Only list the keys of this section
+    pub section: Option<String>,
+}
+
+/// One line of the listing.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableRow {
+    /// The top-level section
+    pub section: String,
+    /// The key below the section, dotted if nested
+    pub key: String,
+    /// The effective value, secrets masked
+    pub value: Value,
+    /// Where the value comes from
+    pub origin: Origin,
+}
+
+/// Renders the effective configuration as an aligned listing for `gim config list`.
+///
+/// Secrets are always masked.
+///
+/// # Arguments
+///
+/// * `options` - Which keys to list and how to format them
+///
+/// # Returns
+///
+/// * `Result<String>` - The listing, one key per line after a header, or an error
+pub fn render_table(options: &TableOptions) -> Result<String> {
+    let rows = table_rows(&get_config()?, &default_values(), current_env().as_deref());
+    Ok(format_rows(&rows, options))
+}
+
+/// Lists the effective value and origin of every key of `config` and `defaults`.
+pub fn table_rows(config: &Value, defaults: &Value, profile: Option<&str>) -> Vec<TableRow> {
+    let now = SystemTime::now();
+    let mut paths = path::leaf_paths(config);
+    for default_path in path::leaf_paths(defaults) {
+        if !paths.contains(&default_path) {
+            paths.push(default_path);
+        }
+    }
+    paths.retain(|key_path| {
+        let section = key_path.split('.').next().unwrap_or_default();
+        section != TEMPORARY_SECTION && section != PROFILES_SECTION && key_path.contains('.')
+    });
+    paths.sort();
+
+    let profile_value = |key_path: &str| {
+        let name = profile?;
+        path::lookup(config.get(PROFILES_SECTION)?.get(name)?, key_path)
+    };
+    paths
+        .into_iter()
+        .filter_map(|key_path| {
+            let session = session_override(&key_path).flatten();
+            let (value, origin) = if let Some(value) = session.as_ref() {
+                (value, Origin::Session)
+            } else if let Some(value) = active_override(config, &key_path, now) {
+                (value, Origin::Temporary)
+            } else if let Some(value) = profile_value(&key_path) {
+                (value, Origin::Profile(profile?.to_string()))
+            } else if let Some(value) = path::lookup(config, &key_path) {
+                (value, Origin::File)
+            } else {
+                (path::lookup(defaults, &key_path)?, Origin::Default)
+            };
+            let value = if is_secret(&key_path) {
+                Value::from(SECRET_PLACEHOLDER)
+            } else {
+                value.clone()
+            };
+            let (section, key) = key_path.split_once('.')?;
+            Some(TableRow {
+                section: section.to_string(),
+                key: key.to_string(),
+                value,
+                origin,
+            })
+        })
+        .collect()
+}
+
+/// Formats rows as aligned columns.
+pub fn format_rows(rows: &[TableRow], options: &TableOptions) -> String {
+    let rows: Vec<&TableRow> = rows
+        .iter()
+        .filter(|row| options.section.as_ref().is_none_or(|s| *s == row.section))
+        .collect();
+    let mut cells: Vec<Vec<String>> = vec![header(options)];
+    for row in &rows {
+        let mut line = vec![
+            row.section.clone(),
+            row.key.clone(),
+            display_value(&row.value),
+        ];
+        if options.show_origin {
+            line.push(row.origin.to_string());
+        }
+        cells.push(line);
+    }
+
+    let columns = cells[0].len();
+    let widths: Vec<usize> = (0..columns)
+        .map(|column| {
+            cells
+                .iter()
+                .map(|line| line[column].chars().count())
+                .max()
+                .unwrap_or(0)
+        })
+        .collect();
+
+    let mut out = String::new();
+    for (index, line) in cells.iter().enumerate() {
+        let mut text = String::new();
+        for (column, cell) in line.iter().enumerate() {
+            if column + 1 == columns {
+                text.push_str(cell);
+            } else {
+                let padding = widths[column] - cell.chars().count();
+                text.push_str(cell);
+                text.push_str(&" ".repeat(padding + 2));
+            }
+        }
+        let masked = index > 0 && rows[index - 1].value.as_str() == Some(SECRET_PLACEHOLDER);
+        match (index == 0, masked) {
+            (true, _) => out.push_str(&options.output.paint(&text, Style::Bold)),
+            (false, true) => out.push_str(&options.output.paint(&text, Style::Dim)),
+            _ => out.push_str(&text),
+        }
+        out.push('\n');
+    }
+    out
+}
+
+fn header(options: &TableOptions) -> Vec<String> {
+    let mut header = vec!["SECTION", "KEY", "VALUE"];
+    if options.show_origin {
+        header.push("ORIGIN");
+    }
+    header.into_iter().map(str::to_string).collect()
+}
+
+fn display_value(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rows_mask_secrets_and_name_origins() {
+        let config: Value = toml::from_str(
+            "[ai]\nmodel = 'gpt-4o'\napikey = 'sk-live'\n[env.work.ai]\nmodel = 'o1'\n",
+        )
+        .unwrap();
+        let defaults: Value = toml::from_str("[ai]\nlanguage = 'English'\n").unwrap();
+        let rows = table_rows(&config, &defaults, Some("work"));
+        let options = TableOptions {
+            show_origin: true,
+            ..TableOptions::default()
+        };
+        assert_eq!(
+            format_rows(&rows, &options),
+            "SECTION  KEY       VALUE       ORIGIN\n\
+             ai       apikey    <redacted>  file\n\
+             ai       language  English     default\n\
+             ai       model     o1          env:work\n"
+        );
+    }
+}
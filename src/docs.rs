@@ -0,0 +1,161 @@
+use std::io::Result;
+use toml::Value;
+
+use crate::config::get_config;
+use crate::path;
+use crate::schema::{KeySchema, schema};
+use crate::secret::SECRET_PLACEHOLDER;
+
+/// The markup [`export_docs`] renders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum DocFormat {
+    /// A Markdown document with one table per section
+    Markdown,
+    /// An HTML fragment with one `<table>` per section
+    Html,
+}
+
+/// Documents every known key together with the user's current value.
+///
+/// Each key is listed with its description, default, allowed values and current value;
+/// secrets are masked and keys the file doesn't set are marked as such.
+///
+/// # Arguments
+///
+/// * `format` - The markup to render
+///
+/// # Returns
+///
+/// * `Result<String>` - The document or an error if the configuration can't be read
+pub fn export_docs(format: DocFormat) -> Result<String> {
+    Ok(render_docs(&schema(), &get_config()?, format))
+}
+
+/// Renders the documentation of `keys` with the current values taken from `config`.
+pub fn render_docs(keys: &[KeySchema], config: &Value, format: DocFormat) -> String {
+    let mut sections: Vec<(&str, Vec<&KeySchema>)> = Vec::new();
+    for key in keys {
+        let section = key.path.split('.').next().unwrap_or_default();
+        match sections.iter_mut().find(|(name, _)| *name == section) {
+            Some((_, keys)) => keys.push(key),
+            None => sections.push((section, vec![key])),
+        }
+    }
+
+    let mut out = String::new();
+    for (section, keys) in sections {
+        let rows: Vec<[String; 5]> = keys
+            .iter()
+            .map(|key| {
+                let current = match path::lookup(config, &key.path) {
+                    Some(_) if key.secret => SECRET_PLACEHOLDER.to_string(),
+                    Some(value) => value.to_string(),
+                    None => NOT_SET.to_string(),
+                };
+                let allowed = if key.allowed.is_empty() {
+                    "any".to_string()
+                } else {
+                    key.allowed
+                        .iter()
+                        .map(Value::to_string)
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                };
+                let default = if key.secret {
+                    SECRET_PLACEHOLDER.to_string()
+                } else {
+                    key.default.to_string()
+                };
+                [
+                    key.path.clone(),
+                    key.description.clone(),
+                    default,
+                    allowed,
+                    current,
+                ]
+            })
+            .collect();
+        match format {
+            DocFormat::Markdown => markdown_section(&mut out, section, &rows),
+            DocFormat::Html => html_section(&mut out, section, &rows),
+        }
+    }
+    out
+}
+
+/// The current value of keys the configuration doesn't set.
+const NOT_SET: &str = "not set";
+
+const HEADERS: [&str; 5] = ["Key", "Description", "Default", "Allowed", "Current"];
+
+fn markdown_section(out: &mut String, section: &str, rows: &[[String; 5]]) {
+    if !out.is_empty() {
+        out.push('\n');
+    }
+    out.push_str(&format!("## {}\n\n", section));
+    out.push_str(&format!("| {} |\n", HEADERS.join(" | ")));
+    out.push_str(&format!("|{}\n", " --- |".repeat(HEADERS.len())));
+    for row in rows {
+        let cells: Vec<String> = row
+            .iter()
+            .enumerate()
+            .map(|(column, cell)| {
+                let cell = cell.replace('|', "\\|");
+                match column {
+                    0 | 2 => format!("`{}`", cell),
+                    4 if cell != NOT_SET => format!("`{}`", cell),
+                    _ => cell,
+                }
+            })
+            .collect();
+        out.push_str(&format!("| {} |\n", cells.join(" | ")));
+    }
+}
+
+fn html_section(out: &mut String, section: &str, rows: &[[String; 5]]) {
+    out.push_str(&format!("<h2>{}</h2>\n<table>\n<tr>", escape_html(section)));
+    for header in HEADERS {
+        out.push_str(&format!("<th>{}</th>", header));
+    }
+    out.push_str("</tr>\n");
+    for row in rows {
+        out.push_str("<tr>");
+        for cell in row {
+            out.push_str(&format!("<td>{}</td>", escape_html(cell)));
+        }
+        out.push_str("</tr>\n");
+    }
+    out.push_str("</table>\n");
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_docs_list_defaults_and_current_values() {
+        let config: Value = toml::from_str("[ai]\nmodel = 'gpt-4o'\napikey = 'sk-live'\n").unwrap();
+        let keys = schema();
+
+        let markdown = render_docs(&keys, &config, DocFormat::Markdown);
+        assert!(markdown.starts_with("## ai\n\n| Key | Description |"));
+        assert!(markdown.contains(
+            "| `ai.model` | Model name, e.g. \"gpt-4o\" | `\"\"` | any | `\"gpt-4o\"` |"
+        ));
+        assert!(markdown.contains("## update"));
+        assert!(!markdown.contains("sk-live"));
+        assert!(markdown.contains("`ai.url`") && markdown.contains("not set"));
+
+        let html = render_docs(&keys, &config, DocFormat::Html);
+        assert!(html.contains("<td>&lt;redacted&gt;</td>"));
+        assert!(html.contains("<td>&quot;gpt-4o&quot;</td>"));
+    }
+}
@@ -0,0 +1,91 @@
+use std::{collections::HashSet, io::Result};
+use toml::Value;
+
+use crate::compat::is_newer_schema;
+use crate::config::get_config;
+use crate::path;
+use crate::schema::is_known_key;
+use crate::trace::{access_report, is_access_tracing_enabled};
+
+/// Why a key is considered stale.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StaleReason {
+    /// The schema doesn't define the key; access tracing was off, so reads are unknown
+    NotInSchema,
+    /// The schema doesn't define the key and nothing read it while tracing was on
+    NotInSchemaAndUnread,
+}
+
+/// A key in the config file that looks like a leftover.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StaleKey {
+    /// The dotted key path
+    pub path: String,
+    /// Why the key is reported
+    pub reason: StaleReason,
+}
+
+/// Reports keys in the config file that the schema doesn't define and nothing has read.
+///
+/// With access tracing enabled (see [`crate::trace`]) keys read by the application during
+/// this run are not reported, so settings of embedding applications that aren't part of
+/// the crate's schema don't count as stale. Without tracing every unknown key is reported.
+///
+/// # Returns
+///
+/// * `Result<Vec<StaleKey>>` - The candidate keys for cleanup, in document order
+pub fn stale_keys() -> Result<Vec<StaleKey>> {
+    Ok(find_stale_keys(&get_config()?))
+}
+
+/// Reports the stale keys of a given configuration, see [`stale_keys`].
+///
+/// Nothing is reported for a file written by a newer release, whose unknown keys are
+/// most likely settings this build doesn't know yet.
+pub fn find_stale_keys(config: &Value) -> Vec<StaleKey> {
+    if is_newer_schema(config) {
+        return Vec::new();
+    }
+    let tracing = is_access_tracing_enabled();
+    let read: HashSet<String> = if tracing {
+        access_report()
+            .into_iter()
+            .filter(|r| r.is_hit())
+            .map(|r| r.path)
+            .collect()
+    } else {
+        HashSet::new()
+    };
+    path::leaf_paths(config)
+        .into_iter()
+        .filter(|key_path| !is_known_key(key_path))
+        .filter(|key_path| !read.contains(key_path))
+        .map(|key_path| StaleKey {
+            path: key_path,
+            reason: if tracing {
+                StaleReason::NotInSchemaAndUnread
+            } else {
+                StaleReason::NotInSchema
+            },
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_stale_keys_reports_unknown_keys() {
+        let config: Value = toml::from_str(
+            "[ai]\nmodel = 'x'\nold_temperature = 1\n[legacy_doctor]\nflag = true\n",
+        )
+        .unwrap();
+        let stale: Vec<String> = find_stale_keys(&config)
+            .into_iter()
+            .map(|k| k.path)
+            .collect();
+        assert_eq!(stale, vec!["ai.old_temperature", "legacy_doctor.flag"]);
+    }
+}
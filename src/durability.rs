@@ -0,0 +1,33 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static FULL: AtomicBool = AtomicBool::new(false);
+
+/// How hard a save tries to survive a crash or power loss.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Durability {
+    /// The new file is written to a temporary file and renamed over the old one, so a
+    /// crash leaves either file intact, but the operating system may still hold both in
+    /// its cache when power is lost
+    #[default]
+    Fast,
+    /// Like `Fast`, but the temporary file is flushed to disk before the rename and the
+    /// directory after it, so a completed save survives an abrupt power loss; saves take
+    /// a few milliseconds longer
+    Full,
+}
+
+/// Sets the durability of every save made by this process from now on, including the
+/// state file and snapshots.
+pub fn set_durability(durability: Durability) {
+    FULL.store(durability == Durability::Full, Ordering::Relaxed);
+}
+
+/// Returns the durability of saves made by this process.
+pub fn durability() -> Durability {
+    if FULL.load(Ordering::Relaxed) {
+        Durability::Full
+    } else {
+        Durability::Fast
+    }
+}
@@ -0,0 +1,275 @@
+use toml::Value;
+use toml_edit::{Array, ArrayOfTables, Decor, DocumentMut, InlineTable, Item, Table, TableLike};
+
+/// Renders `new` by editing the existing document text in place.
+///
+/// Only the keys that differ between `old` (the parsed form of `existing`) and `new` are
+/// touched: their lines are replaced, added at the end of their table or removed. Every
+/// other line keeps its position, formatting and comments, so changing one key produces a
+/// one-line diff for users who keep `config.toml` in version control.
+///
+/// # Arguments
+///
+/// * `existing` - The current text of the file
+/// * `old` - The parsed form of `existing`
+/// * `new` - The document to render
+///
+/// # Returns
+///
+/// * `Option<String>` - The edited text, or `None` if `existing` can't be edited, e.g.
+///   because it doesn't parse
+pub(crate) fn render_minimal(existing: &str, old: &Value, new: &Value) -> Option<String> {
+    let mut document: DocumentMut = existing.parse().ok()?;
+    sync_table(document.as_table_mut(), old.as_table()?, new.as_table()?);
+    Some(document.to_string())
+}
+
+fn sync_table(table: &mut Table, old: &toml::Table, new: &toml::Table) {
+    let removed: Vec<String> = old
+        .keys()
+        .filter(|key| !new.contains_key(*key))
+        .cloned()
+        .collect();
+    for key in removed {
+        table.remove(&key);
+    }
+    for (key, value) in new {
+        let previous = old.get(key);
+        if previous == Some(value) {
+            continue;
+        }
+        if let (Some(Value::Table(previous)), Value::Table(value)) = (previous, value)
+            && let Some(Item::Table(child)) = table.get_mut(key)
+        {
+            sync_table(child, previous, value);
+            continue;
+        }
+        let mut item = to_item(value);
+        match table.get_mut(key) {
+            // Assigning through the existing entry keeps the key with its comments and spacing.
+            Some(current) => {
+                if let (Item::Value(current), Item::Value(replacement)) = (&*current, &mut item) {
+                    *replacement.decor_mut() = current.decor().clone();
+                }
+                *current = item;
+            }
+            None => {
+                table.insert(key, item);
+            }
+        }
+    }
+}
+
+fn to_item(value: &Value) -> Item {
+    match value {
+        Value::Table(table) => Item::Table(to_table(table)),
+        Value::Array(items) if !items.is_empty() && items.iter().all(Value::is_table) => {
+            let mut tables = ArrayOfTables::new();
+            for item in items.iter().filter_map(Value::as_table) {
+                tables.push(to_table(item));
+            }
+            Item::ArrayOfTables(tables)
+        }
+        other => Item::Value(to_value(other)),
+    }
+}
+
+fn to_table(table: &toml::Table) -> Table {
+    let mut result = Table::new();
+    for (key, value) in table {
+        result.insert(key, to_item(value));
+    }
+    result
+}
+
+fn to_value(value: &Value) -> toml_edit::Value {
+    match value {
+        Value::String(s) => s.as_str().into(),
+        Value::Integer(i) => (*i).into(),
+        Value::Float(f) => (*f).into(),
+        Value::Boolean(b) => (*b).into(),
+        Value::Datetime(d) => (*d).into(),
+        Value::Array(items) => {
+            let mut array = Array::new();
+            for item in items {
+                array.push(to_value(item));
+            }
+            array.into()
+        }
+        Value::Table(table) => {
+            let mut inline = InlineTable::new();
+            for (key, item) in table {
+                inline.insert(key, to_value(item));
+            }
+            inline.into()
+        }
+    }
+}
+
+/// Returns the comment attached to the key at `path` in the document `text`.
+///
+/// That is the run of `#` lines directly above the key, or above the header of a table,
+/// without the `#`; a key without one falls back to its trailing comment.
+///
+/// # Returns
+///
+/// * `Option<Option<String>>` - `None` if `text` doesn't parse or has no such key,
+///   otherwise the comment, `None` if the key has none
+pub(crate) fn comment_of(text: &str, path: &[&str]) -> Option<Option<String>> {
+    let document: DocumentMut = text.parse().ok()?;
+    let (last, parents) = path.split_last()?;
+    let mut table: &dyn TableLike = document.as_table();
+    for key in parents {
+        table = table.get(key)?.as_table_like()?;
+    }
+    let (key, item) = table.get_key_value(last)?;
+    let decor = match item {
+        Item::Table(table) => table.decor(),
+        _ => key.leaf_decor(),
+    };
+    let above = comment_lines(decor.prefix().and_then(|p| p.as_str()).unwrap_or(""));
+    let trailing = || {
+        let value = item.as_value()?;
+        comment_lines(value.decor().suffix()?.as_str()?)
+    };
+    Some(above.or_else(trailing))
+}
+
+/// Replaces the comment above the key at `path` in the document `text`.
+///
+/// # Arguments
+///
+/// * `text` - The document
+/// * `path` - The segments of the key path
+/// * `comment` - The new comment, one `#` line per line; empty removes the comment
+///
+/// # Returns
+///
+/// * `Option<String>` - The edited document, or `None` if `text` doesn't parse or has no
+///   such key
+pub(crate) fn with_comment(text: &str, path: &[&str], comment: &str) -> Option<String> {
+    let mut document: DocumentMut = text.parse().ok()?;
+    let (last, parents) = path.split_last()?;
+    let mut table: &mut dyn TableLike = document.as_table_mut();
+    for key in parents {
+        table = table.get_mut(key)?.as_table_like_mut()?;
+    }
+    if table.get(last)?.is_table() {
+        let Some(Item::Table(child)) = table.get_mut(last) else {
+            return None;
+        };
+        set_comment_lines(child.decor_mut(), comment);
+    } else {
+        set_comment_lines(table.key_mut(last)?.leaf_decor_mut(), comment);
+    }
+    Some(document.to_string())
+}
+
+fn comment_lines(decor: &str) -> Option<String> {
+    let lines: Vec<&str> = decor
+        .lines()
+        .map(str::trim)
+        .filter_map(|line| line.strip_prefix('#'))
+        .map(|line| line.strip_prefix(' ').unwrap_or(line).trim_end())
+        .collect();
+    if lines.is_empty() {
+        None
+    } else {
+        Some(lines.join("\n"))
+    }
+}
+
+/// Sets the comment lines of a prefix decor, keeping the blank lines that separate the
+/// key from the one before it.
+fn set_comment_lines(decor: &mut Decor, comment: &str) {
+    let existing = decor.prefix().and_then(|p| p.as_str()).unwrap_or("");
+    let mut prefix: String = existing
+        .split_inclusive('\n')
+        .take_while(|line| line.trim().is_empty())
+        .collect();
+    for line in comment.lines() {
+        if line.is_empty() {
+            prefix.push_str("#\n");
+        } else {
+            prefix.push_str(&format!("# {}\n", line));
+        }
+    }
+    decor.set_prefix(prefix);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DOCUMENT: &str = "\
+# AI provider used to generate commit messages
+[ai]
+# Model name
+model   = \"gpt-4o\"   # pinned
+url = 'https://api.example'
+apikey = \"\"
+
+[update]
+max_try = 5
+";
+
+    fn edit(f: impl FnOnce(&mut Value)) -> String {
+        let old: Value = toml::from_str(DOCUMENT).unwrap();
+        let mut new = old.clone();
+        f(&mut new);
+        let text = render_minimal(DOCUMENT, &old, &new).unwrap();
+        assert_eq!(toml::from_str::<Value>(&text).unwrap(), new);
+        text
+    }
+
+    fn changed_lines(text: &str) -> Vec<(Option<&str>, Option<&str>)> {
+        let before: Vec<&str> = DOCUMENT.lines().collect();
+        let after: Vec<&str> = text.lines().collect();
+        (0..before.len().max(after.len()))
+            .map(|i| (before.get(i).copied(), after.get(i).copied()))
+            .filter(|(a, b)| a != b)
+            .collect()
+    }
+
+    #[test]
+    fn test_changing_one_key_changes_one_line() {
+        let text = edit(|config| {
+            config["ai"]["model"] = Value::from("o1");
+        });
+        assert_eq!(
+            changed_lines(&text),
+            vec![(
+                Some("model   = \"gpt-4o\"   # pinned"),
+                Some("model   = \"o1\"   # pinned")
+            )]
+        );
+        assert_eq!(
+            render_minimal(
+                DOCUMENT,
+                &toml::from_str(DOCUMENT).unwrap(),
+                &toml::from_str(DOCUMENT).unwrap()
+            )
+            .unwrap(),
+            DOCUMENT
+        );
+    }
+
+    #[test]
+    fn test_added_and_removed_keys_leave_other_lines_alone() {
+        let text = edit(|config| {
+            config["update"]
+                .as_table_mut()
+                .unwrap()
+                .insert("timezone".to_string(), Value::from("UTC"));
+        });
+        assert_eq!(
+            changed_lines(&text),
+            vec![(None, Some("timezone = \"UTC\""))]
+        );
+
+        let text = edit(|config| {
+            config["ai"].as_table_mut().unwrap().remove("url");
+        });
+        assert_eq!(text, DOCUMENT.replace("url = 'https://api.example'\n", ""));
+    }
+}
@@ -0,0 +1,205 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicU8, Ordering},
+    },
+};
+
+use crate::{
+    format::Format,
+    init::emit_warning,
+    lenient::{ParseMode, decode_windows_1252, parse_mode},
+};
+
+/// The UTF-8 byte order mark some Windows editors put at the start of files.
+const UTF8_BOM: &[u8] = b"\xEF\xBB\xBF";
+
+static LINE_ENDING: AtomicU8 = AtomicU8::new(0);
+static WARNINGS: Mutex<Vec<EncodingWarning>> = Mutex::new(Vec::new());
+
+/// The line endings configuration files are saved with.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum LineEnding {
+    /// Keep the line endings the file already uses; new files get `\n`
+    #[default]
+    Preserve,
+    /// Always `\n`
+    Lf,
+    /// Always `\r\n`
+    CrLf,
+}
+
+/// A configuration file whose encoding was repaired while reading it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct EncodingWarning {
+    /// The file
+    pub path: PathBuf,
+    /// What was repaired, for the user
+    pub message: String,
+}
+
+/// Sets the line endings of every file saved by this process from now on.
+///
+/// Files are always saved as UTF-8 without a byte order mark. By default a file keeps the
+/// line endings it was found with, so a file edited on Windows with `\r\n` doesn't flip to
+/// `\n` every time the crate saves it.
+pub fn set_line_ending(ending: LineEnding) {
+    let code = match ending {
+        LineEnding::Preserve => 0,
+        LineEnding::Lf => 1,
+        LineEnding::CrLf => 2,
+    };
+    LINE_ENDING.store(code, Ordering::Relaxed);
+}
+
+/// Returns the line endings files are saved with.
+pub fn line_ending() -> LineEnding {
+    match LINE_ENDING.load(Ordering::Relaxed) {
+        1 => LineEnding::Lf,
+        2 => LineEnding::CrLf,
+        _ => LineEnding::Preserve,
+    }
+}
+
+/// Returns the files of this process whose byte order mark was stripped, one entry per
+/// file, e.g. to show them in `gim doctor`.
+pub fn encoding_warnings() -> Vec<EncodingWarning> {
+    WARNINGS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Decodes the content of a configuration file, which must be UTF-8.
+///
+/// A UTF-8 byte order mark is stripped and recorded as an [`EncodingWarning`]. In
+/// [`ParseMode::Lenient`] a TOML file that isn't UTF-8 is read as Windows-1252, what
+/// Notepad saves by default, and recorded the same way.
+pub(crate) fn decode(file: &Path, bytes: Vec<u8>) -> Result<String> {
+    let not_utf8 = |detail: &str| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "'{}' is {}; save it as UTF-8 without a byte order mark",
+                file.display(),
+                detail
+            ),
+        )
+    };
+    if bytes.starts_with(b"\xFF\xFE") || bytes.starts_with(b"\xFE\xFF") {
+        return Err(not_utf8("UTF-16 text"));
+    }
+    let mut text = match String::from_utf8(bytes) {
+        Ok(text) => text,
+        Err(e)
+            if parse_mode() == ParseMode::Lenient
+                && matches!(Format::from_path(file), Ok(Format::Toml)) =>
+        {
+            record_warning(EncodingWarning {
+                path: file.to_path_buf(),
+                message: format!(
+                    "'{}' is not UTF-8 text; it was read as Windows-1252 and is saved as UTF-8 on the next save",
+                    file.display()
+                ),
+            });
+            return Ok(decode_windows_1252(e.as_bytes()));
+        }
+        Err(_) => return Err(not_utf8("not UTF-8 text")),
+    };
+    if text.as_bytes().starts_with(UTF8_BOM) {
+        text.drain(..UTF8_BOM.len());
+        record_warning(EncodingWarning {
+            path: file.to_path_buf(),
+            message: format!(
+                "'{}' starts with a byte order mark; it is ignored and dropped on the next save",
+                file.display()
+            ),
+        });
+    }
+    Ok(text)
+}
+
+/// Returns whether `bytes` are UTF-8 without a byte order mark, the encoding files are
+/// saved in.
+pub(crate) fn is_plain_utf8(bytes: &[u8]) -> bool {
+    !bytes.starts_with(UTF8_BOM) && std::str::from_utf8(bytes).is_ok()
+}
+
+/// Converts the line endings of `content`, about to replace `existing`, to those
+/// [`line_ending`] asks for.
+pub(crate) fn with_line_endings(content: String, existing: Option<&str>) -> String {
+    convert(content, line_ending(), existing)
+}
+
+fn convert(content: String, ending: LineEnding, existing: Option<&str>) -> String {
+    let crlf = match ending {
+        LineEnding::Lf => false,
+        LineEnding::CrLf => true,
+        LineEnding::Preserve => existing.is_some_and(uses_crlf),
+    };
+    if crlf {
+        content.replace("\r\n", "\n").replace('\n', "\r\n")
+    } else if content.contains("\r\n") {
+        content.replace("\r\n", "\n")
+    } else {
+        content
+    }
+}
+
+/// Returns whether most lines of `text` end with `\r\n`.
+fn uses_crlf(text: &str) -> bool {
+    let crlf = text.matches("\r\n").count();
+    crlf > 0 && crlf >= text.matches('\n').count() - crlf
+}
+
+fn record_warning(warning: EncodingWarning) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(file = %warning.path.display(), "stripped byte order mark");
+    emit_warning(&warning.message);
+    let mut warnings = WARNINGS.lock().unwrap_or_else(|e| e.into_inner());
+    warnings.retain(|w| w.path != warning.path);
+    warnings.push(warning);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config_file, get_config_value, update_config_value};
+    use crate::testing::TempConfigDir;
+    use toml::Value;
+
+    #[test]
+    fn test_convert_line_endings() {
+        let lf = "a = 1\nb = 2\n".to_string();
+        let crlf = "a = 1\r\nb = 2\r\n";
+        assert_eq!(convert(lf.clone(), LineEnding::Preserve, Some(crlf)), crlf);
+        assert_eq!(convert(lf.clone(), LineEnding::Preserve, None), lf);
+        assert_eq!(convert(crlf.to_string(), LineEnding::Lf, Some(crlf)), lf);
+        assert_eq!(convert(lf, LineEnding::CrLf, None), crlf);
+    }
+
+    #[test]
+    fn test_bom_is_stripped_and_crlf_kept() {
+        let _dir = TempConfigDir::new().unwrap();
+        let file = get_config_file().unwrap();
+        let mut content = UTF8_BOM.to_vec();
+        content.extend_from_slice(b"[ai]\r\nmodel = \"a\"\r\nlanguage = \"English\"\r\n");
+        std::fs::write(&file, content).unwrap();
+
+        assert_eq!(get_config_value("ai", "model").unwrap().as_str(), Some("a"));
+        assert!(encoding_warnings().iter().any(|w| w.path == file));
+        update_config_value("ai", "model", Value::from("b")).unwrap();
+        let saved = std::fs::read(&file).unwrap();
+        assert!(!saved.starts_with(UTF8_BOM));
+        assert_eq!(
+            String::from_utf8(saved).unwrap(),
+            "[ai]\r\nmodel = \"b\"\r\nlanguage = \"English\"\r\n"
+        );
+
+        std::fs::write(&file, b"\xFF\xFE[\x00").unwrap();
+        assert_eq!(
+            get_config_value("ai", "model").unwrap_err().kind(),
+            ErrorKind::InvalidData
+        );
+    }
+}
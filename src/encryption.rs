@@ -0,0 +1,475 @@
+use std::{
+    fmt, fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Arc, Mutex},
+};
+
+use argon2::Argon2;
+use chacha20poly1305::{
+    ChaCha20Poly1305, Key, Nonce,
+    aead::{Aead, KeyInit},
+};
+use zeroize::{Zeroize, Zeroizing};
+
+use crate::directory::state_dir;
+use crate::storage::{FileLock, write_atomic};
+
+/// Prefix marking a string value as encrypted by this crate.
+pub const ENCRYPTED_PREFIX: &str = "enc:v1:";
+
+/// The environment variable [`EnvKeyProvider::default`] reads the passphrase from.
+pub const PASSPHRASE_ENV: &str = "GIM_CONFIG_PASSPHRASE";
+
+/// The keyring service [`KeyringKeyProvider::default`] looks the passphrase up under.
+pub const KEYRING_SERVICE: &str = "gim-config";
+
+/// The keyring account [`KeyringKeyProvider::default`] looks the passphrase up for.
+pub const KEYRING_ACCOUNT: &str = "passphrase";
+
+const SALT_LEN: usize = 16;
+const NONCE_LEN: usize = 12;
+
+/// The key derived from the passphrase most recently unlocked in this process, with its salt.
+static KEY_CACHE: Mutex<Option<(Vec<u8>, Arc<EncryptionKey>)>> = Mutex::new(None);
+
+/// A source of the passphrase that unlocks encrypted values.
+///
+/// Headless CI typically uses [`EnvKeyProvider`], desktops a [`KeyringKeyProvider`],
+/// interactive terminals a [`PromptKeyProvider`], and [`ChainKeyProvider`] combines them.
+pub trait SecretKeyProvider: Send + Sync {
+    /// Returns a short name of the provider for error messages.
+    fn name(&self) -> &str;
+
+    /// Returns the passphrase, or `None` if this provider has none to offer.
+    fn passphrase(&self) -> Result<Option<String>>;
+}
+
+/// Reads the passphrase from an environment variable.
+#[derive(Debug, Clone)]
+pub struct EnvKeyProvider {
+    var: String,
+}
+
+impl EnvKeyProvider {
+    /// Creates a provider reading the environment variable `var`.
+    pub fn new(var: &str) -> EnvKeyProvider {
+        EnvKeyProvider {
+            var: var.to_string(),
+        }
+    }
+}
+
+impl Default for EnvKeyProvider {
+    fn default() -> Self {
+        EnvKeyProvider::new(PASSPHRASE_ENV)
+    }
+}
+
+impl SecretKeyProvider for EnvKeyProvider {
+    fn name(&self) -> &str {
+        "environment"
+    }
+
+    fn passphrase(&self) -> Result<Option<String>> {
+        Ok(std::env::var(&self.var).ok().filter(|v| !v.is_empty()))
+    }
+}
+
+/// Reads the passphrase from the operating system's keyring.
+///
+/// The lookup runs the platform's credential tool: `security find-generic-password` on
+/// macOS and libsecret's `secret-tool lookup` elsewhere. Without the tool or the entry the
+/// provider has no passphrase to offer.
+#[derive(Debug, Clone)]
+pub struct KeyringKeyProvider {
+    service: String,
+    account: String,
+    program: PathBuf,
+}
+
+impl KeyringKeyProvider {
+    /// Creates a provider looking up the entry of `account` under `service`.
+    pub fn new(service: &str, account: &str) -> KeyringKeyProvider {
+        let program = if cfg!(target_os = "macos") {
+            "security"
+        } else {
+            "secret-tool"
+        };
+        KeyringKeyProvider {
+            service: service.to_string(),
+            account: account.to_string(),
+            program: PathBuf::from(program),
+        }
+    }
+
+    /// Runs `program` instead of the platform's credential tool, with the same arguments.
+    pub fn program(mut self, program: impl Into<PathBuf>) -> KeyringKeyProvider {
+        self.program = program.into();
+        self
+    }
+}
+
+impl Default for KeyringKeyProvider {
+    fn default() -> Self {
+        KeyringKeyProvider::new(KEYRING_SERVICE, KEYRING_ACCOUNT)
+    }
+}
+
+impl SecretKeyProvider for KeyringKeyProvider {
+    fn name(&self) -> &str {
+        "keyring"
+    }
+
+    fn passphrase(&self) -> Result<Option<String>> {
+        let mut command = Command::new(&self.program);
+        if cfg!(target_os = "macos") {
+            command
+                .args(["find-generic-password", "-s", &self.service])
+                .args(["-a", &self.account, "-w"]);
+        } else {
+            command.args(["lookup", "service", &self.service, "account", &self.account]);
+        }
+        let output = match command.stdin(Stdio::null()).stderr(Stdio::null()).output() {
+            Ok(output) => output,
+            Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+            Err(e) => {
+                return Err(Error::new(
+                    e.kind(),
+                    format!("Failed to query the keyring: {}", e),
+                ));
+            }
+        };
+        let status = output.status;
+        let stdout = Zeroizing::new(output.stdout);
+        if !status.success() {
+            return Ok(None);
+        }
+        let passphrase = std::str::from_utf8(&stdout)
+            .map_err(|e| Error::new(ErrorKind::InvalidData, e))?
+            .trim_end_matches(['\r', '\n']);
+        Ok(Some(passphrase.to_string()).filter(|p| !p.is_empty()))
+    }
+}
+
+/// Asks the user for the passphrase through a callback, e.g. a no-echo terminal prompt.
+pub struct PromptKeyProvider<F> {
+    prompt: F,
+}
+
+impl<F> PromptKeyProvider<F>
+where
+    F: Fn(&str) -> Result<String> + Send + Sync,
+{
+    /// Creates a provider calling `prompt` with the message to show.
+    pub fn new(prompt: F) -> PromptKeyProvider<F> {
+        PromptKeyProvider { prompt }
+    }
+}
+
+impl<F> SecretKeyProvider for PromptKeyProvider<F>
+where
+    F: Fn(&str) -> Result<String> + Send + Sync,
+{
+    fn name(&self) -> &str {
+        "prompt"
+    }
+
+    fn passphrase(&self) -> Result<Option<String>> {
+        let passphrase = (self.prompt)("Passphrase for encrypted gim settings: ")?;
+        Ok(Some(passphrase).filter(|p| !p.is_empty()))
+    }
+}
+
+/// Tries several providers in order and uses the first passphrase offered.
+#[derive(Default)]
+pub struct ChainKeyProvider {
+    providers: Vec<Box<dyn SecretKeyProvider>>,
+}
+
+impl ChainKeyProvider {
+    /// Creates an empty chain.
+    pub fn new() -> ChainKeyProvider {
+        ChainKeyProvider::default()
+    }
+
+    /// Appends a provider to the chain.
+    pub fn with(mut self, provider: impl SecretKeyProvider + 'static) -> ChainKeyProvider {
+        self.providers.push(Box::new(provider));
+        self
+    }
+}
+
+impl SecretKeyProvider for ChainKeyProvider {
+    fn name(&self) -> &str {
+        "chain"
+    }
+
+    fn passphrase(&self) -> Result<Option<String>> {
+        for provider in &self.providers {
+            if let Some(passphrase) = provider.passphrase()? {
+                return Ok(Some(passphrase));
+            }
+        }
+        Ok(None)
+    }
+}
+
+/// A 256-bit key derived from a passphrase; wiped from memory on drop.
+pub struct EncryptionKey([u8; 32]);
+
+impl fmt::Debug for EncryptionKey {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str("EncryptionKey(<redacted>)")
+    }
+}
+
+impl Drop for EncryptionKey {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Derives an encryption key from a passphrase and salt with Argon2id.
+///
+/// # Arguments
+///
+/// * `passphrase` - The user's passphrase
+/// * `salt` - The per-installation salt
+///
+/// # Returns
+///
+/// * `Result<EncryptionKey>` - The derived key or an error if derivation fails
+pub fn derive_key(passphrase: &str, salt: &[u8]) -> Result<EncryptionKey> {
+    let mut key = [0u8; 32];
+    Argon2::default()
+        .hash_password_into(passphrase.as_bytes(), salt, &mut key)
+        .map_err(|e| Error::new(ErrorKind::InvalidInput, e.to_string()))?;
+    Ok(EncryptionKey(key))
+}
+
+/// Returns the key unlocking encrypted values, asking `provider` only once per process.
+///
+/// The derivation salt is created on first use and kept in the state directory.
+///
+/// # Arguments
+///
+/// * `provider` - Where to get the passphrase from if no key is cached yet
+///
+/// # Returns
+///
+/// * `Result<Arc<EncryptionKey>>` - The key, or a `PermissionDenied` error if no passphrase
+///   was provided
+pub fn unlock(provider: &dyn SecretKeyProvider) -> Result<Arc<EncryptionKey>> {
+    let salt = load_or_create_salt()?;
+    let mut cache = KEY_CACHE.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((cached_salt, key)) = cache.as_ref()
+        && *cached_salt == salt
+    {
+        return Ok(Arc::clone(key));
+    }
+    let passphrase = provider.passphrase()?.map(Zeroizing::new).ok_or_else(|| {
+        Error::new(
+            ErrorKind::PermissionDenied,
+            format!(
+                "No passphrase available from the {} key provider",
+                provider.name()
+            ),
+        )
+    })?;
+    let key = Arc::new(derive_key(&passphrase, &salt)?);
+    *cache = Some((salt, Arc::clone(&key)));
+    Ok(key)
+}
+
+/// Forgets the key cached by [`unlock`], so the next unlock asks for the passphrase again.
+pub fn forget_key() {
+    *KEY_CACHE.lock().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Returns whether a string value is encrypted by this crate.
+pub fn is_encrypted(value: &str) -> bool {
+    value.starts_with(ENCRYPTED_PREFIX)
+}
+
+/// Encrypts a string value with ChaCha20-Poly1305.
+///
+/// # Arguments
+///
+/// * `key` - The key from [`unlock`] or [`derive_key`]
+/// * `plaintext` - The value to encrypt
+///
+/// # Returns
+///
+/// * `Result<String>` - The value as `enc:v1:<hex nonce and ciphertext>`
+pub fn encrypt_value(key: &EncryptionKey, plaintext: &str) -> Result<String> {
+    encrypt_bytes(key, plaintext.as_bytes())
+        .map(|sealed| format!("{}{}", ENCRYPTED_PREFIX, to_hex(&sealed)))
+}
+
+/// Decrypts a value produced by [`encrypt_value`].
+///
+/// # Arguments
+///
+/// * `key` - The key the value was encrypted with
+/// * `value` - The `enc:v1:` value
+///
+/// # Returns
+///
+/// * `Result<String>` - The plaintext, or an `InvalidData` error if the key is wrong or the
+///   value was tampered with
+pub fn decrypt_value(key: &EncryptionKey, value: &str) -> Result<String> {
+    let sealed = value
+        .strip_prefix(ENCRYPTED_PREFIX)
+        .and_then(from_hex)
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Value is not encrypted"))?;
+    let plaintext = decrypt_bytes(key, &sealed)?;
+    String::from_utf8(plaintext).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Encrypts bytes, returning the random nonce followed by the ciphertext.
+pub(crate) fn encrypt_bytes(key: &EncryptionKey, plaintext: &[u8]) -> Result<Vec<u8>> {
+    let mut nonce = [0u8; NONCE_LEN];
+    getrandom::getrandom(&mut nonce).map_err(|e| Error::other(e.to_string()))?;
+    let cipher = ChaCha20Poly1305::new(Key::from_slice(&key.0));
+    let ciphertext = cipher
+        .encrypt(Nonce::from_slice(&nonce), plaintext)
+        .map_err(|_| Error::other("Encryption failed"))?;
+    Ok([nonce.as_slice(), &ciphertext].concat())
+}
+
+/// Decrypts the output of [`encrypt_bytes`].
+pub(crate) fn decrypt_bytes(key: &EncryptionKey, sealed: &[u8]) -> Result<Vec<u8>> {
+    if sealed.len() < NONCE_LEN {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            "Encrypted data is truncated",
+        ));
+    }
+    let (nonce, ciphertext) = sealed.split_at(NONCE_LEN);
+    ChaCha20Poly1305::new(Key::from_slice(&key.0))
+        .decrypt(Nonce::from_slice(nonce), ciphertext)
+        .map_err(|_| {
+            Error::new(
+                ErrorKind::InvalidData,
+                "Decryption failed: wrong passphrase or corrupted value",
+            )
+        })
+}
+
+/// Returns random bytes suitable as a key derivation salt.
+pub(crate) fn random_salt() -> Result<Vec<u8>> {
+    let mut salt = vec![0u8; SALT_LEN];
+    getrandom::getrandom(&mut salt).map_err(|e| Error::other(e.to_string()))?;
+    Ok(salt)
+}
+
+pub(crate) fn salt_file() -> Result<PathBuf> {
+    Ok(state_dir()?.join("encryption.salt"))
+}
+
+/// Returns the salt of this machine, creating it on first use.
+///
+/// The salt is created under the file's lock, so processes unlocking for the first time at
+/// once all derive their key from the same salt.
+fn load_or_create_salt() -> Result<Vec<u8>> {
+    let file = salt_file()?;
+    if let Some(salt) = read_salt(&file)? {
+        return Ok(salt);
+    }
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let _lock = FileLock::acquire(&file)?;
+    // Another process may have created the salt while this one waited for the lock.
+    if let Some(salt) = read_salt(&file)? {
+        return Ok(salt);
+    }
+    let salt = random_salt()?;
+    write_atomic(&file, to_hex(&salt).as_bytes())?;
+    Ok(salt)
+}
+
+fn read_salt(file: &Path) -> Result<Option<Vec<u8>>> {
+    let content = match fs::read_to_string(file) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    from_hex(content.trim()).map(Some).ok_or_else(|| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Corrupted salt file '{}'", file.display()),
+        )
+    })
+}
+
+pub(crate) fn to_hex(bytes: &[u8]) -> String {
+    bytes.iter().map(|b| format!("{:02x}", b)).collect()
+}
+
+pub(crate) fn from_hex(s: &str) -> Option<Vec<u8>> {
+    if !s.len().is_multiple_of(2) {
+        return None;
+    }
+    (0..s.len())
+        .step_by(2)
+        .map(|i| u8::from_str_radix(s.get(i..i + 2)?, 16).ok())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempConfigDir;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    #[test]
+    fn test_unlock_prompts_once_and_round_trips() {
+        let _dir = TempConfigDir::new().unwrap();
+        let prompts = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&prompts);
+        let provider = ChainKeyProvider::new()
+            .with(EnvKeyProvider::new("GIM_CONFIG_TEST_UNSET_PASSPHRASE"))
+            .with(PromptKeyProvider::new(move |_| {
+                counter.fetch_add(1, Ordering::SeqCst);
+                Ok("correct horse".to_string())
+            }));
+
+        let key = unlock(&provider).unwrap();
+        let again = unlock(&provider).unwrap();
+        assert_eq!(prompts.load(Ordering::SeqCst), 1, "Key should be cached");
+
+        let sealed = encrypt_value(&key, "sk-secret").unwrap();
+        assert!(is_encrypted(&sealed));
+        assert_eq!(decrypt_value(&again, &sealed).unwrap(), "sk-secret");
+
+        let wrong = derive_key("wrong", &random_salt().unwrap()).unwrap();
+        assert!(decrypt_value(&wrong, &sealed).is_err());
+        forget_key();
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_keyring_provider_runs_the_credential_tool() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = TempConfigDir::new().unwrap();
+        let tool = dir.path().join("credential-tool");
+        let script =
+            "#!/bin/sh\ncase \"$*\" in *gim-test*) echo 'from keyring' ;; *) exit 1 ;; esac\n";
+        fs::write(&tool, script).unwrap();
+        fs::set_permissions(&tool, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let provider = KeyringKeyProvider::new("gim-test", "ci").program(&tool);
+        assert_eq!(
+            provider.passphrase().unwrap().as_deref(),
+            Some("from keyring")
+        );
+        let missing = KeyringKeyProvider::new("other", "ci").program(&tool);
+        assert_eq!(missing.passphrase().unwrap(), None);
+        let no_tool = KeyringKeyProvider::default().program(dir.path().join("no-such-tool"));
+        assert_eq!(no_tool.passphrase().unwrap(), None);
+    }
+}
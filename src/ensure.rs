@@ -0,0 +1,89 @@
+use std::io::Result;
+use toml::Value;
+
+use crate::change::ChangeSet;
+use crate::config::modify_config;
+use crate::path;
+
+/// The outcome of an [`ensure`] call.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct EnsureReport {
+    /// Number of entries whose value had to be written
+    pub changed: usize,
+    /// Number of entries that already had the desired value
+    pub unchanged: usize,
+    /// The keys that were written, with their old and new values
+    pub changes: ChangeSet,
+}
+
+impl EnsureReport {
+    /// Returns whether the config file was rewritten.
+    pub fn is_changed(&self) -> bool {
+        self.changed > 0
+    }
+}
+
+/// Makes sure every key path holds the given value, writing only what differs.
+///
+/// When every entry already matches, the config file is not touched at all, so its
+/// modification time is preserved. This makes the call safe to run from idempotent
+/// configuration management tools such as Ansible or dotfile installers.
+///
+/// # Arguments
+///
+/// * `entries` - Pairs of dotted key path and desired value, e.g. `("ai.model", "gpt-4o".into())`
+///
+/// # Returns
+///
+/// * `Result<EnsureReport>` - How many entries changed, or an error if a path is invalid
+pub fn ensure(entries: &[(&str, Value)]) -> Result<EnsureReport> {
+    modify_config(|config| {
+        let before = config.clone();
+        let mut report = EnsureReport::default();
+        for (key_path, value) in entries {
+            if path::lookup(config, key_path) == Some(value) {
+                report.unchanged += 1;
+            } else {
+                path::insert(config, key_path, value.clone())?;
+                report.changed += 1;
+            }
+        }
+        report.changes = ChangeSet::between(&before, config);
+        Ok(report)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_config_file;
+    use crate::testing::TempConfigDir;
+    use std::fs;
+
+    #[test]
+    fn test_ensure_is_idempotent() {
+        let _dir = TempConfigDir::new().unwrap();
+        let entries = [
+            ("ai.model", Value::from("gpt-4o")),
+            ("ai.language", Value::from("English")),
+        ];
+        let first = ensure(&entries).unwrap();
+        assert_eq!((first.changed, first.unchanged), (1, 1));
+
+        let modified = fs::metadata(get_config_file().unwrap())
+            .unwrap()
+            .modified()
+            .unwrap();
+        let second = ensure(&entries).unwrap();
+        assert!(!second.is_changed());
+        assert_eq!(second.unchanged, 2);
+        let after = fs::metadata(get_config_file().unwrap())
+            .unwrap()
+            .modified()
+            .unwrap();
+        assert_eq!(
+            modified, after,
+            "Unchanged ensure must not rewrite the file"
+        );
+    }
+}
@@ -0,0 +1,135 @@
+use std::io::{Error, ErrorKind, Result};
+use toml::{Value, map};
+
+use crate::config::get_config;
+use crate::secret::{SECRET_PLACEHOLDER, is_secret};
+
+/// Key names whose values identify a particular machine or user account.
+const MACHINE_KEY_NAMES: &[&str] = &["machine_id", "id", "path", "dir", "home"];
+
+/// Exports the current configuration as a TOML string that is safe to share.
+///
+/// Secret values are replaced by [`SECRET_PLACEHOLDER`] and machine-specific values
+/// (ids, filesystem paths) are removed, so the output can be pasted into bug reports
+/// or used as team defaults.
+///
+/// # Returns
+///
+/// * `Result<String>` - The sanitized TOML document or an error
+pub fn export_shareable() -> Result<String> {
+    let config = get_config()?;
+    toml::to_string(&sanitize(&config)).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Returns a copy of `config` with secrets masked and machine-specific values removed.
+///
+/// # Arguments
+///
+/// * `config` - The configuration to sanitize
+///
+/// # Returns
+///
+/// * `Value` - The sanitized configuration
+pub fn sanitize(config: &Value) -> Value {
+    sanitize_at(config, "")
+}
+
+/// Sanitizes `value` found at `prefix`; the tables of an array are checked with their keys
+/// below the array's path, e.g. `ai.endpoints.apikey`.
+fn sanitize_at(value: &Value, prefix: &str) -> Value {
+    let table = match value {
+        Value::Table(table) => table,
+        Value::Array(items) => {
+            return Value::Array(items.iter().map(|item| sanitize_at(item, prefix)).collect());
+        }
+        _ => return value.clone(),
+    };
+    let mut sanitized = map::Map::new();
+    for (key, value) in table {
+        let key_path = if prefix.is_empty() {
+            key.clone()
+        } else {
+            format!("{}.{}", prefix, key)
+        };
+        if is_secret(&key_path) {
+            sanitized.insert(key.clone(), Value::from(SECRET_PLACEHOLDER));
+        } else if is_machine_specific(key, value) {
+            continue;
+        } else {
+            sanitized.insert(key.clone(), sanitize_at(value, &key_path));
+        }
+    }
+    Value::Table(sanitized)
+}
+
+fn is_machine_specific(key: &str, value: &Value) -> bool {
+    let key = key.to_ascii_lowercase();
+    if MACHINE_KEY_NAMES.contains(&key.as_str())
+        || key.ends_with("_id")
+        || key.ends_with("_path")
+        || key.ends_with("_dir")
+    {
+        return true;
+    }
+    value.as_str().is_some_and(looks_like_local_path)
+}
+
+fn looks_like_local_path(s: &str) -> bool {
+    let bytes = s.as_bytes();
+    s.starts_with('/')
+        || s.starts_with("~/")
+        || s.starts_with("\\\\")
+        || (bytes.len() > 2
+            && bytes[0].is_ascii_alphabetic()
+            && bytes[1] == b':'
+            && matches!(bytes[2], b'\\' | b'/'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sanitize_masks_secrets_and_drops_machine_values() {
+        let config: Value = toml::from_str(
+            r#"
+            [ai]
+            model = "gpt-4"
+            apikey = "sk-123"
+            url = "https://api.openai.com"
+
+            [prompts]
+            template_path = "/home/me/prompt.txt"
+            extra = "C:\\Users\\me\\x"
+            "#,
+        )
+        .unwrap();
+        let sanitized = sanitize(&config);
+        assert_eq!(sanitized["ai"]["apikey"].as_str(), Some(SECRET_PLACEHOLDER));
+        assert_eq!(sanitized["ai"]["model"].as_str(), Some("gpt-4"));
+        assert_eq!(
+            sanitized["ai"]["url"].as_str(),
+            Some("https://api.openai.com")
+        );
+        assert!(sanitized["prompts"].as_table().unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_sanitize_masks_secrets_in_arrays_of_tables() {
+        let config: Value = toml::from_str(
+            r#"
+            [[ai.endpoints]]
+            name = "work"
+            apikey = "sk-SECRET"
+            cache_dir = "/home/me/cache"
+            "#,
+        )
+        .unwrap();
+        let sanitized = sanitize(&config);
+        let endpoint = &sanitized["ai"]["endpoints"][0];
+        assert_eq!(endpoint["apikey"].as_str(), Some(SECRET_PLACEHOLDER));
+        assert_eq!(endpoint["name"].as_str(), Some("work"));
+        assert!(endpoint.get("cache_dir").is_none());
+        assert!(!toml::to_string(&sanitized).unwrap().contains("sk-SECRET"));
+    }
+}
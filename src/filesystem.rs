@@ -0,0 +1,220 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::storage::{FileLock, write_atomic};
+
+/// The file operations used by logic that should be testable without touching disk.
+pub trait FileSystem: Send + Sync {
+    /// Reads a whole file as UTF-8.
+    fn read_to_string(&self, path: &Path) -> Result<String>;
+
+    /// Replaces a file with `contents`, creating parent directories as needed.
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()>;
+
+    /// Returns whether a file exists.
+    fn exists(&self, path: &Path) -> bool;
+
+    /// Removes a file.
+    fn remove_file(&self, path: &Path) -> Result<()>;
+
+    /// Returns the files directly inside `dir`.
+    fn list_dir(&self, dir: &Path) -> Result<Vec<PathBuf>>;
+
+    /// Returns when a file was last modified.
+    fn modified(&self, path: &Path) -> Result<SystemTime>;
+
+    /// Runs `f` while holding the exclusive lock guarding `path`.
+    fn with_lock(&self, path: &Path, f: &mut dyn FnMut() -> Result<()>) -> Result<()>;
+}
+
+impl<F: FileSystem + ?Sized> FileSystem for Arc<F> {
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        (**self).read_to_string(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        (**self).write(path, contents)
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        (**self).exists(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        (**self).remove_file(path)
+    }
+
+    fn list_dir(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        (**self).list_dir(dir)
+    }
+
+    fn modified(&self, path: &Path) -> Result<SystemTime> {
+        (**self).modified(path)
+    }
+
+    fn with_lock(&self, path: &Path, f: &mut dyn FnMut() -> Result<()>) -> Result<()> {
+        (**self).with_lock(path, f)
+    }
+}
+
+/// The real filesystem, writing atomically and locking with lock files.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealFileSystem;
+
+impl FileSystem for RealFileSystem {
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        fs::read_to_string(path)
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        write_atomic(path, contents)?;
+        crate::config::invalidate_document_cache();
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        path.exists()
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        fs::remove_file(path)
+    }
+
+    fn list_dir(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        let mut files = Vec::new();
+        for entry in fs::read_dir(dir)? {
+            let entry = entry?;
+            if entry.file_type()?.is_file() {
+                files.push(entry.path());
+            }
+        }
+        files.sort();
+        Ok(files)
+    }
+
+    fn modified(&self, path: &Path) -> Result<SystemTime> {
+        fs::metadata(path)?.modified()
+    }
+
+    fn with_lock(&self, path: &Path, f: &mut dyn FnMut() -> Result<()>) -> Result<()> {
+        let _lock = FileLock::acquire(path)?;
+        f()
+    }
+}
+
+/// An in-memory filesystem for tests.
+#[derive(Debug)]
+pub struct MockFileSystem {
+    files: Mutex<BTreeMap<PathBuf, (Vec<u8>, SystemTime)>>,
+    now: Mutex<SystemTime>,
+}
+
+impl Default for MockFileSystem {
+    fn default() -> Self {
+        MockFileSystem {
+            files: Mutex::new(BTreeMap::new()),
+            now: Mutex::new(SystemTime::UNIX_EPOCH),
+        }
+    }
+}
+
+impl MockFileSystem {
+    /// Creates an empty in-memory filesystem.
+    pub fn new() -> MockFileSystem {
+        MockFileSystem::default()
+    }
+
+    /// Sets the modification time recorded for subsequent writes.
+    pub fn set_now(&self, now: SystemTime) {
+        *self.now.lock().unwrap_or_else(|e| e.into_inner()) = now;
+    }
+
+    fn not_found(path: &Path) -> Error {
+        Error::new(
+            ErrorKind::NotFound,
+            format!("File '{}' not found", path.display()),
+        )
+    }
+}
+
+impl FileSystem for MockFileSystem {
+    fn read_to_string(&self, path: &Path) -> Result<String> {
+        let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        let (contents, _) = files.get(path).ok_or_else(|| Self::not_found(path))?;
+        String::from_utf8(contents.clone()).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+    }
+
+    fn write(&self, path: &Path, contents: &[u8]) -> Result<()> {
+        let now = *self.now.lock().unwrap_or_else(|e| e.into_inner());
+        self.files
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .insert(path.to_path_buf(), (contents.to_vec(), now));
+        Ok(())
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        self.files
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .contains_key(path)
+    }
+
+    fn remove_file(&self, path: &Path) -> Result<()> {
+        self.files
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(path)
+            .map(|_| ())
+            .ok_or_else(|| Self::not_found(path))
+    }
+
+    fn list_dir(&self, dir: &Path) -> Result<Vec<PathBuf>> {
+        Ok(self
+            .files
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .keys()
+            .filter(|path| path.parent() == Some(dir))
+            .cloned()
+            .collect())
+    }
+
+    fn modified(&self, path: &Path) -> Result<SystemTime> {
+        let files = self.files.lock().unwrap_or_else(|e| e.into_inner());
+        files
+            .get(path)
+            .map(|(_, modified)| *modified)
+            .ok_or_else(|| Self::not_found(path))
+    }
+
+    fn with_lock(&self, _path: &Path, f: &mut dyn FnMut() -> Result<()>) -> Result<()> {
+        f()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mock_file_system_round_trip() {
+        let fs = MockFileSystem::new();
+        let file = Path::new("/gim/config.toml");
+        assert!(!fs.exists(file));
+        fs.write(file, b"a = 1").unwrap();
+        assert_eq!(fs.read_to_string(file).unwrap(), "a = 1");
+        assert_eq!(fs.list_dir(Path::new("/gim")).unwrap(), vec![file]);
+        fs.remove_file(file).unwrap();
+        assert!(fs.read_to_string(file).is_err());
+    }
+}
@@ -0,0 +1,224 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+use toml::Value;
+
+/// A serialization format configuration documents can be read from or written to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Format {
+    /// TOML, the format of `config.toml`
+    Toml,
+    /// JSON, for a `config.json`
+    #[cfg(feature = "json")]
+    Json,
+    /// YAML, for a `config.yaml` or `config.yml`
+    #[cfg(feature = "yaml")]
+    Yaml,
+}
+
+impl Format {
+    /// Picks the format of a file by its extension.
+    ///
+    /// Files without an extension are TOML.
+    ///
+    /// # Arguments
+    ///
+    /// * `path` - The file, e.g. `config.json`
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Format>` - The format, or an `InvalidInput` error if the extension is unknown
+    ///   or its feature isn't enabled
+    pub fn from_path(path: &Path) -> Result<Format> {
+        let extension = path
+            .extension()
+            .and_then(|ext| ext.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            None | Some("toml") => Ok(Format::Toml),
+            #[cfg(feature = "json")]
+            Some("json") => Ok(Format::Json),
+            #[cfg(feature = "yaml")]
+            Some("yaml" | "yml") => Ok(Format::Yaml),
+            Some(other) => Err(Error::new(
+                ErrorKind::InvalidInput,
+                format!(
+                    "Unsupported config file format '.{}' of '{}'; the json and yaml formats \
+                     need the features of the same name",
+                    other,
+                    path.display()
+                ),
+            )),
+        }
+    }
+
+    /// Returns the file extension of this format, without the dot.
+    pub fn extension(self) -> &'static str {
+        match self {
+            Format::Toml => "toml",
+            #[cfg(feature = "json")]
+            Format::Json => "json",
+            #[cfg(feature = "yaml")]
+            Format::Yaml => "yaml",
+        }
+    }
+
+    /// Parses a document in this format into a TOML Value.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The document text
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Value>` - The parsed document or an `InvalidData` error
+    pub fn parse(self, content: &str) -> Result<Value> {
+        match self {
+            Format::Toml => {
+                toml::from_str(content).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+            }
+            #[cfg(feature = "json")]
+            Format::Json => {
+                serde_json::from_str(content).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+            }
+            #[cfg(feature = "yaml")]
+            Format::Yaml => {
+                serde_yaml::from_str(content).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+            }
+        }
+    }
+
+    /// Serializes a TOML Value into a document in this format.
+    ///
+    /// Formats without a datetime type get datetimes as RFC 3339 strings.
+    ///
+    /// # Arguments
+    ///
+    /// * `value` - The document to serialize
+    ///
+    /// # Returns
+    ///
+    /// * `Result<String>` - The document text or an `InvalidData` error
+    pub fn serialize(self, value: &Value) -> Result<String> {
+        match self {
+            Format::Toml => {
+                toml::to_string(value).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+            }
+            #[cfg(feature = "json")]
+            Format::Json => serde_json::to_string_pretty(&without_datetimes(value))
+                .map(|json| json + "\n")
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e)),
+            #[cfg(feature = "yaml")]
+            Format::Yaml => serde_yaml::to_string(&without_datetimes(value))
+                .map_err(|e| Error::new(ErrorKind::InvalidData, e)),
+        }
+    }
+}
+
+#[cfg(any(feature = "json", feature = "yaml"))]
+fn without_datetimes(value: &Value) -> Value {
+    match value {
+        Value::Datetime(datetime) => Value::String(datetime.to_string()),
+        Value::Array(items) => Value::Array(items.iter().map(without_datetimes).collect()),
+        Value::Table(table) => Value::Table(
+            table
+                .iter()
+                .map(|(key, item)| (key.clone(), without_datetimes(item)))
+                .collect(),
+        ),
+        other => other.clone(),
+    }
+}
+
+/// Converts a TOML Value to JSON, with datetimes as RFC 3339 strings.
+pub(crate) fn to_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::String(s) => serde_json::Value::from(s.as_str()),
+        Value::Integer(i) => serde_json::Value::from(*i),
+        Value::Float(f) => serde_json::Value::from(*f),
+        Value::Boolean(b) => serde_json::Value::from(*b),
+        Value::Datetime(d) => serde_json::Value::from(d.to_string()),
+        Value::Array(items) => items.iter().map(to_json).collect(),
+        Value::Table(table) => serde_json::Value::Object(
+            table
+                .iter()
+                .map(|(key, item)| (key.clone(), to_json(item)))
+                .collect(),
+        ),
+    }
+}
+
+/// Converts JSON to a TOML Value; `None` if it holds a `null`, which TOML can't express.
+pub(crate) fn from_json(json: &serde_json::Value) -> Option<Value> {
+    Some(match json {
+        serde_json::Value::Null => return None,
+        serde_json::Value::Bool(b) => Value::Boolean(*b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => Value::Float(n.as_f64()?),
+        },
+        serde_json::Value::String(s) => Value::String(s.clone()),
+        serde_json::Value::Array(items) => {
+            Value::Array(items.iter().map(from_json).collect::<Option<_>>()?)
+        }
+        serde_json::Value::Object(object) => Value::Table(
+            object
+                .iter()
+                .map(|(key, item)| Some((key.clone(), from_json(item)?)))
+                .collect::<Option<_>>()?,
+        ),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_from_path() {
+        assert_eq!(
+            Format::from_path(Path::new("config.toml")).unwrap(),
+            Format::Toml
+        );
+        assert!(Format::from_path(Path::new("config.ini")).is_err());
+        #[cfg(feature = "json")]
+        {
+            let json = Format::from_path(Path::new("config.JSON")).unwrap();
+            let value = json
+                .parse("{\"ai\": {\"model\": \"gpt-4o\", \"max\": 3}}")
+                .unwrap();
+            assert_eq!(value["ai"]["max"].as_integer(), Some(3));
+            assert_eq!(json.parse(&json.serialize(&value).unwrap()).unwrap(), value);
+        }
+        #[cfg(feature = "yaml")]
+        {
+            let yaml = Format::from_path(Path::new("config.yml")).unwrap();
+            let value = yaml.parse("ai:\n  model: gpt-4o\n").unwrap();
+            assert_eq!(value["ai"]["model"].as_str(), Some("gpt-4o"));
+            assert_eq!(yaml.parse(&yaml.serialize(&value).unwrap()).unwrap(), value);
+        }
+    }
+
+    #[cfg(feature = "json")]
+    #[test]
+    fn test_json_config_file_is_used_by_every_api() {
+        use crate::config::{get_config_value, update_config_value};
+
+        let dir = crate::testing::TempConfigDir::new().unwrap();
+        let file = dir.path().join("config.json");
+        std::fs::write(&file, "{\"ai\": {\"model\": \"gpt-4o\"}}").unwrap();
+        assert_eq!(
+            get_config_value("ai", "model").unwrap().as_str(),
+            Some("gpt-4o")
+        );
+
+        update_config_value("ai", "model", Value::from("o1")).unwrap();
+        let written = Format::Json
+            .parse(&std::fs::read_to_string(&file).unwrap())
+            .unwrap();
+        assert_eq!(written["ai"]["model"].as_str(), Some("o1"));
+        assert!(!dir.path().join("config.toml").exists());
+    }
+}
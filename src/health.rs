@@ -0,0 +1,122 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    time::Duration,
+};
+
+use crate::ai::AiConfig;
+use crate::secret::Secret;
+
+/// How long to wait for the provider before giving up.
+const REQUEST_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// A model the configured account can access.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct RemoteModel {
+    /// The model id to put into `ai.model`
+    pub id: String,
+    /// The organization owning the model, if reported
+    pub owned_by: Option<String>,
+    /// Creation time as a Unix timestamp, if reported
+    pub created: Option<i64>,
+}
+
+/// Lists the models available through the configured provider.
+///
+/// Calls the OpenAI-compatible `GET {ai.url}/models` endpoint with the configured API key,
+/// so `gim config set ai.model` can validate or complete against what the account can use.
+///
+/// # Returns
+///
+/// * `Result<Vec<RemoteModel>>` - The models sorted by id, or an error if the request fails
+pub fn list_remote_models() -> Result<Vec<RemoteModel>> {
+    list_models_for(&AiConfig::load()?)
+}
+
+/// Lists the models available through the given provider settings.
+///
+/// # Arguments
+///
+/// * `ai` - The provider URL and API key to use
+///
+/// # Returns
+///
+/// * `Result<Vec<RemoteModel>>` - The models sorted by id, or an error if the request fails
+pub fn list_models_for(ai: &AiConfig) -> Result<Vec<RemoteModel>> {
+    if ai.url.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "Key 'ai.url' is not configured",
+        ));
+    }
+    let endpoint = models_endpoint(&ai.url);
+    let mut request = ureq::get(&endpoint).timeout(REQUEST_TIMEOUT);
+    if !ai.apikey.expose().is_empty() {
+        let authorization = Secret::new(format!("Bearer {}", ai.apikey.expose()));
+        request = request.set("Authorization", authorization.expose());
+    }
+    let body = match request.call() {
+        Ok(response) => response.into_string()?,
+        Err(ureq::Error::Status(401 | 403, _)) => {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                format!("Provider rejected the API key for {}", endpoint),
+            ));
+        }
+        Err(ureq::Error::Status(code, _)) => {
+            return Err(Error::other(format!(
+                "Provider returned status {} for {}",
+                code, endpoint
+            )));
+        }
+        Err(e) => return Err(Error::other(e)),
+    };
+    parse_models(&body)
+}
+
+fn models_endpoint(url: &str) -> String {
+    format!("{}/models", url.trim_end_matches('/'))
+}
+
+fn parse_models(body: &str) -> Result<Vec<RemoteModel>> {
+    let json: serde_json::Value =
+        serde_json::from_str(body).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    let data = json
+        .get("data")
+        .and_then(|d| d.as_array())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Response has no 'data' list"))?;
+    let mut models: Vec<RemoteModel> = data
+        .iter()
+        .filter_map(|model| {
+            Some(RemoteModel {
+                id: model.get("id")?.as_str()?.to_string(),
+                owned_by: model
+                    .get("owned_by")
+                    .and_then(|o| o.as_str())
+                    .map(str::to_string),
+                created: model.get("created").and_then(|c| c.as_i64()),
+            })
+        })
+        .collect();
+    models.sort_by(|a, b| a.id.cmp(&b.id));
+    Ok(models)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_models() {
+        let body = r#"{"object":"list","data":[
+            {"id":"gpt-4o","object":"model","created":1715367049,"owned_by":"system"},
+            {"id":"gpt-4o-mini","object":"model"}]}"#;
+        let models = parse_models(body).unwrap();
+        assert_eq!(models.len(), 2);
+        assert_eq!(models[0].owned_by.as_deref(), Some("system"));
+        assert_eq!(models[1].created, None);
+        assert_eq!(
+            models_endpoint("https://api.openai.com/v1/"),
+            "https://api.openai.com/v1/models"
+        );
+    }
+}
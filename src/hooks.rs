@@ -0,0 +1,310 @@
+use std::{
+    io::{Error, ErrorKind, Result, Write},
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::Mutex,
+    thread,
+    time::{Duration, Instant},
+};
+use toml::Value;
+
+use crate::change::ChangeSet;
+use crate::config::get_config_file;
+use crate::directory::config_dir;
+use crate::init::emit_warning;
+
+/// The section configuring hook scripts.
+pub const HOOKS_SECTION: &str = "hooks";
+
+/// Set for hook scripts, so that writes made by a hook don't run the hook again.
+pub const HOOK_ENV: &str = "GIM_CONFIG_HOOK";
+
+/// The variables a hook receives when `inherit_env` is off.
+const BASE_ENV: [&str; 5] = ["PATH", "HOME", "USER", "LANG", "SYSTEMROOT"];
+
+static FAILURES: Mutex<Vec<HookFailure>> = Mutex::new(Vec::new());
+
+/// A script run after every successful write, configured in the `[hooks]` section:
+///
+/// ```toml
+/// [hooks]
+/// on_change = "hooks/sync-secrets.sh"
+/// timeout_ms = 5000
+/// inherit_env = false
+/// allow_env = ["SOPS_AGE_KEY_FILE"]
+/// ```
+///
+/// The script gets a JSON summary of the write on stdin, with secrets redacted, and runs
+/// in the config directory.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Hook {
+    /// The script; a relative path is resolved against the config directory
+    pub command: PathBuf,
+    /// How long the script may run before it is killed
+    pub timeout: Duration,
+    /// Whether the script sees the whole environment of the writing process; otherwise
+    /// it only gets `PATH`, `HOME`, `USER`, `LANG` and the variables in `allow_env`
+    pub inherit_env: bool,
+    /// Further variables passed to the script when `inherit_env` is off
+    pub allow_env: Vec<String>,
+}
+
+impl Hook {
+    const DEFAULT_TIMEOUT: Duration = Duration::from_secs(5);
+
+    /// Reads the `on_change` hook from a configuration.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The configuration
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<Hook>>` - The hook, `None` if none is configured, or an
+    ///   `InvalidData` error if a `[hooks]` key has the wrong type
+    pub fn from_config(config: &Value) -> Result<Option<Hook>> {
+        let Some(hooks) = config.get(HOOKS_SECTION) else {
+            return Ok(None);
+        };
+        let invalid = |key: &str, expected: &str| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("'{}.{}' must be {}", HOOKS_SECTION, key, expected),
+            )
+        };
+        let command = match hooks.get("on_change") {
+            None => return Ok(None),
+            Some(Value::String(s)) if s.is_empty() => return Ok(None),
+            Some(Value::String(s)) => PathBuf::from(s),
+            Some(_) => return Err(invalid("on_change", "a path")),
+        };
+        let timeout = match hooks.get("timeout_ms") {
+            None => Hook::DEFAULT_TIMEOUT,
+            Some(Value::Integer(ms)) if *ms > 0 => Duration::from_millis(*ms as u64),
+            Some(_) => return Err(invalid("timeout_ms", "a positive integer")),
+        };
+        let inherit_env = match hooks.get("inherit_env") {
+            None => false,
+            Some(Value::Boolean(b)) => *b,
+            Some(_) => return Err(invalid("inherit_env", "a boolean")),
+        };
+        let allow_env = match hooks.get("allow_env") {
+            None => Vec::new(),
+            Some(Value::Array(items)) => items
+                .iter()
+                .map(|item| item.as_str().map(str::to_string))
+                .collect::<Option<Vec<String>>>()
+                .ok_or_else(|| invalid("allow_env", "an array of variable names"))?,
+            Some(_) => return Err(invalid("allow_env", "an array of variable names")),
+        };
+        Ok(Some(Hook {
+            command,
+            timeout,
+            inherit_env,
+            allow_env,
+        }))
+    }
+
+    /// Runs the script for a write, waiting until it exits or the timeout passes.
+    ///
+    /// # Arguments
+    ///
+    /// * `changes` - The changes of the write; pass them redacted
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - `Ok` if the script exited successfully, otherwise an error saying
+    ///   why it couldn't be started, that it timed out or how it exited
+    pub fn run(&self, changes: &ChangeSet) -> Result<()> {
+        let dir = config_dir()?;
+        let mut command = Command::new(dir.join(&self.command));
+        command
+            .current_dir(&dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::null());
+        if !self.inherit_env {
+            command.env_clear();
+            for var in BASE_ENV
+                .iter()
+                .copied()
+                .chain(self.allow_env.iter().map(String::as_str))
+            {
+                if let Some(value) = std::env::var_os(var) {
+                    command.env(var, value);
+                }
+            }
+        }
+        command.env(HOOK_ENV, "1");
+
+        let mut child = command.spawn().map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!("Failed to start hook '{}': {}", self.command.display(), e),
+            )
+        })?;
+        let summary = change_summary(&get_config_file()?, changes);
+        if let Some(mut stdin) = child.stdin.take() {
+            // Written from its own thread so a script that never reads a summary bigger
+            // than the pipe buffer can't block us past the timeout. The thread isn't
+            // joined: a process the script left behind may hold the pipe open.
+            thread::spawn(move || {
+                // A script that doesn't read its input closes the pipe early; that's fine.
+                let _ = stdin.write_all(summary.as_bytes());
+            });
+        }
+
+        let started = Instant::now();
+        loop {
+            if let Some(status) = child.try_wait()? {
+                return if status.success() {
+                    Ok(())
+                } else {
+                    Err(Error::other(format!(
+                        "Hook '{}' failed with {}",
+                        self.command.display(),
+                        status
+                    )))
+                };
+            }
+            if started.elapsed() >= self.timeout {
+                let _ = child.kill();
+                let _ = child.wait();
+                return Err(Error::new(
+                    ErrorKind::TimedOut,
+                    format!(
+                        "Hook '{}' was killed after {} ms",
+                        self.command.display(),
+                        self.timeout.as_millis()
+                    ),
+                ));
+            }
+            thread::sleep(Duration::from_millis(10));
+        }
+    }
+}
+
+/// A hook run that didn't succeed.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct HookFailure {
+    /// The script
+    pub command: PathBuf,
+    /// Why it failed
+    pub message: String,
+}
+
+/// Returns the hook runs of this process that failed, oldest first, e.g. to show them in
+/// `gim doctor`. A failing hook never fails the write that triggered it.
+pub fn hook_failures() -> Vec<HookFailure> {
+    FAILURES.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Runs the `on_change` hook configured in `config`, the configuration just written.
+///
+/// Nothing runs inside a hook script, see [`HOOK_ENV`].
+pub(crate) fn run_change_hook(config: &Value, changes: &ChangeSet) {
+    if std::env::var_os(HOOK_ENV).is_some() {
+        return;
+    }
+    let (command, result) = match Hook::from_config(config) {
+        Ok(None) => return,
+        Ok(Some(hook)) => (hook.command.clone(), hook.run(changes)),
+        Err(e) => (PathBuf::new(), Err(e)),
+    };
+    if let Err(e) = result {
+        #[cfg(feature = "tracing")]
+        tracing::warn!(hook = %command.display(), error = %e, "config hook failed");
+        emit_warning(&format!(
+            "config hook '{}' failed: {}",
+            command.display(),
+            e
+        ));
+        FAILURES
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(HookFailure {
+                command,
+                message: e.to_string(),
+            });
+    }
+}
+
+/// Returns the JSON document a hook gets on stdin:
+/// `{"file": "...", "changes": [{"path": "ai.model", "old": "a", "new": "b"}]}`, with
+/// `null` for the old value of added and the new value of removed keys.
+fn change_summary(file: &std::path::Path, changes: &ChangeSet) -> String {
+    serde_json::json!({ "file": file.display().to_string(), "changes": changes.to_json() })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hook_from_config() {
+        let config: Value = toml::from_str(
+            "[hooks]\non_change = \"sync.sh\"\ntimeout_ms = 250\nallow_env = [\"TOKEN\"]\n",
+        )
+        .unwrap();
+        let hook = Hook::from_config(&config).unwrap().unwrap();
+        assert_eq!(hook.command, PathBuf::from("sync.sh"));
+        assert_eq!(hook.timeout, Duration::from_millis(250));
+        assert!(!hook.inherit_env);
+        assert_eq!(hook.allow_env, vec!["TOKEN".to_string()]);
+
+        let none: Value = toml::from_str("[hooks]\non_change = \"\"\n").unwrap();
+        assert_eq!(Hook::from_config(&none).unwrap(), None);
+        let invalid: Value = toml::from_str("[hooks]\non_change = 1\n").unwrap();
+        assert!(Hook::from_config(&invalid).is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_on_change_hook_gets_summary_and_times_out() {
+        use crate::config::{get_config, save_config};
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = crate::testing::TempConfigDir::new().unwrap();
+        let write_script = |name: &str, body: &str| {
+            let script = dir.path().join(name);
+            std::fs::write(&script, format!("#!/bin/sh\n{}\n", body)).unwrap();
+            std::fs::set_permissions(&script, std::fs::Permissions::from_mode(0o755)).unwrap();
+        };
+        write_script("record.sh", "cat > summary.json");
+        let mut config = get_config().unwrap();
+        crate::path::insert(&mut config, "hooks.on_change", Value::from("record.sh")).unwrap();
+        save_config(&config).unwrap();
+        crate::path::insert(&mut config, "hooks_test.flag", Value::from(true)).unwrap();
+        save_config(&config).unwrap();
+
+        let summary = std::fs::read_to_string(dir.path().join("summary.json")).unwrap();
+        let summary: serde_json::Value = serde_json::from_str(&summary).unwrap();
+        assert_eq!(summary["changes"][0]["path"], "hooks_test.flag");
+        assert_eq!(summary["changes"][0]["old"], serde_json::Value::Null);
+        assert_eq!(summary["changes"][0]["new"], true);
+
+        write_script("slow.sh", "sleep 5");
+        let slow = Hook {
+            command: PathBuf::from("slow.sh"),
+            timeout: Duration::from_millis(50),
+            inherit_env: false,
+            allow_env: Vec::new(),
+        };
+        let error = slow.run(&ChangeSet::default()).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+
+        // A summary bigger than the pipe buffer, sent to a script that never reads it.
+        let large = ChangeSet {
+            changes: vec![crate::change::Change {
+                path: "hooks_test.blob".to_string(),
+                old: None,
+                new: Some(Value::from("x".repeat(1 << 20))),
+            }],
+        };
+        let started = Instant::now();
+        let error = slow.run(&large).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+}
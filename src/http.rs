@@ -0,0 +1,107 @@
+use std::{
+    io::{Error, ErrorKind, Read, Result, Write as _},
+    net::{SocketAddr, TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// A plain HTTP/1.1 response.
+pub(crate) struct Response {
+    pub(crate) status: u16,
+    pub(crate) body: String,
+}
+
+/// Sends a `GET` request over plain HTTP and reads the whole response.
+///
+/// This is only meant for talking to local services such as an Ollama server; it does not
+/// support TLS, redirects or keep-alive.
+///
+/// # Arguments
+///
+/// * `host` - The `host:port` to connect to
+/// * `path` - The request path, e.g. `"/api/tags"`
+/// * `timeout` - The connect, read and write timeout
+///
+/// # Returns
+///
+/// * `Result<Response>` - The status and body, or an error if the server is unreachable
+pub(crate) fn get(host: &str, path: &str, timeout: Duration) -> Result<Response> {
+    let addrs: Vec<SocketAddr> = host.to_socket_addrs()?.collect();
+    let mut last_error = Error::new(ErrorKind::NotFound, format!("Cannot resolve '{}'", host));
+    for addr in addrs {
+        match TcpStream::connect_timeout(&addr, timeout) {
+            Ok(stream) => return send(stream, host, path, timeout),
+            Err(e) => last_error = e,
+        }
+    }
+    Err(last_error)
+}
+
+fn send(mut stream: TcpStream, host: &str, path: &str, timeout: Duration) -> Result<Response> {
+    stream.set_read_timeout(Some(timeout))?;
+    stream.set_write_timeout(Some(timeout))?;
+    write!(
+        stream,
+        "GET {} HTTP/1.1\r\nHost: {}\r\nAccept: application/json\r\nConnection: close\r\n\r\n",
+        path, host
+    )?;
+    let mut raw = Vec::new();
+    stream.read_to_end(&mut raw)?;
+    parse_response(&raw)
+}
+
+fn parse_response(raw: &[u8]) -> Result<Response> {
+    let invalid = |msg: &str| Error::new(ErrorKind::InvalidData, msg.to_string());
+    let split = raw
+        .windows(4)
+        .position(|w| w == b"\r\n\r\n")
+        .ok_or_else(|| invalid("Malformed HTTP response"))?;
+    let head = String::from_utf8_lossy(&raw[..split]);
+    let body = &raw[split + 4..];
+    let status = head
+        .lines()
+        .next()
+        .and_then(|line| line.split_whitespace().nth(1))
+        .and_then(|code| code.parse().ok())
+        .ok_or_else(|| invalid("Malformed HTTP status line"))?;
+    let chunked = head.lines().any(|line| {
+        let line = line.to_ascii_lowercase();
+        line.starts_with("transfer-encoding:") && line.contains("chunked")
+    });
+    let body = if chunked {
+        decode_chunked(body).ok_or_else(|| invalid("Malformed chunked HTTP body"))?
+    } else {
+        body.to_vec()
+    };
+    Ok(Response {
+        status,
+        body: String::from_utf8(body).map_err(|e| Error::new(ErrorKind::InvalidData, e))?,
+    })
+}
+
+fn decode_chunked(mut body: &[u8]) -> Option<Vec<u8>> {
+    let mut decoded = Vec::new();
+    loop {
+        let line_end = body.windows(2).position(|w| w == b"\r\n")?;
+        let size_line = std::str::from_utf8(&body[..line_end]).ok()?;
+        let size = usize::from_str_radix(size_line.split(';').next()?.trim(), 16).ok()?;
+        body = &body[line_end + 2..];
+        if size == 0 {
+            return Some(decoded);
+        }
+        decoded.extend_from_slice(body.get(..size)?);
+        body = body.get(size + 2..)?;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_chunked_response() {
+        let raw = b"HTTP/1.1 200 OK\r\nTransfer-Encoding: chunked\r\n\r\n4\r\n{\"a\"\r\n3\r\n:1}\r\n0\r\n\r\n";
+        let response = parse_response(raw).unwrap();
+        assert_eq!(response.status, 200);
+        assert_eq!(response.body, "{\"a\":1}");
+    }
+}
@@ -0,0 +1,202 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+};
+use toml::{Value, map};
+
+use crate::apply::{ApplyStrategy, apply_value};
+use crate::change::ChangeSet;
+use crate::config::get_config;
+use crate::language::find_language;
+use crate::merge::merge_into;
+
+/// Another AI commit message tool whose settings can be imported.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Tool {
+    /// aicommits, configured in `~/.aicommits`
+    AiCommit,
+    /// OpenCommit, configured in `~/.opencommit`
+    OpenCommit,
+    /// Aider, configured in `~/.aider.conf.yml`
+    Aider,
+}
+
+/// Every tool [`detect_tools`] looks for.
+const TOOLS: [Tool; 3] = [Tool::AiCommit, Tool::OpenCommit, Tool::Aider];
+
+impl Tool {
+    /// Returns the tool's configuration file in the home directory, whether or not it exists.
+    pub fn config_file(self) -> Option<PathBuf> {
+        let name = match self {
+            Tool::AiCommit => ".aicommits",
+            Tool::OpenCommit => ".opencommit",
+            Tool::Aider => ".aider.conf.yml",
+        };
+        dirs::home_dir().map(|home| home.join(name))
+    }
+
+    /// Maps the content of the tool's configuration file to gim's `[ai]` settings.
+    ///
+    /// Settings without a gim equivalent are ignored.
+    ///
+    /// # Arguments
+    ///
+    /// * `content` - The text of the tool's configuration file
+    ///
+    /// # Returns
+    ///
+    /// * `Value` - A document with an `[ai]` section, empty if nothing could be mapped
+    pub fn map_settings(self, content: &str) -> Value {
+        let (separator, fields): (char, &[(&str, &str)]) = match self {
+            Tool::AiCommit => (
+                '=',
+                &[
+                    ("OPENAI_KEY", "apikey"),
+                    ("model", "model"),
+                    ("locale", "language"),
+                ],
+            ),
+            Tool::OpenCommit => (
+                '=',
+                &[
+                    ("OCO_API_KEY", "apikey"),
+                    ("OCO_OPENAI_API_KEY", "apikey"),
+                    ("OCO_MODEL", "model"),
+                    ("OCO_API_URL", "url"),
+                    ("OCO_OPENAI_BASE_PATH", "url"),
+                    ("OCO_LANGUAGE", "language"),
+                ],
+            ),
+            Tool::Aider => (
+                ':',
+                &[
+                    ("openai-api-key", "apikey"),
+                    ("model", "model"),
+                    ("openai-api-base", "url"),
+                ],
+            ),
+        };
+
+        let mut ai = map::Map::new();
+        for line in content.lines().map(str::trim) {
+            if line.starts_with('#') {
+                continue;
+            }
+            let Some((name, value)) = line.split_once(separator) else {
+                continue;
+            };
+            let value = value.trim().trim_matches(|c| c == '"' || c == '\'');
+            let Some((_, key)) = fields.iter().find(|(field, _)| *field == name.trim()) else {
+                continue;
+            };
+            if value.is_empty() || value == "undefined" {
+                continue;
+            }
+            let value = if *key == "language" {
+                language_name(value)
+            } else {
+                value.to_string()
+            };
+            ai.insert(key.to_string(), Value::from(value));
+        }
+
+        let mut root = map::Map::new();
+        if !ai.is_empty() {
+            root.insert("ai".to_string(), Value::Table(ai));
+        }
+        Value::Table(root)
+    }
+}
+
+/// Returns the tools whose configuration file exists.
+pub fn detect_tools() -> Vec<Tool> {
+    TOOLS
+        .into_iter()
+        .filter(|tool| tool.config_file().is_some_and(|file| file.is_file()))
+        .collect()
+}
+
+/// Imports the API key, model, base URL and language from another tool's configuration.
+///
+/// With `dry_run` nothing is written and the returned changes show what an import would do.
+///
+/// # Arguments
+///
+/// * `tool` - The tool to import from
+/// * `dry_run` - Whether to only compute the changes
+///
+/// # Returns
+///
+/// * `Result<ChangeSet>` - The changed keys, or a `NotFound` error if the tool has no
+///   configuration file
+pub fn import_from_tool(tool: Tool, dry_run: bool) -> Result<ChangeSet> {
+    let file = tool
+        .config_file()
+        .ok_or_else(|| Error::new(ErrorKind::NotFound, "No home directory found"))?;
+    let content = fs::read_to_string(&file).map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!("Can't read {:?} settings '{}': {}", tool, file.display(), e),
+        )
+    })?;
+    import_settings(tool.map_settings(&content), dry_run)
+}
+
+fn import_settings(incoming: Value, dry_run: bool) -> Result<ChangeSet> {
+    if !dry_run {
+        return apply_value(incoming, ApplyStrategy::Merge);
+    }
+    let config = get_config()?;
+    let mut merged = config.clone();
+    merge_into(&mut merged, &incoming, true);
+    Ok(ChangeSet::between(&config, &merged))
+}
+
+fn language_name(locale: &str) -> String {
+    find_language(locale).map_or_else(|| locale.to_string(), |language| language.name.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_map_settings_of_each_tool() {
+        let aicommits = Tool::AiCommit.map_settings("OPENAI_KEY=sk-a\nlocale=zh\ngenerate=3\n");
+        assert_eq!(aicommits["ai"]["apikey"].as_str(), Some("sk-a"));
+        assert_eq!(aicommits["ai"]["language"].as_str(), Some("Chinese"));
+
+        let opencommit = Tool::OpenCommit
+            .map_settings("OCO_API_KEY=sk-o\nOCO_MODEL=gpt-4o-mini\nOCO_API_URL=undefined\n");
+        assert_eq!(opencommit["ai"]["model"].as_str(), Some("gpt-4o-mini"));
+        assert!(opencommit["ai"].get("url").is_none());
+
+        let aider = Tool::Aider
+            .map_settings("# aider\nmodel: gpt-4o\nopenai-api-base: \"http://localhost:8080\"\n");
+        assert_eq!(aider["ai"]["url"].as_str(), Some("http://localhost:8080"));
+        assert!(
+            Tool::Aider
+                .map_settings("dark-mode: true\n")
+                .get("ai")
+                .is_none()
+        );
+    }
+
+    #[test]
+    fn test_dry_run_does_not_write() {
+        let _dir = TempConfigDir::new().unwrap();
+        let incoming = Tool::OpenCommit.map_settings("OCO_MODEL=gpt-4o\n");
+        let preview = import_settings(incoming.clone(), true).unwrap();
+        assert_eq!(preview.paths(), vec!["ai.model"]);
+        assert_eq!(get_config().unwrap()["ai"]["model"].as_str(), Some(""));
+
+        assert_eq!(import_settings(incoming, false).unwrap(), preview);
+        assert_eq!(
+            get_config().unwrap()["ai"]["model"].as_str(),
+            Some("gpt-4o")
+        );
+    }
+}
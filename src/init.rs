@@ -0,0 +1,280 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+    sync::{
+        Arc, Mutex, RwLock,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use toml::Value;
+
+use crate::config::Config;
+use crate::defaults::{InitialContent, set_default_values, set_initial_content};
+use crate::directory::set_config_dir_override;
+use crate::durability::{Durability, set_durability};
+use crate::encoding::{LineEnding, set_line_ending};
+
+type WarningHook = Arc<dyn Fn(&str) + Send + Sync>;
+
+static INITIALIZED: Mutex<bool> = Mutex::new(false);
+static READ_ONLY: AtomicBool = AtomicBool::new(false);
+static SILENT: AtomicBool = AtomicBool::new(false);
+static WARNING_HOOK: RwLock<Option<WarningHook>> = RwLock::new(None);
+
+/// The process-wide settings installed by [`Config::init`].
+///
+/// Every setting is optional; the ones left out keep the behavior of the corresponding
+/// free function's default.
+#[derive(Default)]
+pub struct Options {
+    config_dir: Option<PathBuf>,
+    read_only: bool,
+    silent: bool,
+    defaults: Option<Value>,
+    initial_content: Option<InitialContent>,
+    durability: Option<Durability>,
+    line_ending: Option<LineEnding>,
+    on_warning: Option<WarningHook>,
+}
+
+impl fmt::Debug for Options {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Options")
+            .field("config_dir", &self.config_dir)
+            .field("read_only", &self.read_only)
+            .field("silent", &self.silent)
+            .field("defaults", &self.defaults)
+            .field("initial_content", &self.initial_content)
+            .field("durability", &self.durability)
+            .field("line_ending", &self.line_ending)
+            .field("on_warning", &self.on_warning.is_some())
+            .finish()
+    }
+}
+
+impl Options {
+    /// Creates options that change nothing.
+    pub fn new() -> Options {
+        Options::default()
+    }
+
+    /// Uses `dir` as the config directory, ahead of `GIM_CONFIG_DIR` and the home directory.
+    pub fn config_dir(mut self, dir: impl Into<PathBuf>) -> Options {
+        self.config_dir = Some(dir.into());
+        self
+    }
+
+    /// Rejects every write of the configuration with `PermissionDenied`; a missing file
+    /// isn't created and reads return the defaults.
+    pub fn read_only(mut self, read_only: bool) -> Options {
+        self.read_only = read_only;
+        self
+    }
+
+    /// Guarantees the crate never writes to standard output or standard error, for build
+    /// scripts, git hooks and editor plugins where a stray line corrupts the protocol.
+    ///
+    /// [`crate::config::get_config_and_print`] then prints nothing and warnings only reach
+    /// [`Options::on_warning`]. Change hooks never inherit the standard streams either way.
+    pub fn silent(mut self, silent: bool) -> Options {
+        self.silent = silent;
+        self
+    }
+
+    /// Registers every value of a table as a default, see
+    /// [`set_default`](crate::defaults::set_default).
+    pub fn defaults(mut self, defaults: Value) -> Options {
+        self.defaults = Some(defaults);
+        self
+    }
+
+    /// Sets what newly created files contain, see [`set_initial_content`].
+    pub fn initial_content(mut self, content: InitialContent) -> Options {
+        self.initial_content = Some(content);
+        self
+    }
+
+    /// Sets the durability of saves, see [`set_durability`].
+    pub fn durability(mut self, durability: Durability) -> Options {
+        self.durability = Some(durability);
+        self
+    }
+
+    /// Sets the line endings of saved files, see [`set_line_ending`].
+    pub fn line_ending(mut self, ending: LineEnding) -> Options {
+        self.line_ending = Some(ending);
+        self
+    }
+
+    /// Calls `hook` with every warning the crate records, e.g. a stripped byte order mark,
+    /// a slow read or a failed change hook, so the application can log it its own way.
+    pub fn on_warning(mut self, hook: impl Fn(&str) + Send + Sync + 'static) -> Options {
+        self.on_warning = Some(Arc::new(hook));
+        self
+    }
+}
+
+impl Config {
+    /// Installs the process-wide settings of the crate; call it once at startup, before
+    /// anything reads the configuration.
+    ///
+    /// The settings replace the individual setters scattered across the modules. Without
+    /// a call the crate behaves as if it had been initialized with [`Options::new`].
+    ///
+    /// # Arguments
+    ///
+    /// * `options` - The settings
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success, an `AlreadyExists` error if the process was already
+    ///   initialized, or an `InvalidInput` error if the defaults or the initial document
+    ///   aren't tables; nothing is installed on error
+    pub fn init(options: Options) -> Result<()> {
+        let mut initialized = INITIALIZED.lock().unwrap_or_else(|e| e.into_inner());
+        if *initialized {
+            return Err(Error::new(
+                ErrorKind::AlreadyExists,
+                "gim-config was already initialized in this process",
+            ));
+        }
+        let defaults = match &options.defaults {
+            Some(Value::Table(_)) | None => options.defaults,
+            Some(_) => {
+                return Err(Error::new(
+                    ErrorKind::InvalidInput,
+                    "The defaults must be a table",
+                ));
+            }
+        };
+        if let Some(InitialContent::Document(document)) = &options.initial_content
+            && !document.is_table()
+        {
+            return Err(Error::new(
+                ErrorKind::InvalidInput,
+                "The initial document must be a table",
+            ));
+        }
+        // The defaults are installed all or nothing, so they go first: everything after
+        // them can't fail.
+        if let Some(defaults) = defaults {
+            set_default_values(&defaults)?;
+        }
+        if let Some(content) = options.initial_content {
+            set_initial_content(content)?;
+        }
+        if let Some(dir) = options.config_dir {
+            set_config_dir_override(Some(dir));
+        }
+        if let Some(durability) = options.durability {
+            set_durability(durability);
+        }
+        if let Some(ending) = options.line_ending {
+            set_line_ending(ending);
+        }
+        READ_ONLY.store(options.read_only, Ordering::Relaxed);
+        SILENT.store(options.silent, Ordering::Relaxed);
+        *WARNING_HOOK.write().unwrap_or_else(|e| e.into_inner()) = options.on_warning;
+        *initialized = true;
+        Ok(())
+    }
+
+    /// Returns whether [`Config::init`] was called in this process.
+    pub fn is_initialized() -> bool {
+        *INITIALIZED.lock().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Returns whether the process was initialized read-only.
+pub(crate) fn is_read_only() -> bool {
+    READ_ONLY.load(Ordering::Relaxed)
+}
+
+/// Returns whether the process was initialized silent.
+pub(crate) fn is_silent() -> bool {
+    SILENT.load(Ordering::Relaxed)
+}
+
+/// Passes a warning to the hook installed through [`Options::on_warning`], if any.
+pub(crate) fn emit_warning(message: &str) {
+    let hook = WARNING_HOOK
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone();
+    if let Some(hook) = hook {
+        hook(message);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::path;
+
+    #[test]
+    fn test_init_only_once() {
+        let rejected = Config::init(Options::new().defaults(Value::from(1))).unwrap_err();
+        assert_eq!(rejected.kind(), ErrorKind::InvalidInput);
+        let defaults: Value = toml::from_str("[init_test]\nflag = true\n").unwrap();
+        let rejected = Config::init(
+            Options::new()
+                .defaults(defaults)
+                .initial_content(InitialContent::Document(Value::from(1))),
+        )
+        .unwrap_err();
+        assert_eq!(rejected.kind(), ErrorKind::InvalidInput);
+        let installed = crate::defaults::effective_defaults();
+        assert!(path::lookup(&installed, "init_test.flag").is_none());
+
+        Config::init(Options::new()).unwrap();
+        assert!(Config::is_initialized());
+        assert!(!is_read_only());
+        let again = Config::init(Options::new().read_only(true)).unwrap_err();
+        assert_eq!(again.kind(), ErrorKind::AlreadyExists);
+        assert!(!is_read_only());
+    }
+
+    /// Runs in a child process of the test binary, which owns its standard streams.
+    #[cfg(unix)]
+    #[test]
+    fn test_silent_mode_writes_nothing() {
+        const CHILD: &str = "GIM_CONFIG_SILENT_CHILD";
+        let name = "init::tests::test_silent_mode_writes_nothing";
+        if std::env::var_os(CHILD).is_none() {
+            let status = std::process::Command::new(std::env::current_exe().unwrap())
+                .args(["--exact", name, "--test-threads=1", "--nocapture"])
+                .env(CHILD, "1")
+                .stdout(std::process::Stdio::null())
+                .stderr(std::process::Stdio::null())
+                .status()
+                .unwrap();
+            assert!(status.success());
+            return;
+        }
+
+        let dir = crate::testing::TempConfigDir::new().unwrap();
+        Config::init(Options::new().silent(true)).unwrap();
+        let capture = std::fs::File::create(dir.path().join("output")).unwrap();
+        let fd = std::os::fd::AsRawFd::as_raw_fd(&capture);
+        // SAFETY: the descriptors are valid, and the saved copies are restored before the
+        // test returns; this process runs no other test.
+        let saved = unsafe { [libc::dup(1), libc::dup(2)] };
+        unsafe {
+            libc::dup2(fd, 1);
+            libc::dup2(fd, 2);
+        }
+        crate::config::get_config_and_print().unwrap();
+        crate::config::update_config_value("ai", "model", Value::from("silent")).unwrap();
+        emit_warning("a warning nobody listens to");
+        // SAFETY: restores the descriptors saved above.
+        unsafe {
+            libc::dup2(saved[0], 1);
+            libc::dup2(saved[1], 2);
+        }
+        assert_eq!(
+            std::fs::metadata(dir.path().join("output")).unwrap().len(),
+            0
+        );
+    }
+}
@@ -0,0 +1,285 @@
+use std::io::{Error, ErrorKind, Result};
+use toml::Value;
+
+use crate::path;
+
+/// Expands the `${key.path}` references in every string of `config`.
+///
+/// A reference is replaced by the referenced key's value, itself expanded first; strings,
+/// integers, floats and booleans can be referenced, and `$${` stands for a literal `${`.
+/// So `url = "${ai.url}/v1/chat/completions"` follows `ai.url` instead of repeating it.
+/// A reference without a dot, such as a shell-style `${HOME}` in a hook command, isn't a
+/// key path and is kept as written.
+///
+/// # Arguments
+///
+/// * `config` - The configuration, with its references
+///
+/// # Returns
+///
+/// * `Result<Value>` - A copy with every reference expanded, `NotFound` if a referenced key
+///   doesn't exist, or `InvalidData` for a reference cycle, an unterminated reference or a
+///   reference to a table or an array
+pub fn interpolate(config: &Value) -> Result<Value> {
+    let mut expanded = config.clone();
+    for key_path in path::leaf_paths(config) {
+        if let Some(value) = path::lookup(config, &key_path)
+            && contains_reference(value)
+        {
+            let value = expand(&key_path, value.clone(), &|target| {
+                path::require(config, target).cloned()
+            })?;
+            path::insert(&mut expanded, &key_path, value)?;
+        }
+    }
+    Ok(expanded)
+}
+
+/// Expands the references in every string of `config` like [`interpolate`], but leaves a
+/// value whose references can't be expanded as written, so one broken key doesn't fail
+/// reads of every other key.
+pub(crate) fn interpolate_each(config: &Value) -> Value {
+    let mut expanded = config.clone();
+    for key_path in path::leaf_paths(config) {
+        if let Some(value) = path::lookup(config, &key_path)
+            && contains_reference(value)
+            && let Ok(value) = expand(&key_path, value.clone(), &|target| {
+                path::require(config, target).cloned()
+            })
+        {
+            // The path was just listed as a leaf of the same shape, so it can be inserted.
+            let _ = path::insert(&mut expanded, &key_path, value);
+        }
+    }
+    expanded
+}
+
+/// Returns the key paths `text` references, in order; shell-style references without a
+/// dot and escaped `$${` aren't key references.
+pub(crate) fn referenced_keys(text: &str) -> Vec<&str> {
+    let mut keys = Vec::new();
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("$${") {
+            rest = after;
+        } else if let Some(after) = rest.strip_prefix("${")
+            && let Some(end) = after.find('}')
+        {
+            let target = after[..end].trim();
+            if target.contains('.') {
+                keys.push(target);
+            }
+            rest = &after[end + 1..];
+        } else {
+            rest = &rest[1..];
+        }
+    }
+    keys
+}
+
+/// Returns whether `config` has any reference to expand.
+pub(crate) fn has_references(config: &Value) -> bool {
+    contains_reference(config)
+}
+
+/// Expands the references in `value`, the value of `key_path`, resolving referenced keys
+/// with `lookup`.
+pub(crate) fn expand(
+    key_path: &str,
+    value: Value,
+    lookup: &dyn Fn(&str) -> Result<Value>,
+) -> Result<Value> {
+    expand_within(&mut vec![key_path.to_string()], value, lookup)
+}
+
+fn expand_within(
+    chain: &mut Vec<String>,
+    value: Value,
+    lookup: &dyn Fn(&str) -> Result<Value>,
+) -> Result<Value> {
+    match value {
+        Value::String(text) if text.contains("${") => {
+            expand_string(chain, &text, lookup).map(Value::String)
+        }
+        Value::Array(items) => items
+            .into_iter()
+            .map(|item| expand_within(chain, item, lookup))
+            .collect::<Result<_>>()
+            .map(Value::Array),
+        Value::Table(table) => table
+            .into_iter()
+            .map(|(key, item)| Ok((key, expand_within(chain, item, lookup)?)))
+            .collect::<Result<_>>()
+            .map(Value::Table),
+        value => Ok(value),
+    }
+}
+
+fn expand_string(
+    chain: &mut Vec<String>,
+    text: &str,
+    lookup: &dyn Fn(&str) -> Result<Value>,
+) -> Result<String> {
+    let mut expanded = String::with_capacity(text.len());
+    let mut rest = text;
+    while let Some(start) = rest.find('$') {
+        expanded.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if let Some(after) = rest.strip_prefix("$${") {
+            expanded.push_str("${");
+            rest = after;
+            continue;
+        }
+        let Some(after) = rest.strip_prefix("${") else {
+            expanded.push('$');
+            rest = &rest[1..];
+            continue;
+        };
+        let end = after.find('}').ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Unterminated reference in key '{}'", chain[0]),
+            )
+        })?;
+        let target = after[..end].trim();
+        if target.contains('.') {
+            expanded.push_str(&resolve(chain, target, lookup)?);
+        } else {
+            expanded.push_str(&rest[..end + 3]);
+        }
+        rest = &after[end + 1..];
+    }
+    expanded.push_str(rest);
+    Ok(expanded)
+}
+
+fn resolve(
+    chain: &mut Vec<String>,
+    target: &str,
+    lookup: &dyn Fn(&str) -> Result<Value>,
+) -> Result<String> {
+    let referrer = chain.last().cloned().unwrap_or_default();
+    if chain.iter().any(|key_path| key_path == target) {
+        chain.push(target.to_string());
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Reference cycle: {}", chain.join(" -> ")),
+        ));
+    }
+    let value = lookup(target).map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!(
+                "Key '{}' referenced from '{}' can't be read: {}",
+                target, referrer, e
+            ),
+        )
+    })?;
+    chain.push(target.to_string());
+    let value = expand_within(chain, value, lookup)?;
+    chain.pop();
+    match value {
+        Value::String(text) => Ok(text),
+        Value::Integer(n) => Ok(n.to_string()),
+        Value::Float(n) => Ok(n.to_string()),
+        Value::Boolean(b) => Ok(b.to_string()),
+        _ => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "Key '{}' referenced from '{}' is not a string, number or boolean",
+                target, referrer
+            ),
+        )),
+    }
+}
+
+fn contains_reference(value: &Value) -> bool {
+    match value {
+        Value::String(text) => text.contains("${"),
+        Value::Array(items) => items.iter().any(contains_reference),
+        Value::Table(table) => table.values().any(contains_reference),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::AiConfig;
+    use crate::config::{Config, get_raw_value, get_value_fast, update_config_value};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_interpolate_follows_references_and_detects_cycles() {
+        let config: Value = toml::from_str(
+            r#"
+            [ai]
+            url = "https://api.example.com"
+            chat = "${ai.url}/v1/chat/completions"
+            price = "$${5} at ${update.max_try} tries"
+            [update]
+            max_try = 5
+            "#,
+        )
+        .unwrap();
+        let expanded = interpolate(&config).unwrap();
+        assert_eq!(
+            expanded["ai"]["chat"].as_str(),
+            Some("https://api.example.com/v1/chat/completions")
+        );
+        assert_eq!(expanded["ai"]["price"].as_str(), Some("${5} at 5 tries"));
+
+        let config: Value = toml::from_str("[a]\nx = \"${a.y}\"\ny = \"-${a.x}\"\n").unwrap();
+        let err = interpolate(&config).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+        assert!(err.to_string().contains("a.x -> a.y -> a.x"), "{}", err);
+
+        let config: Value = toml::from_str("[a]\nx = \"${a.missing}\"\n").unwrap();
+        assert_eq!(
+            interpolate(&config).unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+
+        let config: Value = toml::from_str("[hooks]\non_change = \"${HOME}/sync.sh\"\n").unwrap();
+        assert_eq!(interpolate(&config).unwrap(), config);
+    }
+
+    #[test]
+    fn test_accessors_expand_and_raw_accessor_does_not() {
+        let _dir = TempConfigDir::new().unwrap();
+        update_config_value("ai", "url", Value::from("https://api.example.com")).unwrap();
+        update_config_value("ai", "model", Value::from("${ai.url}/v1/chat/completions")).unwrap();
+        assert_eq!(
+            get_value_fast("ai.model").unwrap().as_str(),
+            Some("https://api.example.com/v1/chat/completions")
+        );
+        assert_eq!(
+            get_raw_value("ai.model").unwrap().as_str(),
+            Some("${ai.url}/v1/chat/completions")
+        );
+        let config = Config::load().unwrap();
+        assert_eq!(
+            config.get_str("ai.model"),
+            Some("https://api.example.com/v1/chat/completions")
+        );
+        assert_eq!(
+            config.get_raw("ai.model").and_then(Value::as_str),
+            Some("${ai.url}/v1/chat/completions")
+        );
+
+        // A broken reference only fails reads of its own key.
+        update_config_value("ai", "language", Value::from("${ai.nowhere}")).unwrap();
+        assert!(get_value_fast("ai.language").is_err());
+        let ai = AiConfig::load().unwrap();
+        assert_eq!(ai.model, "https://api.example.com/v1/chat/completions");
+
+        // References inside arrays of tables are expanded too.
+        let endpoints: Value = toml::from_str("[[endpoints]]\nurl = \"${ai.url}/v2\"\n").unwrap();
+        update_config_value("ai", "endpoints", endpoints["endpoints"].clone()).unwrap();
+        let expanded = Value::from("https://api.example.com/v2");
+        assert_eq!(get_value_fast("ai.endpoints").unwrap()[0]["url"], expanded);
+        let config = Config::load().unwrap();
+        assert_eq!(config.get("ai.endpoints").unwrap()[0]["url"], expanded);
+    }
+}
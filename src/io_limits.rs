@@ -0,0 +1,208 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    sync::{Mutex, RwLock, mpsc},
+    thread,
+    time::{Duration, Instant},
+};
+
+use crate::directory::CONFIG_DIR_ENV;
+use crate::init::emit_warning;
+
+static LIMITS: RwLock<IoLimits> = RwLock::new(IoLimits::DEFAULT);
+static WARNINGS: Mutex<Vec<SlowStorageWarning>> = Mutex::new(Vec::new());
+
+/// How long reads and writes of configuration files may take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct IoLimits {
+    /// How long one read may take before it fails with `TimedOut`; `None` waits as long
+    /// as the file system needs. Writes are never cut short, see [`set_io_limits`]
+    pub timeout: Option<Duration>,
+    /// How many milliseconds one read or write may take before a
+    /// [`SlowStorageWarning`] is recorded
+    pub slow_storage_warning_ms: u64,
+}
+
+impl IoLimits {
+    const DEFAULT: IoLimits = IoLimits {
+        timeout: None,
+        slow_storage_warning_ms: 500,
+    };
+}
+
+impl Default for IoLimits {
+    fn default() -> IoLimits {
+        IoLimits::DEFAULT
+    }
+}
+
+/// A read or write of a configuration file that took longer than
+/// [`IoLimits::slow_storage_warning_ms`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SlowStorageWarning {
+    /// What was done, `"read"` or `"write"`
+    pub operation: &'static str,
+    /// The file
+    pub path: PathBuf,
+    /// How long it took
+    pub elapsed: Duration,
+    /// What the user can do about it
+    pub suggestion: String,
+}
+
+/// Sets the limits for reads and writes of configuration files.
+///
+/// A home directory on NFS or in a OneDrive-synced folder can make every read take
+/// seconds. With a timeout such reads fail with a `TimedOut` error naming the file
+/// instead of hanging; the blocked read finishes on a background thread. Writes only get
+/// the slow storage warning: they run while the file lock is held, and a write left
+/// running after the lock is released could replace a newer writer's file.
+pub fn set_io_limits(limits: IoLimits) {
+    *LIMITS.write().unwrap_or_else(|e| e.into_inner()) = limits;
+}
+
+/// Returns the current limits for reads and writes of configuration files.
+pub fn io_limits() -> IoLimits {
+    *LIMITS.read().unwrap_or_else(|e| e.into_inner())
+}
+
+/// Returns the slow reads and writes recorded in this process, one per file and
+/// operation, e.g. to show them in `gim doctor`.
+pub fn slow_storage_warnings() -> Vec<SlowStorageWarning> {
+    WARNINGS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Runs the file operation `f` on `path` within the current [`IoLimits`].
+pub(crate) fn timed<T: Send + 'static>(
+    operation: &'static str,
+    path: &Path,
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    run_timed(io_limits(), operation, path, f)
+}
+
+/// Runs the file operation `f` on `path` to completion, only recording a
+/// [`SlowStorageWarning`] if it is slow; for writes made under the file lock.
+pub(crate) fn measured<T>(
+    operation: &'static str,
+    path: &Path,
+    f: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    run_measured(io_limits(), operation, path, f)
+}
+
+fn run_measured<T>(
+    limits: IoLimits,
+    operation: &'static str,
+    path: &Path,
+    f: impl FnOnce() -> Result<T>,
+) -> Result<T> {
+    let started = Instant::now();
+    let result = f();
+    warn_if_slow(limits, operation, path, started.elapsed());
+    result
+}
+
+fn run_timed<T: Send + 'static>(
+    limits: IoLimits,
+    operation: &'static str,
+    path: &Path,
+    f: impl FnOnce() -> Result<T> + Send + 'static,
+) -> Result<T> {
+    let started = Instant::now();
+    let result = match limits.timeout {
+        None => f(),
+        Some(timeout) => {
+            let (sender, receiver) = mpsc::channel();
+            thread::spawn(move || {
+                let _ = sender.send(f());
+            });
+            receiver.recv_timeout(timeout).unwrap_or_else(|_| {
+                Err(Error::new(
+                    ErrorKind::TimedOut,
+                    format!(
+                        "Timed out after {} ms waiting to {} '{}'; {}",
+                        timeout.as_millis(),
+                        operation,
+                        path.display(),
+                        relocation_hint()
+                    ),
+                ))
+            })
+        }
+    };
+    warn_if_slow(limits, operation, path, started.elapsed());
+    result
+}
+
+fn warn_if_slow(limits: IoLimits, operation: &'static str, path: &Path, elapsed: Duration) {
+    if elapsed.as_millis() >= u128::from(limits.slow_storage_warning_ms) {
+        record_warning(SlowStorageWarning {
+            operation,
+            path: path.to_path_buf(),
+            elapsed,
+            suggestion: format!(
+                "'{}' took {} ms to {}; {}",
+                path.display(),
+                elapsed.as_millis(),
+                operation,
+                relocation_hint()
+            ),
+        });
+    }
+}
+
+fn relocation_hint() -> String {
+    format!(
+        "if the config directory is on a network or synced drive, set {} to a local directory",
+        CONFIG_DIR_ENV
+    )
+}
+
+fn record_warning(warning: SlowStorageWarning) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(
+        file = %warning.path.display(),
+        elapsed_ms = warning.elapsed.as_millis() as u64,
+        "slow config storage"
+    );
+    emit_warning(&warning.suggestion);
+    let mut warnings = WARNINGS.lock().unwrap_or_else(|e| e.into_inner());
+    warnings.retain(|w| w.path != warning.path || w.operation != warning.operation);
+    warnings.push(warning);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_run_timed_times_out_and_warns() {
+        let path = PathBuf::from("/slow/io_limits_test.toml");
+        let limits = IoLimits {
+            timeout: Some(Duration::from_millis(20)),
+            slow_storage_warning_ms: 0,
+        };
+        let error = run_timed(limits, "read", &path, || {
+            thread::sleep(Duration::from_millis(500));
+            Ok(())
+        })
+        .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::TimedOut);
+        assert!(error.to_string().contains(CONFIG_DIR_ENV));
+
+        assert_eq!(run_timed(limits, "read", &path, || Ok(7)).unwrap(), 7);
+        let warnings: Vec<SlowStorageWarning> = slow_storage_warnings()
+            .into_iter()
+            .filter(|w| w.path == path)
+            .collect();
+        assert_eq!(warnings.len(), 1);
+        assert!(warnings[0].suggestion.contains(CONFIG_DIR_ENV));
+
+        let write = run_measured(limits, "write", &path, || {
+            thread::sleep(Duration::from_millis(50));
+            Ok(8)
+        });
+        assert_eq!(write.unwrap(), 8);
+    }
+}
@@ -0,0 +1,322 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{ErrorKind, Result},
+    path::{Path, PathBuf},
+    sync::Mutex,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use toml::{Value, map};
+
+use crate::change::ChangeSet;
+use crate::directory::state_dir;
+use crate::init::emit_warning;
+use crate::path;
+use crate::state::parse_state;
+use crate::storage::{FileLock, write_atomic};
+
+/// The section of the configuration file that turns per-key merging on with
+/// `enabled = true`.
+pub const KEY_TIMES_SECTION: &str = "key_times";
+
+/// How many of the documents this process read or wrote are kept per file as merge bases.
+const MAX_BASES: usize = 16;
+
+/// The documents this process read or wrote, oldest first, any of which a caller may hold
+/// and save later.
+static BASES: Mutex<Vec<Base>> = Mutex::new(Vec::new());
+static CONFLICTS: Mutex<Vec<MergeConflict>> = Mutex::new(Vec::new());
+
+/// A document of a configuration file as this process last saw it.
+struct Base {
+    file: PathBuf,
+    document: Value,
+    seen: SystemTime,
+}
+
+/// A key both a save and an edit made since this process read the file changed differently.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    /// The configuration file
+    pub file: PathBuf,
+    /// The dotted key path
+    pub path: String,
+    /// The value the save wanted, `None` if it removed the key
+    pub ours: Option<Value>,
+    /// The value the other edit wrote, `None` if it removed the key
+    pub theirs: Option<Value>,
+    /// Whether the save's value was kept: the other edit happened before this process last
+    /// saw the document it saved, e.g. a sync tool restored an older file
+    pub ours_kept: bool,
+}
+
+/// Returns the sidecar recording when each key was last changed, `key-times.toml` in the
+/// state directory.
+pub fn key_times_file() -> Result<PathBuf> {
+    Ok(state_dir()?.join("key-times.toml"))
+}
+
+/// Returns whether `config` turns per-key merging on.
+pub fn is_key_times_enabled(config: &Value) -> bool {
+    config
+        .get(KEY_TIMES_SECTION)
+        .and_then(|section| section.get("enabled"))
+        .and_then(Value::as_bool)
+        == Some(true)
+}
+
+/// Returns when `key_path` was last changed, as recorded in the sidecar.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path
+///
+/// # Returns
+///
+/// * `Result<Option<SystemTime>>` - The time, `None` if no change was recorded, or an error
+pub fn key_modified(key_path: &str) -> Result<Option<SystemTime>> {
+    Ok(read_times()?.get(key_path).copied())
+}
+
+/// Returns the merge conflicts of this process, latest last.
+pub fn merge_conflicts() -> Vec<MergeConflict> {
+    CONFLICTS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Remembers `config` as a document of `file` this process handed out or wrote.
+pub(crate) fn remember_base(file: &Path, config: &Value) {
+    if !is_key_times_enabled(config) {
+        return;
+    }
+    let mut bases = BASES.lock().unwrap_or_else(|e| e.into_inner());
+    bases.retain(|base| base.file != file || base.document != *config);
+    bases.push(Base {
+        file: file.to_path_buf(),
+        document: config.clone(),
+        seen: SystemTime::now(),
+    });
+    let kept = bases.iter().filter(|base| base.file == file).count();
+    if kept > MAX_BASES {
+        let oldest = bases.iter().position(|base| base.file == file);
+        bases.remove(oldest.expect("a base of the file exists"));
+    }
+}
+
+/// Returns the remembered document of `file` that `config` was most likely derived from,
+/// the one it differs from in the fewest keys, and when it was last seen.
+fn base_of(file: &Path, config: &Value) -> Option<(Value, SystemTime)> {
+    let bases = BASES.lock().unwrap_or_else(|e| e.into_inner());
+    bases
+        .iter()
+        .filter(|base| base.file == file)
+        .enumerate()
+        // On a tie the older document wins, since a held document is rarely the newest.
+        .min_by_key(|(age, base)| (ChangeSet::between(&base.document, config).len(), *age))
+        .map(|(_, base)| (base.document.clone(), base.seen))
+}
+
+/// Merges a save of `config` with the edits made to `file` since the document it was
+/// derived from was read.
+///
+/// Without per-key merging, or if nobody else edited the file, `config` is returned as is.
+/// Otherwise the keys the save changed are applied to the current document `current` and
+/// every other key keeps its current value. A key both sides changed differently goes to
+/// the later change and is reported: the save counts as made when its document was last
+/// seen, the other edit at its recorded time or the file's modification time.
+pub(crate) fn merge_external_edits(file: &Path, current: &Value, config: Value) -> Result<Value> {
+    if !is_key_times_enabled(current) {
+        return Ok(config);
+    }
+    let Some((base, seen)) = base_of(file, &config).filter(|(base, _)| base != current) else {
+        return Ok(config);
+    };
+    let times = read_times()?;
+    let edited = fs::metadata(file).and_then(|m| m.modified()).ok();
+    let theirs = ChangeSet::between(&base, current);
+    let mut merged = current.clone();
+    for ours in ChangeSet::between(&base, &config).changes {
+        let other = theirs
+            .changes
+            .iter()
+            .find(|change| change.path == ours.path);
+        let ours_kept = match other {
+            None => true,
+            Some(other) if other.new == ours.new => continue,
+            Some(other) => {
+                let theirs_time = times.get(&ours.path).copied().max(edited);
+                let ours_kept = theirs_time.is_none_or(|time| time <= seen);
+                record_conflict(MergeConflict {
+                    file: file.to_path_buf(),
+                    path: ours.path.clone(),
+                    ours: ours.new.clone(),
+                    theirs: other.new.clone(),
+                    ours_kept,
+                });
+                ours_kept
+            }
+        };
+        if ours_kept {
+            match ours.new {
+                Some(value) => {
+                    path::insert(&mut merged, &ours.path, value)?;
+                }
+                None => {
+                    path::remove(&mut merged, &ours.path);
+                }
+            }
+        }
+    }
+    Ok(merged)
+}
+
+/// Records that the keys changed between `old` and `new` changed now, and remembers `new`
+/// as what this process last saw of `file`.
+pub(crate) fn record_key_times(file: &Path, old: &Value, new: &Value) -> Result<()> {
+    if !is_key_times_enabled(new) {
+        return Ok(());
+    }
+    remember_base(file, new);
+    let changes = ChangeSet::between(old, new);
+    if changes.is_empty() {
+        return Ok(());
+    }
+    let sidecar = key_times_file()?;
+    if let Some(parent) = sidecar.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let _lock = FileLock::acquire(&sidecar)?;
+    let mut times = read_times()?;
+    let now = SystemTime::now();
+    for change in changes.changes {
+        times.insert(change.path, now);
+    }
+    let table: map::Map<String, Value> = times
+        .into_iter()
+        .map(|(key_path, time)| (key_path, Value::Integer(unix_millis(time))))
+        .collect();
+    let content = toml::to_string(&Value::Table(table)).map_err(std::io::Error::other)?;
+    write_atomic(&sidecar, content.as_bytes())
+}
+
+fn read_times() -> Result<HashMap<String, SystemTime>> {
+    let content = match fs::read_to_string(key_times_file()?) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(HashMap::new()),
+        Err(e) => return Err(e),
+    };
+    let Value::Table(table) = parse_state(&content)? else {
+        return Ok(HashMap::new());
+    };
+    Ok(table
+        .into_iter()
+        .filter_map(|(key_path, millis)| {
+            let millis = u64::try_from(millis.as_integer()?).ok()?;
+            Some((key_path, UNIX_EPOCH + Duration::from_millis(millis)))
+        })
+        .collect())
+}
+
+fn unix_millis(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_millis() as i64)
+}
+
+fn record_conflict(conflict: MergeConflict) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(file = %conflict.file.display(), key = %conflict.path, "merge conflict");
+    emit_warning(&format!(
+        "'{}' was changed in '{}' while it was being saved; kept the {} value",
+        conflict.path,
+        conflict.file.display(),
+        if conflict.ours_kept {
+            "saved"
+        } else {
+            "edited"
+        }
+    ));
+    CONFLICTS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(conflict);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config, get_config_file, get_value_fast, save_config};
+    use crate::testing::TempConfigDir;
+
+    /// Turns merging on and returns the document a long-running process holds while the
+    /// user edits the file by hand to `ai.url = "http://edited"`, `ai.model = "by-hand"`.
+    fn hold_and_edit(file: &Path) -> Value {
+        let mut config = get_config().unwrap();
+        path::insert(&mut config, "key_times.enabled", Value::Boolean(true)).unwrap();
+        save_config(&config).unwrap();
+        assert!(key_modified("key_times.enabled").unwrap().is_some());
+
+        let held = get_config().unwrap();
+        let mut edited = held.clone();
+        path::insert(&mut edited, "ai.url", Value::from("http://edited")).unwrap();
+        path::insert(&mut edited, "ai.model", Value::from("by-hand")).unwrap();
+        std::thread::sleep(Duration::from_millis(20));
+        fs::write(file, toml::to_string(&edited).unwrap()).unwrap();
+        // An unrelated read sees the edit; the held document stays the merge base.
+        assert_eq!(
+            get_value_fast("ai.model").unwrap().as_str(),
+            Some("by-hand")
+        );
+        held
+    }
+
+    fn save_held(mut held: Value) {
+        path::insert(&mut held, "ai.language", Value::from("German")).unwrap();
+        path::insert(&mut held, "ai.model", Value::from("by-gim")).unwrap();
+        save_config(&held).unwrap();
+    }
+
+    fn conflict_of(file: &Path) -> MergeConflict {
+        let conflict = merge_conflicts()
+            .into_iter()
+            .rfind(|c| c.file == file)
+            .unwrap();
+        assert_eq!(conflict.path, "ai.model");
+        assert_eq!(conflict.theirs, Some(Value::from("by-hand")));
+        conflict
+    }
+
+    #[test]
+    fn test_save_merges_external_edits() {
+        let _dir = TempConfigDir::new().unwrap();
+        let file = get_config_file().unwrap();
+        let held = hold_and_edit(&file);
+        save_held(held);
+
+        let saved = get_config().unwrap();
+        assert_eq!(saved["ai"]["url"].as_str(), Some("http://edited"));
+        assert_eq!(saved["ai"]["language"].as_str(), Some("German"));
+        // The hand edit is newer than the document the process saved.
+        assert_eq!(saved["ai"]["model"].as_str(), Some("by-hand"));
+        assert!(!conflict_of(&file).ours_kept);
+    }
+
+    #[test]
+    fn test_save_wins_over_older_external_edit() {
+        let _dir = TempConfigDir::new().unwrap();
+        let file = get_config_file().unwrap();
+        let held = hold_and_edit(&file);
+        // E.g. a sync tool restored a file written an hour ago.
+        let old = SystemTime::now() - Duration::from_secs(3600);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .unwrap()
+            .set_modified(old)
+            .unwrap();
+        save_held(held);
+
+        let saved = get_config().unwrap();
+        assert_eq!(saved["ai"]["url"].as_str(), Some("http://edited"));
+        assert_eq!(saved["ai"]["model"].as_str(), Some("by-gim"));
+        assert!(conflict_of(&file).ours_kept);
+    }
+}
@@ -0,0 +1,168 @@
+use std::io::{Error, ErrorKind, Result};
+use toml::Value;
+
+use crate::config::update_config_value;
+
+/// A language gim can write commit messages in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Language {
+    /// The BCP 47 code, e.g. `"zh-CN"`
+    pub code: &'static str,
+    /// The English name, which is what `ai.language` stores and prompts use, e.g. `"Chinese"`
+    pub name: &'static str,
+    /// The name in the language itself, for pickers, e.g. `"简体中文"`
+    pub native_name: &'static str,
+    aliases: &'static [&'static str],
+}
+
+/// Every supported language, English first.
+const LANGUAGES: &[Language] = &[
+    Language {
+        code: "en",
+        name: "English",
+        native_name: "English",
+        aliases: &["en-us", "en-gb"],
+    },
+    Language {
+        code: "zh-CN",
+        name: "Chinese",
+        native_name: "简体中文",
+        aliases: &["zh", "zh-hans", "simplified chinese", "中文"],
+    },
+    Language {
+        code: "zh-TW",
+        name: "Traditional Chinese",
+        native_name: "繁體中文",
+        aliases: &["zh-hant", "zh-hk"],
+    },
+    Language {
+        code: "ja",
+        name: "Japanese",
+        native_name: "日本語",
+        aliases: &["ja-jp", "jp"],
+    },
+    Language {
+        code: "ko",
+        name: "Korean",
+        native_name: "한국어",
+        aliases: &["ko-kr"],
+    },
+    Language {
+        code: "de",
+        name: "German",
+        native_name: "Deutsch",
+        aliases: &["de-de"],
+    },
+    Language {
+        code: "fr",
+        name: "French",
+        native_name: "Français",
+        aliases: &["fr-fr"],
+    },
+    Language {
+        code: "es",
+        name: "Spanish",
+        native_name: "Español",
+        aliases: &["es-es"],
+    },
+    Language {
+        code: "pt",
+        name: "Portuguese",
+        native_name: "Português",
+        aliases: &["pt-br", "pt-pt"],
+    },
+    Language {
+        code: "it",
+        name: "Italian",
+        native_name: "Italiano",
+        aliases: &["it-it"],
+    },
+    Language {
+        code: "ru",
+        name: "Russian",
+        native_name: "Русский",
+        aliases: &["ru-ru"],
+    },
+];
+
+/// Returns every language gim supports, for language pickers.
+pub fn supported_languages() -> &'static [Language] {
+    LANGUAGES
+}
+
+/// Finds a language by code, English name, native name or alias, ignoring case and
+/// treating `_` like `-`, so `"zh"`, `"zh_cn"`, `"Chinese"` and `"简体中文"` all find
+/// `zh-CN`.
+///
+/// # Arguments
+///
+/// * `input` - What the user typed or another tool stored
+///
+/// # Returns
+///
+/// * `Option<&'static Language>` - The language, or `None` if it isn't supported
+pub fn find_language(input: &str) -> Option<&'static Language> {
+    let wanted = input.trim().replace('_', "-").to_lowercase();
+    LANGUAGES.iter().find(|language| {
+        [language.code, language.name, language.native_name]
+            .iter()
+            .chain(language.aliases)
+            .any(|candidate| candidate.to_lowercase() == wanted)
+    })
+}
+
+/// Sets `ai.language`, the language of the generated commit messages.
+///
+/// The English name of the language is stored, so a misspelled or unknown language
+/// never reaches the prompt.
+///
+/// # Arguments
+///
+/// * `input` - A code, name or alias, see [`find_language`]
+///
+/// # Returns
+///
+/// * `Result<&'static Language>` - The language that was set, or an `InvalidInput` error
+///   listing the supported codes
+pub fn set_language(input: &str) -> Result<&'static Language> {
+    let language = find_language(input).ok_or_else(|| {
+        let codes: Vec<&str> = LANGUAGES.iter().map(|language| language.code).collect();
+        Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Unsupported language '{}', supported: {}",
+                input,
+                codes.join(", ")
+            ),
+        )
+    })?;
+    update_config_value("ai", "language", Value::from(language.name))?;
+    Ok(language)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ai::AiConfig;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_find_language_resolves_aliases() {
+        for input in ["zh", "zh_CN", "Chinese", "简体中文", " zh-cn "] {
+            assert_eq!(find_language(input).map(|l| l.code), Some("zh-CN"));
+        }
+        assert_eq!(find_language("deutsch").map(|l| l.code), Some("de"));
+        assert!(find_language("Klingon").is_none());
+        assert_eq!(supported_languages()[0].code, "en");
+    }
+
+    #[test]
+    fn test_set_language() {
+        let _dir = TempConfigDir::new().unwrap();
+        assert_eq!(set_language("ja").unwrap().name, "Japanese");
+        assert_eq!(AiConfig::load().unwrap().language, "Japanese");
+        let err = set_language("Englsh").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidInput);
+        assert_eq!(AiConfig::load().unwrap().language, "Japanese");
+    }
+}
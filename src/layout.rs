@@ -0,0 +1,364 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+};
+use toml::{Value, map};
+
+use crate::config::{
+    ensure_persistent, get_config_file, get_config_into_toml, invalidate_document_cache,
+};
+use crate::edit::render_minimal;
+use crate::encoding::{decode, is_plain_utf8, with_line_endings};
+use crate::format::Format;
+use crate::io_limits::{measured, timed};
+use crate::lenient::parse_toml;
+use crate::recovery::classify;
+use crate::storage::{FileLock, write_atomic};
+
+/// The top-level key of `config.toml` that switches on the split layout.
+pub const LAYOUT_KEY: &str = "layout";
+
+/// How the configuration is stored on disk.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Layout {
+    /// Everything lives in `config.toml`
+    Single,
+    /// Each top-level section lives in `config.d/<section>.toml` next to `config.toml`
+    Split,
+}
+
+impl Layout {
+    fn of(config: &Value) -> Layout {
+        match config.get(LAYOUT_KEY).and_then(Value::as_str) {
+            Some("split") => Layout::Split,
+            _ => Layout::Single,
+        }
+    }
+}
+
+/// Returns the layout of the configuration on disk.
+///
+/// # Returns
+///
+/// * `Result<Layout>` - The current layout or an error if the configuration can't be read
+pub fn current_layout() -> Result<Layout> {
+    Ok(Layout::of(&get_config_into_toml(false)?))
+}
+
+/// Moves every top-level section of `config.toml` into its own file, e.g. `config.d/ai.toml`.
+///
+/// The files are still presented as one logical configuration, and each write only
+/// touches the files of the sections it changed, which keeps dotfile repositories free of
+/// unrelated merge conflicts. `config.toml` keeps the top-level keys that aren't tables
+/// together with `layout = "split"`; sections whose names aren't usable as file names stay
+/// there as well. The `config.d` directory belongs to the layout: every file of the root's
+/// format in it is read as a section, and files of removed sections are deleted, while
+/// other files next to `config.toml` are never touched. Migrating an already split
+/// configuration does nothing.
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if reading or writing fails
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "gim_config.migrate", level = "info", err)
+)]
+pub fn migrate_to_split_layout() -> Result<()> {
+    ensure_persistent()?;
+    get_config_into_toml(false)?;
+    let config_file = get_config_file()?;
+    let _lock = FileLock::acquire(&config_file)?;
+    let mut config = read_document(&config_file)?;
+    if Layout::of(&config) == Layout::Split {
+        return Ok(());
+    }
+    if let Some(root) = config.as_table_mut() {
+        root.insert(LAYOUT_KEY.to_string(), Value::from("split"));
+    }
+    let result = write_document(&config_file, &config);
+    invalidate_document_cache();
+    result
+}
+
+/// Reads the logical configuration whose root file is `config_file`.
+pub(crate) fn read_document(config_file: &Path) -> Result<Value> {
+    let mut config = parse_file(config_file)?;
+    if Layout::of(&config) == Layout::Single {
+        return Ok(config);
+    }
+    let root = config
+        .as_table_mut()
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Config is not a table"))?;
+    for (section, file) in section_files(config_file)? {
+        root.insert(section, parse_file(&file)?);
+    }
+    Ok(config)
+}
+
+/// Writes the logical configuration `config`, routing sections to their own files if the
+/// split layout is active. Unchanged section files are left alone.
+///
+/// Callers are expected to hold the lock of `config_file`.
+pub(crate) fn write_document(config_file: &Path, config: &Value) -> Result<()> {
+    if Layout::of(config) == Layout::Single {
+        return write_value(config_file, config);
+    }
+    let mut root = map::Map::new();
+    let mut sections = map::Map::new();
+    for (key, value) in config.as_table().into_iter().flatten() {
+        if value.is_table() && is_section_file_name(key) {
+            sections.insert(key.clone(), value.clone());
+        } else {
+            root.insert(key.clone(), value.clone());
+        }
+    }
+    for (section, file) in section_files(config_file)? {
+        if !sections.contains_key(&section) {
+            fs::remove_file(file)?;
+        }
+    }
+    if !sections.is_empty() {
+        fs::create_dir_all(section_dir(config_file))?;
+    }
+    for (section, value) in &sections {
+        let file = section_file(config_file, section);
+        // A file that isn't plain UTF-8 is re-encoded even if its settings didn't change.
+        if file.exists()
+            && parse_file(&file)? == *value
+            && read_bytes(&file).is_ok_and(|bytes| is_plain_utf8(&bytes))
+        {
+            continue;
+        }
+        write_value(&file, value)?;
+    }
+    write_value(config_file, &Value::Table(root))
+}
+
+/// Returns every file the logical configuration rooted at `config_file` is read from.
+pub(crate) fn document_files(config_file: &Path) -> Result<Vec<PathBuf>> {
+    let mut files = vec![config_file.to_path_buf()];
+    if Layout::of(&parse_file(config_file)?) == Layout::Split {
+        files.extend(
+            section_files(config_file)?
+                .into_iter()
+                .map(|(_, file)| file),
+        );
+    }
+    Ok(files)
+}
+
+/// Returns the section files the logical configuration rooted at `config_file` is read
+/// from, as `(section, file)`; empty with the single-file layout.
+pub(crate) fn active_section_files(config_file: &Path) -> Result<Vec<(String, PathBuf)>> {
+    if Layout::of(&parse_file(config_file)?) == Layout::Single {
+        return Ok(Vec::new());
+    }
+    section_files(config_file)
+}
+
+/// Returns the tables of the root file that section files replace, as
+/// `(section, replaced table, section file)`.
+pub(crate) fn replaced_sections(config_file: &Path) -> Result<Vec<(String, Value, PathBuf)>> {
+    let root = parse_file(config_file)?;
+    let mut replaced = Vec::new();
+    for (section, file) in active_section_files(config_file)? {
+        if let Some(table) = root.get(&section) {
+            replaced.push((section, table.clone(), file));
+        }
+    }
+    Ok(replaced)
+}
+
+/// Returns the file that stores `key_path` and the key's path within that file.
+///
+/// With the split layout keys of a section live in its section file; the section itself
+/// has no key there, so asking for a whole split section returns `Unsupported`.
+pub(crate) fn key_file(config_file: &Path, key_path: &str) -> Result<(PathBuf, String)> {
+    let (section, rest) = match key_path.split_once('.') {
+        Some((section, rest)) => (section, Some(rest)),
+        None => (key_path, None),
+    };
+    let split = active_section_files(config_file)?
+        .into_iter()
+        .find(|(name, _)| name == section);
+    match (split, rest) {
+        (None, _) => Ok((config_file.to_path_buf(), key_path.to_string())),
+        (Some((_, file)), Some(rest)) => Ok((file, rest.to_string())),
+        (Some((_, file)), None) => Err(Error::new(
+            ErrorKind::Unsupported,
+            format!(
+                "Section '{}' is stored in '{}' and has no key of its own",
+                section,
+                file.display()
+            ),
+        )),
+    }
+}
+
+/// Returns the extension of section files, which use the format of `config_file`.
+fn section_extension(config_file: &Path) -> &'static str {
+    Format::from_path(config_file).map_or("toml", Format::extension)
+}
+
+/// Returns the directory owned by the split layout, `config.d` for `config.toml`.
+fn section_dir(config_file: &Path) -> PathBuf {
+    let stem = config_file
+        .file_stem()
+        .and_then(|stem| stem.to_str())
+        .unwrap_or("config");
+    config_file.with_file_name(format!("{}.d", stem))
+}
+
+fn section_file(config_file: &Path, section: &str) -> PathBuf {
+    section_dir(config_file).join(format!("{}.{}", section, section_extension(config_file)))
+}
+
+/// Lists the `<section>.toml` files, or those of the root's format, in the section
+/// directory of `config_file`, sorted by section name.
+fn section_files(config_file: &Path) -> Result<Vec<(String, PathBuf)>> {
+    let dir = section_dir(config_file);
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let suffix = format!(".{}", section_extension(config_file));
+    let mut files = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        if !path.is_file() {
+            continue;
+        }
+        if let Some(section) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(&suffix))
+            && is_section_file_name(section)
+        {
+            files.push((section.to_string(), path.clone()));
+        }
+    }
+    files.sort();
+    Ok(files)
+}
+
+fn is_section_file_name(section: &str) -> bool {
+    !section.is_empty()
+        && section != "config"
+        && section
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-')
+}
+
+/// Parses a file in the format its extension selects.
+fn parse_file(file: &Path) -> Result<Value> {
+    let content = read_file(file)?;
+    let format = Format::from_path(file)?;
+    let parsed = if format == Format::Toml {
+        parse_toml(file, &content)
+    } else {
+        format.parse(&content)
+    };
+    parsed.map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Failed to parse '{}': {}", file.display(), e),
+        )
+    })
+}
+
+/// Writes `value` to `file` in the format its extension selects.
+///
+/// TOML files are edited in place so unchanged lines stay as they are.
+fn write_value(file: &Path, value: &Value) -> Result<()> {
+    let format = Format::from_path(file)?;
+    let raw = match read_bytes(file) {
+        Ok(bytes) => Some(bytes),
+        Err(e) if e.kind() == ErrorKind::TimedOut => return Err(e),
+        Err(_) => None,
+    };
+    let existing = raw.clone().and_then(|bytes| decode(file, bytes).ok());
+    let edited = existing
+        .as_deref()
+        .filter(|_| format == Format::Toml)
+        .and_then(|text| {
+            let old: Value = toml::from_str(text).ok()?;
+            render_minimal(text, &old, value)
+        });
+    let content = match edited {
+        Some(content) => content,
+        None => format.serialize(value)?,
+    };
+    let content = with_line_endings(content, existing.as_deref());
+    // Compared undecoded, so a byte order mark or Windows-1252 text is rewritten as UTF-8
+    // even when the settings didn't change.
+    if raw.as_deref() == Some(content.as_bytes()) {
+        return Ok(());
+    }
+    // Never abandoned on a timeout: the caller holds the lock until the write is done.
+    measured("write", file, || write_atomic(file, content.as_bytes()))
+}
+
+/// Reads `file` within the configured [`crate::io_limits::IoLimits`].
+///
+/// The file must be UTF-8; a byte order mark is stripped, see [`crate::encoding`].
+pub(crate) fn read_file(file: &Path) -> Result<String> {
+    decode(file, read_bytes(file)?)
+}
+
+/// Reads the undecoded content of `file` within the configured
+/// [`crate::io_limits::IoLimits`].
+fn read_bytes(file: &Path) -> Result<Vec<u8>> {
+    let target = file.to_path_buf();
+    timed("read", file, move || fs::read(target)).map_err(|e| classify(e, file))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config_value, update_config_value};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_split_layout_routes_writes_to_section_files() {
+        let dir = TempConfigDir::new().unwrap();
+        update_config_value("ai", "model", Value::from("gpt-4o")).unwrap();
+        migrate_to_split_layout().unwrap();
+        assert_eq!(current_layout().unwrap(), Layout::Split);
+        // Unrelated files next to config.toml are neither read nor deleted.
+        let notes = dir.path().join("notes.toml");
+        fs::write(&notes, "[todo]\nitem = 'x'\n").unwrap();
+        assert!(get_config_value("todo", "item").is_err());
+        let ai_file = dir.path().join("config.d").join("ai.toml");
+        assert!(fs::read_to_string(&ai_file).unwrap().contains("gpt-4o"));
+        assert!(
+            !fs::read_to_string(dir.path().join("config.toml"))
+                .unwrap()
+                .contains("[ai]")
+        );
+
+        let update_file = dir.path().join("config.d").join("update.toml");
+        let update_before = fs::metadata(&update_file).unwrap().modified().unwrap();
+        update_config_value("ai", "language", Value::from("Dutch")).unwrap();
+        assert_eq!(
+            get_config_value("ai", "language").unwrap(),
+            Value::from("Dutch")
+        );
+        assert!(fs::read_to_string(&ai_file).unwrap().contains("Dutch"));
+        assert_eq!(
+            fs::metadata(&update_file).unwrap().modified().unwrap(),
+            update_before,
+            "Unchanged sections are not rewritten"
+        );
+        assert!(notes.exists());
+
+        // ...unless they aren't saved as plain UTF-8.
+        let mut content = b"\xEF\xBB\xBF".to_vec();
+        content.extend(fs::read(&update_file).unwrap());
+        fs::write(&update_file, content).unwrap();
+        update_config_value("ai", "language", Value::from("French")).unwrap();
+        assert!(!fs::read(&update_file).unwrap().starts_with(b"\xEF\xBB\xBF"));
+    }
+}
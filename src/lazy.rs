@@ -0,0 +1,228 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use toml::{Value, map};
+
+use crate::config::{commit_lazy_section, get_config_file, modify_config};
+use crate::memory::is_in_memory;
+use crate::path;
+use crate::storage::write_atomic;
+
+/// The key that marks a section of `config.toml` as lazily loaded.
+pub const LAZY_KEY: &str = "lazy";
+
+type Loaded = (SystemTime, u64, Arc<Value>);
+
+/// The parsed side files, keyed by path and validated by modification time and size.
+static LOADED: Mutex<Option<HashMap<PathBuf, Loaded>>> = Mutex::new(None);
+
+/// Returns the side file of a lazily loaded section, `<section>.lazy.toml` next to the
+/// configuration file.
+pub fn lazy_section_file(section: &str) -> Result<PathBuf> {
+    let valid = !section.is_empty()
+        && section
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Invalid lazy section '{}': use letters, digits, '-' or '_'",
+                section
+            ),
+        ));
+    }
+    Ok(get_config_file()?.with_file_name(format!("{}.lazy.toml", section)))
+}
+
+/// Returns whether `config` marks `section` as lazily loaded, i.e. holds
+/// `[section] lazy = true`.
+pub fn is_lazy(config: &Value, section: &str) -> bool {
+    config
+        .get(section)
+        .and_then(|table| table.get(LAZY_KEY))
+        .and_then(Value::as_bool)
+        == Some(true)
+}
+
+/// Loads a lazily loaded section from its side file.
+///
+/// Large sections such as model catalogs or prompt libraries live in a side file that is
+/// only parsed when a key below them is first read, so startup stays fast however big
+/// they get. Reads through [`get_value_fast`](crate::config::get_value_fast),
+/// [`get_many`](crate::config::get_many) and
+/// [`get_config_value`](crate::config::get_config_value) load the section on their own,
+/// and [`Config::load`](crate::config::Config::load) loads every lazy section into its
+/// handle; the parse is kept until the side file changes. The document returned by
+/// [`get_config`](crate::config::get_config) holds the `lazy = true` marker instead.
+///
+/// # Arguments
+///
+/// * `section` - The section name
+///
+/// # Returns
+///
+/// * `Result<Arc<Value>>` - The section table, empty if the side file doesn't exist, or an
+///   error if it can't be read or parsed
+pub fn load_lazy_section(section: &str) -> Result<Arc<Value>> {
+    let file = lazy_section_file(section)?;
+    let metadata = match fs::metadata(&file) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == ErrorKind::NotFound => {
+            return Ok(Arc::new(Value::Table(map::Map::new())));
+        }
+        Err(e) => return Err(e),
+    };
+    let stamp = (metadata.modified()?, metadata.len());
+    let mut loaded = LOADED.lock().unwrap_or_else(|e| e.into_inner());
+    let loaded = loaded.get_or_insert_with(HashMap::new);
+    if let Some((modified, len, value)) = loaded.get(&file)
+        && (*modified, *len) == stamp
+    {
+        return Ok(Arc::clone(value));
+    }
+    let content = fs::read_to_string(&file)?;
+    let value: Value = toml::from_str(&content).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Failed to parse '{}': {}", file.display(), e),
+        )
+    })?;
+    let value = Arc::new(value);
+    loaded.insert(file, (stamp.0, stamp.1, Arc::clone(&value)));
+    Ok(value)
+}
+
+/// Stores a section in its side file and marks it as lazily loaded in `config.toml`.
+///
+/// Whatever the section held in `config.toml` is replaced by the `lazy = true` marker. The
+/// section's keys go through the write policy, the key locks and the cooldowns like any
+/// other write, and the side file is only written once they allow it. A configuration
+/// read by [`crate::config::Config::from_reader`] has no side files, so there the section
+/// is kept in the document like any other.
+///
+/// # Arguments
+///
+/// * `section` - The section name
+/// * `value` - The section table
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if `value` isn't a table, the write is denied or
+///   writing fails
+pub fn save_lazy_section(section: &str, value: &Value) -> Result<()> {
+    let file = lazy_section_file(section)?;
+    if !value.is_table() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Lazy section '{}' must be a table", section),
+        ));
+    }
+    if is_in_memory() {
+        return modify_config(|config| path::insert(config, section, value.clone()).map(drop));
+    }
+    let mut marker = map::Map::new();
+    marker.insert(LAZY_KEY.to_string(), Value::Boolean(true));
+    commit_lazy_section(section, value, &Value::Table(marker), |stored| {
+        let content = toml::to_string(stored).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        if fs::read_to_string(&file).ok().as_deref() != Some(content.as_str()) {
+            write_atomic(&file, content.as_bytes())?;
+        }
+        Ok(())
+    })
+}
+
+/// Looks up `key_path` in the side file if its section is lazily loaded in `config`.
+pub(crate) fn lookup_lazy(config: &Value, key_path: &str) -> Option<Result<Value>> {
+    let (section, rest) = match key_path.split_once('.') {
+        Some((section, rest)) => (section, Some(rest)),
+        None => (key_path, None),
+    };
+    if !is_lazy(config, section) {
+        return None;
+    }
+    Some(load_lazy_section(section).and_then(|table| match rest {
+        None => Ok(Value::clone(&table)),
+        Some(rest) => path::require(&table, rest).cloned().map_err(|e| {
+            Error::new(
+                e.kind(),
+                format!("Key '{}' not found in lazy section '{}'", rest, section),
+            )
+        }),
+    }))
+}
+
+/// Returns the sections `config` marks as lazily loaded.
+pub(crate) fn lazy_sections(config: &Value) -> Vec<String> {
+    config
+        .as_table()
+        .into_iter()
+        .flatten()
+        .map(|(section, _)| section)
+        .filter(|section| is_lazy(config, section))
+        .cloned()
+        .collect()
+}
+
+/// Replaces the `lazy = true` marker of every lazily loaded section in `config` with the
+/// content of its side file.
+pub(crate) fn resolve_lazy(config: &mut Value) -> Result<()> {
+    for section in lazy_sections(config) {
+        let table = load_lazy_section(&section)?;
+        path::insert(config, &section, Value::clone(&table))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, get_config, get_config_value, get_value_fast};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_lazy_section_is_read_from_its_side_file() {
+        let _dir = TempConfigDir::new().unwrap();
+        let catalog: Value =
+            toml::from_str("[gpt-4o]\ncontext = 128000\n[o1]\ncontext = 200000\n").unwrap();
+        save_lazy_section("lazy_catalog", &catalog).unwrap();
+
+        let config = get_config().unwrap();
+        assert!(is_lazy(&config, "lazy_catalog"));
+        assert!(config["lazy_catalog"].get("o1").is_none());
+        let handle = Config::load().unwrap();
+        assert_eq!(handle.get_integer("lazy_catalog.o1.context"), Some(200000));
+        assert!(handle.get("lazy_catalog.lazy").is_none());
+        assert_eq!(
+            get_value_fast("lazy_catalog.o1.context").unwrap(),
+            Value::Integer(200000)
+        );
+        assert_eq!(
+            get_config_value("lazy_catalog", "gpt-4o").unwrap()["context"],
+            Value::Integer(128000)
+        );
+        assert_eq!(
+            get_value_fast("lazy_catalog.o3").unwrap_err().kind(),
+            ErrorKind::NotFound
+        );
+        assert!(lazy_section_file("../x").is_err());
+
+        let side_file = lazy_section_file("lazy_catalog").unwrap();
+        let saved = fs::read_to_string(&side_file).unwrap();
+        let locked = Value::Array(vec![Value::from("lazy_catalog.o1")]);
+        modify_config(|config| path::insert(config, "locked", locked).map(drop)).unwrap();
+        let changed: Value = toml::from_str("[o1]\ncontext = 1\n").unwrap();
+        assert_eq!(
+            save_lazy_section("lazy_catalog", &changed)
+                .unwrap_err()
+                .kind(),
+            ErrorKind::PermissionDenied
+        );
+        assert_eq!(fs::read_to_string(&side_file).unwrap(), saved);
+    }
+}
@@ -0,0 +1,399 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    io::Result,
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+use toml::Value;
+
+use crate::format::Format;
+
+static LENIENT: AtomicBool = AtomicBool::new(false);
+static APPLIED: Mutex<BTreeMap<PathBuf, Vec<LenientFix>>> = Mutex::new(BTreeMap::new());
+
+/// How strictly TOML configuration files are parsed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ParseMode {
+    /// Only valid TOML is accepted
+    #[default]
+    Strict,
+    /// Common hand-editing mistakes are repaired before parsing
+    Lenient,
+}
+
+/// A kind of mistake the lenient mode repairs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum FixKind {
+    /// Curly quotes, e.g. pasted from a word processor, around a string value
+    CurlyQuotes,
+    /// A comma after a value or the last entry of an inline table
+    TrailingComma,
+    /// A key set twice in the same table; the last value wins
+    DuplicateKey {
+        /// The 1-based line of the definition that was dropped
+        replaced_line: usize,
+    },
+}
+
+/// One repair applied while parsing leniently.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LenientFix {
+    /// The 1-based line that was repaired
+    pub line: usize,
+    /// The dotted key path defined on that line
+    pub path: String,
+    /// What was repaired
+    pub kind: FixKind,
+    /// A description for the user, e.g. to show as a warning
+    pub message: String,
+}
+
+/// Sets how configuration files are parsed from now on.
+///
+/// The default is [`ParseMode::Strict`]. In the lenient mode a file that isn't valid TOML
+/// is repaired in memory and the repairs are reported through [`applied_fixes`]; the file
+/// itself is rewritten in valid TOML on the next write. Files that parse strictly are
+/// never changed by the lenient mode.
+pub fn set_parse_mode(mode: ParseMode) {
+    LENIENT.store(mode == ParseMode::Lenient, Ordering::Relaxed);
+}
+
+/// Returns how configuration files are currently parsed.
+pub fn parse_mode() -> ParseMode {
+    if LENIENT.load(Ordering::Relaxed) {
+        ParseMode::Lenient
+    } else {
+        ParseMode::Strict
+    }
+}
+
+/// Returns the repairs applied by the latest lenient parse of each file that needed any.
+pub fn applied_fixes() -> BTreeMap<PathBuf, Vec<LenientFix>> {
+    APPLIED.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Parses TOML, repairing curly quotes, trailing commas and duplicate keys if needed.
+///
+/// # Arguments
+///
+/// * `content` - The document text
+///
+/// # Returns
+///
+/// * `Result<(Value, Vec<LenientFix>)>` - The document with the repairs applied, empty if
+///   the text was valid, or the strict parse error if the repaired text still doesn't parse
+pub fn parse_lenient(content: &str) -> Result<(Value, Vec<LenientFix>)> {
+    let error = match Format::Toml.parse(content) {
+        Ok(value) => return Ok((value, Vec::new())),
+        Err(e) => e,
+    };
+    let (repaired, fixes) = repair(content);
+    match Format::Toml.parse(&repaired) {
+        Ok(value) if !fixes.is_empty() => Ok((value, fixes)),
+        _ => Err(error),
+    }
+}
+
+/// Decodes text that isn't valid UTF-8, reading every byte that doesn't belong to a UTF-8
+/// sequence as Windows-1252, the encoding older Windows editors save curly quotes in.
+pub(crate) fn decode_windows_1252(bytes: &[u8]) -> String {
+    let mut text = String::with_capacity(bytes.len());
+    for chunk in bytes.utf8_chunks() {
+        text.push_str(chunk.valid());
+        text.extend(chunk.invalid().iter().map(|&byte| windows_1252_char(byte)));
+    }
+    text
+}
+
+fn windows_1252_char(byte: u8) -> char {
+    // 0x80 to 0x9f; the five bytes Windows-1252 leaves undefined map to C1 controls.
+    const HIGH: [char; 32] = [
+        '\u{20ac}', '\u{81}', '\u{201a}', '\u{192}', '\u{201e}', '\u{2026}', '\u{2020}',
+        '\u{2021}', '\u{2c6}', '\u{2030}', '\u{160}', '\u{2039}', '\u{152}', '\u{8d}', '\u{17d}',
+        '\u{8f}', '\u{90}', '\u{2018}', '\u{2019}', '\u{201c}', '\u{201d}', '\u{2022}', '\u{2013}',
+        '\u{2014}', '\u{2dc}', '\u{2122}', '\u{161}', '\u{203a}', '\u{153}', '\u{9d}', '\u{17e}',
+        '\u{178}',
+    ];
+    match byte {
+        0x80..=0x9f => HIGH[usize::from(byte - 0x80)],
+        _ => char::from(byte),
+    }
+}
+
+/// Parses the TOML content of `file` in the current [`ParseMode`].
+pub(crate) fn parse_toml(file: &Path, content: &str) -> Result<Value> {
+    if parse_mode() == ParseMode::Strict {
+        return Format::Toml.parse(content);
+    }
+    let (value, fixes) = parse_lenient(content)?;
+    let mut applied = APPLIED.lock().unwrap_or_else(|e| e.into_inner());
+    if fixes.is_empty() {
+        applied.remove(file);
+    } else {
+        applied.insert(file.to_path_buf(), fixes);
+    }
+    Ok(value)
+}
+
+fn repair(content: &str) -> (String, Vec<LenientFix>) {
+    let mut lines: Vec<String> = content.lines().map(str::to_string).collect();
+    let mut fixes = Vec::new();
+    let mut table = String::new();
+    let mut seen: HashMap<(String, String), usize> = HashMap::new();
+    // The last line of the definition starting on each line.
+    let mut ends: Vec<usize> = (0..lines.len()).collect();
+    let mut start = 0;
+    let mut multiline: Option<&str> = None;
+    let mut depth = 0;
+
+    for index in 0..lines.len() {
+        if multiline.is_some() || depth > 0 {
+            // A multi-line string, array or inline table continued from an earlier line.
+            ends[start] = index;
+            let mut rest = lines[index].as_str();
+            if let Some(delimiter) = multiline {
+                let Some(close) = rest
+                    .rfind(delimiter)
+                    .filter(|_| rest.matches(delimiter).count() % 2 == 1)
+                else {
+                    continue;
+                };
+                multiline = None;
+                rest = &rest[close + delimiter.len()..];
+            }
+            depth = bracket_depth(depth, rest);
+            continue;
+        }
+        let trimmed = lines[index].trim();
+        if trimmed.is_empty() || trimmed.starts_with('#') {
+            continue;
+        }
+        if trimmed.starts_with('[') {
+            table = trimmed
+                .trim_matches(|c| c == '[' || c == ']')
+                .trim()
+                .to_string();
+            // Each `[[array]]` entry is a fresh table.
+            seen.retain(|(name, _), _| *name != table);
+            continue;
+        }
+        let Some((key, value)) = lines[index].split_once('=') else {
+            continue;
+        };
+        let key_name = key
+            .trim()
+            .trim_matches(|c| c == '"' || c == '\'')
+            .to_string();
+        let key_path = if table.is_empty() {
+            key_name.clone()
+        } else {
+            format!("{}.{}", table, key_name)
+        };
+        let mut value = value.to_string();
+
+        if let Some(fixed) = straighten_quotes(&value) {
+            value = fixed;
+            fixes.push(fix(
+                index,
+                &key_path,
+                FixKind::CurlyQuotes,
+                format!("Replaced curly quotes around the value of '{}'", key_name),
+            ));
+        }
+        if let Some(fixed) = strip_trailing_commas(&value) {
+            value = fixed;
+            fixes.push(fix(
+                index,
+                &key_path,
+                FixKind::TrailingComma,
+                format!("Removed a trailing comma from the value of '{}'", key_name),
+            ));
+        }
+        for delimiter in ["\"\"\"", "'''"] {
+            if value.matches(delimiter).count() % 2 == 1 {
+                multiline = Some(delimiter);
+            }
+        }
+        if multiline.is_none() {
+            depth = bracket_depth(0, &value);
+        }
+        start = index;
+        lines[index] = format!("{}={}", key, value);
+
+        if let Some(previous) = seen.insert((table.clone(), key_name.clone()), index) {
+            for line in &mut lines[previous..=ends[previous]] {
+                *line = format!("# {}", line);
+            }
+            fixes.push(fix(
+                index,
+                &key_path,
+                FixKind::DuplicateKey {
+                    replaced_line: previous + 1,
+                },
+                format!(
+                    "'{}' is set twice; the value on line {} replaces the one on line {}",
+                    key_name,
+                    index + 1,
+                    previous + 1
+                ),
+            ));
+        }
+    }
+    (lines.join("\n"), fixes)
+}
+
+fn fix(index: usize, path: &str, kind: FixKind, message: String) -> LenientFix {
+    LenientFix {
+        line: index + 1,
+        path: path.to_string(),
+        kind,
+        message,
+    }
+}
+
+/// Replaces curly quotes delimiting a string value with straight ones.
+fn straighten_quotes(value: &str) -> Option<String> {
+    let start = value.len() - value.trim_start().len();
+    let opening = value[start..].chars().next()?;
+    let (quote, closing): (char, &[char]) = match opening {
+        '\u{201c}' | '\u{201d}' => ('"', &['\u{201c}', '\u{201d}', '"']),
+        '\u{2018}' | '\u{2019}' => ('\'', &['\u{2018}', '\u{2019}', '\'']),
+        _ => return None,
+    };
+    let inner = &value[start + opening.len_utf8()..];
+    let end = inner.rfind(closing)?;
+    let close_len = inner[end..].chars().next()?.len_utf8();
+    Some(format!(
+        "{}{}{}{}{}",
+        &value[..start],
+        quote,
+        &inner[..end],
+        quote,
+        &inner[end + close_len..]
+    ))
+}
+
+/// Returns how many arrays and inline tables are still open after `text`, given `depth`
+/// were open before it; brackets in strings and comments don't count.
+fn bracket_depth(mut depth: usize, text: &str) -> usize {
+    let mut quote: Option<char> = None;
+    let mut escaped = false;
+    for c in text.chars() {
+        match quote {
+            Some('"') if escaped => escaped = false,
+            Some('"') if c == '\\' => escaped = true,
+            Some(open) if c == open => quote = None,
+            Some(_) => {}
+            None => match c {
+                '"' | '\'' => quote = Some(c),
+                '#' => break,
+                '[' | '{' => depth += 1,
+                ']' | '}' => depth = depth.saturating_sub(1),
+                _ => {}
+            },
+        }
+    }
+    depth
+}
+
+/// Drops a comma after a value and before the closing brace of an inline table.
+fn strip_trailing_commas(value: &str) -> Option<String> {
+    let mut fixed = value.trim_end().to_string();
+    let mut changed = false;
+    if fixed.ends_with(',') {
+        fixed.pop();
+        changed = true;
+    }
+    if fixed.trim_start().starts_with('{') {
+        let trimmed = fixed.trim_end();
+        if let Some(body) = trimmed.strip_suffix('}')
+            && body.trim_end().ends_with(',')
+        {
+            fixed = format!("{}}}", body.trim_end().trim_end_matches(','));
+            changed = true;
+        }
+    }
+    changed.then_some(fixed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_lenient_repairs_common_mistakes() {
+        let content = "\
+[ai]
+model = \u{201c}gpt-4o\u{201d}
+url = 'https://api.example',
+options = { retries = 2, }
+model = \"o1\"
+";
+        assert!(Format::Toml.parse(content).is_err());
+        let (value, fixes) = parse_lenient(content).unwrap();
+        assert_eq!(value["ai"]["model"].as_str(), Some("o1"));
+        assert_eq!(value["ai"]["url"].as_str(), Some("https://api.example"));
+        assert_eq!(value["ai"]["options"]["retries"].as_integer(), Some(2));
+        assert!(fixes.iter().all(|f| f.path.starts_with("ai.")));
+        let kinds: Vec<(usize, FixKind)> = fixes.iter().map(|f| (f.line, f.kind)).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                (2, FixKind::CurlyQuotes),
+                (3, FixKind::TrailingComma),
+                (4, FixKind::TrailingComma),
+                (5, FixKind::DuplicateKey { replaced_line: 2 }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_lenient_keeps_valid_documents_and_real_errors() {
+        let (value, fixes) = parse_lenient("[ai]\nmodel = \"it\u{2019}s\"\n").unwrap();
+        assert_eq!(value["ai"]["model"].as_str(), Some("it\u{2019}s"));
+        assert!(fixes.is_empty());
+        assert!(parse_lenient("[ai\nmodel = ").is_err());
+        assert_eq!(parse_mode(), ParseMode::Strict);
+    }
+
+    #[test]
+    fn test_parse_lenient_repairs_multi_line_duplicates_and_windows_1252() {
+        let content = "\
+[ai]
+prompt = \"\"\"
+first = [
+\"\"\"
+models = [
+  \"a\", # ]
+  \"b\",
+]
+prompt = 'second'
+models = [\"c\"]
+";
+        let (value, fixes) = parse_lenient(content).unwrap();
+        assert_eq!(value["ai"]["prompt"].as_str(), Some("second"));
+        assert_eq!(value["ai"]["models"].as_array().unwrap().len(), 1);
+        let kinds: Vec<FixKind> = fixes.iter().map(|f| f.kind).collect();
+        assert_eq!(
+            kinds,
+            vec![
+                FixKind::DuplicateKey { replaced_line: 2 },
+                FixKind::DuplicateKey { replaced_line: 5 },
+            ]
+        );
+
+        let text = decode_windows_1252(b"[ai]\nmodel = \x93gpt-4o\x94 # caf\xc3\xa9 \x85\n");
+        assert_eq!(
+            text,
+            "[ai]\nmodel = \u{201c}gpt-4o\u{201d} # caf\u{e9} \u{2026}\n"
+        );
+        let (value, fixes) = parse_lenient(&text).unwrap();
+        assert_eq!(value["ai"]["model"].as_str(), Some("gpt-4o"));
+        assert_eq!(fixes[0].kind, FixKind::CurlyQuotes);
+    }
+}
@@ -1,2 +1,86 @@
 pub mod directory;
-pub mod config;
\ No newline at end of file
+pub mod config;
+pub mod accounts;
+pub mod advice;
+pub mod ai;
+pub mod apikey;
+pub mod apply;
+#[cfg(feature = "encryption")]
+pub mod bundle;
+pub mod cache;
+pub mod capabilities;
+pub mod change;
+pub mod clock;
+pub mod comments;
+pub mod compat;
+pub mod cooldown;
+pub mod counter;
+pub mod date;
+pub mod defaults;
+pub mod describe;
+pub mod diagnostics;
+pub mod display;
+pub mod docs;
+pub mod doctor;
+pub mod durability;
+pub mod encoding;
+#[cfg(feature = "encryption")]
+pub mod encryption;
+mod edit;
+pub mod ensure;
+pub mod export;
+pub mod filesystem;
+pub mod format;
+#[cfg(feature = "health")]
+pub mod health;
+pub mod hooks;
+mod http;
+pub mod import;
+pub mod init;
+pub mod interpolate;
+pub mod io_limits;
+pub mod key_times;
+pub mod layout;
+pub mod language;
+pub mod lazy;
+pub mod lenient;
+pub mod limits;
+pub mod locks;
+pub mod machine;
+pub mod maintenance;
+pub mod memory;
+pub mod merge;
+pub mod metadata;
+pub mod normalize;
+pub mod notify;
+pub mod ollama;
+pub mod output;
+pub mod path;
+pub mod pipeline;
+pub mod policy;
+pub mod prelude;
+pub mod profile;
+pub mod project;
+pub mod recovery;
+pub mod reset;
+pub mod schema;
+pub mod scope;
+pub mod secret;
+pub mod session;
+pub mod session_token;
+pub mod shadow;
+pub mod shell;
+pub mod snapshot;
+pub mod state;
+mod storage;
+pub mod storm;
+pub mod stream;
+pub mod sync;
+pub mod tables;
+pub mod templates;
+pub mod temporary;
+#[cfg(any(test, feature = "testing"))]
+pub mod testing;
+pub mod trace;
+pub mod update;
+pub mod usage;
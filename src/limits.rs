@@ -0,0 +1,188 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+};
+use toml::{Value, map};
+
+use crate::profile::effective_config;
+
+/// The default of `limits.max_diff_bytes`.
+pub const DEFAULT_MAX_DIFF_BYTES: i64 = 100_000;
+
+/// The default of `limits.max_files`.
+pub const DEFAULT_MAX_FILES: i64 = 50;
+
+/// What gim does with a diff larger than `limits.max_diff_bytes`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum TruncationStrategy {
+    /// Send the beginning of the diff, `"head"`
+    #[default]
+    Head,
+    /// Give every file an equal share of the limit, `"per-file"`
+    PerFile,
+    /// Refuse to generate a message, `"reject"`
+    Reject,
+}
+
+impl TruncationStrategy {
+    /// Parses the value of `limits.truncation`.
+    pub fn parse(s: &str) -> Result<TruncationStrategy> {
+        match s {
+            "head" => Ok(TruncationStrategy::Head),
+            "per-file" => Ok(TruncationStrategy::PerFile),
+            "reject" => Ok(TruncationStrategy::Reject),
+            _ => Err(Error::new(
+                ErrorKind::InvalidData,
+                format!(
+                    "Invalid truncation strategy '{}': use \"head\", \"per-file\" or \"reject\"",
+                    s
+                ),
+            )),
+        }
+    }
+}
+
+impl fmt::Display for TruncationStrategy {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            TruncationStrategy::Head => "head",
+            TruncationStrategy::PerFile => "per-file",
+            TruncationStrategy::Reject => "reject",
+        })
+    }
+}
+
+/// The `[limits]` settings that bound how much of a repository gim sends to the model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct LimitsConfig {
+    /// Largest diff, in bytes, sent to the model
+    pub max_diff_bytes: u64,
+    /// Most changed files included in the diff
+    pub max_files: u64,
+    /// What happens to a larger diff
+    pub truncation: TruncationStrategy,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> LimitsConfig {
+        LimitsConfig {
+            max_diff_bytes: DEFAULT_MAX_DIFF_BYTES as u64,
+            max_files: DEFAULT_MAX_FILES as u64,
+            truncation: TruncationStrategy::default(),
+        }
+    }
+}
+
+impl LimitsConfig {
+    /// Reads the settings from a configuration's `[limits]` section.
+    ///
+    /// Missing keys take their defaults, so files written before the section existed keep
+    /// working.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The whole configuration
+    ///
+    /// # Returns
+    ///
+    /// * `Result<LimitsConfig>` - The settings, or an `InvalidData` error naming a key that isn't
+    ///   a positive integer or a known strategy
+    pub fn from_config(config: &Value) -> Result<LimitsConfig> {
+        let defaults = LimitsConfig::default();
+        let Some(limits) = config.get("limits") else {
+            return Ok(defaults);
+        };
+        let positive = |key: &str, default: u64| match limits.get(key) {
+            None => Ok(default),
+            Some(value) => value
+                .as_integer()
+                .filter(|n| *n > 0)
+                .map(|n| n as u64)
+                .ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        format!("Key 'limits.{}' must be a positive integer", key),
+                    )
+                }),
+        };
+        let truncation = match limits.get("truncation") {
+            None => defaults.truncation,
+            Some(value) => TruncationStrategy::parse(value.as_str().ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    "Key 'limits.truncation' must be a string",
+                )
+            })?)?,
+        };
+        Ok(LimitsConfig {
+            max_diff_bytes: positive("max_diff_bytes", defaults.max_diff_bytes)?,
+            max_files: positive("max_files", defaults.max_files)?,
+            truncation,
+        })
+    }
+
+    /// Loads the settings from the configuration file, including `GIM_ENV` profile overrides.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<LimitsConfig>` - The current `[limits]` settings or an error
+    pub fn load() -> Result<LimitsConfig> {
+        LimitsConfig::from_config(&effective_config()?)
+    }
+
+    /// Returns the settings as a `[limits]` section table.
+    pub fn to_value(&self) -> Value {
+        let mut table = map::Map::new();
+        table.insert(
+            "max_diff_bytes".to_string(),
+            Value::Integer(self.max_diff_bytes as i64),
+        );
+        table.insert(
+            "max_files".to_string(),
+            Value::Integer(self.max_files as i64),
+        );
+        table.insert(
+            "truncation".to_string(),
+            Value::from(self.truncation.to_string()),
+        );
+        Value::Table(table)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limits_defaults_and_validation() {
+        let empty: Value = toml::from_str("[ai]\nmodel = 'm'\n").unwrap();
+        assert_eq!(
+            LimitsConfig::from_config(&empty).unwrap(),
+            LimitsConfig::default()
+        );
+
+        let config: Value =
+            toml::from_str("[limits]\nmax_files = 5\ntruncation = 'per-file'\n").unwrap();
+        let limits = LimitsConfig::from_config(&config).unwrap();
+        assert_eq!(limits.max_files, 5);
+        assert_eq!(limits.max_diff_bytes, DEFAULT_MAX_DIFF_BYTES as u64);
+        assert_eq!(limits.truncation, TruncationStrategy::PerFile);
+        let mut root = map::Map::new();
+        root.insert("limits".to_string(), limits.to_value());
+        assert_eq!(
+            LimitsConfig::from_config(&Value::Table(root)).unwrap(),
+            limits
+        );
+
+        for bad in [
+            "max_files = 0",
+            "max_diff_bytes = 'big'",
+            "truncation = 'tail'",
+        ] {
+            let config: Value = toml::from_str(&format!("[limits]\n{}\n", bad)).unwrap();
+            let err = LimitsConfig::from_config(&config).unwrap_err();
+            assert_eq!(err.kind(), ErrorKind::InvalidData);
+        }
+    }
+}
@@ -0,0 +1,313 @@
+use std::{
+    fmt, fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use toml::Value;
+
+use crate::change::ChangeSet;
+use crate::directory::system_config_dir;
+use crate::merge::merge_into;
+use crate::path;
+
+/// The top-level key listing the locked key paths of a layer.
+pub const LOCKED_KEY: &str = "locked";
+
+type SystemStamp = Option<(SystemTime, u64)>;
+
+/// The parsed system file, reused while its modification time and size are unchanged.
+static SYSTEM: Mutex<Option<(PathBuf, SystemStamp, Arc<Value>)>> = Mutex::new(None);
+
+/// A configuration layer that can lock keys.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConfigLayer {
+    /// The admin-distributed file in [`system_config_dir`]
+    System,
+    /// The user's own configuration file
+    User,
+}
+
+impl fmt::Display for ConfigLayer {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            ConfigLayer::System => "system",
+            ConfigLayer::User => "user",
+        })
+    }
+}
+
+/// A key path locked against modification.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct LockedKey {
+    /// The locked dotted path; locking a table locks every key below it
+    pub path: String,
+    /// The layer that locked the key
+    pub layer: ConfigLayer,
+    /// The file of that layer
+    pub file: PathBuf,
+}
+
+impl LockedKey {
+    /// Returns whether the lock covers `key_path`.
+    pub fn covers(&self, key_path: &str) -> bool {
+        key_path == self.path
+            || key_path
+                .strip_prefix(self.path.as_str())
+                .is_some_and(|rest| rest.starts_with('.'))
+    }
+}
+
+/// The error payload of a write touching a locked key.
+///
+/// It is returned inside an `io::Error` of kind `PermissionDenied`; use
+/// `error.get_ref().and_then(|e| e.downcast_ref::<LockedKeyError>())` to inspect it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct LockedKeyError {
+    /// The key the write tried to change
+    pub path: String,
+    /// The lock that prevented it
+    pub lock: LockedKey,
+}
+
+impl fmt::Display for LockedKeyError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Key '{}' is locked by the {} configuration '{}'",
+            self.path,
+            self.lock.layer,
+            self.lock.file.display()
+        )
+    }
+}
+
+impl std::error::Error for LockedKeyError {}
+
+/// Returns the path of the system configuration file.
+///
+/// Its values are layered under the user's: reads such as
+/// [`get_value_fast`](crate::config::get_value_fast) and
+/// [`Config::load`](crate::config::Config::load) fall back to them for keys the user's
+/// configuration lacks, and a key it lists in `locked` always resolves to the system value,
+/// even if the user's file was edited by hand or an override is set.
+pub fn system_config_file() -> PathBuf {
+    system_config_dir().join("config.toml")
+}
+
+/// Returns the system configuration, an empty table if there is no system file.
+fn system_document() -> Result<Arc<Value>> {
+    let file = system_config_file();
+    let stamp = fs::metadata(&file)
+        .ok()
+        .and_then(|metadata| Some((metadata.modified().ok()?, metadata.len())));
+    let mut cached = SYSTEM.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some((cached_file, cached_stamp, value)) = cached.as_ref()
+        && *cached_file == file
+        && *cached_stamp == stamp
+    {
+        return Ok(Arc::clone(value));
+    }
+    let value = match fs::read_to_string(&file) {
+        Ok(content) => {
+            toml::from_str(&content).map_err(|e| Error::new(ErrorKind::InvalidData, e))?
+        }
+        Err(e) if e.kind() == ErrorKind::NotFound => Value::Table(toml::map::Map::new()),
+        Err(e) => return Err(e),
+    };
+    let value = Arc::new(value);
+    *cached = Some((file, stamp, Arc::clone(&value)));
+    Ok(value)
+}
+
+/// Returns the key paths the system layer locks.
+pub(crate) fn pinned_keys() -> Result<Vec<String>> {
+    let mut locks = Vec::new();
+    collect_locks(
+        &*system_document()?,
+        ConfigLayer::System,
+        Path::new(""),
+        &mut locks,
+    );
+    Ok(locks.into_iter().map(|lock| lock.path).collect())
+}
+
+/// Returns the system layer's value of `key_path` if the system layer locks it.
+///
+/// Reads always get this pinned value, however the user's file was edited.
+pub(crate) fn pinned_value(key_path: &str) -> Option<Result<Value>> {
+    let system = match system_document() {
+        Ok(system) => system,
+        Err(e) => return Some(Err(e)),
+    };
+    let mut locks = Vec::new();
+    collect_locks(&system, ConfigLayer::System, Path::new(""), &mut locks);
+    if !locks.iter().any(|lock| lock.covers(key_path)) {
+        return None;
+    }
+    path::lookup(&system, key_path).cloned().map(Ok)
+}
+
+/// Returns the system layer's value of `key_path`, which reads fall back to when the user's
+/// configuration lacks the key.
+pub(crate) fn system_value(key_path: &str) -> Option<Result<Value>> {
+    if path::split(key_path).ok()?.first() == Some(&LOCKED_KEY) {
+        return None;
+    }
+    match system_document() {
+        Ok(system) => path::lookup(&system, key_path).cloned().map(Ok),
+        Err(e) => Some(Err(e)),
+    }
+}
+
+/// Replaces the keys below `key_path` in `value`, the value read at `key_path`, that the
+/// system layer locks with their pinned values.
+pub(crate) fn pin_within(key_path: &str, value: &mut Value) -> Result<()> {
+    let system = system_document()?;
+    for pinned_key in pinned_keys()? {
+        let rest = if key_path.is_empty() {
+            Some(pinned_key.as_str())
+        } else {
+            pinned_key
+                .strip_prefix(key_path)
+                .and_then(|rest| rest.strip_prefix('.'))
+        };
+        if let Some(rest) = rest
+            && let Some(pinned) = path::lookup(&system, &pinned_key)
+        {
+            path::insert(value, rest, pinned.clone())?;
+        }
+    }
+    Ok(())
+}
+
+/// Layers `config` over the system configuration and replaces the keys the system layer
+/// locks with their system values.
+pub(crate) fn apply_system(config: &mut Value) -> Result<()> {
+    let system = system_document()?;
+    let Some(table) = system.as_table().filter(|table| !table.is_empty()) else {
+        return Ok(());
+    };
+    let mut layered = Value::Table(table.clone());
+    if let Some(root) = layered.as_table_mut() {
+        root.remove(LOCKED_KEY);
+    }
+    merge_into(&mut layered, config, true);
+    *config = layered;
+    pin_within("", config)
+}
+
+/// Returns every locked key of the system layer and of the user configuration `user`.
+///
+/// # Arguments
+///
+/// * `user` - The user's configuration document
+/// * `user_file` - The path `user` was read from
+///
+/// # Returns
+///
+/// * `Result<Vec<LockedKey>>` - The locks, system layer first, or an error if the system
+///   file exists but can't be parsed
+pub fn locked_keys(user: &Value, user_file: PathBuf) -> Result<Vec<LockedKey>> {
+    let mut locks = Vec::new();
+    collect_locks(
+        &*system_document()?,
+        ConfigLayer::System,
+        &system_config_file(),
+        &mut locks,
+    );
+    collect_locks(user, ConfigLayer::User, &user_file, &mut locks);
+    Ok(locks)
+}
+
+/// Fails with a [`LockedKeyError`] if going from `old` to `new` changes a locked key.
+///
+/// The locks are taken from `old`, so a write can't unlock and change a key at once.
+#[cfg_attr(
+    feature = "tracing",
+    tracing::instrument(name = "gim_config.validate_locks", level = "debug", skip_all, err)
+)]
+pub(crate) fn check_locks(old: &Value, new: &Value, user_file: PathBuf) -> Result<()> {
+    let locks = locked_keys(old, user_file)?;
+    if locks.is_empty() {
+        return Ok(());
+    }
+    for change in ChangeSet::between(old, new).changes {
+        if let Some(lock) = locks.iter().find(|lock| lock.covers(&change.path)) {
+            return Err(Error::new(
+                ErrorKind::PermissionDenied,
+                LockedKeyError {
+                    path: change.path,
+                    lock: lock.clone(),
+                },
+            ));
+        }
+    }
+    Ok(())
+}
+
+fn collect_locks(config: &Value, layer: ConfigLayer, file: &Path, locks: &mut Vec<LockedKey>) {
+    let Some(paths) = config.get(LOCKED_KEY).and_then(Value::as_array) else {
+        return;
+    };
+    locks.extend(
+        paths
+            .iter()
+            .filter_map(Value::as_str)
+            .map(|path| LockedKey {
+                path: path.to_string(),
+                layer,
+                file: file.to_path_buf(),
+            }),
+    );
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config_value, update_config_value};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_system_layer_locks_keys() {
+        let dir = TempConfigDir::new().unwrap();
+        fs::create_dir_all(dir.path().join("system")).unwrap();
+        fs::write(
+            system_config_file(),
+            "locked = [\"ai.url\"]\n\n[ai]\nurl = \"https://approved.example\"\norganization = \"acme\"\n",
+        )
+        .unwrap();
+        assert_eq!(
+            get_config_value("ai", "organization").unwrap(),
+            Value::from("acme")
+        );
+
+        let error =
+            update_config_value("ai", "url", Value::from("https://evil.example")).unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+        let locked = error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<LockedKeyError>())
+            .unwrap();
+        assert_eq!(locked.path, "ai.url");
+        assert_eq!(locked.lock.layer, ConfigLayer::System);
+
+        update_config_value("ai", "model", Value::from("gpt-4o")).unwrap();
+        assert_eq!(
+            get_config_value("ai", "model").unwrap(),
+            Value::from("gpt-4o")
+        );
+
+        let file = crate::config::get_config_file().unwrap();
+        let content = fs::read_to_string(&file).unwrap();
+        fs::write(&file, content + "url = \"https://evil.example\"\n").unwrap();
+        let approved = Value::from("https://approved.example");
+        assert_eq!(get_config_value("ai", "url").unwrap(), approved);
+        let config = crate::config::Config::load().unwrap();
+        assert_eq!(config.get("ai.url"), Some(&approved));
+        assert_eq!(config.get_str("ai.model"), Some("gpt-4o"));
+    }
+}
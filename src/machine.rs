@@ -0,0 +1,97 @@
+use std::io::{Error, Result};
+use toml::Value;
+
+use crate::path;
+use crate::state::{modify_state, read_state};
+
+/// The state key holding the machine identifier.
+pub const MACHINE_ID_KEY: &str = "machine_id";
+
+/// Returns the identifier of this machine, generating it on first use.
+///
+/// The identifier is a random UUID (version 4) kept in the state file, so it never
+/// reveals anything about the hardware and survives upgrades, but not a reinstall that
+/// removes the state directory. Telemetry, sync conflict resolution and audit entries use
+/// it to tell machines apart.
+///
+/// # Returns
+///
+/// * `Result<String>` - The identifier, e.g. `"0b8f6c1e-5d1a-4c0e-9a3b-7f2d4e6a8c10"`, or
+///   an error if the state file can't be read or written
+pub fn machine_id() -> Result<String> {
+    if let Some(id) = stored_id(&read_state()?) {
+        return Ok(id);
+    }
+    let candidate = new_uuid()?;
+    modify_state(|state| match stored_id(state) {
+        // Another process generated one between the read and the lock.
+        Some(id) => Ok(id),
+        None => {
+            path::insert(state, MACHINE_ID_KEY, Value::from(candidate.as_str()))?;
+            Ok(candidate)
+        }
+    })
+}
+
+/// Replaces the machine identifier with a new random one, e.g. after cloning a disk image.
+///
+/// # Returns
+///
+/// * `Result<String>` - The new identifier, or an error if writing the state file fails
+pub fn reset_machine_id() -> Result<String> {
+    let id = new_uuid()?;
+    modify_state(|state| path::insert(state, MACHINE_ID_KEY, Value::from(id.as_str())))?;
+    Ok(id)
+}
+
+fn stored_id(state: &Value) -> Option<String> {
+    state
+        .get(MACHINE_ID_KEY)
+        .and_then(Value::as_str)
+        .filter(|id| is_uuid(id))
+        .map(str::to_string)
+}
+
+/// Generates a random version 4 UUID in its hyphenated lowercase form.
+fn new_uuid() -> Result<String> {
+    let mut bytes = [0u8; 16];
+    getrandom::getrandom(&mut bytes).map_err(|e| Error::other(e.to_string()))?;
+    bytes[6] = (bytes[6] & 0x0f) | 0x40;
+    bytes[8] = (bytes[8] & 0x3f) | 0x80;
+    let hex: String = bytes.iter().map(|b| format!("{:02x}", b)).collect();
+    Ok(format!(
+        "{}-{}-{}-{}-{}",
+        &hex[..8],
+        &hex[8..12],
+        &hex[12..16],
+        &hex[16..20],
+        &hex[20..]
+    ))
+}
+
+fn is_uuid(id: &str) -> bool {
+    id.len() == 36
+        && id.char_indices().all(|(i, c)| match i {
+            8 | 13 | 18 | 23 => c == '-',
+            _ => c.is_ascii_hexdigit(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_machine_id_is_persisted_until_reset() {
+        let _dir = TempConfigDir::new().unwrap();
+        let id = machine_id().unwrap();
+        assert!(is_uuid(&id));
+        assert_eq!(&id[14..15], "4");
+        assert_eq!(machine_id().unwrap(), id);
+
+        let reset = reset_machine_id().unwrap();
+        assert_ne!(reset, id);
+        assert_eq!(machine_id().unwrap(), reset);
+    }
+}
@@ -0,0 +1,206 @@
+use std::{fs, io::Result, path::PathBuf};
+use toml::Value;
+
+use crate::cache::remove_outdated_caches;
+use crate::config::{ensure_persistent, modify_config};
+use crate::directory::{AppDirs, app_dirs};
+use crate::language::find_language;
+use crate::normalize::{Repair, load_normalized};
+use crate::snapshot::{BACKUP_PREFIX, SnapshotInfo, list_snapshots};
+use crate::storage::remove_stale_temp_files;
+
+/// How many of the automatic backups taken before resets are kept.
+pub const KEEP_RESET_BACKUPS: usize = 10;
+
+/// Everything [`run_maintenance`] did, each list in the order it happened.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct MaintenanceReport {
+    /// Missing defaults filled in and values coerced to the types the schema expects
+    pub repairs: Vec<Repair>,
+    /// Keys whose value was an alias rewritten to its canonical form, e.g. `ai.language`
+    /// from `"zh"` to `"Chinese"`
+    pub rewritten_aliases: Vec<String>,
+    /// The oldest reset backups beyond [`KEEP_RESET_BACKUPS`], deleted
+    pub pruned_backups: Vec<SnapshotInfo>,
+    /// Cache files written by another version of the crate, deleted
+    pub removed_cache_files: Vec<PathBuf>,
+    /// Temporary files of interrupted writes, deleted
+    pub removed_temp_files: Vec<PathBuf>,
+    /// Application directories that other users could read, made private again
+    pub fixed_permissions: Vec<PathBuf>,
+    /// Why steps failed; the other steps still ran
+    pub errors: Vec<String>,
+}
+
+impl MaintenanceReport {
+    /// Returns whether there was nothing to do and nothing failed.
+    pub fn is_clean(&self) -> bool {
+        self.repairs.is_empty()
+            && self.rewritten_aliases.is_empty()
+            && self.pruned_backups.is_empty()
+            && self.removed_cache_files.is_empty()
+            && self.removed_temp_files.is_empty()
+            && self.fixed_permissions.is_empty()
+            && self.errors.is_empty()
+    }
+}
+
+/// Brings the configuration and the application directories up to date in one call, for
+/// the CLI to run at startup or from `gim config maintain`.
+///
+/// The configuration is repaired (see [`crate::normalize::normalize_config`]) and aliases
+/// are rewritten, old reset backups are pruned, outdated caches and the leftovers of
+/// interrupted writes are deleted, and application directories are made private again.
+/// Running it twice in a row does nothing the second time. In an ephemeral or read-only
+/// setup nothing is touched and the report holds the reason.
+///
+/// # Returns
+///
+/// * `MaintenanceReport` - What was done and which steps failed
+pub fn run_maintenance() -> MaintenanceReport {
+    if let Err(e) = ensure_persistent() {
+        return MaintenanceReport {
+            errors: vec![e.to_string()],
+            ..MaintenanceReport::default()
+        };
+    }
+    let mut errors = Vec::new();
+    let repairs = or_record(&mut errors, load_normalized(true).map(|(_, n)| n.repairs));
+    let rewritten_aliases = or_record(&mut errors, modify_config(|c| Ok(rewrite_aliases(c))));
+    let pruned_backups = or_record(&mut errors, prune_reset_backups());
+    let removed_cache_files = or_record(&mut errors, remove_outdated_caches());
+    let mut removed_temp_files = Vec::new();
+    let mut fixed_permissions = Vec::new();
+    if let Some(dirs) = or_record(&mut errors, app_dirs().map(Some)) {
+        for dir in [&dirs.config, &dirs.state] {
+            removed_temp_files.extend(or_record(&mut errors, remove_stale_temp_files(dir)));
+        }
+        fixed_permissions = or_record(&mut errors, fix_permissions(&dirs));
+    }
+    MaintenanceReport {
+        repairs,
+        rewritten_aliases,
+        pruned_backups,
+        removed_cache_files,
+        removed_temp_files,
+        fixed_permissions,
+        errors,
+    }
+}
+
+/// Returns the outcome of a step, or records why it failed and returns nothing.
+fn or_record<T: Default>(errors: &mut Vec<String>, result: Result<T>) -> T {
+    result.unwrap_or_else(|e| {
+        errors.push(e.to_string());
+        T::default()
+    })
+}
+
+/// Rewrites aliases to their canonical values and returns the rewritten key paths.
+fn rewrite_aliases(config: &mut Value) -> Vec<String> {
+    let mut rewritten = Vec::new();
+    if let Some(Value::String(language)) =
+        config.get_mut("ai").and_then(|ai| ai.get_mut("language"))
+        && let Some(canonical) = find_language(language)
+        && language != canonical.name
+    {
+        *language = canonical.name.to_string();
+        rewritten.push("ai.language".to_string());
+    }
+    rewritten
+}
+
+/// Deletes the oldest reset backups beyond [`KEEP_RESET_BACKUPS`]; named snapshots stay.
+fn prune_reset_backups() -> Result<Vec<SnapshotInfo>> {
+    let backups: Vec<SnapshotInfo> = list_snapshots()?
+        .into_iter()
+        .filter(|snapshot| snapshot.label.starts_with(BACKUP_PREFIX))
+        .collect();
+    let excess = backups.len().saturating_sub(KEEP_RESET_BACKUPS);
+    let pruned: Vec<SnapshotInfo> = backups.into_iter().take(excess).collect();
+    for backup in &pruned {
+        fs::remove_file(&backup.path)?;
+    }
+    Ok(pruned)
+}
+
+/// Removes the group and other permissions of the existing application directories.
+#[cfg(unix)]
+fn fix_permissions(dirs: &AppDirs) -> Result<Vec<PathBuf>> {
+    use std::os::unix::fs::PermissionsExt;
+
+    let mut fixed = Vec::new();
+    for dir in [
+        &dirs.config,
+        &dirs.data,
+        &dirs.cache,
+        &dirs.state,
+        &dirs.backups,
+    ] {
+        let Ok(metadata) = fs::metadata(dir) else {
+            continue;
+        };
+        let mode = metadata.permissions().mode();
+        if metadata.is_dir() && mode & 0o077 != 0 {
+            fs::set_permissions(dir, fs::Permissions::from_mode(mode & !0o077))?;
+            fixed.push(dir.clone());
+        }
+    }
+    Ok(fixed)
+}
+
+/// Platforms without Unix permission bits have nothing to fix.
+#[cfg(not(unix))]
+fn fix_permissions(_dirs: &AppDirs) -> Result<Vec<PathBuf>> {
+    Ok(Vec::new())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config_value, update_config_value};
+    use crate::snapshot::{save_backup, snapshot};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_maintenance_is_idempotent() {
+        let _dir = TempConfigDir::new().unwrap();
+        update_config_value("ai", "language", Value::from("zh")).unwrap();
+        for n in 0..KEEP_RESET_BACKUPS + 2 {
+            save_backup(&format!("reset-{}", n)).unwrap();
+        }
+        snapshot("known-good").unwrap();
+        let dirs = crate::directory::ensure_app_dirs().unwrap();
+        let outdated = dirs.cache.join("models.bin");
+        fs::write(&outdated, b"GIMCACHE0").unwrap();
+        let temp = dirs.config.join("config.toml.1.2.tmp");
+        let old = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+        fs::File::create(&temp).unwrap().set_modified(old).unwrap();
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            fs::set_permissions(&dirs.state, fs::Permissions::from_mode(0o755)).unwrap();
+        }
+
+        let report = run_maintenance();
+        assert!(report.errors.is_empty(), "{:?}", report.errors);
+        assert_eq!(report.rewritten_aliases, vec!["ai.language"]);
+        assert_eq!(
+            get_config_value("ai", "language").unwrap().as_str(),
+            Some("Chinese")
+        );
+        assert_eq!(report.pruned_backups.len(), 2);
+        assert!(
+            list_snapshots()
+                .unwrap()
+                .iter()
+                .any(|s| s.label == "known-good")
+        );
+        assert_eq!(report.removed_cache_files, vec![outdated]);
+        assert_eq!(report.removed_temp_files, vec![temp]);
+        #[cfg(unix)]
+        assert!(report.fixed_permissions.contains(&dirs.state));
+
+        assert!(run_maintenance().is_clean());
+    }
+}
@@ -0,0 +1,143 @@
+use std::{
+    collections::HashMap,
+    io::{Error, ErrorKind, Read, Result},
+    path::{Path, PathBuf},
+    sync::{Arc, Mutex},
+};
+use toml::Value;
+
+use crate::config::{Config, get_config_file, invalidate_document_cache};
+use crate::pipeline;
+
+type Documents = HashMap<PathBuf, Arc<Value>>;
+
+/// The configurations read by [`Config::from_reader`], by the file they stand in for.
+static DOCUMENTS: Mutex<Option<Documents>> = Mutex::new(None);
+/// Held by writers of the in-memory documents for a whole read-modify-write cycle.
+static WRITING: Mutex<()> = Mutex::new(());
+
+impl Config {
+    /// Reads the configuration from `reader` instead of the file, e.g. from standard input
+    /// for `gim --config -`.
+    ///
+    /// For the rest of the process every API serves this document in place of the
+    /// configuration file, which is never read, created or written. Writes go through the
+    /// write policy and the key locks as usual and are kept in memory, or rejected with
+    /// `PermissionDenied` if the process was initialized read-only. Calling it again
+    /// replaces the document.
+    ///
+    /// # Arguments
+    ///
+    /// * `reader` - Yields the TOML document
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Config>` - A handle on the document, or an `InvalidData` error if it isn't
+    ///   a TOML table
+    pub fn from_reader(mut reader: impl Read) -> Result<Config> {
+        let mut text = String::new();
+        reader.read_to_string(&mut text)?;
+        let mut document: Value =
+            toml::from_str(&text).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+        let file = get_config_file()?;
+        pipeline::run_read(&file, &mut document)?;
+        with_documents(|documents| documents.insert(file, Arc::new(document)));
+        invalidate_document_cache();
+        Config::load()
+    }
+}
+
+/// Returns whether this process serves the configuration read by [`Config::from_reader`].
+pub fn is_in_memory() -> bool {
+    get_config_file().is_ok_and(|file| in_memory_document(&file).is_some())
+}
+
+/// Returns the document that stands in for `file`, if one was read.
+pub(crate) fn in_memory_document(file: &Path) -> Option<Arc<Value>> {
+    with_documents(|documents| documents.get(file).cloned())
+}
+
+/// Runs a read-modify-write cycle on the in-memory document of `file`.
+///
+/// Concurrent writers run one after the other, so they don't lose each other's changes;
+/// `commit` checks a change and returns the document to keep.
+///
+/// # Returns
+///
+/// * `Result<(Value, Value, T)>` - The old and the new document and whatever `f`
+///   returned, or the error of `f` or `commit`
+pub(crate) fn modify_in_memory<T>(
+    file: &Path,
+    f: impl FnOnce(&mut Value) -> Result<T>,
+    commit: impl FnOnce(&Value, Value) -> Result<Value>,
+) -> Result<(Value, Value, T)> {
+    let _writing = WRITING.lock().unwrap_or_else(|e| e.into_inner());
+    let original = in_memory_document(file).ok_or_else(|| {
+        Error::new(
+            ErrorKind::NotFound,
+            "No configuration was read from a stream in this process",
+        )
+    })?;
+    let original = Value::clone(&original);
+    let mut config = original.clone();
+    let result = f(&mut config)?;
+    let written = if config == original {
+        config
+    } else {
+        let written = commit(&original, config)?;
+        let kept = Arc::new(written.clone());
+        with_documents(|documents| documents.insert(file.to_path_buf(), kept));
+        invalidate_document_cache();
+        written
+    };
+    Ok((original, written, result))
+}
+
+fn with_documents<T>(f: impl FnOnce(&mut Documents) -> T) -> T {
+    let mut documents = DOCUMENTS.lock().unwrap_or_else(|e| e.into_inner());
+    f(documents.get_or_insert_with(HashMap::new))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config_value, get_value_fast, update_config_value};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_config_from_reader_never_touches_the_file() {
+        let _dir = TempConfigDir::new().unwrap();
+        assert!(!is_in_memory());
+        let config = Config::from_reader("[ai]\nmodel = \"stdin\"\n".as_bytes()).unwrap();
+        assert_eq!(config.get_str("ai.model"), Some("stdin"));
+        assert!(is_in_memory());
+        assert_eq!(get_value_fast("ai.model").unwrap().as_str(), Some("stdin"));
+
+        update_config_value("ai", "model", Value::from("captured")).unwrap();
+        assert_eq!(
+            get_config_value("ai", "model").unwrap().as_str(),
+            Some("captured")
+        );
+        assert!(!get_config_file().unwrap().exists());
+
+        let rejected = crate::templates::init_config_from_template("minimal").unwrap_err();
+        assert_eq!(rejected.kind(), ErrorKind::PermissionDenied);
+        assert!(crate::layout::migrate_to_split_layout().is_err());
+        assert!(crate::comments::set_comment("ai.model", "from stdin").is_err());
+        let section: Value = toml::from_str("prompt = \"inline\"\n").unwrap();
+        crate::lazy::save_lazy_section("memory_test", &section).unwrap();
+        assert_eq!(
+            get_config_value("memory_test", "prompt").unwrap().as_str(),
+            Some("inline")
+        );
+        assert!(!get_config_file().unwrap().exists());
+        assert!(
+            !crate::lazy::lazy_section_file("memory_test")
+                .unwrap()
+                .exists()
+        );
+
+        let err = Config::from_reader("[ai".as_bytes()).unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::InvalidData);
+    }
+}
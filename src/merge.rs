@@ -0,0 +1,158 @@
+use toml::Value;
+
+/// How an array in the source is combined with an array at the same path in the target.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ArrayStrategy {
+    /// The source array replaces the target array, e.g. a project's model list
+    #[default]
+    Replace,
+    /// The source items are appended to the target array
+    Append,
+    /// The source items missing from the target array are appended, e.g. extra ignore patterns
+    Union,
+}
+
+/// Per-path rules for merging one configuration into another.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct MergePolicy {
+    arrays: ArrayStrategy,
+    paths: Vec<(String, ArrayStrategy)>,
+}
+
+impl MergePolicy {
+    /// Creates a policy that replaces arrays everywhere.
+    pub fn new() -> MergePolicy {
+        MergePolicy::default()
+    }
+
+    /// Sets the strategy for arrays without a rule of their own.
+    pub fn arrays(mut self, strategy: ArrayStrategy) -> MergePolicy {
+        self.arrays = strategy;
+        self
+    }
+
+    /// Sets the strategy for the array at a dotted key path, e.g. `"commit.ignore"`.
+    pub fn array_at(mut self, key_path: &str, strategy: ArrayStrategy) -> MergePolicy {
+        self.paths.retain(|(path, _)| path != key_path);
+        self.paths.push((key_path.to_string(), strategy));
+        self
+    }
+
+    /// Returns the strategy for the array at a dotted key path.
+    pub fn strategy_for(&self, key_path: &str) -> ArrayStrategy {
+        self.paths
+            .iter()
+            .find(|(path, _)| path == key_path)
+            .map_or(self.arrays, |(_, strategy)| *strategy)
+    }
+}
+
+/// Recursively merges `source` into `target` following `policy`.
+///
+/// Tables are merged key by key, arrays are combined according to the policy and any
+/// other value in `source` replaces the one in `target`.
+///
+/// # Arguments
+///
+/// * `target` - The value to merge into
+/// * `source` - The value to merge from
+/// * `policy` - How arrays are combined
+pub fn merge(target: &mut Value, source: &Value, policy: &MergePolicy) {
+    merge_at(target, source, true, policy, "");
+}
+
+/// Recursively merges `source` into `target`.
+///
+/// Tables are merged key by key; for any other value `source` wins if `overwrite` is set,
+/// otherwise the existing value in `target` is kept.
+///
+/// # Arguments
+///
+/// * `target` - The value to merge into
+/// * `source` - The value to merge from
+/// * `overwrite` - Whether values present in both replace the ones in `target`
+pub(crate) fn merge_into(target: &mut Value, source: &Value, overwrite: bool) {
+    merge_at(target, source, overwrite, &MergePolicy::default(), "");
+}
+
+/// Merges like [`merge_into`], combining arrays according to `policy`.
+///
+/// Appending and union apply even without `overwrite`, since they never discard items.
+pub(crate) fn merge_with_policy(
+    target: &mut Value,
+    source: &Value,
+    overwrite: bool,
+    policy: &MergePolicy,
+) {
+    merge_at(target, source, overwrite, policy, "");
+}
+
+fn merge_at(target: &mut Value, source: &Value, overwrite: bool, policy: &MergePolicy, path: &str) {
+    match (target, source) {
+        (Value::Table(target), Value::Table(source)) => {
+            for (key, value) in source {
+                let key_path = if path.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", path, key)
+                };
+                match target.get_mut(key) {
+                    Some(existing) => merge_at(existing, value, overwrite, policy, &key_path),
+                    None => {
+                        target.insert(key.clone(), value.clone());
+                    }
+                }
+            }
+        }
+        (Value::Array(target), Value::Array(source)) => match policy.strategy_for(path) {
+            ArrayStrategy::Replace => {
+                if overwrite {
+                    *target = source.clone();
+                }
+            }
+            ArrayStrategy::Append => target.extend(source.iter().cloned()),
+            ArrayStrategy::Union => {
+                for item in source {
+                    if !target.contains(item) {
+                        target.push(item.clone());
+                    }
+                }
+            }
+        },
+        (target, source) => {
+            if overwrite {
+                *target = source.clone();
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_merge_policy_array_strategies() {
+        let mut base: Value =
+            toml::from_str("[commit]\nignore = ['*.lock']\n[ai]\nmodels = ['a', 'b']\n").unwrap();
+        let project: Value = toml::from_str(
+            "[commit]\nignore = ['*.lock', 'dist/*']\n[ai]\nmodels = ['c']\nmodel = 'c'\n",
+        )
+        .unwrap();
+        let policy = MergePolicy::new().array_at("commit.ignore", ArrayStrategy::Union);
+        merge(&mut base, &project, &policy);
+
+        let strings = |path: &str| -> Vec<String> {
+            crate::path::lookup(&base, path)
+                .and_then(Value::as_array)
+                .unwrap()
+                .iter()
+                .filter_map(|v| v.as_str().map(str::to_string))
+                .collect()
+        };
+        assert_eq!(strings("commit.ignore"), vec!["*.lock", "dist/*"]);
+        assert_eq!(strings("ai.models"), vec!["c"]);
+        assert_eq!(base["ai"]["model"].as_str(), Some("c"));
+    }
+}
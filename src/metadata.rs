@@ -0,0 +1,78 @@
+use std::{fs, io::Result, path::PathBuf, time::SystemTime};
+
+use crate::config::{ensure_config_file, ensure_persistent, was_created_this_run};
+
+/// Facts about the configuration file, e.g. to show in a settings screen.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigMetadata {
+    /// The configuration file, see [`get_config_file`](crate::config::get_config_file)
+    pub path: PathBuf,
+    /// The size in bytes
+    pub size: u64,
+    /// When the file was last modified
+    pub modified: SystemTime,
+    /// Whether the file is read-only for everyone
+    pub readonly: bool,
+    /// The permission bits, e.g. `0o600`; `None` on platforms without them
+    pub mode: Option<u32>,
+    /// Whether this process created the file with the default configuration
+    pub created_this_run: bool,
+}
+
+/// Returns the path, size, modification time and permissions of the configuration file.
+///
+/// The file is created with the default configuration if it doesn't exist yet, which
+/// `created_this_run` then reports. Unlike [`get_config`](crate::config::get_config) the
+/// file isn't parsed, so this also works for a file with a syntax error.
+///
+/// # Returns
+///
+/// * `Result<ConfigMetadata>` - The metadata, or a `PermissionDenied` error if no
+///   configuration directory is available
+pub fn config_metadata() -> Result<ConfigMetadata> {
+    ensure_persistent()?;
+    let path = ensure_config_file()?;
+    let metadata = fs::metadata(&path)?;
+    let permissions = metadata.permissions();
+    #[cfg(unix)]
+    let mode = {
+        use std::os::unix::fs::PermissionsExt;
+        Some(permissions.mode() & 0o7777)
+    };
+    #[cfg(not(unix))]
+    let mode = None;
+    Ok(ConfigMetadata {
+        size: metadata.len(),
+        modified: metadata.modified()?,
+        readonly: permissions.readonly(),
+        mode,
+        created_this_run: was_created_this_run(&path),
+        path,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_config_file;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_config_metadata() {
+        let dir = TempConfigDir::new().unwrap();
+        let metadata = config_metadata().unwrap();
+        assert_eq!(metadata.path, get_config_file().unwrap());
+        assert!(metadata.path.starts_with(dir.path()));
+        assert!(metadata.created_this_run);
+        assert!(!metadata.readonly);
+        assert_eq!(metadata.size, fs::metadata(&metadata.path).unwrap().len());
+        drop(dir);
+
+        let _dir = TempConfigDir::new().unwrap();
+        let file = get_config_file().unwrap();
+        fs::write(&file, "[ai]\nmodel = ").unwrap();
+        let metadata = config_metadata().unwrap();
+        assert!(!metadata.created_this_run);
+        assert_eq!(metadata.size, 13);
+    }
+}
@@ -0,0 +1,314 @@
+use std::{
+    io::Result,
+    sync::atomic::{AtomicBool, Ordering},
+};
+use toml::Value;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::compat::is_newer_schema;
+use crate::config::{get_config, modify_config};
+use crate::defaults::default_values;
+use crate::path;
+use crate::schema::key_schema;
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+
+/// Characters that are invisible when pasted but make a value differ, e.g. a byte order mark.
+const INVISIBLE: &[char] = &['\u{feff}', '\u{200b}', '\u{200c}', '\u{200d}', '\u{2060}'];
+
+/// Starts normalizing string values whenever the configuration is read or written.
+///
+/// Copy-pasted API keys and URLs often carry a trailing newline, a byte order mark or a
+/// decomposed accent, which makes a provider reject them without any visible difference.
+/// Normalization is off by default, since it changes what the file stores on the next write.
+pub fn enable_string_normalization() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Stops normalizing string values.
+pub fn disable_string_normalization() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Returns whether string values are normalized on read and write.
+pub fn is_string_normalization_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Normalizes one string: NFC composition, with whitespace and invisible characters such
+/// as byte order marks trimmed from both ends.
+///
+/// # Arguments
+///
+/// * `s` - The string to normalize
+///
+/// # Returns
+///
+/// * `String` - The normalized string
+pub fn normalize_string(s: &str) -> String {
+    s.trim_matches(|c: char| c.is_whitespace() || INVISIBLE.contains(&c))
+        .nfc()
+        .collect()
+}
+
+/// Normalizes every string in `value`, including those in arrays and nested tables.
+///
+/// Keys are left untouched.
+pub fn normalize_value(value: &mut Value) {
+    match value {
+        Value::String(s) => *s = normalize_string(s),
+        Value::Array(items) => items.iter_mut().for_each(normalize_value),
+        Value::Table(table) => table.iter_mut().for_each(|(_, item)| normalize_value(item)),
+        _ => {}
+    }
+}
+
+/// What [`normalize_config`] did to one key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum RepairKind {
+    /// The key was missing and got its default
+    FilledDefault,
+    /// The value had the wrong type and was converted, e.g. `"3"` to `3`
+    CoercedType,
+    /// Whitespace or invisible characters were trimmed or the text was NFC-composed
+    NormalizedString,
+}
+
+/// One change made by [`normalize_config`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Repair {
+    /// The dotted key path
+    pub path: String,
+    /// What was done
+    pub kind: RepairKind,
+    /// The value before; `None` if the key was missing
+    pub old: Option<Value>,
+    /// The value after
+    pub new: Value,
+}
+
+/// A value [`normalize_config`] couldn't repair and left as it is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct InvalidValue {
+    /// The dotted key path
+    pub path: String,
+    /// The value
+    pub value: Value,
+    /// What is wrong with it, for the user
+    pub message: String,
+}
+
+/// Everything [`normalize_config`] changed or found wrong, in document order.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct NormalizationReport {
+    /// The changes made
+    pub repairs: Vec<Repair>,
+    /// The values that are still wrong
+    pub invalid: Vec<InvalidValue>,
+}
+
+impl NormalizationReport {
+    /// Returns whether the configuration was already clean.
+    pub fn is_clean(&self) -> bool {
+        self.repairs.is_empty() && self.invalid.is_empty()
+    }
+}
+
+/// Reads the configuration and repairs it in one pass, see [`normalize_config`].
+///
+/// # Arguments
+///
+/// * `write_back` - Whether to save the repaired configuration; the file is only written
+///   if something changed
+///
+/// # Returns
+///
+/// * `Result<(Value, NormalizationReport)>` - The repaired configuration and what was
+///   done to it, or an error if reading or writing fails
+pub fn load_normalized(write_back: bool) -> Result<(Value, NormalizationReport)> {
+    if !write_back {
+        let mut config = get_config()?;
+        let report = normalize_config(&mut config);
+        return Ok((config, report));
+    }
+    modify_config(|config| {
+        let report = normalize_config(config);
+        Ok((config.clone(), report))
+    })
+}
+
+/// Repairs a configuration in place.
+///
+/// Missing keys get their defaults, strings are normalized like [`normalize_string`]
+/// whether or not [`enable_string_normalization`] was called, and values whose type
+/// differs from their default's are converted where that is lossless, e.g. `"true"` to
+/// `true` or `2` to `2.0`. Values that can't be converted or aren't among the allowed
+/// values of their key are reported and left alone. There are no key migrations yet, so
+/// renamed keys are not moved. A file written by a newer release only gets missing keys
+/// and strings repaired, since its types may have changed on purpose.
+///
+/// # Arguments
+///
+/// * `config` - The configuration to repair
+///
+/// # Returns
+///
+/// * `NormalizationReport` - What was changed and what is still wrong
+pub fn normalize_config(config: &mut Value) -> NormalizationReport {
+    let mut report = NormalizationReport::default();
+    let defaults = default_values();
+    let newer = is_newer_schema(config);
+    for key_path in path::leaf_paths(&defaults) {
+        if path::lookup(config, &key_path).is_some() {
+            continue;
+        }
+        let value = path::lookup(&defaults, &key_path)
+            .cloned()
+            .expect("leaf path of the defaults");
+        if path::insert(config, &key_path, value.clone()).is_ok() {
+            report.repairs.push(Repair {
+                path: key_path,
+                kind: RepairKind::FilledDefault,
+                old: None,
+                new: value,
+            });
+        }
+    }
+    for key_path in path::leaf_paths(config) {
+        let Some(value) = path::lookup_mut(config, &key_path) else {
+            continue;
+        };
+        let mut normalized = value.clone();
+        normalize_value(&mut normalized);
+        if normalized != *value {
+            report.repairs.push(Repair {
+                path: key_path.clone(),
+                kind: RepairKind::NormalizedString,
+                old: Some(value.clone()),
+                new: normalized.clone(),
+            });
+            *value = normalized;
+        }
+        if newer {
+            continue;
+        }
+        if let Some(default) = path::lookup(&defaults, &key_path)
+            && value.type_str() != default.type_str()
+        {
+            match coerce(value, default) {
+                Some(coerced) => {
+                    report.repairs.push(Repair {
+                        path: key_path.clone(),
+                        kind: RepairKind::CoercedType,
+                        old: Some(value.clone()),
+                        new: coerced.clone(),
+                    });
+                    *value = coerced;
+                }
+                None => report.invalid.push(InvalidValue {
+                    path: key_path.clone(),
+                    value: value.clone(),
+                    message: format!(
+                        "expected {}, found {}",
+                        default.type_str(),
+                        value.type_str()
+                    ),
+                }),
+            }
+        }
+        if let Some(schema) = key_schema(&key_path)
+            && !schema.allowed.is_empty()
+            && !schema.allowed.contains(value)
+        {
+            let allowed: Vec<String> = schema.allowed.iter().map(Value::to_string).collect();
+            report.invalid.push(InvalidValue {
+                path: key_path,
+                value: value.clone(),
+                message: format!("expected one of {}", allowed.join(", ")),
+            });
+        }
+    }
+    report
+}
+
+/// Converts `value` to the type of `default` if that loses nothing.
+fn coerce(value: &Value, default: &Value) -> Option<Value> {
+    match (default, value) {
+        (Value::Integer(_), Value::String(s)) => s.parse().ok().map(Value::Integer),
+        (Value::Integer(_), Value::Float(f)) if f.fract() == 0.0 && f.abs() < 9e15 => {
+            Some(Value::Integer(*f as i64))
+        }
+        (Value::Float(_), Value::Integer(i)) => Some(Value::Float(*i as f64)),
+        (Value::Float(_), Value::String(s)) => s.parse().ok().map(Value::Float),
+        (Value::Boolean(_), Value::String(s)) => match s.to_ascii_lowercase().as_str() {
+            "true" => Some(Value::Boolean(true)),
+            "false" => Some(Value::Boolean(false)),
+            _ => None,
+        },
+        (Value::String(_), Value::Integer(i)) => Some(Value::from(i.to_string())),
+        (Value::String(_), Value::Float(f)) => Some(Value::from(f.to_string())),
+        (Value::String(_), Value::Boolean(b)) => Some(Value::from(b.to_string())),
+        _ => None,
+    }
+}
+
+/// Normalizes `value` if normalization is enabled.
+pub(crate) fn normalize_if_enabled(value: &mut Value) {
+    if is_string_normalization_enabled() {
+        normalize_value(value);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_strings() {
+        assert_eq!(normalize_string("\u{feff}sk-123 \n"), "sk-123");
+        assert_eq!(
+            normalize_string("https://api.example\u{200b}"),
+            "https://api.example"
+        );
+        assert_eq!(normalize_string("Franc\u{0327}ais"), "Fran\u{e7}ais");
+
+        let mut config: Value = toml::from_str("[ai]\nurls = [' a ']\nmax = 3\n").unwrap();
+        normalize_value(&mut config);
+        assert_eq!(config["ai"]["urls"][0].as_str(), Some("a"));
+        assert_eq!(config["ai"]["max"].as_integer(), Some(3));
+    }
+
+    #[test]
+    fn test_load_normalized_repairs_and_reports() {
+        let _dir = crate::testing::TempConfigDir::new().unwrap();
+        let file = crate::config::get_config_file().unwrap();
+        std::fs::write(
+            &file,
+            "[ai]\nmodel = \" gpt-4o\\n\"\n[update]\nmax_try = '3'\ntry_interval_days = [1]\n",
+        )
+        .unwrap();
+
+        let (config, report) = load_normalized(false).unwrap();
+        assert_eq!(config["ai"]["model"].as_str(), Some("gpt-4o"));
+        assert_eq!(config["update"]["max_try"].as_integer(), Some(3));
+        assert!(config["ai"].get("language").is_some());
+        let kinds: Vec<(&str, RepairKind)> = report
+            .repairs
+            .iter()
+            .map(|r| (r.path.as_str(), r.kind))
+            .collect();
+        assert!(kinds.contains(&("ai.model", RepairKind::NormalizedString)));
+        assert!(kinds.contains(&("update.max_try", RepairKind::CoercedType)));
+        assert!(kinds.contains(&("ai.language", RepairKind::FilledDefault)));
+        assert_eq!(report.invalid.len(), 1);
+        assert_eq!(report.invalid[0].path, "update.try_interval_days");
+        assert!(std::fs::read_to_string(&file).unwrap().contains("'3'"));
+
+        load_normalized(true).unwrap();
+        assert_eq!(
+            get_config().unwrap()["update"]["max_try"].as_integer(),
+            Some(3)
+        );
+    }
+}
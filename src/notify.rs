@@ -0,0 +1,109 @@
+use std::sync::{
+    Arc, RwLock,
+    atomic::{AtomicU64, Ordering},
+};
+use toml::Value;
+
+use crate::change::ChangeSet;
+use crate::config::get_config_file;
+use crate::hooks::{HOOKS_SECTION, run_change_hook};
+use crate::init::emit_warning;
+use crate::stream::{append_change_event, is_change_stream_enabled};
+
+type Listener = Arc<dyn Fn(&ChangeSet) + Send + Sync>;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(1);
+static LISTENERS: RwLock<Vec<(ListenerId, Listener)>> = RwLock::new(Vec::new());
+
+/// Identifies a listener registered with [`on_change`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct ListenerId(u64);
+
+/// Registers a callback fired after every successful write of the configuration file.
+///
+/// The callback receives the changed keys with their old and new values, secrets redacted.
+/// It runs on the writing thread after the file lock is released, so it may read or write
+/// the configuration itself. The writes of a write storm (see [`crate::storm`]) are
+/// announced once per deferred save, on the thread flushing it.
+///
+/// # Arguments
+///
+/// * `listener` - Called with the changes of each write
+///
+/// # Returns
+///
+/// * `ListenerId` - The handle to pass to [`remove_listener`]
+pub fn on_change(listener: impl Fn(&ChangeSet) + Send + Sync + 'static) -> ListenerId {
+    let id = ListenerId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    LISTENERS
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((id, Arc::new(listener)));
+    id
+}
+
+/// Unregisters a listener; returns whether it was registered.
+pub fn remove_listener(id: ListenerId) -> bool {
+    let mut listeners = LISTENERS.write().unwrap_or_else(|e| e.into_inner());
+    let before = listeners.len();
+    listeners.retain(|(listener_id, _)| *listener_id != id);
+    listeners.len() != before
+}
+
+/// Fires the registered listeners and the configured hook script (see [`crate::hooks`])
+/// for a write from `old` to `new`, and appends it to the change stream (see
+/// [`crate::stream`]) if that is enabled.
+pub(crate) fn notify_change(old: &Value, new: &Value) {
+    let listeners: Vec<Listener> = LISTENERS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(_, listener)| Arc::clone(listener))
+        .collect();
+    let stream = is_change_stream_enabled(new);
+    if listeners.is_empty() && new.get(HOOKS_SECTION).is_none() && !stream {
+        return;
+    }
+    let changes = ChangeSet::between(old, new).redacted();
+    if changes.is_empty() {
+        return;
+    }
+    if stream
+        && let Err(e) = get_config_file().and_then(|file| append_change_event(&file, &changes))
+    {
+        emit_warning(&format!("Failed to append to the change stream: {}", e));
+    }
+    for listener in listeners {
+        listener(&changes);
+    }
+    run_change_hook(new, &changes);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::update_config_value;
+    use crate::secret::SECRET_PLACEHOLDER;
+    use crate::testing::TempConfigDir;
+    use std::sync::Mutex;
+
+    #[test]
+    fn test_on_change_receives_redacted_changes() {
+        let _dir = TempConfigDir::new().unwrap();
+        let seen = Arc::new(Mutex::new(Vec::new()));
+        let sink = Arc::clone(&seen);
+        let id = on_change(move |changes| {
+            sink.lock().unwrap().extend(
+                changes
+                    .changes
+                    .iter()
+                    .filter(|c| c.new == Some(Value::from(SECRET_PLACEHOLDER)))
+                    .map(|c| c.path.clone()),
+            )
+        });
+
+        update_config_value("ai", "apikey", Value::from("sk-notify-test")).unwrap();
+        assert!(remove_listener(id));
+        assert!(seen.lock().unwrap().contains(&"ai.apikey".to_string()));
+    }
+}
@@ -0,0 +1,149 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    time::Duration,
+};
+
+use crate::ai::AiConfig;
+use crate::http;
+use crate::secret::Secret;
+
+/// The address a local Ollama server listens on by default.
+pub const DEFAULT_OLLAMA_HOST: &str = "localhost:11434";
+
+/// How long to wait for a local server before concluding there is none.
+const PROBE_TIMEOUT: Duration = Duration::from_millis(500);
+
+/// A model installed on the local Ollama server.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OllamaModel {
+    /// The model tag, e.g. `"llama3:latest"`
+    pub name: String,
+    /// Size on disk in bytes, if reported
+    pub size: Option<u64>,
+}
+
+/// A running Ollama server found on this machine.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OllamaDetection {
+    /// The base URL of the server's OpenAI-compatible API
+    pub url: String,
+    /// The installed models
+    pub models: Vec<OllamaModel>,
+    /// A ready-to-use `[ai]` configuration for the first installed model
+    pub suggested: AiConfig,
+}
+
+/// Probes `http://localhost:11434` for a running Ollama server.
+///
+/// This lets the setup wizard offer a local configuration that needs no API key.
+///
+/// # Returns
+///
+/// * `Result<Option<OllamaDetection>>` - The detected server, `None` if nothing is listening,
+///   or an error if something answered but not like an Ollama server
+pub fn detect_local_ollama() -> Result<Option<OllamaDetection>> {
+    detect_ollama_at(DEFAULT_OLLAMA_HOST)
+}
+
+/// Probes the given `host:port` for a running Ollama server.
+///
+/// # Arguments
+///
+/// * `host` - The `host:port` to probe
+///
+/// # Returns
+///
+/// * `Result<Option<OllamaDetection>>` - See [`detect_local_ollama`]
+pub fn detect_ollama_at(host: &str) -> Result<Option<OllamaDetection>> {
+    let response = match http::get(host, "/api/tags", PROBE_TIMEOUT) {
+        Ok(response) => response,
+        Err(e)
+            if matches!(
+                e.kind(),
+                ErrorKind::ConnectionRefused
+                    | ErrorKind::TimedOut
+                    | ErrorKind::WouldBlock
+                    | ErrorKind::NotFound
+                    | ErrorKind::AddrNotAvailable
+                    | ErrorKind::HostUnreachable
+                    | ErrorKind::NetworkUnreachable
+            ) =>
+        {
+            return Ok(None);
+        }
+        Err(e) => return Err(e),
+    };
+    if response.status != 200 {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Unexpected status {} from {}", response.status, host),
+        ));
+    }
+    let models = parse_models(&response.body)?;
+    let url = format!("http://{}/v1", host);
+    let suggested = AiConfig {
+        model: models.first().map(|m| m.name.clone()).unwrap_or_default(),
+        apikey: Secret::default(),
+        url: url.clone(),
+        language: "English".to_string(),
+    };
+    Ok(Some(OllamaDetection {
+        url,
+        models,
+        suggested,
+    }))
+}
+
+fn parse_models(body: &str) -> Result<Vec<OllamaModel>> {
+    let json: serde_json::Value =
+        serde_json::from_str(body).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    let models = json
+        .get("models")
+        .and_then(|m| m.as_array())
+        .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Response has no 'models' list"))?;
+    Ok(models
+        .iter()
+        .filter_map(|model| {
+            Some(OllamaModel {
+                name: model.get("name")?.as_str()?.to_string(),
+                size: model.get("size").and_then(|s| s.as_u64()),
+            })
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{
+        io::{Read, Write},
+        net::TcpListener,
+        thread,
+    };
+
+    #[test]
+    fn test_detect_ollama_at_lists_models() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let host = listener.local_addr().unwrap().to_string();
+        let server = thread::spawn(move || {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut buf = [0u8; 1024];
+            let _ = stream.read(&mut buf).unwrap();
+            let body = r#"{"models":[{"name":"llama3:latest","size":42},{"name":"qwen2"}]}"#;
+            write!(
+                stream,
+                "HTTP/1.1 200 OK\r\nContent-Length: {}\r\n\r\n{}",
+                body.len(),
+                body
+            )
+            .unwrap();
+        });
+
+        let detection = detect_ollama_at(&host).unwrap().unwrap();
+        server.join().unwrap();
+        assert_eq!(detection.models.len(), 2);
+        assert_eq!(detection.models[0].size, Some(42));
+        assert_eq!(detection.suggested.model, "llama3:latest");
+        assert_eq!(detection.suggested.url, format!("http://{}/v1", host));
+    }
+}
@@ -0,0 +1,123 @@
+use std::io::IsTerminal;
+
+/// An ANSI style the crate's own output may use.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum Style {
+    /// Headers
+    Bold,
+    /// Masked or less important text
+    Dim,
+    /// Added values
+    Green,
+    /// Removed values
+    Red,
+    /// Changed values
+    Yellow,
+}
+
+impl Style {
+    fn code(self) -> &'static str {
+        match self {
+            Style::Bold => "\x1b[1m",
+            Style::Dim => "\x1b[2m",
+            Style::Green => "\x1b[32m",
+            Style::Red => "\x1b[31m",
+            Style::Yellow => "\x1b[33m",
+        }
+    }
+}
+
+/// Where the crate's output ends up: a terminal or a pipe, and what the environment asks for.
+///
+/// Renderers take an `OutputContext` instead of styling text unconditionally, so piped
+/// output and CI logs stay free of escape codes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct OutputContext {
+    /// Whether standard output is a terminal
+    pub terminal: bool,
+    /// Whether `NO_COLOR` is set to a non-empty value
+    pub no_color: bool,
+    /// Whether `CI` is set, i.e. nobody is there to answer prompts
+    pub ci: bool,
+}
+
+impl OutputContext {
+    /// Inspects standard output and the `NO_COLOR` and `CI` environment variables.
+    pub fn detect() -> OutputContext {
+        OutputContext::from_env(std::io::stdout().is_terminal(), |var| {
+            std::env::var(var).ok()
+        })
+    }
+
+    /// Builds a context from a terminal flag and an environment lookup.
+    ///
+    /// # Arguments
+    ///
+    /// * `terminal` - Whether the output goes to a terminal
+    /// * `var` - Returns the value of an environment variable
+    ///
+    /// # Returns
+    ///
+    /// * `OutputContext` - The context
+    pub fn from_env(terminal: bool, var: impl Fn(&str) -> Option<String>) -> OutputContext {
+        OutputContext {
+            terminal,
+            no_color: var("NO_COLOR").is_some_and(|v| !v.is_empty()),
+            ci: var("CI").is_some_and(|v| !v.is_empty() && v != "0" && v != "false"),
+        }
+    }
+
+    /// A context for plain output, e.g. when writing to a file.
+    pub fn plain() -> OutputContext {
+        OutputContext::default()
+    }
+
+    /// Returns whether output may contain ANSI colors.
+    pub fn use_color(&self) -> bool {
+        self.terminal && !self.no_color && !self.ci
+    }
+
+    /// Returns whether the user can be prompted.
+    pub fn is_interactive(&self) -> bool {
+        self.terminal && !self.ci
+    }
+
+    /// Wraps `text` in `style` if colors are allowed.
+    pub fn paint(&self, text: &str, style: Style) -> String {
+        if self.use_color() {
+            format!("{}{}\x1b[0m", style.code(), text)
+        } else {
+            text.to_string()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_output_context_from_env() {
+        let env = |pairs: &'static [(&'static str, &'static str)]| {
+            move |var: &str| {
+                pairs
+                    .iter()
+                    .find(|(name, _)| *name == var)
+                    .map(|(_, value)| value.to_string())
+            }
+        };
+        let terminal = OutputContext::from_env(true, env(&[]));
+        assert!(terminal.use_color() && terminal.is_interactive());
+        assert_eq!(terminal.paint("x", Style::Red), "\x1b[31mx\x1b[0m");
+
+        assert!(!OutputContext::from_env(true, env(&[("NO_COLOR", "1")])).use_color());
+        let ci = OutputContext::from_env(true, env(&[("CI", "true")]));
+        assert!(!ci.use_color() && !ci.is_interactive());
+        assert!(OutputContext::from_env(true, env(&[("CI", "false")])).is_interactive());
+        assert_eq!(
+            OutputContext::from_env(false, env(&[])).paint("x", Style::Red),
+            "x"
+        );
+    }
+}
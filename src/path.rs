@@ -0,0 +1,189 @@
+use std::io::{Error, ErrorKind, Result};
+use toml::{Value, map};
+
+/// Splits a dotted key path such as `"ai.model"` into its segments.
+///
+/// # Arguments
+///
+/// * `path` - The dotted key path
+///
+/// # Returns
+///
+/// * `Result<Vec<&str>>` - The segments or an `InvalidInput` error if any segment is empty
+pub fn split(path: &str) -> Result<Vec<&str>> {
+    let segments: Vec<&str> = path.split('.').collect();
+    if segments.iter().any(|s| s.is_empty()) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid key path '{}'", path),
+        ));
+    }
+    Ok(segments)
+}
+
+/// Looks up the value at a dotted key path.
+///
+/// # Arguments
+///
+/// * `root` - The value to search in
+/// * `path` - The dotted key path
+///
+/// # Returns
+///
+/// * `Option<&Value>` - The value if every segment of the path exists
+pub fn lookup<'a>(root: &'a Value, path: &str) -> Option<&'a Value> {
+    path.split('.')
+        .try_fold(root, |value, segment| value.as_table()?.get(segment))
+}
+
+/// Looks up the value at a dotted key path for modification.
+///
+/// # Arguments
+///
+/// * `root` - The value to search in
+/// * `path` - The dotted key path
+///
+/// # Returns
+///
+/// * `Option<&mut Value>` - The value if every segment of the path exists
+pub fn lookup_mut<'a>(root: &'a mut Value, path: &str) -> Option<&'a mut Value> {
+    path.split('.').try_fold(root, |value, segment| {
+        value.as_table_mut()?.get_mut(segment)
+    })
+}
+
+/// Looks up the value at a dotted key path, reporting which segment was missing.
+///
+/// # Arguments
+///
+/// * `root` - The value to search in
+/// * `path` - The dotted key path
+///
+/// # Returns
+///
+/// * `Result<&Value>` - The value or a `NotFound`/`InvalidData` error naming the path
+pub fn require<'a>(root: &'a Value, path: &str) -> Result<&'a Value> {
+    let mut value = root;
+    for segment in split(path)? {
+        let table = value.as_table().ok_or_else(|| {
+            Error::new(
+                ErrorKind::InvalidData,
+                format!("Cannot look up '{}': parent is not a table", path),
+            )
+        })?;
+        value = table
+            .get(segment)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, format!("Key '{}' not found", path)))?;
+    }
+    Ok(value)
+}
+
+/// Sets the value at a dotted key path, creating intermediate tables as needed.
+///
+/// # Arguments
+///
+/// * `root` - The value to modify
+/// * `path` - The dotted key path
+/// * `value` - The new value
+///
+/// # Returns
+///
+/// * `Result<Option<Value>>` - The previous value, or an error if a parent is not a table
+pub fn insert(root: &mut Value, path: &str, value: Value) -> Result<Option<Value>> {
+    let segments = split(path)?;
+    let (last, parents) = segments
+        .split_last()
+        .expect("split never returns no segments");
+    let mut table = root.as_table_mut().ok_or_else(|| not_a_table(path))?;
+    for segment in parents {
+        table = table
+            .entry(segment.to_string())
+            .or_insert_with(|| Value::Table(map::Map::new()))
+            .as_table_mut()
+            .ok_or_else(|| not_a_table(path))?;
+    }
+    Ok(table.insert(last.to_string(), value))
+}
+
+/// Removes the value at a dotted key path.
+///
+/// # Arguments
+///
+/// * `root` - The value to modify
+/// * `path` - The dotted key path
+///
+/// # Returns
+///
+/// * `Option<Value>` - The removed value if it existed
+pub fn remove(root: &mut Value, path: &str) -> Option<Value> {
+    let (parents, last) = path
+        .rsplit_once('.')
+        .map_or((None, path), |(p, l)| (Some(p), l));
+    let parent = match parents {
+        Some(parents) => parents.split('.').try_fold(&mut *root, |value, segment| {
+            value.as_table_mut()?.get_mut(segment)
+        })?,
+        None => root,
+    };
+    parent.as_table_mut()?.remove(last)
+}
+
+/// Returns the dotted paths of every non-table value, in document order.
+///
+/// # Arguments
+///
+/// * `root` - The value to walk
+///
+/// # Returns
+///
+/// * `Vec<String>` - The paths of all leaf values
+pub fn leaf_paths(root: &Value) -> Vec<String> {
+    let mut paths = Vec::new();
+    collect_leaves(root, "", &mut paths);
+    paths
+}
+
+fn collect_leaves(value: &Value, prefix: &str, paths: &mut Vec<String>) {
+    match value.as_table() {
+        Some(table) => {
+            for (key, value) in table {
+                let path = if prefix.is_empty() {
+                    key.clone()
+                } else {
+                    format!("{}.{}", prefix, key)
+                };
+                collect_leaves(value, &path, paths);
+            }
+        }
+        None if !prefix.is_empty() => paths.push(prefix.to_string()),
+        None => {}
+    }
+}
+
+fn not_a_table(path: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Cannot set '{}': parent is not a table", path),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_insert_lookup_remove_round_trip() {
+        let mut root = Value::Table(map::Map::new());
+        assert_eq!(
+            insert(&mut root, "ai.model", Value::from("gpt-4")).unwrap(),
+            None
+        );
+        assert_eq!(lookup(&root, "ai.model"), Some(&Value::from("gpt-4")));
+        assert!(lookup(&root, "ai.url").is_none());
+        assert!(require(&root, "ai.url").is_err());
+        assert!(split("ai..model").is_err());
+
+        assert_eq!(remove(&mut root, "ai.model"), Some(Value::from("gpt-4")));
+        assert!(lookup(&root, "ai.model").is_none());
+    }
+}
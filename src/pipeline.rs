@@ -0,0 +1,197 @@
+use std::{
+    io::Result,
+    path::Path,
+    sync::{
+        Arc, RwLock,
+        atomic::{AtomicU64, Ordering},
+    },
+};
+use toml::Value;
+
+use crate::compat::check_read;
+use crate::normalize::normalize_if_enabled;
+
+/// One transformation of the configuration between the file and the application.
+///
+/// Reads pass the parsed document through the built-in stages and then through every
+/// registered stage in registration order; writes pass it through the same stages in
+/// reverse, so a stage that decrypts values on read sees its own output again on write
+/// and can encrypt them before anything else touches the document.
+pub trait Stage: Send + Sync {
+    /// Names the stage in [`stage_names`] and in errors.
+    fn name(&self) -> &str;
+
+    /// Transforms a document just read from `file`; an error fails the read.
+    fn read(&self, _file: &Path, _config: &mut Value) -> Result<()> {
+        Ok(())
+    }
+
+    /// Transforms a document about to be written to `file`; an error fails the write.
+    fn write(&self, _file: &Path, _config: &mut Value) -> Result<()> {
+        Ok(())
+    }
+}
+
+/// Identifies a stage registered with [`add_stage`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct StageId(u64);
+
+type SharedStage = Arc<dyn Stage>;
+
+static NEXT_ID: AtomicU64 = AtomicU64::new(0);
+static STAGES: RwLock<Vec<(StageId, SharedStage)>> = RwLock::new(Vec::new());
+
+/// Rejects files written for a newer schema under `ForwardCompatibility::Reject`.
+struct SchemaVersion;
+
+impl Stage for SchemaVersion {
+    fn name(&self) -> &str {
+        "schema-version"
+    }
+
+    fn read(&self, file: &Path, config: &mut Value) -> Result<()> {
+        check_read(file, config)
+    }
+}
+
+/// Trims and normalizes string values if string normalization is enabled.
+struct Normalize;
+
+impl Stage for Normalize {
+    fn name(&self) -> &str {
+        "normalize"
+    }
+
+    fn read(&self, _file: &Path, config: &mut Value) -> Result<()> {
+        normalize_if_enabled(config);
+        Ok(())
+    }
+
+    fn write(&self, _file: &Path, config: &mut Value) -> Result<()> {
+        normalize_if_enabled(config);
+        Ok(())
+    }
+}
+
+const BUILTIN: &[&dyn Stage] = &[&SchemaVersion, &Normalize];
+
+/// Appends a stage to the pipeline every read and write of the configuration goes through.
+///
+/// # Arguments
+///
+/// * `stage` - The stage; it runs after the built-in ones and earlier registered ones on
+///   read, and before them on write
+///
+/// # Returns
+///
+/// * `StageId` - The id to pass to [`remove_stage`]
+pub fn add_stage(stage: impl Stage + 'static) -> StageId {
+    let id = StageId(NEXT_ID.fetch_add(1, Ordering::Relaxed));
+    STAGES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .push((id, Arc::new(stage)));
+    id
+}
+
+/// Unregisters a stage; returns whether it was registered.
+pub fn remove_stage(id: StageId) -> bool {
+    let mut stages = STAGES.write().unwrap_or_else(|e| e.into_inner());
+    let before = stages.len();
+    stages.retain(|(stage_id, _)| *stage_id != id);
+    stages.len() != before
+}
+
+/// Returns the names of every stage in read order, the built-in ones first.
+pub fn stage_names() -> Vec<String> {
+    let mut names: Vec<String> = BUILTIN.iter().map(|s| s.name().to_string()).collect();
+    names.extend(registered().iter().map(|stage| stage.name().to_string()));
+    names
+}
+
+/// Passes a document just read from `file` through every stage.
+pub(crate) fn run_read(file: &Path, config: &mut Value) -> Result<()> {
+    for stage in BUILTIN {
+        stage.read(file, config)?;
+    }
+    for stage in registered() {
+        stage.read(file, config)?;
+    }
+    Ok(())
+}
+
+/// Passes a document about to be written to `file` through every stage, in reverse.
+pub(crate) fn run_write(file: &Path, config: &mut Value) -> Result<()> {
+    for stage in registered().iter().rev() {
+        stage.write(file, config)?;
+    }
+    for stage in BUILTIN.iter().rev() {
+        stage.write(file, config)?;
+    }
+    Ok(())
+}
+
+fn registered() -> Vec<SharedStage> {
+    // Cloned, so a stage may read the configuration itself without deadlocking.
+    STAGES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(_, stage)| Arc::clone(stage))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config_file, get_value_fast, update_config_value};
+    use crate::testing::TempConfigDir;
+    use std::path::PathBuf;
+
+    /// Stores `ai.model` with a prefix in one file, like an encrypting stage would.
+    struct Prefix(PathBuf);
+
+    impl Stage for Prefix {
+        fn name(&self) -> &str {
+            "prefix"
+        }
+
+        fn read(&self, file: &Path, config: &mut Value) -> Result<()> {
+            if let Some(Value::String(model)) =
+                config.get_mut("ai").and_then(|ai| ai.get_mut("model"))
+                && file == self.0
+            {
+                *model = model.trim_start_matches("enc:").to_string();
+            }
+            Ok(())
+        }
+
+        fn write(&self, file: &Path, config: &mut Value) -> Result<()> {
+            if let Some(Value::String(model)) =
+                config.get_mut("ai").and_then(|ai| ai.get_mut("model"))
+                && file == self.0
+            {
+                *model = format!("enc:{}", model);
+            }
+            Ok(())
+        }
+    }
+
+    #[test]
+    fn test_stage_transforms_reads_and_writes() {
+        let _dir = TempConfigDir::new().unwrap();
+        let file = get_config_file().unwrap();
+        let id = add_stage(Prefix(file.clone()));
+        assert!(
+            stage_names().starts_with(&["schema-version".to_string(), "normalize".to_string()])
+        );
+
+        update_config_value("ai", "model", Value::from("piped")).unwrap();
+        let text = std::fs::read_to_string(&file).unwrap();
+        assert!(text.contains("model = \"enc:piped\""));
+        assert_eq!(get_value_fast("ai.model").unwrap().as_str(), Some("piped"));
+
+        assert!(remove_stage(id));
+        assert!(!remove_stage(id));
+    }
+}
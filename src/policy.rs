@@ -0,0 +1,116 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    sync::{Arc, RwLock},
+};
+use toml::Value;
+
+use crate::change::ChangeSet;
+
+type WritePolicy = Arc<dyn Fn(&ChangeSet) -> PolicyDecision + Send + Sync>;
+
+/// The policy consulted before every write, if one is installed.
+static WRITE_POLICY: RwLock<Option<WritePolicy>> = RwLock::new(None);
+
+/// What a write policy decided about a set of changes.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum PolicyDecision {
+    /// Write the changes as they are
+    Allow,
+    /// Reject the write; the reason is reported in the `PermissionDenied` error
+    Deny(String),
+    /// Write these changes instead, relative to the configuration before the write
+    Rewrite(ChangeSet),
+}
+
+/// Installs a policy that every write of the configuration file is submitted to.
+///
+/// Embedding applications use this to enforce rules centrally, e.g. forbidding plaintext
+/// API keys or restricting `ai.url` to an allowlist. The policy replaces any previous one
+/// and sees the changes with real values, so it must not log them unredacted.
+///
+/// # Arguments
+///
+/// * `policy` - Decides about the changes of each write
+pub fn set_write_policy(policy: impl Fn(&ChangeSet) -> PolicyDecision + Send + Sync + 'static) {
+    *WRITE_POLICY.write().unwrap_or_else(|e| e.into_inner()) = Some(Arc::new(policy));
+}
+
+/// Removes the installed write policy.
+pub fn clear_write_policy() {
+    *WRITE_POLICY.write().unwrap_or_else(|e| e.into_inner()) = None;
+}
+
+/// Submits the write from `old` to `new` to the installed policy.
+///
+/// # Returns
+///
+/// * `Result<Option<Value>>` - `None` to write `new` unchanged, the rewritten configuration,
+///   or a `PermissionDenied` error if the policy denied the write
+pub(crate) fn enforce_write_policy(old: &Value, new: &Value) -> Result<Option<Value>> {
+    let Some(policy) = WRITE_POLICY
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .clone()
+    else {
+        return Ok(None);
+    };
+    match policy(&ChangeSet::between(old, new)) {
+        PolicyDecision::Allow => Ok(None),
+        PolicyDecision::Deny(reason) => Err(Error::new(
+            ErrorKind::PermissionDenied,
+            format!("Write denied by policy: {}", reason),
+        )),
+        PolicyDecision::Rewrite(changes) => {
+            let mut rewritten = old.clone();
+            changes.apply_to(&mut rewritten)?;
+            Ok(Some(rewritten))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::change::Change;
+    use crate::config::{get_value_fast, modify_config};
+    use crate::path;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_write_policy_denies_and_rewrites() {
+        let _dir = TempConfigDir::new().unwrap();
+        // Other tests write concurrently, so the policy only reacts to its own keys.
+        set_write_policy(|changes| {
+            if changes.paths().contains(&"policy_test.denied") {
+                return PolicyDecision::Deny("policy_test.denied is managed".to_string());
+            }
+            let rewritten: Vec<Change> = changes
+                .changes
+                .iter()
+                .map(|c| match &c.new {
+                    Some(Value::String(s)) if c.path == "policy_test.shout" => Change {
+                        new: Some(Value::from(s.to_uppercase())),
+                        ..c.clone()
+                    },
+                    _ => c.clone(),
+                })
+                .collect();
+            PolicyDecision::Rewrite(ChangeSet { changes: rewritten })
+        });
+        let set = |key: &str, value: &str| {
+            modify_config(|config| path::insert(config, key, Value::from(value)).map(|_| ()))
+        };
+
+        let denied = set("policy_test.denied", "x");
+        let rewritten = set("policy_test.shout", "quiet");
+        clear_write_policy();
+
+        assert_eq!(denied.unwrap_err().kind(), ErrorKind::PermissionDenied);
+        rewritten.unwrap();
+        assert_eq!(
+            get_value_fast("policy_test.shout").unwrap(),
+            Value::from("QUIET")
+        );
+    }
+}
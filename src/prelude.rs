@@ -0,0 +1,42 @@
+pub use crate::ai::AiConfig;
+pub use crate::change::{Change, ChangeSet};
+pub use crate::config::{
+    Config, get_config, get_config_file, get_config_value, get_value_fast, save_config,
+    update_config_value,
+};
+pub use crate::describe::{KeyDescription, describe_key};
+pub use crate::display::Origin;
+pub use crate::init::Options;
+pub use crate::notify::{ListenerId, on_change, remove_listener};
+pub use crate::recovery::{StorageError, StorageFailure, storage_error};
+pub use crate::schema::{KeySchema, is_known_key, key_schema, schema};
+pub use crate::secret::{Secret, get_secret};
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::{io::Result, path::PathBuf};
+    use toml::Value;
+
+    /// Fails to compile when a signature of the stable surface changes.
+    #[test]
+    fn test_stable_signatures() {
+        let _: fn() -> Result<Value> = get_config;
+        let _: fn() -> Result<PathBuf> = get_config_file;
+        let _: fn(&str, &str) -> Result<Value> = get_config_value;
+        let _: fn(&str) -> Result<Value> = get_value_fast;
+        let _: fn(&Value) -> Result<()> = save_config;
+        let _: fn(&str, &str, Value) -> Result<()> = update_config_value;
+        let _: fn() -> Result<Config> = Config::load;
+        let _: fn(Options) -> Result<()> = Config::init;
+        let _: fn() -> Result<AiConfig> = AiConfig::load;
+        let _: fn(&str) -> Result<Option<KeyDescription>> = describe_key;
+        let _: fn(ListenerId) -> bool = remove_listener;
+        let _: fn(&std::io::Error) -> Option<&StorageError> = storage_error;
+        let _: fn(&str) -> Option<KeySchema> = key_schema;
+        let _: fn(&str) -> bool = is_known_key;
+        let _: fn() -> Vec<KeySchema> = schema;
+        let _: fn(&str) -> Result<Secret<String>> = get_secret;
+        assert!(remove_listener(on_change(|_: &ChangeSet| {})));
+    }
+}
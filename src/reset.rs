@@ -0,0 +1,145 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    time::{SystemTime, UNIX_EPOCH},
+};
+use toml::Value;
+
+use crate::change::ChangeSet;
+use crate::config::{get_config, modify_config};
+use crate::defaults::default_values;
+use crate::layout::LAYOUT_KEY;
+use crate::path;
+use crate::snapshot::{BACKUP_PREFIX, SnapshotInfo, list_snapshots, save_backup};
+
+/// The outcome of a [`reset`] call.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ResetReport {
+    /// The snapshot of the configuration before the reset, `None` if nothing changed
+    pub backup: Option<SnapshotInfo>,
+    /// The keys that were reset, with their old and new values
+    pub changes: ChangeSet,
+}
+
+/// Restores the default value of a key, a section or the whole configuration.
+///
+/// Defaults are the built-in ones overridden by those the application registered. A key
+/// or section without a default is removed. Resetting everything keeps the storage layout
+/// but drops every other setting, including profiles and temporary overrides.
+///
+/// Before anything is written the current configuration is saved as a snapshot labelled
+/// `reset-<unix seconds>`, which [`restore`](crate::snapshot::restore) brings back. When
+/// the configuration already holds the defaults nothing is written and no snapshot is
+/// taken.
+///
+/// # Arguments
+///
+/// * `path_or_section` - A dotted key path such as `"ai.model"`, a section such as
+///   `"ai"`, or `None` for the whole configuration
+///
+/// # Returns
+///
+/// * `Result<ResetReport>` - The backup and the changes, or an error if the path is
+///   invalid, a reset key is locked or writing fails
+pub fn reset(path_or_section: Option<&str>) -> Result<ResetReport> {
+    if let Some(key_path) = path_or_section {
+        path::split(key_path)?;
+    }
+    let defaults = default_values();
+    let current = get_config()?;
+    let mut target = current.clone();
+    reset_value(&mut target, &defaults, path_or_section)?;
+    if ChangeSet::between(&current, &target).is_empty() {
+        return Ok(ResetReport::default());
+    }
+
+    let backup = save_backup(&backup_label()?)?;
+    let changes = modify_config(|config| {
+        let before = config.clone();
+        reset_value(config, &defaults, path_or_section)?;
+        Ok(ChangeSet::between(&before, config))
+    })?;
+    Ok(ResetReport {
+        backup: Some(backup),
+        changes,
+    })
+}
+
+fn reset_value(config: &mut Value, defaults: &Value, path_or_section: Option<&str>) -> Result<()> {
+    let Some(key_path) = path_or_section else {
+        let mut reset = defaults.clone();
+        if let (Some(layout), Some(root)) = (config.get(LAYOUT_KEY).cloned(), reset.as_table_mut())
+        {
+            root.insert(LAYOUT_KEY.to_string(), layout);
+        }
+        *config = reset;
+        return Ok(());
+    };
+    match path::lookup(defaults, key_path) {
+        Some(default) => path::insert(config, key_path, default.clone()).map(|_| ()),
+        None if key_path == LAYOUT_KEY => Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "'{}' selects the storage layout; use the layout functions to change it",
+                LAYOUT_KEY
+            ),
+        )),
+        None => {
+            path::remove(config, key_path);
+            Ok(())
+        }
+    }
+}
+
+/// Returns a snapshot label for the backup that no existing snapshot uses.
+fn backup_label() -> Result<String> {
+    let seconds = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |elapsed| elapsed.as_secs());
+    let base = format!("{}{}", BACKUP_PREFIX, seconds);
+    let taken: Vec<String> = list_snapshots()?.into_iter().map(|s| s.label).collect();
+    let mut label = base.clone();
+    let mut n = 2;
+    while taken.contains(&label) {
+        label = format!("{}-{}", base, n);
+        n += 1;
+    }
+    Ok(label)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config_value, update_config_value};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_reset_key_section_and_file() {
+        let _dir = TempConfigDir::new().unwrap();
+        let default_model = path::lookup(&default_values(), "ai.model")
+            .cloned()
+            .unwrap();
+        update_config_value("ai", "model", Value::from("custom-model")).unwrap();
+
+        let report = reset(Some("ai.model")).unwrap();
+        assert_eq!(report.changes.paths(), vec!["ai.model"]);
+        assert_eq!(get_config_value("ai", "model").unwrap(), default_model);
+        let backup = report.backup.unwrap();
+        assert!(list_snapshots().unwrap().contains(&backup));
+        assert!(fs_contains(&backup, "custom-model"));
+
+        let again = reset(Some("ai")).unwrap();
+        assert!(again.changes.is_empty() && again.backup.is_none());
+
+        update_config_value("ai", "model", Value::from("other")).unwrap();
+        let whole = reset(None).unwrap();
+        assert_eq!(whole.changes.paths(), vec!["ai.model"]);
+        assert_ne!(whole.backup.unwrap().label, backup.label);
+        assert!(reset(Some(LAYOUT_KEY)).is_err());
+    }
+
+    fn fs_contains(backup: &SnapshotInfo, text: &str) -> bool {
+        std::fs::read_to_string(&backup.path)
+            .unwrap()
+            .contains(text)
+    }
+}
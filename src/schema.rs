@@ -0,0 +1,239 @@
+use std::{io::Result, time::Duration};
+use toml::{Value, map};
+
+use crate::cache::load_cached;
+use crate::defaults::{
+    DefaultDocument, builtin_defaults, default_values, render_with_default_comments,
+};
+use crate::path;
+use crate::secret::is_secret;
+
+/// What the crate knows about one configuration key.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KeySchema {
+    /// The dotted key path
+    pub path: String,
+    /// The built-in default value
+    pub default: Value,
+    /// A human-readable description
+    pub description: String,
+    /// Whether the value is classified as a secret
+    pub secret: bool,
+    /// The values the key accepts; empty if it isn't restricted
+    pub allowed: Vec<Value>,
+    /// A realistic value to show in sample files, if one is declared
+    pub example: Option<Value>,
+    /// The minimum time between two changes, if the key is rate-limited
+    pub cooldown: Option<Duration>,
+}
+
+impl KeySchema {
+    /// Returns the value a sample file shows for this key.
+    ///
+    /// That is the default unless it is empty, then the declared example, then the first
+    /// allowed value, and finally a `<key.path>` placeholder of the default's type.
+    pub fn sample_value(&self) -> Value {
+        let empty = match &self.default {
+            Value::String(s) => s.is_empty(),
+            Value::Array(items) => items.is_empty(),
+            Value::Table(table) => table.is_empty(),
+            _ => false,
+        };
+        if !empty {
+            return self.default.clone();
+        }
+        self.example
+            .clone()
+            .or_else(|| self.allowed.first().cloned())
+            .unwrap_or_else(|| match &self.default {
+                Value::Array(_) => Value::Array(vec![Value::from(self.placeholder())]),
+                Value::Table(_) => Value::Table(map::Map::new()),
+                _ => Value::from(self.placeholder()),
+            })
+    }
+
+    fn placeholder(&self) -> String {
+        format!("<{}>", self.path)
+    }
+}
+
+/// What a generated sample configuration puts in secret keys.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SecretsPolicy {
+    /// A `<key.path>` placeholder the user is meant to replace
+    #[default]
+    Placeholder,
+    /// An empty string
+    Empty,
+    /// Secret keys are left out
+    Omit,
+}
+
+/// Returns the schema of every key known to the crate, in document order.
+///
+/// # Returns
+///
+/// * `Vec<KeySchema>` - One entry per known key
+pub fn schema() -> Vec<KeySchema> {
+    let defaults = default_values();
+    let declared = builtin_defaults();
+    path::leaf_paths(&defaults)
+        .into_iter()
+        .map(|key_path| {
+            let key = declared.keys().iter().find(|key| key.path == key_path);
+            KeySchema {
+                default: path::lookup(&defaults, &key_path)
+                    .cloned()
+                    .expect("leaf paths exist"),
+                description: key.map(|key| key.comment.clone()).unwrap_or_default(),
+                secret: is_secret(&key_path) || key.is_some_and(|key| key.secret),
+                allowed: key.map(|key| key.allowed.clone()).unwrap_or_default(),
+                example: key.and_then(|key| key.example.clone()),
+                cooldown: key.and_then(|key| key.cooldown),
+                path: key_path,
+            }
+        })
+        .collect()
+}
+
+/// Returns the schema of a single key.
+pub fn key_schema(key_path: &str) -> Option<KeySchema> {
+    schema().into_iter().find(|key| key.path == key_path)
+}
+
+/// Returns whether a dotted key path is defined by the schema.
+pub fn is_known_key(key_path: &str) -> bool {
+    key_schema(key_path).is_some()
+}
+
+/// Returns the schema like [`schema`], from the on-disk cache when it is up to date.
+///
+/// Applications with large registered catalogs call this at startup instead of
+/// [`schema`], see [`crate::cache::load_cached`] for when the cache is rebuilt.
+///
+/// # Arguments
+///
+/// * `version` - Identifies the registered defaults, e.g. the application's version
+///
+/// # Returns
+///
+/// * `Result<Vec<KeySchema>>` - One entry per known key
+pub fn cached_schema(version: &str) -> Result<Vec<KeySchema>> {
+    let cached = load_cached("schema", version, || Ok(schema_to_value(&schema())))?;
+    Ok(schema_from_value(&cached).unwrap_or_else(schema))
+}
+
+fn schema_to_value(keys: &[KeySchema]) -> Value {
+    let entries = keys
+        .iter()
+        .map(|key| {
+            let mut entry = map::Map::new();
+            entry.insert("path".to_string(), Value::from(key.path.as_str()));
+            entry.insert("default".to_string(), key.default.clone());
+            entry.insert(
+                "description".to_string(),
+                Value::from(key.description.as_str()),
+            );
+            entry.insert("secret".to_string(), Value::from(key.secret));
+            entry.insert("allowed".to_string(), Value::Array(key.allowed.clone()));
+            if let Some(example) = &key.example {
+                entry.insert("example".to_string(), example.clone());
+            }
+            if let Some(cooldown) = key.cooldown {
+                entry.insert(
+                    "cooldown_ms".to_string(),
+                    Value::Integer(cooldown.as_millis() as i64),
+                );
+            }
+            Value::Table(entry)
+        })
+        .collect();
+    Value::Array(entries)
+}
+
+fn schema_from_value(value: &Value) -> Option<Vec<KeySchema>> {
+    value
+        .as_array()?
+        .iter()
+        .map(|entry| {
+            Some(KeySchema {
+                path: entry.get("path")?.as_str()?.to_string(),
+                default: entry.get("default")?.clone(),
+                description: entry.get("description")?.as_str()?.to_string(),
+                secret: entry.get("secret")?.as_bool()?,
+                allowed: entry.get("allowed")?.as_array()?.clone(),
+                example: entry.get("example").cloned(),
+                cooldown: match entry.get("cooldown_ms") {
+                    None => None,
+                    Some(ms) => Some(Duration::from_millis(u64::try_from(ms.as_integer()?).ok()?)),
+                },
+            })
+        })
+        .collect()
+}
+
+/// Generates a fully populated example configuration from the schema.
+///
+/// Every known key gets a value, see [`KeySchema::sample_value`], and the keys carry the
+/// comments of the default document. The output is deterministic, so it suits
+/// documentation, test fixtures and `gim config init --sample`.
+///
+/// # Arguments
+///
+/// * `secrets` - What to put in secret keys; real secrets are never written
+///
+/// # Returns
+///
+/// * `DefaultDocument` - The commented TOML text and its parsed Value
+pub fn generate_sample_config(secrets: SecretsPolicy) -> DefaultDocument {
+    let mut value = Value::Table(map::Map::new());
+    for key in schema() {
+        let sample = match (key.secret, secrets) {
+            (true, SecretsPolicy::Omit) => continue,
+            (true, SecretsPolicy::Empty) => Value::from(""),
+            (true, SecretsPolicy::Placeholder) => Value::from(key.placeholder()),
+            (false, _) => key.sample_value(),
+        };
+        path::insert(&mut value, &key.path, sample).expect("schema paths are valid");
+    }
+    DefaultDocument {
+        text: render_with_default_comments(&value),
+        value,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_schema_covers_defaults() {
+        let model = key_schema("ai.model").unwrap();
+        assert_eq!(model.default, Value::from(""));
+        assert!(!model.description.is_empty());
+        assert!(key_schema("ai.apikey").unwrap().secret);
+        assert!(!is_known_key("ai.temperature"));
+    }
+
+    #[test]
+    fn test_cached_schema_matches_schema() {
+        let _dir = crate::testing::TempConfigDir::new().unwrap();
+        assert_eq!(cached_schema("test").unwrap(), schema());
+        assert_eq!(cached_schema("test").unwrap(), schema());
+    }
+
+    #[test]
+    fn test_generate_sample_config_populates_every_key() {
+        let sample = generate_sample_config(SecretsPolicy::Placeholder);
+        assert_eq!(path::leaf_paths(&sample.value).len(), schema().len());
+        assert_eq!(sample.value["ai"]["model"].as_str(), Some("gpt-4o"));
+        assert_eq!(sample.value["ai"]["apikey"].as_str(), Some("<ai.apikey>"));
+        assert_eq!(sample.value["ai"]["language"].as_str(), Some("English"));
+        assert!(sample.text.contains("# API key of the provider"));
+        assert_eq!(toml::from_str::<Value>(&sample.text).unwrap(), sample.value);
+
+        let omitted = generate_sample_config(SecretsPolicy::Omit);
+        assert!(path::lookup(&omitted.value, "ai.apikey").is_none());
+    }
+}
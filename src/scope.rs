@@ -0,0 +1,181 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+};
+use toml::Value;
+
+use crate::change::ChangeSet;
+use crate::config::{get_value_fast, modify_config};
+use crate::path;
+
+/// The error payload of a write through a [`ConfigHandle`] outside its scopes.
+///
+/// It is returned inside an `io::Error` of kind `PermissionDenied`; use
+/// `error.get_ref().and_then(|e| e.downcast_ref::<OutOfScope>())` to inspect it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub struct OutOfScope {
+    /// The key the write tried to change
+    pub path: String,
+    /// The prefixes the handle may write
+    pub scopes: Vec<String>,
+}
+
+impl fmt::Display for OutOfScope {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "Key '{}' is outside the writable scopes [{}]",
+            self.path,
+            self.scopes.join(", ")
+        )
+    }
+}
+
+impl std::error::Error for OutOfScope {}
+
+/// A view of the configuration for one component of an application, e.g. a plugin.
+///
+/// The handle reads every key but writes only below the key paths it was granted, so a
+/// plugin granted `plugin.foo` can't rewrite `ai.apikey`. Writes are checked key by key
+/// before anything is saved; one key out of scope rejects the whole write.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ConfigHandle {
+    scopes: Vec<String>,
+}
+
+impl ConfigHandle {
+    /// Creates a handle writing only below `scopes`.
+    ///
+    /// # Arguments
+    ///
+    /// * `scopes` - Dotted key paths such as `"plugin.foo"`; each covers itself and every
+    ///   key below it
+    ///
+    /// # Returns
+    ///
+    /// * `Result<ConfigHandle>` - The handle, or an error if a scope isn't a valid key path
+    pub fn scoped<S: AsRef<str>>(scopes: impl IntoIterator<Item = S>) -> Result<ConfigHandle> {
+        let scopes = scopes
+            .into_iter()
+            .map(|scope| {
+                let scope = scope.as_ref();
+                path::split(scope)?;
+                Ok(scope.to_string())
+            })
+            .collect::<Result<Vec<String>>>()?;
+        Ok(ConfigHandle { scopes })
+    }
+
+    /// Returns the key paths the handle may write below.
+    pub fn scopes(&self) -> &[String] {
+        &self.scopes
+    }
+
+    /// Returns whether the handle may write `key_path`.
+    pub fn can_write(&self, key_path: &str) -> bool {
+        self.scopes.iter().any(|scope| {
+            key_path == scope
+                || key_path
+                    .strip_prefix(scope.as_str())
+                    .is_some_and(|rest| rest.starts_with('.'))
+        })
+    }
+
+    /// Returns a value by dotted key path, like [`get_value_fast`]; reads aren't scoped.
+    pub fn get(&self, key_path: &str) -> Result<Value> {
+        get_value_fast(key_path)
+    }
+
+    /// Sets a value by dotted key path, creating missing tables below the scope.
+    ///
+    /// # Arguments
+    ///
+    /// * `key_path` - The dotted key path, e.g. `"plugin.foo.enabled"`
+    /// * `value` - The new value
+    ///
+    /// # Returns
+    ///
+    /// * `Result<()>` - Success, or a `PermissionDenied` error carrying [`OutOfScope`] if
+    ///   the key is outside the handle's scopes
+    pub fn set(&self, key_path: &str, value: Value) -> Result<()> {
+        self.modify(|config| path::insert(config, key_path, value).map(|_| ()))
+    }
+
+    /// Removes a value by dotted key path, returning it if it existed.
+    pub fn remove(&self, key_path: &str) -> Result<Option<Value>> {
+        self.modify(|config| Ok(path::remove(config, key_path)))
+    }
+
+    /// Modifies the configuration in a locked read-modify-write cycle.
+    ///
+    /// # Arguments
+    ///
+    /// * `f` - Modifies the configuration in place
+    ///
+    /// # Returns
+    ///
+    /// * `Result<T>` - Whatever `f` returned, or a `PermissionDenied` error carrying
+    ///   [`OutOfScope`] if `f` changed a key outside the handle's scopes; nothing is
+    ///   written then
+    pub fn modify<T>(&self, f: impl FnOnce(&mut Value) -> Result<T>) -> Result<T> {
+        modify_config(|config| {
+            let before = config.clone();
+            let result = f(config)?;
+            self.check(&before, config)?;
+            Ok(result)
+        })
+    }
+
+    fn check(&self, before: &Value, after: &Value) -> Result<()> {
+        match ChangeSet::between(before, after)
+            .changes
+            .into_iter()
+            .find(|change| !self.can_write(&change.path))
+        {
+            Some(change) => Err(Error::new(
+                ErrorKind::PermissionDenied,
+                OutOfScope {
+                    path: change.path,
+                    scopes: self.scopes.clone(),
+                },
+            )),
+            None => Ok(()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_scoped_handle_only_writes_its_scopes() {
+        let _dir = TempConfigDir::new().unwrap();
+        let plugin = ConfigHandle::scoped(["plugin.foo"]).unwrap();
+        plugin.set("plugin.foo.enabled", Value::from(true)).unwrap();
+        assert_eq!(plugin.get("plugin.foo.enabled").unwrap(), Value::from(true));
+        assert_eq!(plugin.get("ai.language").unwrap(), Value::from("English"));
+
+        let error = plugin
+            .set("ai.apikey", Value::from("sk-stolen"))
+            .unwrap_err();
+        assert_eq!(error.kind(), ErrorKind::PermissionDenied);
+        let out_of_scope = error
+            .get_ref()
+            .and_then(|e| e.downcast_ref::<OutOfScope>())
+            .unwrap();
+        assert_eq!(out_of_scope.path, "ai.apikey");
+        assert!(!plugin.can_write("plugin.foobar"));
+        assert!(
+            plugin
+                .modify(|config| {
+                    path::insert(config, "plugin.foo.count", Value::Integer(1))?;
+                    path::insert(config, "plugin.bar.count", Value::Integer(1))
+                })
+                .is_err()
+        );
+        assert!(get_value_fast("plugin.foo.count").is_err());
+    }
+}
@@ -0,0 +1,328 @@
+use std::{
+    fmt,
+    io::{Error, ErrorKind, Result},
+    sync::RwLock,
+};
+use toml::Value;
+use zeroize::Zeroize;
+
+use crate::config::{get_value_fast, modify_config};
+use crate::normalize::normalize_string;
+use crate::path;
+
+/// The value shown in place of secrets wherever they would otherwise be displayed or shared.
+pub const SECRET_PLACEHOLDER: &str = "<redacted>";
+
+/// A secret value that is wiped from memory when dropped and never printed.
+///
+/// `Debug` shows [`SECRET_PLACEHOLDER`] and there is no `Display`, so a secret only ends
+/// up in a log or panic message if the code asks for it with [`Secret::expose`].
+///
+/// Only the copies handed out as a `Secret` are protected: those of [`get_secret`],
+/// [`AiConfig::apikey`](crate::ai::AiConfig::apikey) and
+/// [`get_valid_session_token`](crate::session_token::get_valid_session_token). The cached
+/// configuration and the plain readers such as
+/// [`get_config_value`](crate::config::get_config_value), [`get_value_fast`] and
+/// [`Config::get_str`](crate::config::Config::get_str) hold secrets as ordinary `Value`s,
+/// which aren't wiped; read secret keys through [`get_secret`] instead.
+#[derive(Clone, Default, PartialEq, Eq)]
+pub struct Secret<T: Zeroize>(T);
+
+impl<T: Zeroize> Secret<T> {
+    /// Wraps a secret value.
+    pub fn new(value: T) -> Secret<T> {
+        Secret(value)
+    }
+
+    /// Returns the secret value, e.g. to put it into an `Authorization` header.
+    pub fn expose(&self) -> &T {
+        &self.0
+    }
+}
+
+impl<T: Zeroize> From<T> for Secret<T> {
+    fn from(value: T) -> Secret<T> {
+        Secret(value)
+    }
+}
+
+impl<T: Zeroize> fmt::Debug for Secret<T> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "Secret({:?})", SECRET_PLACEHOLDER)
+    }
+}
+
+impl<T: Zeroize> Drop for Secret<T> {
+    fn drop(&mut self) {
+        self.0.zeroize();
+    }
+}
+
+/// Patterns that are always classified as secrets.
+const BUILTIN_PATTERNS: &[&str] = &[
+    "ai.apikey",
+    "**.apikey",
+    "**.api_key",
+    "**.token",
+    "**.secret",
+    "**.password",
+];
+
+/// Patterns registered by the embedding application.
+static REGISTERED_PATTERNS: RwLock<Vec<String>> = RwLock::new(Vec::new());
+
+/// Classifies every key matching `pattern` as a secret.
+///
+/// Redaction, shareable export and change reporting all consult this one registry.
+/// Patterns are dotted key paths where `*` matches exactly one segment and `**` matches
+/// any number of segments, e.g. `"accounts.*.apikey"` or `"**.token"`. Matching ignores
+/// ASCII case, so `"**.token"` also covers `auth.Token`.
+///
+/// # Arguments
+///
+/// * `pattern` - The key path or glob pattern to classify
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an `InvalidInput` error if the pattern is malformed
+pub fn mark_secret(pattern: &str) -> Result<()> {
+    validate_pattern(pattern)?;
+    let mut patterns = REGISTERED_PATTERNS
+        .write()
+        .unwrap_or_else(|e| e.into_inner());
+    if !patterns.iter().any(|p| p == pattern) {
+        patterns.push(pattern.to_string());
+    }
+    Ok(())
+}
+
+/// Returns every pattern currently classifying keys as secrets, built-ins first.
+pub fn secret_patterns() -> Vec<String> {
+    let registered = REGISTERED_PATTERNS
+        .read()
+        .unwrap_or_else(|e| e.into_inner());
+    BUILTIN_PATTERNS
+        .iter()
+        .map(|p| p.to_string())
+        .chain(registered.iter().cloned())
+        .collect()
+}
+
+/// Returns whether the value at a dotted key path is classified as a secret.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path, e.g. `"ai.apikey"`
+///
+/// # Returns
+///
+/// * `bool` - `true` if the value must never be displayed or shared in plain text
+pub fn is_secret(key_path: &str) -> bool {
+    let key_path = key_path.to_ascii_lowercase();
+    let segments: Vec<&str> = key_path.split('.').collect();
+    if BUILTIN_PATTERNS
+        .iter()
+        .any(|p| matches_pattern(p, &segments))
+    {
+        return true;
+    }
+    REGISTERED_PATTERNS
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .any(|p| matches_pattern(p, &segments))
+}
+
+/// Returns a copy of `value`, found at `key_path`, with every secret in it replaced by
+/// [`SECRET_PLACEHOLDER`]; the tables of an array are checked with their keys below the
+/// array's path, e.g. `ai.endpoints.apikey`.
+pub(crate) fn mask_secrets(value: &Value, key_path: &str) -> Value {
+    if is_secret(key_path) {
+        return Value::from(SECRET_PLACEHOLDER);
+    }
+    match value {
+        Value::Table(table) => Value::Table(
+            table
+                .iter()
+                .map(|(key, value)| {
+                    let path = format!("{}.{}", key_path, key);
+                    (key.clone(), mask_secrets(value, &path))
+                })
+                .collect(),
+        ),
+        Value::Array(items) => Value::Array(
+            items
+                .iter()
+                .map(|item| mask_secrets(item, key_path))
+                .collect(),
+        ),
+        _ => value.clone(),
+    }
+}
+
+/// Returns the string value of a key classified as secret.
+///
+/// Resolved like [`get_value_fast`], including overrides and `env:` references of
+/// profiles. The returned copy is wiped from memory when dropped.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted path of a key classified as secret, e.g. `"ai.apikey"`
+///
+/// # Returns
+///
+/// * `Result<Secret<String>>` - The secret, or an `InvalidInput` error if the key isn't a
+///   secret, `InvalidData` if its value isn't a string, or `NotFound` if it is missing
+pub fn get_secret(key_path: &str) -> Result<Secret<String>> {
+    if !is_secret(key_path) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Key '{}' is not classified as a secret", key_path),
+        ));
+    }
+    match get_value_fast(key_path)? {
+        Value::String(value) => Ok(Secret::new(value)),
+        _ => Err(Error::new(
+            ErrorKind::InvalidData,
+            format!("Secret '{}' is not a string", key_path),
+        )),
+    }
+}
+
+/// Asks for a secret through `prompt` and writes it, so it never appears in argv or shell history.
+///
+/// `prompt` receives the message to show and is expected not to echo the input, e.g. a
+/// terminal password prompt. Surrounding whitespace and invisible characters picked up
+/// while pasting are removed before the value is written.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted path of a key classified as secret, e.g. `"ai.apikey"`
+/// * `prompt` - Reads the value without echoing it
+///
+/// # Returns
+///
+/// * `Result<()>` - Success, or an `InvalidInput` error if the key isn't a secret or the
+///   entered value is empty
+pub fn set_secret_interactive(
+    key_path: &str,
+    prompt: impl FnOnce(&str) -> Result<String>,
+) -> Result<()> {
+    if !is_secret(key_path) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Key '{}' is not classified as a secret", key_path),
+        ));
+    }
+    let entered = Secret::new(prompt(&format!("{}: ", key_path))?);
+    let value = normalize_string(entered.expose());
+    if value.is_empty() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("No value entered for '{}'", key_path),
+        ));
+    }
+    modify_config(|config| path::insert(config, key_path, Value::from(value)).map(|_| ()))
+}
+
+fn validate_pattern(pattern: &str) -> Result<()> {
+    let valid = !pattern.is_empty()
+        && pattern.split('.').all(|segment| {
+            !segment.is_empty() && (segment == "*" || segment == "**" || !segment.contains('*'))
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("Invalid secret pattern '{}'", pattern),
+        ))
+    }
+}
+
+/// Matches `pattern` against lowercased `segments`.
+fn matches_pattern(pattern: &str, segments: &[&str]) -> bool {
+    let pattern = pattern.to_ascii_lowercase();
+    let pattern: Vec<&str> = pattern.split('.').collect();
+    matches_segments(&pattern, segments)
+}
+
+fn matches_segments(pattern: &[&str], segments: &[&str]) -> bool {
+    match (pattern.first(), segments.first()) {
+        (None, None) => true,
+        (Some(&"**"), _) => {
+            matches_segments(&pattern[1..], segments)
+                || (!segments.is_empty() && matches_segments(pattern, &segments[1..]))
+        }
+        (Some(&p), Some(&s)) if p == "*" || p == s => {
+            matches_segments(&pattern[1..], &segments[1..])
+        }
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_secret() {
+        assert!(is_secret("ai.apikey"));
+        assert!(is_secret("accounts.work.api_key"));
+        assert!(!is_secret("ai.model"));
+    }
+
+    #[test]
+    fn test_is_secret_ignores_case() {
+        assert!(is_secret("ai.ApiKey"));
+        assert!(is_secret("ai.APIKEY"));
+        assert!(is_secret("deploy.API_KEY"));
+        assert!(is_secret("Accounts.Work.Token"));
+        assert!(!is_secret("AI.Model"));
+    }
+
+    /// Removes the patterns a test registered, even if it panics.
+    struct Registered(&'static [&'static str]);
+
+    impl Drop for Registered {
+        fn drop(&mut self) {
+            REGISTERED_PATTERNS
+                .write()
+                .unwrap_or_else(|e| e.into_inner())
+                .retain(|p| !self.0.contains(&p.as_str()));
+        }
+    }
+
+    #[test]
+    fn test_mark_secret_globs() {
+        let _registered = Registered(&["plugins.*.credentials", "vault.**"]);
+        mark_secret("plugins.*.credentials").unwrap();
+        assert!(is_secret("plugins.jira.credentials"));
+        assert!(!is_secret("plugins.jira.nested.credentials"));
+
+        mark_secret("vault.**").unwrap();
+        assert!(is_secret("vault.a.b.c"));
+        assert!(mark_secret("bad.pat*tern").is_err());
+        assert!(secret_patterns().contains(&"vault.**".to_string()));
+    }
+
+    #[test]
+    fn test_set_secret_interactive() {
+        let _dir = crate::testing::TempConfigDir::new().unwrap();
+        set_secret_interactive("ai.apikey", |message| {
+            assert_eq!(message, "ai.apikey: ");
+            Ok("\u{feff}sk-123\n".to_string())
+        })
+        .unwrap();
+        assert_eq!(
+            crate::config::get_value_fast("ai.apikey").unwrap().as_str(),
+            Some("sk-123")
+        );
+        let secret = get_secret("ai.apikey").unwrap();
+        assert_eq!(secret.expose(), "sk-123");
+        assert_eq!(format!("{:?}", secret), "Secret(\"<redacted>\")");
+        assert!(get_secret("ai.model").is_err());
+        assert!(set_secret_interactive("ai.model", |_| Ok("m".to_string())).is_err());
+        assert!(set_secret_interactive("ai.apikey", |_| Ok(" ".to_string())).is_err());
+    }
+}
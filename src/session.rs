@@ -0,0 +1,127 @@
+use std::{
+    collections::BTreeMap,
+    io::Result,
+    sync::{Arc, RwLock},
+};
+use toml::Value;
+
+use crate::path;
+
+/// The overrides of this process, keyed by dotted key path.
+static OVERRIDES: RwLock<BTreeMap<String, Value>> = RwLock::new(BTreeMap::new());
+
+/// Overrides a setting for the rest of this process, e.g. for `gim commit --model X`.
+///
+/// Reads through [`get_value_fast`](crate::config::get_value_fast),
+/// [`get_many`](crate::config::get_many), [`get_config_value`](crate::config::get_config_value)
+/// and [`Config`](crate::config::Config) return the override ahead of temporary overrides,
+/// the `GIM_ENV` profile and the file. Nothing is written: the file, other processes and
+/// [`get_config`](crate::config::get_config) keep seeing the stored value. Overriding a
+/// table overrides every key below it.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path, e.g. `"ai.model"`
+/// * `value` - The value reads return
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if the path is invalid
+pub fn override_for_session(key_path: &str, value: Value) -> Result<()> {
+    path::split(key_path)?;
+    OVERRIDES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .insert(key_path.to_string(), value);
+    Ok(())
+}
+
+/// Removes the session override of a key.
+///
+/// # Returns
+///
+/// * `Option<Value>` - The value the override returned, `None` if there was none
+pub fn clear_session_override(key_path: &str) -> Option<Value> {
+    OVERRIDES
+        .write()
+        .unwrap_or_else(|e| e.into_inner())
+        .remove(key_path)
+}
+
+/// Removes every session override.
+pub fn clear_session_overrides() {
+    OVERRIDES.write().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// Lists the session overrides, sorted by key path.
+pub fn session_overrides() -> Vec<(String, Value)> {
+    OVERRIDES
+        .read()
+        .unwrap_or_else(|e| e.into_inner())
+        .iter()
+        .map(|(key_path, value)| (key_path.clone(), value.clone()))
+        .collect()
+}
+
+/// Returns the session override of `key_path`, looking inside an overridden table.
+///
+/// `Some(None)` means a table above `key_path` is overridden and doesn't contain it.
+pub(crate) fn session_override(key_path: &str) -> Option<Option<Value>> {
+    let overrides = OVERRIDES.read().unwrap_or_else(|e| e.into_inner());
+    if let Some(value) = overrides.get(key_path) {
+        return Some(Some(value.clone()));
+    }
+    overrides.iter().find_map(|(overridden, value)| {
+        let rest = key_path
+            .strip_prefix(overridden.as_str())?
+            .strip_prefix('.')?;
+        Some(path::lookup(value, rest).cloned())
+    })
+}
+
+/// Applies the session overrides to `document`, returning the overridden paths.
+pub(crate) fn apply_session_overrides(document: &mut Arc<Value>) -> Result<Vec<String>> {
+    let overrides = OVERRIDES.read().unwrap_or_else(|e| e.into_inner());
+    if overrides.is_empty() {
+        return Ok(Vec::new());
+    }
+    let merged = Arc::make_mut(document);
+    for (key_path, value) in overrides.iter() {
+        path::insert(merged, key_path, value.clone())?;
+    }
+    Ok(overrides.keys().cloned().collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, get_config, get_value_fast};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_session_override_is_never_persisted() {
+        let _dir = TempConfigDir::new().unwrap();
+        override_for_session("session_test.model", Value::from("gpt-4o")).unwrap();
+        override_for_session("session_table", toml::from_str("a = 1").unwrap()).unwrap();
+
+        assert_eq!(
+            get_value_fast("session_test.model").unwrap().as_str(),
+            Some("gpt-4o")
+        );
+        assert_eq!(
+            get_value_fast("session_table.a").unwrap().as_integer(),
+            Some(1)
+        );
+        assert!(get_value_fast("session_table.b").is_err());
+        let config = Config::load().unwrap();
+        assert_eq!(config.get_str("session_test.model"), Some("gpt-4o"));
+        assert!(get_config().unwrap().get("session_test").is_none());
+
+        assert_eq!(
+            clear_session_override("session_test.model"),
+            Some(Value::from("gpt-4o"))
+        );
+        clear_session_override("session_table");
+        assert!(get_value_fast("session_test.model").is_err());
+    }
+}
@@ -0,0 +1,110 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    time::{Duration, UNIX_EPOCH},
+};
+use toml::{Value, map};
+
+use crate::clock::{Clock, SystemClock};
+use crate::path;
+use crate::secret::Secret;
+use crate::state::{modify_state, read_state};
+
+/// Where the state file keeps the session token, with `value` and `expires_at` in Unix
+/// seconds.
+pub const SESSION_TOKEN_KEY: &str = "ai.session";
+
+/// Stores a short-lived API token, e.g. one exchanged for the long-lived key at login.
+///
+/// The token lives in the state file rather than `config.toml`, so refreshing it never
+/// rewrites the user's configuration or shows up in a synced dotfiles repository.
+///
+/// # Arguments
+///
+/// * `token` - The token
+/// * `ttl` - How long the token stays valid from now
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if the state file can't be written
+pub fn store_session_token(token: &str, ttl: Duration) -> Result<()> {
+    store_at(token, ttl, &SystemClock)
+}
+
+/// Returns the stored session token if it hasn't expired.
+///
+/// # Returns
+///
+/// * `Result<Option<Secret<String>>>` - The token, `None` if none is stored or it expired,
+///   or an error if the state file can't be read
+pub fn get_valid_session_token() -> Result<Option<Secret<String>>> {
+    valid_at(&SystemClock)
+}
+
+/// Forgets the session token, e.g. at logout.
+pub fn clear_session_token() -> Result<()> {
+    modify_state(|state| {
+        path::remove(state, SESSION_TOKEN_KEY);
+        Ok(())
+    })
+}
+
+fn store_at(token: &str, ttl: Duration, clock: &dyn Clock) -> Result<()> {
+    let expires_at = unix_seconds(clock)?.saturating_add(ttl.as_secs() as i64);
+    let mut session = map::Map::new();
+    session.insert("value".to_string(), Value::from(token));
+    session.insert("expires_at".to_string(), Value::Integer(expires_at));
+    modify_state(|state| path::insert(state, SESSION_TOKEN_KEY, Value::Table(session)).map(|_| ()))
+}
+
+fn valid_at(clock: &dyn Clock) -> Result<Option<Secret<String>>> {
+    let state = read_state()?;
+    let Some(session) = path::lookup(&state, SESSION_TOKEN_KEY) else {
+        return Ok(None);
+    };
+    let token = session.get("value").and_then(Value::as_str);
+    let expires_at = session.get("expires_at").and_then(Value::as_integer);
+    Ok(match (token, expires_at) {
+        (Some(token), Some(expires_at)) if unix_seconds(clock)? < expires_at => {
+            Some(Secret::new(token.to_string()))
+        }
+        _ => None,
+    })
+}
+
+fn unix_seconds(clock: &dyn Clock) -> Result<i64> {
+    clock
+        .now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs() as i64)
+        .map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::config::get_config_file;
+    use crate::testing::TempConfigDir;
+    use std::time::SystemTime;
+
+    #[test]
+    fn test_session_token_expires() {
+        let _dir = TempConfigDir::new().unwrap();
+        assert!(get_valid_session_token().unwrap().is_none());
+
+        let clock = MockClock::new(SystemTime::now());
+        store_at("tok-123", Duration::from_secs(60), &clock).unwrap();
+        assert_eq!(
+            valid_at(&clock).unwrap().map(|t| t.expose().clone()),
+            Some("tok-123".to_string())
+        );
+        assert!(!get_config_file().unwrap().exists());
+
+        clock.advance(Duration::from_secs(61));
+        assert!(valid_at(&clock).unwrap().is_none());
+        store_session_token("tok-456", Duration::from_secs(60)).unwrap();
+        assert!(get_valid_session_token().unwrap().is_some());
+        clear_session_token().unwrap();
+        assert!(get_valid_session_token().unwrap().is_none());
+    }
+}
@@ -0,0 +1,252 @@
+use std::{
+    fmt,
+    io::Result,
+    path::{Path, PathBuf},
+};
+use toml::Value;
+
+use crate::config::{get_config, get_config_file};
+use crate::layout::{active_section_files, document_files, replaced_sections};
+use crate::lenient::{FixKind, applied_fixes};
+use crate::path;
+use crate::profile::{PROFILES_SECTION, current_env};
+use crate::secret::{SECRET_PLACEHOLDER, is_secret};
+use crate::temporary::list_temporary_overrides;
+
+/// Where a definition of a configuration value lives.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValueSource {
+    /// A configuration file, with the line if it is known
+    File {
+        /// The file
+        file: PathBuf,
+        /// The 1-based line of the definition
+        line: Option<usize>,
+    },
+    /// The overrides of the profile selected by `GIM_ENV`
+    Profile(String),
+    /// An active temporary override
+    Temporary,
+}
+
+impl fmt::Display for ValueSource {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ValueSource::File {
+                file,
+                line: Some(line),
+            } => write!(f, "{}:{}", file.display(), line),
+            ValueSource::File { file, line: None } => write!(f, "{}", file.display()),
+            ValueSource::Profile(name) => write!(f, "profile '{}'", name),
+            ValueSource::Temporary => f.write_str("a temporary override"),
+        }
+    }
+}
+
+/// A definition that has no effect because another one takes precedence.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ShadowedValue {
+    /// The dotted key path
+    pub path: String,
+    /// The value that is ignored, secrets masked; `None` for a dropped duplicate key
+    pub value: Option<Value>,
+    /// Where the ignored definition lives
+    pub source: ValueSource,
+    /// Where the definition that wins lives
+    pub shadowed_by: ValueSource,
+}
+
+impl fmt::Display for ShadowedValue {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "'{}'", self.path)?;
+        if let Some(value) = &self.value {
+            write!(f, " = {}", value)?;
+        }
+        write!(f, " in {} is shadowed by {}", self.source, self.shadowed_by)
+    }
+}
+
+/// Lists every definition of the configuration that is shadowed by another one.
+///
+/// A value has no effect when a section file of the split layout replaces its table in
+/// `config.toml`, when the active `GIM_ENV` profile or a temporary override sets the
+/// same key, or when the key is set twice in one file and the lenient parser kept the
+/// last definition. This explains why editing such a value "did nothing".
+///
+/// # Returns
+///
+/// * `Result<Vec<ShadowedValue>>` - The shadowed definitions or an error if the
+///   configuration can't be read
+pub fn shadow_report() -> Result<Vec<ShadowedValue>> {
+    let config_file = get_config_file()?;
+    let config = get_config()?;
+    let sections = active_section_files(&config_file)?;
+    let file_of = |key_path: &str| {
+        let section = key_path.split('.').next().unwrap_or_default();
+        let file = sections
+            .iter()
+            .find(|(name, _)| name == section)
+            .map_or(config_file.as_path(), |(_, file)| file.as_path());
+        in_file(file)
+    };
+    let mut report = Vec::new();
+
+    let fixes = applied_fixes();
+    for file in document_files(&config_file)? {
+        let prefix = sections
+            .iter()
+            .find(|(_, section_file)| *section_file == file)
+            .map(|(name, _)| format!("{}.", name))
+            .unwrap_or_default();
+        for fix in fixes.get(&file).into_iter().flatten() {
+            if let FixKind::DuplicateKey { replaced_line } = fix.kind {
+                report.push(ShadowedValue {
+                    path: format!("{}{}", prefix, fix.path),
+                    value: None,
+                    source: ValueSource::File {
+                        file: file.clone(),
+                        line: Some(replaced_line),
+                    },
+                    shadowed_by: ValueSource::File {
+                        file: file.clone(),
+                        line: Some(fix.line),
+                    },
+                });
+            }
+        }
+    }
+
+    for (section, table, file) in replaced_sections(&config_file)? {
+        for leaf in path::leaf_paths(&table) {
+            let key_path = format!("{}.{}", section, leaf);
+            report.push(shadowed(
+                &key_path,
+                path::lookup(&table, &leaf),
+                in_file(&config_file),
+                in_file(&file),
+            ));
+        }
+    }
+
+    let profile = current_env().and_then(|name| {
+        let overrides = config.get(PROFILES_SECTION)?.get(&name)?;
+        Some((name, overrides))
+    });
+    if let Some((name, overrides)) = &profile {
+        for key_path in path::leaf_paths(overrides) {
+            if let Some(value) = path::lookup(&config, &key_path) {
+                report.push(shadowed(
+                    &key_path,
+                    Some(value),
+                    file_of(&key_path),
+                    ValueSource::Profile(name.clone()),
+                ));
+            }
+        }
+    }
+
+    for temporary in list_temporary_overrides()? {
+        let key_path = temporary.path.as_str();
+        if let Some((name, overrides)) = &profile
+            && let Some(value) = path::lookup(overrides, key_path)
+        {
+            report.push(shadowed(
+                key_path,
+                Some(value),
+                ValueSource::Profile(name.clone()),
+                ValueSource::Temporary,
+            ));
+        }
+        if let Some(value) = path::lookup(&config, key_path) {
+            report.push(shadowed(
+                key_path,
+                Some(value),
+                file_of(key_path),
+                ValueSource::Temporary,
+            ));
+        }
+    }
+    Ok(report)
+}
+
+fn in_file(file: &Path) -> ValueSource {
+    ValueSource::File {
+        file: file.to_path_buf(),
+        line: None,
+    }
+}
+
+fn shadowed(
+    key_path: &str,
+    value: Option<&Value>,
+    source: ValueSource,
+    shadowed_by: ValueSource,
+) -> ShadowedValue {
+    let value = if is_secret(key_path) {
+        value.map(|_| Value::from(SECRET_PLACEHOLDER))
+    } else {
+        value.cloned()
+    };
+    ShadowedValue {
+        path: key_path.to_string(),
+        value,
+        source,
+        shadowed_by,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempConfigDir;
+    use std::fs;
+
+    #[test]
+    fn test_shadow_report_lists_section_files_and_temporary_overrides() {
+        let dir = TempConfigDir::new().unwrap();
+        let config_file = dir.path().join("config.toml");
+        let section_file = dir.path().join("config.d").join("ai.toml");
+        fs::write(
+            &config_file,
+            "layout = \"split\"\n[ai]\nmodel = \"root\"\napikey = \"sk-old\"\n\
+             [temporary_overrides.\"ai.model\"]\nvalue = \"today\"\nuntil = 4102444800\n",
+        )
+        .unwrap();
+        fs::create_dir_all(section_file.parent().unwrap()).unwrap();
+        fs::write(&section_file, "model = \"file\"\n").unwrap();
+
+        let report = shadow_report().unwrap();
+        let summary: Vec<(&str, Option<&str>, &ValueSource)> = report
+            .iter()
+            .map(|s| {
+                (
+                    s.path.as_str(),
+                    s.value.as_ref().and_then(Value::as_str),
+                    &s.source,
+                )
+            })
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (
+                    "ai.apikey",
+                    Some(SECRET_PLACEHOLDER),
+                    &in_file(&config_file)
+                ),
+                ("ai.model", Some("root"), &in_file(&config_file)),
+                ("ai.model", Some("file"), &in_file(&section_file)),
+            ]
+        );
+        assert_eq!(report[2].shadowed_by, ValueSource::Temporary);
+        assert_eq!(
+            report[1].to_string(),
+            format!(
+                "'ai.model' = \"root\" in {} is shadowed by {}",
+                config_file.display(),
+                section_file.display()
+            )
+        );
+    }
+}
@@ -0,0 +1,77 @@
+use std::io::{Error, ErrorKind, Result};
+use toml::Value;
+
+use crate::config::get_value_fast;
+
+/// Returns a value formatted for shell command substitution, e.g. `MODEL=$(gim config get ai.model)`.
+///
+/// See [`to_shell_string`] for the format.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path, e.g. `"ai.model"`
+///
+/// # Returns
+///
+/// * `Result<String>` - The formatted value or an error if the key doesn't exist or is a table
+pub fn get_raw_for_shell(key_path: &str) -> Result<String> {
+    to_shell_string(&get_value_fast(key_path)?).map_err(|e| {
+        Error::new(
+            e.kind(),
+            format!("Key '{}' can't be printed for a shell: {}", key_path, e),
+        )
+    })
+}
+
+/// Serializes a value exactly once, without TOML quoting.
+///
+/// Strings are returned verbatim, numbers, booleans and datetimes in their TOML form and
+/// arrays one item per line, with nested arrays and tables as inline TOML. The result never
+/// ends with a newline the value doesn't contain, so callers decide how to terminate it.
+///
+/// # Arguments
+///
+/// * `value` - The value to format
+///
+/// # Returns
+///
+/// * `Result<String>` - The formatted value or an `InvalidInput` error for a table
+pub fn to_shell_string(value: &Value) -> Result<String> {
+    match value {
+        Value::String(s) => Ok(s.clone()),
+        Value::Array(items) => Ok(items
+            .iter()
+            .map(|item| match item {
+                Value::String(s) => s.clone(),
+                other => other.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\n")),
+        Value::Table(_) => Err(Error::new(
+            ErrorKind::InvalidInput,
+            "a table has no single value; use a key below it",
+        )),
+        other => Ok(other.to_string()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_shell_strings_are_unquoted() {
+        let config: Value = toml::from_str(
+            "s = 'gpt-4o'\nq = 'say \"hi\"'\nn = 5\nf = 0.5\nb = true\na = ['x', 2]\n[t]\nk = 1\n",
+        )
+        .unwrap();
+        let shell = |key: &str| to_shell_string(&config[key]);
+        assert_eq!(shell("s").unwrap(), "gpt-4o");
+        assert_eq!(shell("q").unwrap(), "say \"hi\"");
+        assert_eq!(shell("n").unwrap(), "5");
+        assert_eq!(shell("f").unwrap(), "0.5");
+        assert_eq!(shell("b").unwrap(), "true");
+        assert_eq!(shell("a").unwrap(), "x\n2");
+        assert_eq!(shell("t").unwrap_err().kind(), ErrorKind::InvalidInput);
+    }
+}
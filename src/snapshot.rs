@@ -0,0 +1,209 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+    time::SystemTime,
+};
+use toml::Value;
+
+use crate::config::{get_config, get_config_file, replace_config};
+use crate::directory::backups_dir;
+use crate::format::Format;
+use crate::layout;
+use crate::pipeline;
+use crate::storage::{FileLock, write_atomic};
+
+/// The label prefix of the backups [`crate::reset::reset`] takes, which
+/// [`crate::maintenance::run_maintenance`] prunes; other snapshots can't use it.
+pub const BACKUP_PREFIX: &str = "reset-";
+
+/// A named restore point of the configuration file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SnapshotInfo {
+    /// The label the snapshot was saved under
+    pub label: String,
+    /// When the snapshot was last written
+    pub created: SystemTime,
+    /// Where the snapshot is stored
+    pub path: PathBuf,
+}
+
+/// Returns the directory holding named snapshots, see [`backups_dir`].
+fn snapshots_dir() -> Result<PathBuf> {
+    backups_dir()
+}
+
+/// Returns the file a snapshot label is stored in, rejecting labels that aren't plain names.
+fn snapshot_file(label: &str) -> Result<PathBuf> {
+    let valid = !label.is_empty()
+        && !label.starts_with('.')
+        && label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'));
+    if !valid {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Invalid snapshot label '{}': use letters, digits, '-', '_' or '.'",
+                label
+            ),
+        ));
+    }
+    Ok(snapshots_dir()?.join(format!("{}.toml", label)))
+}
+
+/// Saves a full copy of the current configuration under `label`.
+///
+/// Snapshots are independent of any automatic backups and are kept until overwritten
+/// by a snapshot with the same label. With the split layout the section files are
+/// captured as well.
+///
+/// # Arguments
+///
+/// * `label` - The name of the restore point, e.g. `"known-good"`
+///
+/// # Returns
+///
+/// * `Result<SnapshotInfo>` - The stored snapshot, or an error such as `InvalidInput` for
+///   a label starting with [`BACKUP_PREFIX`]
+pub fn snapshot(label: &str) -> Result<SnapshotInfo> {
+    if label.starts_with(BACKUP_PREFIX) {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!(
+                "Snapshot labels starting with '{}' are reserved for reset backups",
+                BACKUP_PREFIX
+            ),
+        ));
+    }
+    save_snapshot(label)
+}
+
+/// Saves the backup that [`crate::reset::reset`] takes under `label`, which starts with
+/// [`BACKUP_PREFIX`].
+pub(crate) fn save_backup(label: &str) -> Result<SnapshotInfo> {
+    save_snapshot(label)
+}
+
+fn save_snapshot(label: &str) -> Result<SnapshotInfo> {
+    let file = snapshot_file(label)?;
+    get_config()?;
+    let config_file = get_config_file()?;
+    let _lock = FileLock::acquire(&config_file)?;
+    // Snapshots are one TOML file holding the values as stored; a single TOML file is
+    // copied as is, keeping its comments.
+    let content = if Format::from_path(&config_file)? == Format::Toml
+        && layout::active_section_files(&config_file)?.is_empty()
+    {
+        fs::read(&config_file)?
+    } else {
+        Format::Toml
+            .serialize(&layout::read_document(&config_file)?)?
+            .into_bytes()
+    };
+    fs::create_dir_all(snapshots_dir()?)?;
+    write_atomic(&file, &content)?;
+    Ok(SnapshotInfo {
+        label: label.to_string(),
+        created: fs::metadata(&file)?.modified()?,
+        path: file,
+    })
+}
+
+/// Lists all named snapshots, oldest first.
+///
+/// # Returns
+///
+/// * `Result<Vec<SnapshotInfo>>` - The stored snapshots or an error
+pub fn list_snapshots() -> Result<Vec<SnapshotInfo>> {
+    let dir = snapshots_dir()?;
+    if !dir.exists() {
+        return Ok(Vec::new());
+    }
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        if path.extension().is_none_or(|ext| ext != "toml") {
+            continue;
+        }
+        let Some(label) = path.file_stem().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        snapshots.push(SnapshotInfo {
+            label: label.to_string(),
+            created: fs::metadata(&path)?.modified()?,
+            path: path.clone(),
+        });
+    }
+    snapshots.sort_by(|a, b| a.created.cmp(&b.created).then(a.label.cmp(&b.label)));
+    Ok(snapshots)
+}
+
+/// Replaces the current configuration with the snapshot saved under `label`.
+///
+/// Every key gets the value of the snapshot, also keys edited by hand since the snapshot
+/// was taken; the write is still subject to the policy and the key locks.
+///
+/// # Arguments
+///
+/// * `label` - The name of the restore point
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or a `NotFound` error if no such snapshot exists
+pub fn restore(label: &str) -> Result<()> {
+    let file = snapshot_file(label)?;
+    if !file.exists() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("Snapshot '{}' not found", label),
+        ));
+    }
+    let content = fs::read_to_string(&file)?;
+    let mut config: Value =
+        toml::from_str(&content).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    // The snapshot holds the values as stored, which saving stores again.
+    pipeline::run_read(&get_config_file()?, &mut config)?;
+    replace_config(&config)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config_value, update_config_value};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_snapshot_and_restore() {
+        let _dir = TempConfigDir::new().unwrap();
+        update_config_value("ai", "model", Value::from("gpt-4")).unwrap();
+        snapshot("known-good").unwrap();
+
+        update_config_value("ai", "model", Value::from("experimental")).unwrap();
+        restore("known-good").unwrap();
+        assert_eq!(
+            get_config_value("ai", "model").unwrap(),
+            Value::from("gpt-4")
+        );
+
+        let labels: Vec<String> = list_snapshots()
+            .unwrap()
+            .into_iter()
+            .map(|s| s.label)
+            .collect();
+        assert_eq!(labels, vec!["known-good".to_string()]);
+        assert!(restore("missing").is_err());
+        assert!(snapshot("../escape").is_err());
+        assert!(snapshot("reset-1").is_err());
+
+        crate::layout::migrate_to_split_layout().unwrap();
+        update_config_value("ai", "model", Value::from("split")).unwrap();
+        snapshot("split").unwrap();
+        update_config_value("ai", "model", Value::from("experimental")).unwrap();
+        restore("split").unwrap();
+        assert_eq!(
+            get_config_value("ai", "model").unwrap(),
+            Value::from("split")
+        );
+    }
+}
@@ -0,0 +1,148 @@
+use std::{
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+};
+use toml::{Value, map};
+
+use crate::directory::state_dir;
+use crate::path;
+use crate::storage::{FileLock, write_atomic};
+
+/// The largest state file, in bytes, the crate writes.
+pub const STATE_SIZE_LIMIT: usize = 256 * 1024;
+
+/// The section of the state file holding disposable data, dropped first when the state
+/// grows beyond [`STATE_SIZE_LIMIT`].
+pub const CACHE_SECTION: &str = "cache";
+
+/// Returns the path of the machine-local state file.
+///
+/// Unlike the configuration, the state file holds data the crate records on its own,
+/// e.g. update counters and the history of update checks, and is never meant to be synced
+/// between machines. Keeping it apart means `config.toml` is only rewritten when the user
+/// changes a setting.
+pub fn state_file() -> Result<PathBuf> {
+    Ok(state_dir()?.join("state.toml"))
+}
+
+/// Returns the state value at a dotted key path, e.g. an onboarding flag.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path, e.g. `"onboarding.done"`
+///
+/// # Returns
+///
+/// * `Result<Option<Value>>` - The value, `None` if it isn't recorded, or an error
+pub fn get_state(key_path: &str) -> Result<Option<Value>> {
+    Ok(path::lookup(&read_state()?, key_path).cloned())
+}
+
+/// Records a state value at a dotted key path.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path; values below [`CACHE_SECTION`] may be evicted
+/// * `value` - The value to record
+///
+/// # Returns
+///
+/// * `Result<()>` - Success, or an error if writing fails or the state would exceed
+///   [`STATE_SIZE_LIMIT`]
+pub fn set_state(key_path: &str, value: Value) -> Result<()> {
+    modify_state(|state| path::insert(state, key_path, value).map(|_| ()))
+}
+
+/// Removes a state value, returning it if it was recorded.
+pub fn remove_state(key_path: &str) -> Result<Option<Value>> {
+    modify_state(|state| Ok(path::remove(state, key_path)))
+}
+
+/// Reads the state file, returning an empty table if it doesn't exist yet.
+pub(crate) fn read_state() -> Result<Value> {
+    match fs::read_to_string(state_file()?) {
+        Ok(content) => parse_state(&content),
+        Err(e) if e.kind() == ErrorKind::NotFound => Ok(Value::Table(map::Map::new())),
+        Err(e) => Err(e),
+    }
+}
+
+/// Parses the content of a state file.
+pub(crate) fn parse_state(content: &str) -> Result<Value> {
+    toml::from_str(content).map_err(|e| Error::new(ErrorKind::InvalidData, e))
+}
+
+/// Runs a locked read-modify-write cycle on the state file.
+///
+/// The file is only rewritten if `f` actually changed the state.
+pub(crate) fn modify_state<T>(f: impl FnOnce(&mut Value) -> Result<T>) -> Result<T> {
+    let file = state_file()?;
+    if let Some(parent) = file.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let _lock = FileLock::acquire(&file)?;
+    let original = read_state()?;
+    let mut state = original.clone();
+    let result = f(&mut state)?;
+    if state != original {
+        write_atomic(&file, serialize_state(&mut state)?.as_bytes())?;
+    }
+    Ok(result)
+}
+
+/// Serializes the state, evicting [`CACHE_SECTION`] if the result is too large.
+///
+/// # Returns
+///
+/// * `Result<String>` - The file content, or an `InvalidData` error if the state is too
+///   large even without caches
+pub(crate) fn serialize_state(state: &mut Value) -> Result<String> {
+    let to_string =
+        |state: &Value| toml::to_string(state).map_err(|e| Error::new(ErrorKind::InvalidData, e));
+    let mut content = to_string(state)?;
+    if content.len() > STATE_SIZE_LIMIT && path::remove(state, CACHE_SECTION).is_some() {
+        content = to_string(state)?;
+    }
+    if content.len() > STATE_SIZE_LIMIT {
+        return Err(Error::new(
+            ErrorKind::InvalidData,
+            format!(
+                "State would take {} bytes, more than the limit of {}",
+                content.len(),
+                STATE_SIZE_LIMIT
+            ),
+        ));
+    }
+    Ok(content)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_state_values_and_size_limit() {
+        let _dir = TempConfigDir::new().unwrap();
+        assert_eq!(get_state("onboarding.done").unwrap(), None);
+        set_state("onboarding.done", Value::from(true)).unwrap();
+        assert_eq!(
+            get_state("onboarding.done").unwrap(),
+            Some(Value::from(true))
+        );
+
+        let large = Value::from("x".repeat(STATE_SIZE_LIMIT));
+        set_state("cache.models", large.clone()).unwrap();
+        assert_eq!(get_state("cache.models").unwrap(), None);
+        assert_eq!(
+            get_state("onboarding.done").unwrap(),
+            Some(Value::from(true))
+        );
+        assert!(set_state("usage.blob", large).is_err());
+        assert_eq!(
+            remove_state("onboarding.done").unwrap(),
+            Some(Value::from(true))
+        );
+    }
+}
@@ -0,0 +1,278 @@
+use std::{
+    ffi::OsString,
+    fs,
+    io::{Error, ErrorKind, Result, Write as _},
+    path::{Path, PathBuf},
+    sync::atomic::{AtomicU64, Ordering},
+    thread,
+    time::{Duration, Instant, SystemTime},
+};
+
+use crate::durability::{Durability, durability};
+use crate::recovery::classify;
+use crate::sync::detect_sync_provider;
+
+/// How long to wait for a competing writer before giving up.
+const LOCK_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Lock files older than this are considered abandoned by a crashed process.
+const LOCK_STALE_AFTER: Duration = Duration::from_secs(30);
+
+static TEMP_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// An exclusive advisory lock on a file, held for as long as the value lives.
+///
+/// The lock is a sibling `<name>.lock` file created with `create_new`, which is
+/// atomic on every supported platform and works across threads and processes. Files in a
+/// cloud-synced folder are locked through a file in the local temp dir instead.
+pub(crate) struct FileLock {
+    path: PathBuf,
+}
+
+impl FileLock {
+    /// Acquires the lock guarding `target`, waiting for other holders to release it.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The file the lock protects
+    ///
+    /// # Returns
+    ///
+    /// * `Result<FileLock>` - The held lock or a `TimedOut` error
+    #[cfg_attr(
+        feature = "tracing",
+        tracing::instrument(
+            name = "gim_config.lock",
+            level = "debug",
+            skip_all,
+            fields(file = %target.display())
+        )
+    )]
+    pub(crate) fn acquire(target: &Path) -> Result<FileLock> {
+        let path = lock_path(target);
+        if let Some(dir) = path.parent()
+            && !dir.exists()
+        {
+            fs::create_dir_all(dir)?;
+        }
+        let started = Instant::now();
+        let mut backoff = Duration::from_micros(200);
+        loop {
+            match fs::OpenOptions::new()
+                .write(true)
+                .create_new(true)
+                .open(&path)
+            {
+                Ok(mut file) => {
+                    let _ = write!(file, "{}", std::process::id());
+                    #[cfg(feature = "tracing")]
+                    tracing::debug!(
+                        waited_ms = started.elapsed().as_millis() as u64,
+                        "lock acquired"
+                    );
+                    return Ok(FileLock { path });
+                }
+                Err(e) if e.kind() == ErrorKind::AlreadyExists => {
+                    if is_stale(&path) {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(lock = %path.display(), "removing stale lock");
+                        let _ = fs::remove_file(&path);
+                        continue;
+                    }
+                    if started.elapsed() > LOCK_TIMEOUT {
+                        #[cfg(feature = "tracing")]
+                        tracing::warn!(lock = %path.display(), "timed out waiting for lock");
+                        return Err(Error::new(
+                            ErrorKind::TimedOut,
+                            format!("Timed out waiting for lock '{}'", path.display()),
+                        ));
+                    }
+                    thread::sleep(backoff);
+                    backoff = (backoff * 2).min(Duration::from_millis(20));
+                }
+                Err(e) => return Err(classify(e, &path)),
+            }
+        }
+    }
+
+    /// Acquires the lock guarding `target` only if nobody holds it, without waiting.
+    ///
+    /// # Arguments
+    ///
+    /// * `target` - The file the lock protects
+    ///
+    /// # Returns
+    ///
+    /// * `Result<Option<FileLock>>` - The held lock, `None` if it is taken, or an error
+    pub(crate) fn try_acquire(target: &Path) -> Result<Option<FileLock>> {
+        let path = lock_path(target);
+        match fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(mut file) => {
+                let _ = write!(file, "{}", std::process::id());
+                Ok(Some(FileLock { path }))
+            }
+            Err(e) if e.kind() == ErrorKind::AlreadyExists => Ok(None),
+            Err(e) => Err(classify(e, &path)),
+        }
+    }
+}
+
+impl Drop for FileLock {
+    fn drop(&mut self) {
+        let _ = fs::remove_file(&self.path);
+    }
+}
+
+fn lock_path(target: &Path) -> PathBuf {
+    let mut name = target.file_name().unwrap_or_default().to_os_string();
+    name.push(".lock");
+    if detect_sync_provider(target).is_some() {
+        // Sync tools upload, delay and resurrect lock files, so those of synced files are
+        // kept in the local temp dir, keyed by the full path of the target.
+        let hash = target
+            .as_os_str()
+            .as_encoded_bytes()
+            .iter()
+            .fold(0xcbf2_9ce4_8422_2325_u64, |hash, byte| {
+                (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+            });
+        let mut local = OsString::from(format!("{:016x}-", hash));
+        local.push(name);
+        return std::env::temp_dir().join("gim-locks").join(local);
+    }
+    target.with_file_name(name)
+}
+
+fn is_stale(lock: &Path) -> bool {
+    fs::metadata(lock)
+        .and_then(|m| m.modified())
+        .ok()
+        .and_then(|modified| SystemTime::now().duration_since(modified).ok())
+        .is_some_and(|age| age > LOCK_STALE_AFTER)
+}
+
+/// Removes the temporary files in `dir` left behind by writes that crashed before their
+/// rename.
+pub(crate) fn remove_stale_temp_files(dir: &Path) -> Result<Vec<PathBuf>> {
+    if !dir.is_dir() {
+        return Ok(Vec::new());
+    }
+    let mut removed = Vec::new();
+    for entry in fs::read_dir(dir)? {
+        let path = entry?.path();
+        let name = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .unwrap_or_default();
+        // `<name>.<pid>.<counter>.tmp`, see `write_atomic_with`
+        let parts: Vec<&str> = name.rsplitn(4, '.').collect();
+        let temp = parts.len() == 4
+            && parts[0] == "tmp"
+            && [parts[1], parts[2]]
+                .iter()
+                .all(|n| !n.is_empty() && n.bytes().all(|b| b.is_ascii_digit()));
+        if temp && is_stale(&path) {
+            fs::remove_file(&path)?;
+            removed.push(path);
+        }
+    }
+    Ok(removed)
+}
+
+/// Writes `contents` to `path` so that readers only ever observe the old or the new file.
+///
+/// The data goes to a unique temporary file in the same directory which is then renamed
+/// over the destination. With [`Durability::Full`] the temporary file is synced before the
+/// rename and the directory after it.
+///
+/// # Arguments
+///
+/// * `path` - The destination file
+/// * `contents` - The bytes to write
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if writing or renaming fails
+pub(crate) fn write_atomic(path: &Path, contents: &[u8]) -> Result<()> {
+    write_atomic_with(path, contents, durability())
+}
+
+fn write_atomic_with(path: &Path, contents: &[u8], durability: Durability) -> Result<()> {
+    let full = durability == Durability::Full;
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(format!(
+        ".{}.{}.tmp",
+        std::process::id(),
+        TEMP_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    let temp = path.with_file_name(name);
+    let result = fs::File::create(&temp)
+        .and_then(|mut file| {
+            file.write_all(contents)?;
+            if full {
+                file.sync_all()?;
+            }
+            // A full disk can cut the temp file short without failing the write; renaming
+            // it would replace the configuration with an empty or truncated one.
+            if file.metadata()?.len() != contents.len() as u64 {
+                return Err(Error::from(ErrorKind::StorageFull));
+            }
+            Ok(())
+        })
+        .and_then(|_| fs::rename(&temp, path));
+    if let Err(e) = result {
+        let _ = fs::remove_file(&temp);
+        return Err(classify(e, path));
+    }
+    if full {
+        sync_parent_dir(path).map_err(|e| classify(e, path))?;
+    }
+    Ok(())
+}
+
+/// Flushes the directory entry of `path`, making a rename into it durable.
+#[cfg(unix)]
+fn sync_parent_dir(path: &Path) -> Result<()> {
+    match path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        Some(dir) => fs::File::open(dir)?.sync_all(),
+        None => fs::File::open(".")?.sync_all(),
+    }
+}
+
+/// Windows makes renames durable once the file data is flushed; directories can't be synced.
+#[cfg(not(unix))]
+fn sync_parent_dir(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_lock_is_exclusive_and_released_on_drop() {
+        let dir = TempConfigDir::new().unwrap();
+        let target = dir.path().join("config.toml");
+
+        let lock = FileLock::acquire(&target).unwrap();
+        assert!(
+            lock_path(&target).exists(),
+            "Lock file should exist while held"
+        );
+        drop(lock);
+        assert!(
+            !lock_path(&target).exists(),
+            "Lock file should be removed on drop"
+        );
+
+        write_atomic(&target, b"a = 1\n").unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "a = 1\n");
+        write_atomic_with(&target, b"a = 2\n", Durability::Full).unwrap();
+        assert_eq!(fs::read_to_string(&target).unwrap(), "a = 2\n");
+    }
+}
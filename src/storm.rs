@@ -0,0 +1,389 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    io::{Error, Result},
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+    thread,
+    time::{Duration, Instant},
+};
+use toml::Value;
+
+use crate::change::ChangeSet;
+use crate::clock::SystemClock;
+use crate::config::invalidate_document_cache;
+use crate::cooldown::record_changes;
+use crate::init::emit_warning;
+use crate::key_times::record_key_times;
+use crate::layout;
+use crate::notify::notify_change;
+use crate::pipeline;
+use crate::storage::FileLock;
+
+/// More writes of one file than this within [`STORM_WINDOW`] are a write storm.
+pub const STORM_WRITES: usize = 100;
+
+/// The window write storms are detected in.
+pub const STORM_WINDOW: Duration = Duration::from_secs(1);
+
+/// How often the configuration is saved while a write storm lasts.
+pub const STORM_FLUSH_INTERVAL: Duration = Duration::from_millis(250);
+
+type Pending = HashMap<PathBuf, Deferred>;
+
+/// The save of a file deferred by a write storm.
+struct Deferred {
+    /// What the file held before the first deferred write
+    original: Value,
+    /// The latest configuration
+    value: Value,
+    /// The cooldown paths the deferred writes changed, see [`crate::cooldown`]
+    cooldowns: Vec<String>,
+}
+
+/// The recent writes of each file.
+static RECENT: Mutex<Option<HashMap<PathBuf, VecDeque<Instant>>>> = Mutex::new(None);
+/// The latest configuration of each file whose save is deferred.
+static PENDING: Mutex<Option<Pending>> = Mutex::new(None);
+/// Whether the background thread saving the deferred configurations runs.
+static FLUSHING: AtomicBool = AtomicBool::new(false);
+static WARNINGS: Mutex<Vec<WriteStorm>> = Mutex::new(Vec::new());
+
+/// A burst of writes, typically a consumer calling a setter in a loop.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WriteStorm {
+    /// The configuration file
+    pub path: PathBuf,
+    /// How many writes the burst had within [`STORM_WINDOW`] when it was detected
+    pub writes: usize,
+}
+
+/// Returns the write storms of this process, one per file, e.g. to show them in
+/// `gim doctor`.
+pub fn write_storms() -> Vec<WriteStorm> {
+    WARNINGS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Saves every configuration whose save a write storm deferred.
+///
+/// During a storm the file is saved about every [`STORM_FLUSH_INTERVAL`] instead of on
+/// every write, while reads in this process already see the latest values. Each save
+/// applies the keys the deferred writes changed onto the file as it is then, so the writes
+/// of other processes survive unless they changed the same keys. Each save also records
+/// the key times and cooldowns of the writes it covers and announces them to listeners
+/// and the hook as one change. Deferred saves are also flushed, unannounced,
+/// when the process exits normally, except those of files another thread is saving at
+/// that moment; call this before exiting abruptly, e.g. through `std::process::abort`.
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or the first error of a failed save; failed saves stay deferred
+pub fn flush_pending_writes() -> Result<()> {
+    let files: Vec<PathBuf> = with_pending(|pending| pending.keys().cloned().collect());
+    let mut result = Ok(());
+    for file in files {
+        let flushed = flush_file(&file);
+        if result.is_ok() {
+            result = flushed;
+        }
+    }
+    result
+}
+
+/// Records a write of `file` and returns whether its save should be deferred because a
+/// write storm is under way.
+pub(crate) fn is_storming(file: &Path) -> bool {
+    let mut recent = RECENT.lock().unwrap_or_else(|e| e.into_inner());
+    let writes = recent
+        .get_or_insert_with(HashMap::new)
+        .entry(file.to_path_buf())
+        .or_default();
+    let count = note_write(writes, Instant::now());
+    if count <= STORM_WRITES {
+        return false;
+    }
+    if count == STORM_WRITES + 1 {
+        record_storm(WriteStorm {
+            path: file.to_path_buf(),
+            writes: count,
+        });
+    }
+    true
+}
+
+/// Defers saving `value` to `file` until the next flush.
+///
+/// # Arguments
+///
+/// * `file` - The configuration file
+/// * `original` - What the file held before the write
+/// * `value` - The configuration to save
+/// * `cooldowns` - The cooldown paths the write changes
+pub(crate) fn defer(file: &Path, original: &Value, value: Value, cooldowns: Vec<String>) {
+    with_pending(|pending| {
+        let deferred = pending
+            .entry(file.to_path_buf())
+            .or_insert_with(|| Deferred {
+                original: original.clone(),
+                value: Value::Boolean(false),
+                cooldowns: Vec::new(),
+            });
+        deferred.value = value;
+        for path in cooldowns {
+            if !deferred.cooldowns.contains(&path) {
+                deferred.cooldowns.push(path);
+            }
+        }
+        if !FLUSHING.swap(true, Ordering::AcqRel) {
+            #[cfg(unix)]
+            flush_at_exit();
+            thread::spawn(flush_periodically);
+        }
+    });
+}
+
+/// Drops the deferred configuration of `file` once a regular save has superseded it.
+pub(crate) fn discard_pending(file: &Path) {
+    with_pending(|pending| pending.remove(file));
+}
+
+/// Returns the deferred configuration of `file`, which reads must see instead of the file.
+pub(crate) fn pending_document(file: &Path) -> Option<Value> {
+    with_pending(|pending| pending.get(file).map(|deferred| deferred.value.clone()))
+}
+
+/// Returns `value`, the configuration deferred writes made of `original`, applied onto
+/// what `file` holds now, so the writes other processes saved meanwhile aren't lost.
+///
+/// Callers are expected to hold the lock of `file`.
+pub(crate) fn onto_current(file: &Path, original: &Value, value: &Value) -> Result<Value> {
+    let mut current = layout::read_document(file)?;
+    pipeline::run_read(file, &mut current)?;
+    ChangeSet::between(original, value).apply_to(&mut current)?;
+    Ok(current)
+}
+
+/// Returns what `file` held before its deferred writes and the cooldown paths they
+/// changed, which a regular save superseding them records for them.
+pub(crate) fn pending_base(file: &Path) -> Option<(Value, Vec<String>)> {
+    with_pending(|pending| {
+        pending
+            .get(file)
+            .map(|deferred| (deferred.original.clone(), deferred.cooldowns.clone()))
+    })
+}
+
+/// Adds a write at `now` to `writes` and returns how many fall within the window.
+fn note_write(writes: &mut VecDeque<Instant>, now: Instant) -> usize {
+    while writes
+        .front()
+        .is_some_and(|&write| now.duration_since(write) >= STORM_WINDOW)
+    {
+        writes.pop_front();
+    }
+    writes.push_back(now);
+    writes.len()
+}
+
+fn with_pending<T>(f: impl FnOnce(&mut Pending) -> T) -> T {
+    let mut pending = PENDING.lock().unwrap_or_else(|e| e.into_inner());
+    f(pending.get_or_insert_with(HashMap::new))
+}
+
+fn flush_file(file: &Path) -> Result<()> {
+    // Writers hold the lock while they read the deferred configuration, so it is only
+    // taken out of `PENDING` once no writer can be in the middle of changing it.
+    let lock = FileLock::acquire(file)?;
+    let Some(deferred) = with_pending(|pending| pending.remove(file)) else {
+        return Ok(());
+    };
+    let deferred = match save(file, deferred) {
+        Ok(deferred) => deferred,
+        Err((deferred, e)) => {
+            // Writers waited for the lock, so nothing newer was deferred meanwhile.
+            with_pending(|pending| {
+                pending.entry(file.to_path_buf()).or_insert(deferred);
+            });
+            return Err(e);
+        }
+    };
+    drop(lock);
+    notify_change(&deferred.original, &deferred.value);
+    Ok(())
+}
+
+/// Saves a deferred configuration and records the changes it covers.
+///
+/// # Returns
+///
+/// * `Result<Deferred, (Deferred, Error)>` - The saved configuration, or it and the error
+///   of the failed save
+fn save(file: &Path, deferred: Deferred) -> std::result::Result<Deferred, (Deferred, Error)> {
+    let result = onto_current(file, &deferred.original, &deferred.value).and_then(|mut written| {
+        pipeline::run_write(file, &mut written)?;
+        layout::write_document(file, &written)
+    });
+    invalidate_document_cache();
+    if let Err(e) = result {
+        return Err((deferred, e));
+    }
+    // The save already succeeded; a state file that can't be updated only loses the
+    // timestamps, like for a regular write.
+    let _ = record_changes(&deferred.cooldowns, &SystemClock);
+    let _ = record_key_times(file, &deferred.original, &deferred.value);
+    Ok(deferred)
+}
+
+fn flush_periodically() {
+    loop {
+        thread::sleep(STORM_FLUSH_INTERVAL);
+        let _ = flush_pending_writes();
+        let done = with_pending(|pending| {
+            // Cleared under the lock, so `defer` either sees the flag or its entry is seen.
+            let done = pending.is_empty();
+            if done {
+                FLUSHING.store(false, Ordering::Release);
+            }
+            done
+        });
+        if done {
+            return;
+        }
+    }
+}
+
+#[cfg(unix)]
+fn flush_at_exit() {
+    extern "C" fn flush() {
+        // Other threads keep running while the process exits, and one may hold the pending
+        // configurations or a file lock; waiting for it here could hang the exit, so busy
+        // files are skipped. Listeners and hooks aren't run this late.
+        let Ok(pending) = PENDING.try_lock() else {
+            return;
+        };
+        let files: Vec<PathBuf> = pending.iter().flat_map(|p| p.keys().cloned()).collect();
+        drop(pending);
+        for file in files {
+            let Ok(Some(_lock)) = FileLock::try_acquire(&file) else {
+                continue;
+            };
+            let deferred = PENDING
+                .try_lock()
+                .ok()
+                .and_then(|mut pending| pending.as_mut()?.remove(&file));
+            if let Some(deferred) = deferred {
+                let _ = save(&file, deferred);
+            }
+        }
+    }
+    static REGISTERED: std::sync::Once = std::sync::Once::new();
+    // SAFETY: `flush` is a plain `extern "C"` function that never unwinds into libc.
+    REGISTERED.call_once(|| unsafe {
+        libc::atexit(flush);
+    });
+}
+
+fn record_storm(storm: WriteStorm) {
+    #[cfg(feature = "tracing")]
+    tracing::warn!(file = %storm.path.display(), writes = storm.writes, "config write storm");
+    emit_warning(&format!(
+        "'{}' was written more than {} times within {} ms; saves are coalesced until the \
+         writes slow down",
+        storm.path.display(),
+        STORM_WRITES,
+        STORM_WINDOW.as_millis()
+    ));
+    let mut warnings = WARNINGS.lock().unwrap_or_else(|e| e.into_inner());
+    warnings.retain(|w| w.path != storm.path);
+    warnings.push(storm);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config_file, get_value_fast, update_config_value};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_note_write_counts_the_window() {
+        let start = Instant::now();
+        let mut writes = VecDeque::new();
+        for i in 0..150 {
+            note_write(&mut writes, start + Duration::from_millis(i));
+        }
+        assert_eq!(writes.len(), 150);
+        assert_eq!(
+            note_write(&mut writes, start + Duration::from_millis(1100)),
+            50
+        );
+    }
+
+    #[test]
+    fn test_deferred_write_is_read_and_flushed() {
+        let _dir = TempConfigDir::new().unwrap();
+        update_config_value("ai", "model", Value::from("a")).unwrap();
+        let file = get_config_file().unwrap();
+        let mut config = crate::config::get_config().unwrap();
+        crate::path::insert(&mut config, "ai.model", Value::from("storm")).unwrap();
+        // Inserted directly, so no flusher thread saves it behind the test's back.
+        let original = crate::config::get_config().unwrap();
+        with_pending(|pending| {
+            let deferred = Deferred {
+                original,
+                value: config,
+                cooldowns: Vec::new(),
+            };
+            pending.insert(file.clone(), deferred)
+        });
+
+        assert_eq!(get_value_fast("ai.model").unwrap().as_str(), Some("storm"));
+        assert!(!std::fs::read_to_string(&file).unwrap().contains("storm"));
+        // Another process saves the file meanwhile.
+        let mut text = std::fs::read_to_string(&file).unwrap();
+        text.push_str("\n[other]\nkey = 'external'\n");
+        std::fs::write(&file, text).unwrap();
+        update_config_value("ai", "language", Value::from("German")).unwrap();
+        flush_pending_writes().unwrap();
+        let text = std::fs::read_to_string(&file).unwrap();
+        assert!(text.contains("storm") && text.contains("German"));
+        assert!(text.contains("external"), "{}", text);
+        assert!(pending_document(&file).is_none());
+    }
+
+    #[test]
+    fn test_storm_announces_each_save_once() {
+        let _dir = TempConfigDir::new().unwrap();
+        update_config_value("ai", "model", Value::from("storm-0")).unwrap();
+        let file = get_config_file().unwrap();
+        let now = Instant::now();
+        RECENT
+            .lock()
+            .unwrap()
+            .get_or_insert_with(HashMap::new)
+            .insert(file.clone(), (0..STORM_WRITES).map(|_| now).collect());
+        let seen = std::sync::Arc::new(Mutex::new(Vec::new()));
+        let sink = std::sync::Arc::clone(&seen);
+        let id = crate::notify::on_change(move |changes| {
+            let ours = changes.changes.iter().filter(|c| c.path == "ai.model");
+            sink.lock().unwrap().extend(ours.cloned());
+        });
+
+        for n in 1..=5 {
+            update_config_value("ai", "model", Value::from(format!("storm-{}", n))).unwrap();
+        }
+        assert!(!std::fs::read_to_string(&file).unwrap().contains("storm-5"));
+        flush_pending_writes().unwrap();
+        // The flusher thread may have saved the writes first; either way once.
+        while pending_document(&file).is_some() {
+            thread::sleep(Duration::from_millis(10));
+        }
+        crate::notify::remove_listener(id);
+        let seen = seen.lock().unwrap();
+        assert_eq!(seen.len(), 1, "{:?}", seen);
+        assert_eq!(seen[0].old, Some(Value::from("storm-0")));
+        assert_eq!(seen[0].new, Some(Value::from("storm-5")));
+        assert!(std::fs::read_to_string(&file).unwrap().contains("storm-5"));
+    }
+}
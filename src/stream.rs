@@ -0,0 +1,167 @@
+use std::{
+    fs,
+    io::{ErrorKind, Result, Write},
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use toml::Value;
+
+use crate::change::{Change, ChangeSet};
+use crate::date::{TimeZone, format_rfc3339, parse_rfc3339};
+use crate::directory::state_dir;
+use crate::format::from_json;
+use crate::storage::FileLock;
+
+/// The section of the configuration file that turns the change stream on with
+/// `enabled = true`.
+pub const CHANGE_STREAM_SECTION: &str = "change_stream";
+
+/// One write of the configuration, as recorded in the change stream.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ChangeEvent {
+    /// The position in the stream, starting at 1
+    pub seq: u64,
+    /// When the write happened, to the second
+    pub time: SystemTime,
+    /// The configuration file that was written
+    pub file: PathBuf,
+    /// The changed keys, secrets redacted
+    pub changes: ChangeSet,
+}
+
+/// Returns the change stream, `changes.jsonl` in the state directory.
+pub fn change_stream_file() -> Result<PathBuf> {
+    Ok(state_dir()?.join("changes.jsonl"))
+}
+
+/// Returns whether `config` turns the change stream on.
+pub fn is_change_stream_enabled(config: &Value) -> bool {
+    config
+        .get(CHANGE_STREAM_SECTION)
+        .and_then(|section| section.get("enabled"))
+        .and_then(Value::as_bool)
+        == Some(true)
+}
+
+/// Reads the events of the change stream after `since`.
+///
+/// With `[change_stream] enabled = true` in the configuration every write by any process
+/// appends one line to `changes.jsonl`, e.g.
+/// `{"seq":3,"time":"2026-03-29T09:30:00Z","file":"...","changes":[{"path":"ai.model","old":"a","new":"b"}]}`,
+/// so dotfile sync daemons and dashboards can tail it without watching the file system.
+/// The file is never truncated by the crate.
+///
+/// # Arguments
+///
+/// * `since` - The `seq` of the last event already seen, `0` to read them all
+///
+/// # Returns
+///
+/// * `Result<Vec<ChangeEvent>>` - The newer events, oldest first; unreadable lines are
+///   skipped
+pub fn read_change_stream(since: u64) -> Result<Vec<ChangeEvent>> {
+    let content = match fs::read_to_string(change_stream_file()?) {
+        Ok(content) => content,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    Ok(content
+        .lines()
+        .filter_map(parse_event)
+        .filter(|event| event.seq > since)
+        .collect())
+}
+
+/// Appends the redacted `changes` of a write of `file` to the change stream.
+pub(crate) fn append_change_event(file: &Path, changes: &ChangeSet) -> Result<()> {
+    let stream = change_stream_file()?;
+    let _lock = FileLock::acquire(&stream)?;
+    let last = match fs::read_to_string(&stream) {
+        Ok(content) => content.lines().rev().find_map(parse_event).map(|e| e.seq),
+        Err(e) if e.kind() == ErrorKind::NotFound => None,
+        Err(e) => return Err(e),
+    };
+    let event = ChangeEvent {
+        seq: last.unwrap_or(0) + 1,
+        time: SystemTime::now(),
+        file: file.to_path_buf(),
+        changes: changes.clone(),
+    };
+    let mut line = event_to_json(&event).to_string();
+    line.push('\n');
+    fs::OpenOptions::new()
+        .create(true)
+        .append(true)
+        .open(&stream)?
+        .write_all(line.as_bytes())
+}
+
+fn event_to_json(event: &ChangeEvent) -> serde_json::Value {
+    serde_json::json!({
+        "seq": event.seq,
+        "time": format_rfc3339(event.time, TimeZone::Utc),
+        "file": event.file.display().to_string(),
+        "changes": event.changes.to_json(),
+    })
+}
+
+fn parse_event(line: &str) -> Option<ChangeEvent> {
+    let json: serde_json::Value = serde_json::from_str(line).ok()?;
+    let value = |change: &serde_json::Value, key: &str| {
+        change.get(key).filter(|v| !v.is_null()).and_then(from_json)
+    };
+    let changes = json
+        .get("changes")?
+        .as_array()?
+        .iter()
+        .map(|change| {
+            Some(Change {
+                path: change.get("path")?.as_str()?.to_string(),
+                old: value(change, "old"),
+                new: value(change, "new"),
+            })
+        })
+        .collect::<Option<Vec<Change>>>()?;
+    Some(ChangeEvent {
+        seq: json.get("seq")?.as_u64()?,
+        time: parse_rfc3339(json.get("time")?.as_str()?).ok()?,
+        file: PathBuf::from(json.get("file")?.as_str()?),
+        changes: ChangeSet { changes },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config, save_config, update_config_value};
+    use crate::path;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_change_stream_records_writes() {
+        let _dir = TempConfigDir::new().unwrap();
+        update_config_value("ai", "model", Value::from("a")).unwrap();
+        assert!(read_change_stream(0).unwrap().is_empty());
+
+        let mut config = get_config().unwrap();
+        path::insert(&mut config, "change_stream.enabled", Value::Boolean(true)).unwrap();
+        save_config(&config).unwrap();
+        update_config_value("ai", "model", Value::from("b")).unwrap();
+        update_config_value("ai", "apikey", Value::from("sk-secret")).unwrap();
+
+        let events = read_change_stream(0).unwrap();
+        assert_eq!(events.len(), 3);
+        assert_eq!(events[0].changes.paths(), vec!["change_stream.enabled"]);
+        let model = &events[1].changes.changes[0];
+        assert_eq!(
+            (model.old.clone(), model.new.clone()),
+            (Some("a".into()), Some("b".into()))
+        );
+        assert!(
+            !std::fs::read_to_string(change_stream_file().unwrap())
+                .unwrap()
+                .contains("sk-secret")
+        );
+        assert_eq!(read_change_stream(events[1].seq).unwrap().len(), 1);
+    }
+}
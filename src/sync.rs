@@ -0,0 +1,236 @@
+use std::{
+    fmt, fs,
+    io::{Error, ErrorKind, Result},
+    path::{Component, Path, PathBuf},
+};
+use toml::Value;
+
+use crate::change::ChangeSet;
+use crate::config::{get_config_file, modify_config};
+use crate::format::Format;
+use crate::merge::merge_into;
+
+/// A file synchronization service that may hold the config directory.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum SyncProvider {
+    /// Dropbox
+    Dropbox,
+    /// Microsoft OneDrive, including business accounts
+    OneDrive,
+    /// Apple iCloud Drive
+    ICloud,
+    /// Google Drive for desktop
+    GoogleDrive,
+}
+
+impl fmt::Display for SyncProvider {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            SyncProvider::Dropbox => "Dropbox",
+            SyncProvider::OneDrive => "OneDrive",
+            SyncProvider::ICloud => "iCloud Drive",
+            SyncProvider::GoogleDrive => "Google Drive",
+        })
+    }
+}
+
+/// Returns the sync service whose folder contains `path`, judged by the folder names
+/// the services create, e.g. `~/Dropbox` or `~/OneDrive - Contoso`.
+///
+/// Inside such folders the crate keeps its lock files in the local temp dir, since sync
+/// tools upload, delay and resurrect lock files and so break them.
+///
+/// # Arguments
+///
+/// * `path` - A file or directory
+///
+/// # Returns
+///
+/// * `Option<SyncProvider>` - The service, or `None` for ordinary directories
+pub fn detect_sync_provider(path: &Path) -> Option<SyncProvider> {
+    let names: Vec<String> = path
+        .components()
+        .filter_map(|c| match c {
+            Component::Normal(name) => Some(name.to_string_lossy().to_lowercase()),
+            _ => None,
+        })
+        .collect();
+    names.iter().enumerate().find_map(|(i, name)| {
+        if name == "dropbox" || name.starts_with("dropbox (") {
+            Some(SyncProvider::Dropbox)
+        } else if name == "onedrive" || name.starts_with("onedrive - ") {
+            Some(SyncProvider::OneDrive)
+        } else if name == "icloud drive"
+            || name == "iclouddrive"
+            || (name == "mobile documents" && i > 0 && names[i - 1] == "library")
+        {
+            Some(SyncProvider::ICloud)
+        } else if name == "google drive" || name == "my drive" || name.starts_with("googledrive") {
+            Some(SyncProvider::GoogleDrive)
+        } else {
+            None
+        }
+    })
+}
+
+/// Returns the sync service holding the configuration file, if any.
+pub fn config_sync_provider() -> Result<Option<SyncProvider>> {
+    Ok(detect_sync_provider(&get_config_file()?))
+}
+
+/// Lists the conflict copies sync services left next to the configuration file.
+///
+/// Dropbox names them `config (<name>'s conflicted copy <date>).toml`, iCloud
+/// `config 2.toml`, and OneDrive and Google Drive `config-<machine>.toml` or
+/// `config (1).toml`.
+///
+/// # Returns
+///
+/// * `Result<Vec<PathBuf>>` - The conflict copies, sorted by name
+pub fn find_conflict_files() -> Result<Vec<PathBuf>> {
+    let config_file = get_config_file()?;
+    let (Some(dir), Some(stem), Some(extension)) = (
+        config_file.parent(),
+        config_file.file_stem().and_then(|s| s.to_str()),
+        config_file.extension().and_then(|s| s.to_str()),
+    ) else {
+        return Ok(Vec::new());
+    };
+    let entries = match fs::read_dir(dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e),
+    };
+    let onedrive = detect_sync_provider(&config_file) == Some(SyncProvider::OneDrive);
+    let mut conflicts = Vec::new();
+    for entry in entries {
+        let path = entry?.path();
+        let Some(name) = path.file_name().and_then(|s| s.to_str()) else {
+            continue;
+        };
+        if path != config_file && is_conflict_name(name, stem, extension, onedrive) {
+            conflicts.push(path);
+        }
+    }
+    conflicts.sort();
+    Ok(conflicts)
+}
+
+fn is_conflict_name(name: &str, stem: &str, extension: &str, onedrive: bool) -> bool {
+    let Some(middle) = name
+        .strip_prefix(stem)
+        .and_then(|rest| rest.strip_suffix(extension))
+        .and_then(|rest| rest.strip_suffix('.'))
+    else {
+        return false;
+    };
+    if middle.contains("conflicted copy") || middle.contains("Conflict") {
+        return true;
+    }
+    let numbered = |s: &str| !s.is_empty() && s.chars().all(|c| c.is_ascii_digit());
+    if let Some(number) = middle.strip_prefix(' ') {
+        return numbered(number)
+            || number
+                .strip_prefix('(')
+                .and_then(|n| n.strip_suffix(')'))
+                .is_some_and(numbered);
+    }
+    onedrive && middle.len() > 1 && middle.starts_with('-')
+}
+
+/// How [`reconcile_conflict`] combines a conflict copy with the configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ConflictResolution {
+    /// The configuration stays as it is and the copy is discarded
+    KeepCurrent,
+    /// The copy replaces the configuration
+    TakeConflict,
+    /// Keys only present in the copy are added; keys in both keep the current value
+    Merge,
+}
+
+/// Resolves a conflict copy found by [`find_conflict_files`] and deletes it.
+///
+/// # Arguments
+///
+/// * `conflict` - The conflict copy
+/// * `resolution` - How to combine it with the configuration
+///
+/// # Returns
+///
+/// * `Result<ChangeSet>` - The changes made to the configuration, or an error if the copy
+///   doesn't parse or writing fails; the copy is kept on error
+pub fn reconcile_conflict(conflict: &Path, resolution: ConflictResolution) -> Result<ChangeSet> {
+    let content = fs::read_to_string(conflict)?;
+    let copy: Value = Format::from_path(conflict)?.parse(&content).map_err(|e| {
+        Error::new(
+            ErrorKind::InvalidData,
+            format!("Failed to parse '{}': {}", conflict.display(), e),
+        )
+    })?;
+    let changes = modify_config(|config| {
+        let before = config.clone();
+        match resolution {
+            ConflictResolution::KeepCurrent => {}
+            ConflictResolution::TakeConflict => *config = copy,
+            ConflictResolution::Merge => merge_into(config, &copy, false),
+        }
+        Ok(ChangeSet::between(&before, config))
+    })?;
+    fs::remove_file(conflict)?;
+    Ok(changes)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{get_config_value, update_config_value};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_detect_sync_provider() {
+        let detect = |path: &str| detect_sync_provider(Path::new(path));
+        assert_eq!(
+            detect("/home/u/Dropbox/dotfiles/gim/config.toml"),
+            Some(SyncProvider::Dropbox)
+        );
+        assert_eq!(
+            detect("C:/Users/u/OneDrive - Contoso/gim"),
+            Some(SyncProvider::OneDrive)
+        );
+        assert_eq!(
+            detect("/Users/u/Library/Mobile Documents/com~apple~CloudDocs/gim"),
+            Some(SyncProvider::ICloud)
+        );
+        assert_eq!(detect("/home/u/.config/gim/config.toml"), None);
+        assert_eq!(detect("/home/u/Documents/gim"), None);
+    }
+
+    #[test]
+    fn test_find_and_reconcile_conflict_files() {
+        let dir = TempConfigDir::new().unwrap();
+        update_config_value("ai", "model", Value::from("current")).unwrap();
+        let dropbox = dir
+            .path()
+            .join("config (Sam's conflicted copy 2024-05-01).toml");
+        fs::write(&dropbox, "[ai]\nmodel = \"theirs\"\n[sync_test]\nflag = true\n").unwrap();
+        let icloud = dir.path().join("config 2.toml");
+        fs::write(&icloud, "[ai]\nmodel = \"icloud\"\n").unwrap();
+        fs::write(dir.path().join("config-notes.toml"), "").unwrap();
+
+        assert_eq!(
+            find_conflict_files().unwrap(),
+            vec![dropbox.clone(), icloud.clone()]
+        );
+        let changes = reconcile_conflict(&dropbox, ConflictResolution::Merge).unwrap();
+        assert_eq!(changes.paths(), vec!["sync_test.flag"]);
+        assert_eq!(
+            get_config_value("ai", "model").unwrap().as_str(),
+            Some("current")
+        );
+        reconcile_conflict(&icloud, ConflictResolution::KeepCurrent).unwrap();
+        assert!(find_conflict_files().unwrap().is_empty());
+    }
+}
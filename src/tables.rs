@@ -0,0 +1,204 @@
+use std::io::{Error, ErrorKind, Result};
+use toml::{Value, map};
+
+use crate::config::{get_config, modify_config};
+use crate::path;
+
+/// Selects one table of an array of tables such as `[[ai.endpoints]]`.
+#[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
+pub enum TableSelector {
+    /// The table at this position
+    Index(usize),
+    /// The first table whose `field` equals `value`, e.g. `name = "work"`
+    Matching {
+        /// The field to compare
+        field: String,
+        /// The value the field must have
+        value: Value,
+    },
+}
+
+impl TableSelector {
+    /// Selects the first table whose `field` equals `value`.
+    pub fn matching(field: &str, value: impl Into<Value>) -> TableSelector {
+        TableSelector::Matching {
+            field: field.to_string(),
+            value: value.into(),
+        }
+    }
+
+    fn position(&self, tables: &[Value]) -> Option<usize> {
+        match self {
+            TableSelector::Index(index) => (*index < tables.len()).then_some(*index),
+            TableSelector::Matching { field, value } => tables
+                .iter()
+                .position(|table| table.get(field.as_str()) == Some(value)),
+        }
+    }
+}
+
+/// Returns the tables of the array at a dotted key path; a missing array is empty.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted path of the array, e.g. `"ai.endpoints"`
+///
+/// # Returns
+///
+/// * `Result<Vec<map::Map<String, Value>>>` - The tables, or an `InvalidData` error if the
+///   value isn't an array of tables
+pub fn get_tables(key_path: &str) -> Result<Vec<map::Map<String, Value>>> {
+    let config = get_config()?;
+    let Some(value) = path::lookup(&config, key_path) else {
+        return Ok(Vec::new());
+    };
+    Ok(as_tables(key_path, value)?
+        .iter()
+        .filter_map(|table| table.as_table().cloned())
+        .collect())
+}
+
+/// Appends a table to the array at a dotted key path, creating the array if needed.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted path of the array
+/// * `table` - The table to append
+///
+/// # Returns
+///
+/// * `Result<usize>` - The index of the new table or an error
+pub fn push_table(key_path: &str, table: map::Map<String, Value>) -> Result<usize> {
+    modify_config(|config| {
+        let tables = tables_mut(config, key_path)?;
+        tables.push(Value::Table(table));
+        Ok(tables.len() - 1)
+    })
+}
+
+/// Modifies the selected table of the array at a dotted key path.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted path of the array
+/// * `selector` - Which table to modify
+/// * `f` - Modifies the table in place
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or a `NotFound` error if no table is selected
+pub fn update_table(
+    key_path: &str,
+    selector: &TableSelector,
+    f: impl FnOnce(&mut map::Map<String, Value>),
+) -> Result<()> {
+    modify_config(|config| {
+        let tables = tables_mut(config, key_path)?;
+        let index = selector
+            .position(tables)
+            .ok_or_else(|| not_selected(key_path, selector))?;
+        f(tables[index].as_table_mut().expect("checked by tables_mut"));
+        Ok(())
+    })
+}
+
+/// Removes the selected table from the array at a dotted key path.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted path of the array
+/// * `selector` - Which table to remove
+///
+/// # Returns
+///
+/// * `Result<Option<map::Map<String, Value>>>` - The removed table, `None` if nothing was
+///   selected, or an error
+pub fn remove_table(
+    key_path: &str,
+    selector: &TableSelector,
+) -> Result<Option<map::Map<String, Value>>> {
+    modify_config(|config| {
+        if path::lookup(config, key_path).is_none() {
+            return Ok(None);
+        }
+        let tables = tables_mut(config, key_path)?;
+        Ok(selector
+            .position(tables)
+            .map(|index| match tables.remove(index) {
+                Value::Table(table) => table,
+                _ => unreachable!("checked by tables_mut"),
+            }))
+    })
+}
+
+fn as_tables<'a>(key_path: &str, value: &'a Value) -> Result<&'a Vec<Value>> {
+    value
+        .as_array()
+        .filter(|items| items.iter().all(Value::is_table))
+        .ok_or_else(|| not_an_array_of_tables(key_path))
+}
+
+fn tables_mut<'a>(config: &'a mut Value, key_path: &str) -> Result<&'a mut Vec<Value>> {
+    if path::lookup(config, key_path).is_none() {
+        path::insert(config, key_path, Value::Array(Vec::new()))?;
+    }
+    let value = path::lookup_mut(config, key_path).expect("inserted above");
+    as_tables(key_path, value)?;
+    Ok(value.as_array_mut().expect("checked by as_tables"))
+}
+
+fn not_an_array_of_tables(key_path: &str) -> Error {
+    Error::new(
+        ErrorKind::InvalidData,
+        format!("Key '{}' is not an array of tables", key_path),
+    )
+}
+
+fn not_selected(key_path: &str, selector: &TableSelector) -> Error {
+    Error::new(
+        ErrorKind::NotFound,
+        format!("No table of '{}' matches {:?}", key_path, selector),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::testing::TempConfigDir;
+
+    fn endpoint(name: &str, url: &str) -> map::Map<String, Value> {
+        let mut table = map::Map::new();
+        table.insert("name".to_string(), Value::from(name));
+        table.insert("url".to_string(), Value::from(url));
+        table
+    }
+
+    #[test]
+    fn test_array_of_tables_round_trip() {
+        let _dir = TempConfigDir::new().unwrap();
+        assert!(get_tables("ai.endpoints").unwrap().is_empty());
+        push_table("ai.endpoints", endpoint("work", "https://work.example")).unwrap();
+        assert_eq!(
+            push_table("ai.endpoints", endpoint("home", "http://localhost")).unwrap(),
+            1
+        );
+
+        update_table(
+            "ai.endpoints",
+            &TableSelector::matching("name", "work"),
+            |table| {
+                table.insert("url".to_string(), Value::from("https://new.example"));
+            },
+        )
+        .unwrap();
+        let removed = remove_table("ai.endpoints", &TableSelector::Index(1)).unwrap();
+        assert_eq!(removed.unwrap()["name"].as_str(), Some("home"));
+
+        let tables = get_tables("ai.endpoints").unwrap();
+        assert_eq!(tables.len(), 1);
+        assert_eq!(tables[0]["url"].as_str(), Some("https://new.example"));
+        assert!(update_table("ai.endpoints", &TableSelector::Index(5), |_| {}).is_err());
+        assert!(get_tables("ai.model").is_err());
+    }
+}
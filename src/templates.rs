@@ -0,0 +1,189 @@
+use std::io::{Error, ErrorKind, Result};
+use toml::Value;
+
+use crate::config::create_config;
+use crate::defaults::{default_values, render_with_default_comments};
+use crate::merge::merge_into;
+
+/// A named bootstrap profile for a fresh configuration.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Template {
+    /// The name passed to [`init_config_from_template`]
+    pub name: &'static str,
+    /// A one-line summary shown to users picking a template
+    pub description: &'static str,
+    /// TOML merged over the built-in defaults
+    overrides: &'static str,
+}
+
+impl Template {
+    /// Returns the full configuration this template produces.
+    ///
+    /// # Returns
+    ///
+    /// * `Value` - The built-in defaults with the template's settings applied
+    pub fn config(&self) -> Value {
+        let mut config = default_values();
+        let overrides: Value =
+            toml::from_str(self.overrides).expect("built-in templates are valid TOML");
+        merge_into(&mut config, &overrides, true);
+        config
+    }
+
+    /// Returns the commented TOML document this template produces.
+    pub fn document(&self) -> String {
+        render_with_default_comments(&self.config())
+    }
+}
+
+const TEMPLATES: &[Template] = &[
+    Template {
+        name: "minimal",
+        description: "Built-in defaults only; fill in your provider yourself",
+        overrides: "",
+    },
+    Template {
+        name: "team-shared",
+        description: "OpenAI-compatible endpoint with conservative defaults for shared setups",
+        overrides: r#"
+            [ai]
+            url = "https://api.openai.com/v1"
+            model = "gpt-4o-mini"
+            language = "English"
+
+            [update]
+            max_try = 3
+            try_interval_days = 14
+        "#,
+    },
+    Template {
+        name: "offline",
+        description: "Local Ollama server without API key and without update checks",
+        overrides: r#"
+            [ai]
+            url = "http://localhost:11434/v1"
+            model = "llama3"
+            apikey = ""
+
+            [update]
+            max_try = 0
+        "#,
+    },
+    Template {
+        name: "ci",
+        description: "Non-interactive CI runs: no update checks, key supplied by the environment",
+        overrides: r#"
+            [ai]
+            language = "English"
+
+            [update]
+            max_try = 0
+            try_interval_days = 36500
+        "#,
+    },
+];
+
+/// Lists the built-in configuration templates.
+///
+/// # Returns
+///
+/// * `&'static [Template]` - All templates, in the order they should be presented
+pub fn available_templates() -> &'static [Template] {
+    TEMPLATES
+}
+
+/// Looks up a built-in template by name.
+///
+/// # Arguments
+///
+/// * `name` - The template name, e.g. `"offline"`
+///
+/// # Returns
+///
+/// * `Result<&'static Template>` - The template or a `NotFound` error listing valid names
+pub fn find_template(name: &str) -> Result<&'static Template> {
+    TEMPLATES.iter().find(|t| t.name == name).ok_or_else(|| {
+        let names: Vec<&str> = TEMPLATES.iter().map(|t| t.name).collect();
+        Error::new(
+            ErrorKind::NotFound,
+            format!(
+                "Template '{}' not found, available: {}",
+                name,
+                names.join(", ")
+            ),
+        )
+    })
+}
+
+/// Creates the configuration file from a built-in template.
+///
+/// # Arguments
+///
+/// * `name` - The template name, see [`available_templates`]
+///
+/// # Returns
+///
+/// * `Result<()>` - Success, or an error if the template is unknown, a config file exists
+///   or the configuration was read from a stream
+pub fn init_config_from_template(name: &str) -> Result<()> {
+    create_config(&find_template(name)?.document())
+}
+
+/// Creates the configuration file with a document of the application's own.
+///
+/// # Arguments
+///
+/// * `document` - The initial configuration; it must be a table
+///
+/// # Returns
+///
+/// * `Result<()>` - Success, or an `InvalidInput` error if `document` isn't a table or an
+///   `AlreadyExists` error if a config file exists
+pub fn init_with(document: Value) -> Result<()> {
+    if !document.is_table() {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            "The initial document must be a table",
+        ));
+    }
+    let text = toml::to_string(&document).map_err(|e| Error::new(ErrorKind::InvalidData, e))?;
+    create_config(&text)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::get_config;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_init_config_from_template() {
+        let _dir = TempConfigDir::new().unwrap();
+        for template in available_templates() {
+            let config = template.config();
+            assert!(config.get("ai").is_some() && config.get("update").is_some());
+        }
+
+        init_config_from_template("offline").unwrap();
+        let config = get_config().unwrap();
+        assert_eq!(config["ai"]["model"].as_str(), Some("llama3"));
+        assert_eq!(config["update"]["max_try"].as_integer(), Some(0));
+
+        let err = init_config_from_template("offline").unwrap_err();
+        assert_eq!(err.kind(), ErrorKind::AlreadyExists);
+        assert!(find_template("nope").is_err());
+    }
+
+    #[test]
+    fn test_init_with_own_document() {
+        let _dir = TempConfigDir::new().unwrap();
+        let document: Value = toml::from_str("[editor]\ntheme = \"dark\"\n").unwrap();
+        assert!(init_with(Value::from(1)).is_err());
+        init_with(document.clone()).unwrap();
+        assert_eq!(get_config().unwrap(), document);
+        assert_eq!(
+            init_with(document).unwrap_err().kind(),
+            ErrorKind::AlreadyExists
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+use toml::{Value, map};
+
+use crate::config::{get_config, modify_config};
+use crate::path;
+
+/// The table holding the temporary overrides, keyed by dotted key path.
+pub(crate) const TEMPORARY_SECTION: &str = "temporary_overrides";
+
+/// A value that replaces a setting until it expires.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TemporaryOverride {
+    /// The dotted key path that is overridden
+    pub path: String,
+    /// The value reads return while the override is active
+    pub value: Value,
+    /// When the override stops applying
+    pub until: SystemTime,
+}
+
+/// Overrides a setting until `until`, e.g. "use gpt-4o-mini for today".
+///
+/// The override is stored in the `[temporary_overrides]` table of the configuration file,
+/// so it applies to every process; the regular value is left untouched and comes back
+/// once the override expires.
+///
+/// # Arguments
+///
+/// * `key_path` - The dotted key path to override, e.g. `"ai.model"`
+/// * `value` - The temporary value
+/// * `until` - When the override expires
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if the path is invalid or saving fails
+pub fn set_temporary(key_path: &str, value: Value, until: SystemTime) -> Result<()> {
+    path::split(key_path)?;
+    let mut entry = map::Map::new();
+    entry.insert("value".to_string(), value);
+    entry.insert("until".to_string(), Value::Integer(to_unix_seconds(until)));
+    modify_config(|config| {
+        let root = config
+            .as_table_mut()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "Config is not a table"))?;
+        root.entry(TEMPORARY_SECTION.to_string())
+            .or_insert_with(|| Value::Table(map::Map::new()))
+            .as_table_mut()
+            .ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Section '{}' is not a table", TEMPORARY_SECTION),
+                )
+            })?
+            .insert(key_path.to_string(), Value::Table(entry));
+        Ok(())
+    })
+}
+
+/// Lists the overrides that are still active, sorted by key path.
+///
+/// # Returns
+///
+/// * `Result<Vec<TemporaryOverride>>` - The unexpired overrides or an error
+pub fn list_temporary_overrides() -> Result<Vec<TemporaryOverride>> {
+    let config = get_config()?;
+    let now = SystemTime::now();
+    let Some(overrides) = config.get(TEMPORARY_SECTION).and_then(Value::as_table) else {
+        return Ok(Vec::new());
+    };
+    let mut list: Vec<TemporaryOverride> = overrides
+        .iter()
+        .filter_map(|(key_path, entry)| {
+            let (value, until) = parse_entry(entry)?;
+            (until > now).then(|| TemporaryOverride {
+                path: key_path.clone(),
+                value: value.clone(),
+                until,
+            })
+        })
+        .collect();
+    list.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(list)
+}
+
+/// Returns the active override of `key_path` in `config`, if any.
+pub(crate) fn active_override<'a>(
+    config: &'a Value,
+    key_path: &str,
+    now: SystemTime,
+) -> Option<&'a Value> {
+    let entry = config.get(TEMPORARY_SECTION)?.get(key_path)?;
+    let (value, until) = parse_entry(entry)?;
+    (until > now).then_some(value)
+}
+
+/// Removes expired and malformed overrides from `config`, dropping the table once empty.
+pub(crate) fn prune_expired(config: &mut Value, now: SystemTime) {
+    let Some(root) = config.as_table_mut() else {
+        return;
+    };
+    let Some(Value::Table(overrides)) = root.get_mut(TEMPORARY_SECTION) else {
+        return;
+    };
+    overrides.retain(|_, entry| parse_entry(entry).is_some_and(|(_, until)| until > now));
+    if overrides.is_empty() {
+        root.remove(TEMPORARY_SECTION);
+    }
+}
+
+fn parse_entry(entry: &Value) -> Option<(&Value, SystemTime)> {
+    let value = entry.get("value")?;
+    let seconds = entry.get("until")?.as_integer()?;
+    let until = UNIX_EPOCH + Duration::from_secs(u64::try_from(seconds).ok()?);
+    Some((value, until))
+}
+
+fn to_unix_seconds(time: SystemTime) -> i64 {
+    time.duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs() as i64)
+        .unwrap_or(0)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, get_config_value, get_value_fast, save_config};
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_temporary_override_applies_until_expiry() {
+        let _dir = TempConfigDir::new().unwrap();
+        let tomorrow = SystemTime::now() + Duration::from_secs(24 * 60 * 60);
+        set_temporary("ai.model", Value::from("gpt-4o-mini"), tomorrow).unwrap();
+        set_temporary("ai.language", Value::from("German"), UNIX_EPOCH).unwrap();
+
+        assert_eq!(
+            get_value_fast("ai.model").unwrap(),
+            Value::from("gpt-4o-mini")
+        );
+        assert_eq!(
+            get_config_value("ai", "model").unwrap(),
+            Value::from("gpt-4o-mini")
+        );
+        assert_eq!(
+            Config::load().unwrap().get_str("ai.language"),
+            Some("English")
+        );
+        let active = list_temporary_overrides().unwrap();
+        assert_eq!(active.len(), 1);
+        assert_eq!(active[0].path, "ai.model");
+
+        save_config(&get_config().unwrap()).unwrap();
+        let overrides = get_config().unwrap();
+        let overrides = overrides.get(TEMPORARY_SECTION).unwrap();
+        assert!(overrides.get("ai.language").is_none(), "Expired on save");
+        assert!(overrides.get("ai.model").is_some());
+    }
+}
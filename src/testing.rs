@@ -0,0 +1,281 @@
+use std::{
+    cell::RefCell,
+    collections::BTreeMap,
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::{Path, PathBuf},
+    sync::{
+        Mutex,
+        atomic::{AtomicU64, Ordering},
+    },
+    thread,
+    time::{SystemTime, UNIX_EPOCH},
+};
+use toml::{Value, map};
+
+use crate::config::{get_config, get_config_value, save_config, update_config_value};
+
+thread_local! {
+    static THREAD_CONFIG_DIR: RefCell<Option<PathBuf>> = const { RefCell::new(None) };
+}
+
+static DIR_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// The application defaults registered while a [`TempConfigDir`] was installed, by its path.
+static DIR_DEFAULTS: Mutex<BTreeMap<PathBuf, Option<Value>>> = Mutex::new(BTreeMap::new());
+
+/// Returns the config directory override installed for the current thread, if any.
+pub(crate) fn thread_config_dir() -> Option<PathBuf> {
+    THREAD_CONFIG_DIR.with(|dir| dir.borrow().clone())
+}
+
+/// Runs `f` on the application defaults registered for the config directory `dir`.
+pub(crate) fn with_dir_defaults<T>(dir: &Path, f: impl FnOnce(&mut Option<Value>) -> T) -> T {
+    let mut defaults = DIR_DEFAULTS.lock().unwrap_or_else(|e| e.into_inner());
+    f(defaults.entry(dir.to_path_buf()).or_default())
+}
+
+/// A uniquely named temporary config directory, removed again on drop.
+///
+/// While installed on a thread, every API of this crate called from that thread reads
+/// and writes inside this directory instead of `~/.config/gim/`. Defaults registered
+/// meanwhile (see [`crate::defaults::set_default`]) apply to this directory only, on top
+/// of the process-wide ones, and are dropped with it.
+pub struct TempConfigDir {
+    path: PathBuf,
+}
+
+impl TempConfigDir {
+    /// Creates a fresh temporary directory and installs it for the current thread.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<TempConfigDir>` - The guard owning the directory or an error
+    pub fn new() -> Result<TempConfigDir> {
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|d| d.as_nanos())
+            .unwrap_or_default();
+        let path = std::env::temp_dir().join(format!(
+            "gim-config-{}-{}-{}",
+            std::process::id(),
+            nanos,
+            DIR_COUNTER.fetch_add(1, Ordering::Relaxed)
+        ));
+        fs::create_dir_all(&path)?;
+        let dir = TempConfigDir { path };
+        dir.install();
+        Ok(dir)
+    }
+
+    /// Returns the path of the temporary directory.
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+
+    /// Installs the directory as the config directory of the calling thread.
+    ///
+    /// Use this from threads spawned by a test that should share the same directory.
+    pub fn install(&self) {
+        install_thread_config_dir(Some(self.path.clone()));
+    }
+}
+
+impl Drop for TempConfigDir {
+    fn drop(&mut self) {
+        if thread_config_dir().as_deref() == Some(self.path.as_path()) {
+            install_thread_config_dir(None);
+        }
+        DIR_DEFAULTS
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .remove(&self.path);
+        let _ = fs::remove_dir_all(&self.path);
+    }
+}
+
+fn install_thread_config_dir(dir: Option<PathBuf>) {
+    THREAD_CONFIG_DIR.with(|current| *current.borrow_mut() = dir);
+}
+
+/// Parameters of a [`stress`] run.
+#[derive(Debug, Clone)]
+pub struct StressOptions {
+    /// Number of concurrent worker threads
+    pub threads: usize,
+    /// Number of get/update cycles performed by each worker
+    pub iterations: usize,
+    /// Number of distinct keys each worker writes to
+    pub keys_per_thread: usize,
+    /// Seed for the per-worker random operation mix
+    pub seed: u64,
+}
+
+impl Default for StressOptions {
+    fn default() -> Self {
+        StressOptions {
+            threads: 8,
+            iterations: 100,
+            keys_per_thread: 4,
+            seed: 0x9e37_79b9_7f4a_7c15,
+        }
+    }
+}
+
+/// Outcome of a [`stress`] run.
+#[derive(Debug, Clone, Default)]
+pub struct StressReport {
+    /// Number of successful reads
+    pub reads: usize,
+    /// Number of successful updates
+    pub updates: usize,
+    /// Reads that failed because the file could not be parsed
+    pub parse_failures: usize,
+    /// Keys whose final value differs from the last value written to them
+    pub lost_updates: Vec<String>,
+}
+
+impl StressReport {
+    /// Returns whether the run observed neither lost updates nor unparsable files.
+    pub fn is_clean(&self) -> bool {
+        self.parse_failures == 0 && self.lost_updates.is_empty()
+    }
+}
+
+/// Hammers a temporary config with concurrent randomized get/update cycles.
+///
+/// Every worker owns a disjoint set of keys in a `[stress]` section and remembers the last
+/// value it wrote to each of them, so after all workers finish any mismatch is a lost update.
+///
+/// # Arguments
+///
+/// * `dir` - The config directory to run against; it is used exclusively by the workers
+/// * `options` - The size and seed of the run
+///
+/// # Returns
+///
+/// * `Result<StressReport>` - The observed outcome or an error if a worker hit an I/O failure
+pub fn stress(dir: &Path, options: &StressOptions) -> Result<StressReport> {
+    let dir = dir.to_path_buf();
+    let mut handles = Vec::with_capacity(options.threads);
+
+    {
+        let _scope = ScopedDir::new(&dir);
+        let mut config = get_config()?;
+        if let Some(root) = config.as_table_mut() {
+            root.insert("stress".to_string(), Value::Table(map::Map::new()));
+        }
+        save_config(&config)?;
+    }
+
+    for worker in 0..options.threads {
+        let dir = dir.clone();
+        let options = options.clone();
+        handles.push(thread::spawn(move || run_worker(&dir, worker, &options)));
+    }
+
+    let mut report = StressReport::default();
+    let mut expected = BTreeMap::new();
+    for handle in handles {
+        let outcome = handle
+            .join()
+            .map_err(|_| Error::other("stress worker panicked"))??;
+        report.reads += outcome.reads;
+        report.updates += outcome.updates;
+        report.parse_failures += outcome.parse_failures;
+        expected.extend(outcome.last_written);
+    }
+
+    let _scope = ScopedDir::new(&dir);
+    for (key, value) in expected {
+        match get_config_value("stress", &key) {
+            Ok(actual) if actual == Value::Integer(value) => {}
+            _ => report.lost_updates.push(key),
+        }
+    }
+    Ok(report)
+}
+
+struct WorkerOutcome {
+    reads: usize,
+    updates: usize,
+    parse_failures: usize,
+    last_written: BTreeMap<String, i64>,
+}
+
+fn run_worker(dir: &Path, worker: usize, options: &StressOptions) -> Result<WorkerOutcome> {
+    let _scope = ScopedDir::new(dir);
+    let mut rng = options.seed ^ ((worker as u64 + 1).wrapping_mul(0xa076_1d64_78bd_642f));
+    let mut outcome = WorkerOutcome {
+        reads: 0,
+        updates: 0,
+        parse_failures: 0,
+        last_written: BTreeMap::new(),
+    };
+
+    for iteration in 0..options.iterations {
+        let roll = next_random(&mut rng);
+        if roll.is_multiple_of(2) {
+            match get_config() {
+                Ok(_) => outcome.reads += 1,
+                Err(e) if e.kind() == ErrorKind::InvalidData => outcome.parse_failures += 1,
+                Err(e) => return Err(e),
+            }
+        } else {
+            let key = format!(
+                "w{}_k{}",
+                worker,
+                (roll >> 1) as usize % options.keys_per_thread.max(1)
+            );
+            let value = iteration as i64;
+            update_config_value("stress", &key, Value::Integer(value))?;
+            outcome.last_written.insert(key, value);
+            outcome.updates += 1;
+        }
+    }
+    Ok(outcome)
+}
+
+/// Installs a config directory for the current thread until dropped.
+struct ScopedDir {
+    previous: Option<PathBuf>,
+}
+
+impl ScopedDir {
+    fn new(dir: &Path) -> ScopedDir {
+        let previous = thread_config_dir();
+        install_thread_config_dir(Some(dir.to_path_buf()));
+        ScopedDir { previous }
+    }
+}
+
+impl Drop for ScopedDir {
+    fn drop(&mut self) {
+        install_thread_config_dir(self.previous.take());
+    }
+}
+
+fn next_random(state: &mut u64) -> u64 {
+    *state ^= *state << 13;
+    *state ^= *state >> 7;
+    *state ^= *state << 17;
+    *state
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stress_loses_no_updates() {
+        let dir = TempConfigDir::new().unwrap();
+        let options = StressOptions {
+            threads: 4,
+            iterations: 40,
+            ..StressOptions::default()
+        };
+        let report = stress(dir.path(), &options).unwrap();
+        assert!(report.updates > 0, "Stress run should perform updates");
+        assert!(report.is_clean(), "Stress run was not clean: {:?}", report);
+    }
+}
@@ -0,0 +1,140 @@
+use std::{
+    path::PathBuf,
+    sync::{
+        Mutex,
+        atomic::{AtomicBool, Ordering},
+    },
+};
+
+static ENABLED: AtomicBool = AtomicBool::new(false);
+static RECORDS: Mutex<Vec<AccessRecord>> = Mutex::new(Vec::new());
+
+/// Where a value that was read came from.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum ValueOrigin {
+    /// The value was read from a configuration file, or the side file of a lazily loaded
+    /// section
+    File(PathBuf),
+    /// An override for the session, see [`crate::session::override_for_session`]
+    Session,
+    /// An unexpired temporary override, see [`crate::temporary::set_temporary`]
+    Temporary,
+    /// The `GIM_ENV` profile of this name, see [`crate::profile`]
+    Profile(String),
+    /// The project configuration of this repository root, see [`crate::project`]
+    Project(PathBuf),
+    /// The system configuration of this path, which lacks the key or
+    /// locks it, see [`crate::locks`]
+    System(PathBuf),
+    /// The default, because the file lacks the key, see
+    /// [`crate::defaults::set_default_fallback`]
+    Default,
+}
+
+/// One read of a configuration key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AccessRecord {
+    /// The dotted key path that was read
+    pub path: String,
+    /// Where the value came from, `None` if the key was missing
+    pub origin: Option<ValueOrigin>,
+}
+
+impl AccessRecord {
+    /// Returns whether the key was found.
+    pub fn is_hit(&self) -> bool {
+        self.origin.is_some()
+    }
+}
+
+/// Starts recording every key read by this process.
+///
+/// Tracing is off by default and costs a single atomic load per read while disabled.
+pub fn enable_access_tracing() {
+    ENABLED.store(true, Ordering::Relaxed);
+}
+
+/// Stops recording key reads; already recorded reads are kept.
+pub fn disable_access_tracing() {
+    ENABLED.store(false, Ordering::Relaxed);
+}
+
+/// Returns whether key reads are currently being recorded.
+pub fn is_access_tracing_enabled() -> bool {
+    ENABLED.load(Ordering::Relaxed)
+}
+
+/// Returns every key read recorded since tracing was enabled, in order.
+///
+/// This helps diagnose reports like "gim is ignoring my setting" by showing which keys
+/// were actually consulted and whether they were found.
+pub fn access_report() -> Vec<AccessRecord> {
+    RECORDS.lock().unwrap_or_else(|e| e.into_inner()).clone()
+}
+
+/// Discards all recorded key reads.
+pub fn clear_access_report() {
+    RECORDS.lock().unwrap_or_else(|e| e.into_inner()).clear();
+}
+
+/// Records a read of `path` served from what `origin` returns, `None` for a miss, if
+/// tracing is enabled.
+pub(crate) fn record_read(path: &str, origin: impl FnOnce() -> Option<ValueOrigin>) {
+    if !is_access_tracing_enabled() {
+        return;
+    }
+    let origin = origin();
+    RECORDS
+        .lock()
+        .unwrap_or_else(|e| e.into_inner())
+        .push(AccessRecord {
+            path: path.to_string(),
+            origin,
+        });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{Config, get_config_value, get_value_fast};
+    use crate::temporary::set_temporary;
+    use crate::testing::TempConfigDir;
+    use std::time::{Duration, SystemTime};
+    use toml::Value;
+
+    #[test]
+    fn test_access_report_records_hits_and_misses() {
+        let dir = TempConfigDir::new().unwrap();
+        let was_enabled = is_access_tracing_enabled();
+        enable_access_tracing();
+        let file = dir.path().join("config.toml");
+        std::fs::write(&file, "[ai]\nlanguage = 'Dutch'\n").unwrap();
+        let later = SystemTime::now() + Duration::from_secs(3600);
+        set_temporary("ai.model", Value::from("trace-model"), later).unwrap();
+        get_config_value("ai", "language").unwrap();
+        get_value_fast("ai.model").unwrap();
+        get_value_fast("update.max_try").unwrap();
+        let handle = Config::load().unwrap();
+        handle.get("trace_test.missing");
+        handle.get("ai.model");
+
+        let report = access_report();
+        let origin = |path: &str| {
+            report
+                .iter()
+                .rev()
+                .find(|r| r.path == path)
+                .unwrap()
+                .origin
+                .clone()
+        };
+        assert_eq!(origin("ai.language"), Some(ValueOrigin::File(file)));
+        assert_eq!(origin("ai.model"), Some(ValueOrigin::Temporary));
+        assert_eq!(origin("update.max_try"), Some(ValueOrigin::Default));
+        assert_eq!(origin("trace_test.missing"), None);
+        if !was_enabled {
+            disable_access_tracing();
+        }
+    }
+}
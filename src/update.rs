@@ -0,0 +1,549 @@
+use std::{
+    io::{Error, ErrorKind, Result},
+    path::PathBuf,
+    time::SystemTime,
+};
+use toml::{Value, map};
+
+use crate::clock::{Clock, SystemClock};
+use crate::config::get_config;
+use crate::date::{Day, TimeZone, format_rfc3339, parse_rfc3339};
+use crate::defaults::{effective_defaults, is_default_fallback_enabled};
+use crate::filesystem::{FileSystem, RealFileSystem};
+use crate::format::Format;
+use crate::merge::merge_into;
+use crate::state::{modify_state, parse_state, read_state, serialize_state, state_file};
+
+/// The day an update check is assumed to have last run if none was ever recorded.
+const NEVER_TRIED_DAY: &str = "2000-01-01";
+
+/// The `[update]` settings that drive how often gim looks for a new release.
+///
+/// `max_try`, `try_interval_days` and `timezone` are user settings from `config.toml`;
+/// `tried`, `last_try_day` and `last_try` are counters kept in the state file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateSettings {
+    /// Number of checks performed in the current interval
+    pub tried: i64,
+    /// Maximum number of checks per interval
+    pub max_try: i64,
+    /// The day of the last check in `timezone`
+    pub last_try_day: Day,
+    /// Number of days after which a new interval starts
+    pub try_interval_days: i64,
+    /// The instant of the last check, if it was recorded with one
+    pub last_try: Option<SystemTime>,
+    /// The zone that decides where a day begins, the system zone, UTC or a fixed offset
+    pub timezone: TimeZone,
+}
+
+impl UpdateSettings {
+    /// Reads the settings from a configuration's `[update]` section.
+    ///
+    /// Missing counters mean no check was recorded yet. Other missing keys are taken from
+    /// the defaults, unless [`crate::defaults::set_default_fallback`] turned that off.
+    ///
+    /// # Arguments
+    ///
+    /// * `config` - The whole configuration
+    ///
+    /// # Returns
+    ///
+    /// * `Result<UpdateSettings>` - The settings or an error naming the missing or invalid key
+    pub fn from_config(config: &Value) -> Result<UpdateSettings> {
+        let mut filled = effective_defaults();
+        let config = if is_default_fallback_enabled() {
+            merge_into(&mut filled, config, true);
+            &filled
+        } else {
+            config
+        };
+        let update = config
+            .get("update")
+            .and_then(Value::as_table)
+            .ok_or_else(|| Error::new(ErrorKind::NotFound, "Section 'update' not found"))?;
+        let integer = |key: &str| {
+            update.get(key).and_then(Value::as_integer).ok_or_else(|| {
+                Error::new(
+                    ErrorKind::InvalidData,
+                    format!("Key 'update.{}' must be an integer", key),
+                )
+            })
+        };
+        let timezone = match update.get("timezone").and_then(Value::as_str) {
+            Some(zone) => TimeZone::parse(zone)?,
+            None => TimeZone::System,
+        };
+        let last_try = match update.get("last_try") {
+            Some(Value::String(s)) if s.is_empty() => None,
+            Some(Value::String(s)) => Some(parse_rfc3339(s)?),
+            Some(Value::Datetime(datetime)) => Some(parse_rfc3339(&datetime.to_string())?),
+            _ => None,
+        };
+        // The instant wins over the stored day, so changing the zone moves the day with it.
+        let last_try_day = match last_try {
+            Some(time) => Day::in_zone(time, timezone),
+            None => Day::parse(match update.get("last_try_day") {
+                None => NEVER_TRIED_DAY,
+                Some(day) => day.as_str().ok_or_else(|| {
+                    Error::new(
+                        ErrorKind::InvalidData,
+                        "Key 'update.last_try_day' must be a date string",
+                    )
+                })?,
+            })?,
+        };
+        Ok(UpdateSettings {
+            tried: match update.get("tried") {
+                None => 0,
+                Some(_) => integer("tried")?,
+            },
+            max_try: integer("max_try")?,
+            last_try_day,
+            try_interval_days: integer("try_interval_days")?,
+            last_try,
+            timezone,
+        })
+    }
+
+    /// Reads the settings from a configuration with the counters recorded in `state`.
+    ///
+    /// Counters in the state file win over leftovers in the configuration.
+    pub fn from_config_and_state(config: &Value, state: &Value) -> Result<UpdateSettings> {
+        let mut config = config.clone();
+        if let Some(counters) = state.get("update") {
+            let mut overlay = map::Map::new();
+            overlay.insert("update".to_string(), counters.clone());
+            merge_into(&mut config, &Value::Table(overlay), true);
+        }
+        UpdateSettings::from_config(&config)
+    }
+
+    /// Returns the day containing `now` in the configured zone.
+    pub fn today(&self, now: SystemTime) -> Day {
+        Day::in_zone(now, self.timezone)
+    }
+
+    /// Returns the number of calendar days between the last check and `now`.
+    ///
+    /// Both days are computed in the configured zone, so daylight saving time and a change
+    /// of `update.timezone` don't shift the count.
+    pub fn days_since_last_try(&self, now: SystemTime) -> i64 {
+        self.today(now).0 - self.last_try_day.0
+    }
+
+    /// Returns whether a check is due on `today`.
+    ///
+    /// A new interval starts once `try_interval_days` have passed since the last check;
+    /// within an interval gim checks at most once per day and at most `max_try` times.
+    /// A `max_try` of zero disables update checks entirely.
+    pub fn should_check(&self, today: Day) -> bool {
+        if self.max_try <= 0 {
+            return false;
+        }
+        if self.interval_elapsed(today) {
+            return true;
+        }
+        self.tried < self.max_try && today != self.last_try_day
+    }
+
+    /// Returns the settings after recording a check on `today`.
+    pub fn after_try(&self, today: Day) -> UpdateSettings {
+        let tried = if self.interval_elapsed(today) {
+            1
+        } else {
+            self.tried + 1
+        };
+        UpdateSettings {
+            tried,
+            last_try_day: today,
+            ..self.clone()
+        }
+    }
+
+    /// Returns the settings after recording a check at `now`.
+    pub fn after_try_at(&self, now: SystemTime) -> UpdateSettings {
+        UpdateSettings {
+            last_try: Some(now),
+            ..self.after_try(self.today(now))
+        }
+    }
+
+    fn interval_elapsed(&self, today: Day) -> bool {
+        today.0 - self.last_try_day.0 >= self.try_interval_days
+    }
+}
+
+/// The number of update attempts kept by [`record_update_attempt`].
+pub const UPDATE_HISTORY_LIMIT: usize = 50;
+
+/// The result of one update check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[non_exhaustive]
+pub enum UpdateOutcome {
+    /// The running version is the latest
+    UpToDate,
+    /// A newer version was found
+    UpdateAvailable,
+    /// The check failed, e.g. because the network was down
+    Failed(String),
+}
+
+/// One recorded update check.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UpdateAttempt {
+    /// When the check ran
+    pub time: SystemTime,
+    /// The latest version found, if the check got that far
+    pub version: Option<String>,
+    /// What the check found
+    pub outcome: UpdateOutcome,
+}
+
+impl UpdateAttempt {
+    fn to_value(&self) -> Value {
+        let mut table = map::Map::new();
+        table.insert(
+            "time".to_string(),
+            Value::from(format_rfc3339(self.time, TimeZone::Utc)),
+        );
+        if let Some(version) = &self.version {
+            table.insert("version".to_string(), Value::from(version.as_str()));
+        }
+        let outcome = match &self.outcome {
+            UpdateOutcome::UpToDate => "up-to-date",
+            UpdateOutcome::UpdateAvailable => "update-available",
+            UpdateOutcome::Failed(error) => {
+                table.insert("error".to_string(), Value::from(error.as_str()));
+                "failed"
+            }
+        };
+        table.insert("outcome".to_string(), Value::from(outcome));
+        Value::Table(table)
+    }
+
+    fn from_value(value: &Value) -> Option<UpdateAttempt> {
+        let text = |key: &str| value.get(key).and_then(Value::as_str);
+        let outcome = match text("outcome")? {
+            "up-to-date" => UpdateOutcome::UpToDate,
+            "update-available" => UpdateOutcome::UpdateAvailable,
+            "failed" => UpdateOutcome::Failed(text("error").unwrap_or_default().to_string()),
+            _ => return None,
+        };
+        Some(UpdateAttempt {
+            time: parse_rfc3339(text("time")?).ok()?,
+            version: text("version").map(str::to_string),
+            outcome,
+        })
+    }
+}
+
+/// Appends an update check to the history in the state file.
+///
+/// Only the latest [`UPDATE_HISTORY_LIMIT`] attempts are kept.
+///
+/// # Arguments
+///
+/// * `attempt` - The check to record
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if the state file can't be written
+pub fn record_update_attempt(attempt: &UpdateAttempt) -> Result<()> {
+    modify_state(|state| {
+        let root = state
+            .as_table_mut()
+            .ok_or_else(|| Error::new(ErrorKind::InvalidData, "State is not a table"))?;
+        let history = root
+            .entry("update_history".to_string())
+            .or_insert_with(|| Value::Array(Vec::new()));
+        if !history.is_array() {
+            *history = Value::Array(Vec::new());
+        }
+        let attempts = history.as_array_mut().expect("history is an array");
+        attempts.push(attempt.to_value());
+        let excess = attempts.len().saturating_sub(UPDATE_HISTORY_LIMIT);
+        attempts.drain(..excess);
+        Ok(())
+    })
+}
+
+/// Returns the recorded update checks, oldest first.
+///
+/// This helps diagnose reports like "gim keeps nagging me" from data instead of guesses.
+///
+/// # Returns
+///
+/// * `Result<Vec<UpdateAttempt>>` - The history; unreadable entries are skipped
+pub fn update_history() -> Result<Vec<UpdateAttempt>> {
+    Ok(read_state()?
+        .get("update_history")
+        .and_then(Value::as_array)
+        .map(|attempts| {
+            attempts
+                .iter()
+                .filter_map(UpdateAttempt::from_value)
+                .collect()
+        })
+        .unwrap_or_default())
+}
+
+/// Update-check throttling over an injectable clock and filesystem.
+///
+/// The settings are read from the configuration and the counters from the state file,
+/// which is the only file a recorded check writes.
+pub struct UpdateThrottle {
+    clock: Box<dyn Clock>,
+    fs: Box<dyn FileSystem>,
+    /// `None` reads the user's configuration like [`get_config`] does
+    config_file: Option<PathBuf>,
+    state_file: PathBuf,
+}
+
+impl UpdateThrottle {
+    /// Creates a throttle over the real clock, the user's configuration and state file.
+    ///
+    /// The configuration is read like [`get_config`] does, so the split layout and the
+    /// encoding repairs apply.
+    ///
+    /// # Returns
+    ///
+    /// * `Result<UpdateThrottle>` - The throttle or an error if the config can't be created
+    pub fn new() -> Result<UpdateThrottle> {
+        get_config()?;
+        Ok(UpdateThrottle {
+            clock: Box::new(SystemClock),
+            fs: Box::new(RealFileSystem),
+            config_file: None,
+            state_file: state_file()?,
+        })
+    }
+
+    /// Creates a throttle over the given clock, filesystem, config file and state file.
+    ///
+    /// The config file is read through `fs` as a single file.
+    pub fn with(
+        clock: Box<dyn Clock>,
+        fs: Box<dyn FileSystem>,
+        config_file: PathBuf,
+        state_file: PathBuf,
+    ) -> UpdateThrottle {
+        UpdateThrottle {
+            clock,
+            fs,
+            config_file: Some(config_file),
+            state_file,
+        }
+    }
+
+    /// Returns the current day according to the throttle's clock and `update.timezone`.
+    pub fn today(&self) -> Day {
+        let zone = self.settings().map(|s| s.timezone).unwrap_or_default();
+        Day::in_zone(self.clock.now(), zone)
+    }
+
+    /// Reads the current `[update]` settings.
+    pub fn settings(&self) -> Result<UpdateSettings> {
+        UpdateSettings::from_config_and_state(&self.read()?, &self.read_state()?)
+    }
+
+    /// Returns whether an update check is due today.
+    pub fn should_check_update(&self) -> Result<bool> {
+        let settings = self.settings()?;
+        Ok(settings.should_check(settings.today(self.clock.now())))
+    }
+
+    /// Returns the number of calendar days since the last update check.
+    pub fn days_since_last_try(&self) -> Result<i64> {
+        Ok(self.settings()?.days_since_last_try(self.clock.now()))
+    }
+
+    /// Records that an update check was performed today.
+    ///
+    /// Only the state file is written; `config.toml` is left untouched.
+    pub fn record_update_try(&self) -> Result<()> {
+        let now = self.clock.now();
+        let config = self.read()?;
+        self.fs.with_lock(&self.state_file, &mut || {
+            let mut state = self.read_state()?;
+            let next = UpdateSettings::from_config_and_state(&config, &state)?.after_try_at(now);
+            let mut counters = map::Map::new();
+            counters.insert("tried".to_string(), Value::Integer(next.tried));
+            counters.insert(
+                "last_try_day".to_string(),
+                Value::String(next.last_try_day.to_string()),
+            );
+            counters.insert(
+                "last_try".to_string(),
+                Value::String(format_rfc3339(now, next.timezone)),
+            );
+            let root = state
+                .as_table_mut()
+                .ok_or_else(|| Error::new(ErrorKind::InvalidData, "State is not a table"))?;
+            root.insert("update".to_string(), Value::Table(counters));
+            self.fs
+                .write(&self.state_file, serialize_state(&mut state)?.as_bytes())
+        })
+    }
+
+    fn read(&self) -> Result<Value> {
+        match &self.config_file {
+            Some(file) => Format::from_path(file)?.parse(&self.fs.read_to_string(file)?),
+            None => get_config(),
+        }
+    }
+
+    fn read_state(&self) -> Result<Value> {
+        match self.fs.read_to_string(&self.state_file) {
+            Ok(content) => parse_state(&content),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Value::Table(map::Map::new())),
+            Err(e) => Err(e),
+        }
+    }
+}
+
+/// Returns whether gim should check for a new release today.
+///
+/// # Returns
+///
+/// * `Result<bool>` - Whether a check is due, or an error if the settings are invalid
+pub fn should_check_update() -> Result<bool> {
+    UpdateThrottle::new()?.should_check_update()
+}
+
+/// Returns the number of calendar days since gim last checked for a new release.
+///
+/// # Returns
+///
+/// * `Result<i64>` - The day count in `update.timezone`, or an error if the settings are
+///   invalid
+pub fn days_since_last_try() -> Result<i64> {
+    UpdateThrottle::new()?.days_since_last_try()
+}
+
+/// Records that gim checked for a new release today.
+///
+/// # Returns
+///
+/// * `Result<()>` - Success or an error if the settings are invalid or saving fails
+pub fn record_update_try() -> Result<()> {
+    UpdateThrottle::new()?.record_update_try()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::MockClock;
+    use crate::filesystem::MockFileSystem;
+    use std::{
+        path::Path,
+        sync::Arc,
+        time::{Duration, SystemTime},
+    };
+
+    #[test]
+    fn test_throttle_checks_once_per_day_up_to_max_try() {
+        let day = Duration::from_secs(86_400);
+        let clock = Arc::new(MockClock::new(SystemTime::UNIX_EPOCH + day * 20_000));
+        let fs = Arc::new(MockFileSystem::new());
+        let file = Path::new("/gim/config.toml");
+        let config = b"[update]\ntried = 0\nmax_try = 2\nlast_try_day = \"2000-01-01\"\ntry_interval_days = 30\n";
+        fs.write(file, config).unwrap();
+        let throttle = UpdateThrottle::with(
+            Box::new(clock.clone()),
+            Box::new(fs.clone()),
+            file.into(),
+            "/state/state.toml".into(),
+        );
+
+        assert!(throttle.should_check_update().unwrap());
+        throttle.record_update_try().unwrap();
+        assert!(
+            !throttle.should_check_update().unwrap(),
+            "Only one try per day"
+        );
+
+        clock.advance(day);
+        assert!(throttle.should_check_update().unwrap());
+        throttle.record_update_try().unwrap();
+        clock.advance(day);
+        assert!(!throttle.should_check_update().unwrap(), "max_try reached");
+
+        clock.advance(day * 30);
+        assert!(throttle.should_check_update().unwrap(), "New interval");
+        assert_eq!(fs.read_to_string(file).unwrap().as_bytes(), config);
+    }
+
+    #[test]
+    fn test_days_since_last_try_follows_the_zone() {
+        let config: Value = toml::from_str(
+            "[update]\ntried = 1\nmax_try = 5\nlast_try_day = \"2000-01-01\"\n\
+             try_interval_days = 30\nlast_try = \"2026-03-28T23:30:00Z\"\ntimezone = \"UTC\"\n",
+        )
+        .unwrap();
+        let now = parse_rfc3339("2026-03-29T22:00:00Z").unwrap();
+        let utc = UpdateSettings::from_config(&config).unwrap();
+        assert_eq!(utc.last_try_day.to_string(), "2026-03-28");
+        assert_eq!(utc.days_since_last_try(now), 1);
+
+        let tokyo = with_zone(&config, TimeZone::Fixed(9 * 3600));
+        let tokyo = UpdateSettings::from_config(&tokyo).unwrap();
+        assert_eq!(tokyo.last_try_day.to_string(), "2026-03-29");
+        assert_eq!(tokyo.days_since_last_try(now), 1);
+        let same_day = parse_rfc3339("2026-03-29T10:00:00Z").unwrap();
+        assert!(!tokyo.should_check(tokyo.today(same_day)));
+    }
+
+    fn with_zone(config: &Value, zone: TimeZone) -> Value {
+        let mut config = config.clone();
+        config["update"]
+            .as_table_mut()
+            .unwrap()
+            .insert("timezone".to_string(), Value::from(zone.to_string()));
+        config
+    }
+
+    #[test]
+    fn test_update_history_is_bounded() {
+        let _dir = crate::testing::TempConfigDir::new().unwrap();
+        for i in 0..UPDATE_HISTORY_LIMIT + 2 {
+            record_update_attempt(&UpdateAttempt {
+                time: SystemTime::UNIX_EPOCH + Duration::from_secs(i as u64),
+                version: Some(format!("1.0.{}", i)),
+                outcome: UpdateOutcome::UpToDate,
+            })
+            .unwrap();
+        }
+        record_update_attempt(&UpdateAttempt {
+            time: SystemTime::UNIX_EPOCH,
+            version: None,
+            outcome: UpdateOutcome::Failed("offline".to_string()),
+        })
+        .unwrap();
+
+        let history = update_history().unwrap();
+        assert_eq!(history.len(), UPDATE_HISTORY_LIMIT);
+        assert_eq!(history[0].version.as_deref(), Some("1.0.3"));
+        assert_eq!(
+            history.last().unwrap().outcome,
+            UpdateOutcome::Failed("offline".to_string())
+        );
+    }
+
+    #[test]
+    fn test_throttle_reads_the_split_layout_and_defaults() {
+        let dir = crate::testing::TempConfigDir::new().unwrap();
+        crate::layout::migrate_to_split_layout().unwrap();
+        let update_file = dir.path().join("config.d").join("update.toml");
+        std::fs::write(&update_file, "timezone = \"UTC\"\n").unwrap();
+
+        assert!(should_check_update().unwrap());
+        record_update_try().unwrap();
+        assert!(!should_check_update().unwrap(), "Only one try per day");
+        assert_eq!(days_since_last_try().unwrap(), 0);
+        assert_eq!(
+            std::fs::read_to_string(&update_file).unwrap(),
+            "timezone = \"UTC\"\n"
+        );
+    }
+}
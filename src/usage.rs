@@ -0,0 +1,154 @@
+use std::io::Result;
+use toml::Value;
+
+use crate::capabilities::capabilities;
+use crate::config::get_config;
+use crate::defaults::default_values;
+use crate::key_times::KEY_TIMES_SECTION;
+use crate::path;
+use crate::profile::PROFILES_SECTION;
+use crate::stream::CHANGE_STREAM_SECTION;
+
+/// The section of the configuration file that consents to usage statistics with
+/// `enabled = true`.
+pub const TELEMETRY_SECTION: &str = "telemetry";
+
+/// The only keys [`usage_stats`] reports on. Keys an application or the user added never
+/// show up, whatever their name.
+pub const USAGE_KEYS: &[&str] = &[
+    "ai.apikey",
+    "ai.language",
+    "ai.model",
+    "ai.url",
+    "update.max_try",
+    "update.timezone",
+    "update.try_interval_days",
+    "limits.max_diff_bytes",
+    "limits.max_files",
+    "limits.truncation",
+];
+
+/// The only opt-in sections [`usage_stats`] reports on.
+pub const USAGE_SECTIONS: &[&str] = &[CHANGE_STREAM_SECTION, KEY_TIMES_SECTION];
+
+/// Which parts of the configuration are in use, without any of their values.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UsageStats {
+    /// The version of this crate
+    pub crate_version: &'static str,
+    /// The keys of [`USAGE_KEYS`] set to something other than their default
+    pub customized_keys: Vec<&'static str>,
+    /// The sections of [`USAGE_SECTIONS`] turned on with `enabled = true`
+    pub enabled_sections: Vec<&'static str>,
+    /// How many `GIM_ENV` profiles are declared
+    pub profiles: usize,
+    /// The Cargo features compiled in, e.g. `"json"`
+    pub features: Vec<String>,
+}
+
+impl UsageStats {
+    /// Serializes the statistics as a JSON object for gim's opt-in metrics.
+    pub fn to_json(&self) -> String {
+        serde_json::json!({
+            "crate_version": self.crate_version,
+            "customized_keys": self.customized_keys,
+            "enabled_sections": self.enabled_sections,
+            "profiles": self.profiles,
+            "features": self.features,
+        })
+        .to_string()
+    }
+}
+
+/// Returns whether `config` consents to usage statistics.
+pub fn is_telemetry_enabled(config: &Value) -> bool {
+    config
+        .get(TELEMETRY_SECTION)
+        .and_then(|section| section.get("enabled"))
+        .and_then(Value::as_bool)
+        == Some(true)
+}
+
+/// Aggregates which features and keys of the configuration are in use, for gim's opt-in
+/// metrics.
+///
+/// Nothing is collected unless the configuration consents with `enabled = true` in the
+/// `[telemetry]` section. Only key names from [`USAGE_KEYS`] and section names from
+/// [`USAGE_SECTIONS`] are reported, never a value, so neither the model, the URL nor
+/// the API key can leak.
+///
+/// # Returns
+///
+/// * `Result<Option<UsageStats>>` - The statistics, `None` without consent, or an error
+///   if the configuration can't be read
+pub fn usage_stats() -> Result<Option<UsageStats>> {
+    let config = get_config()?;
+    if !is_telemetry_enabled(&config) {
+        return Ok(None);
+    }
+    let defaults = default_values();
+    let customized_keys = USAGE_KEYS
+        .iter()
+        .copied()
+        .filter(|key| {
+            path::lookup(&config, key)
+                .is_some_and(|value| path::lookup(&defaults, key) != Some(value))
+        })
+        .collect();
+    let enabled_sections = USAGE_SECTIONS
+        .iter()
+        .copied()
+        .filter(|section| {
+            config
+                .get(*section)
+                .and_then(|section| section.get("enabled"))
+                .and_then(Value::as_bool)
+                == Some(true)
+        })
+        .collect();
+    let profiles = config
+        .get(PROFILES_SECTION)
+        .and_then(Value::as_table)
+        .map_or(0, |profiles| profiles.len());
+    let features = capabilities()
+        .into_iter()
+        .filter(|capability| capability.compiled)
+        .map(|capability| capability.feature.to_string())
+        .collect();
+    Ok(Some(UsageStats {
+        crate_version: env!("CARGO_PKG_VERSION"),
+        customized_keys,
+        enabled_sections,
+        profiles,
+        features,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::save_config;
+    use crate::testing::TempConfigDir;
+
+    #[test]
+    fn test_usage_stats_need_consent_and_omit_values() {
+        let _dir = TempConfigDir::new().unwrap();
+        assert_eq!(usage_stats().unwrap(), None);
+
+        let mut config = get_config().unwrap();
+        path::insert(&mut config, "telemetry.enabled", Value::Boolean(true)).unwrap();
+        path::insert(&mut config, "ai.model", Value::from("private-model")).unwrap();
+        path::insert(&mut config, "ai.apikey", Value::from("sk-private")).unwrap();
+        path::insert(&mut config, "change_stream.enabled", Value::Boolean(true)).unwrap();
+        path::insert(&mut config, "myapp.secret_key", Value::from("x")).unwrap();
+        save_config(&config).unwrap();
+
+        let stats = usage_stats().unwrap().unwrap();
+        assert_eq!(stats.customized_keys, vec!["ai.apikey", "ai.model"]);
+        assert_eq!(stats.enabled_sections, vec![CHANGE_STREAM_SECTION]);
+        let json = stats.to_json();
+        for leaked in ["private-model", "sk-private", "myapp"] {
+            assert!(!json.contains(leaked), "{}", json);
+        }
+    }
+}